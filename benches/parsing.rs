@@ -0,0 +1,112 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use snekdown::format::renderer::{HtmlRenderer, Renderer};
+use snekdown::parser::ParserOptions;
+use snekdown::Parser;
+use std::fs;
+use std::path::PathBuf;
+
+const CHAPTER: &str = r#"# Chapter
+
+Some *formatted* text with **bold**, ~~striked~~ and `monospace` parts,
+a [link](https://github.com/Trivernis/snekdown) and a footnote[^note].
+
+## Section
+
+- first item
+- second item with :rocket:
+    - nested item
+1. ordered item
+2. another ordered item
+
+| Column | Other Column |
+|--------|--------------|
+| value  | other value  |
+
+```rust
+fn main() {
+    println!("Hello World");
+}
+```
+
+> A quote
+> over multiple lines
+
+[^note]: The definition of the footnote
+"#;
+
+/// Creates a document with the given number of chapters
+fn create_document(chapters: usize) -> String {
+    CHAPTER.repeat(chapters)
+}
+
+/// Writes a document that imports the given number of chapters into a temporary directory
+/// and returns the path of the main document
+fn create_imports(chapters: usize) -> PathBuf {
+    let dir = std::env::temp_dir().join("snekdown-bench-imports");
+    fs::create_dir_all(&dir).unwrap();
+    let mut main = String::new();
+
+    for i in 0..chapters {
+        fs::write(dir.join(format!("chapter{}.md", i)), CHAPTER).unwrap();
+        main.push_str(&format!("<[chapter{}.md]\n", i));
+    }
+    let path = dir.join("main.md");
+    fs::write(&path, main).unwrap();
+
+    path
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+
+    for chapters in [10, 100].iter() {
+        let text = create_document(*chapters);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(chapters), &text, |b, text| {
+            b.iter(|| Parser::new(text.clone(), None).parse())
+        });
+    }
+    group.finish();
+}
+
+fn parse_imports(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_imports");
+    let path = create_imports(20);
+
+    for deterministic in [false, true].iter() {
+        group.bench_with_input(
+            BenchmarkId::new("deterministic", deterministic),
+            deterministic,
+            |b, deterministic| {
+                b.iter(|| {
+                    let options = ParserOptions::default()
+                        .add_path(path.clone())
+                        .with_deterministic_imports(*deterministic);
+                    Parser::with_defaults(options).parse()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn render_html(c: &mut Criterion) {
+    let document = Parser::new(create_document(100), None).parse();
+
+    c.bench_function("render_html", |b| {
+        b.iter(|| {
+            let mut out = Vec::new();
+            HtmlRenderer.render(&document, &mut out).unwrap();
+            out
+        })
+    });
+}
+
+criterion_group!(benches, parse, parse_imports, render_html);
+criterion_main!(benches);
@@ -0,0 +1,127 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::parser::import_cache::ImportCache;
+use crate::parser::{ImportErrorPolicy, InputNormalization, ParserOptions};
+use crate::settings::style_settings::Theme;
+use crate::settings::SettingsResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the project configuration file
+pub const CONFIG_FILE: &str = "snekdown.toml";
+
+/// The project configuration that is read before the document is parsed.
+/// The settings of the document itself are still read from its Manifest.toml
+/// which overrides the theme set here.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Config {
+    /// The theme of the html output
+    pub theme: Option<Theme>,
+    pub parser: ParserConfig,
+    /// Values for [[value:key]] placeholders
+    pub values: HashMap<String, String>,
+    /// The outputs that are rendered when no output is given on the command line
+    pub outputs: Vec<OutputConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ParserConfig {
+    pub autolinks: bool,
+    pub smart_punctuation: bool,
+    pub raw_html: bool,
+    pub line_comments: bool,
+    pub strict_values: bool,
+    /// Directories that are searched for imports that don't exist relative to the document
+    pub import_roots: Vec<PathBuf>,
+    /// How imports that fail are handled
+    pub import_errors: ImportErrorPolicy,
+    /// Reads unchanged imported documents from the cache instead of parsing them again
+    pub cache_imports: bool,
+    /// The number of spaces tabs in the indentation of lines are replaced with
+    pub tab_width: Option<usize>,
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            autolinks: true,
+            smart_punctuation: true,
+            raw_html: false,
+            line_comments: false,
+            strict_values: false,
+            import_roots: Vec::new(),
+            import_errors: ImportErrorPolicy::Warning,
+            cache_imports: false,
+            tab_width: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OutputConfig {
+    pub format: String,
+    pub path: PathBuf,
+}
+
+impl Config {
+    /// Loads the configuration from the given file.
+    /// Relative paths in the configuration are resolved from the directory of the file.
+    pub fn load(path: &Path) -> SettingsResult<Self> {
+        let mut config: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+        if let Some(dir) = path.parent() {
+            config.parser.import_roots = config
+                .parser
+                .import_roots
+                .iter()
+                .map(|root| dir.join(root))
+                .collect();
+            config
+                .outputs
+                .iter_mut()
+                .for_each(|output| output.path = dir.join(&output.path));
+        }
+
+        Ok(config)
+    }
+
+    /// Searches for the configuration file in the given directory and its parents
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Creates parser options with the configured values
+    pub fn parser_options(&self) -> ParserOptions {
+        let mut options = ParserOptions::default()
+            .with_autolinks(self.parser.autolinks)
+            .with_smart_punctuation(self.parser.smart_punctuation)
+            .with_raw_html(self.parser.raw_html)
+            .with_line_comments(self.parser.line_comments)
+            .with_strict_values(self.parser.strict_values)
+            .with_values(self.values.clone())
+            .with_import_roots(self.parser.import_roots.clone())
+            .with_import_error_policy(self.parser.import_errors)
+            .with_normalization(InputNormalization {
+                tab_width: self.parser.tab_width,
+                ..InputNormalization::default()
+            });
+        if self.parser.cache_imports {
+            options = options.with_import_cache(ImportCache::new());
+        }
+        if let Some(theme) = &self.theme {
+            options.document.config.lock().style.theme = theme.clone();
+        }
+
+        options
+    }
+}
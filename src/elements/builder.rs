@@ -0,0 +1,286 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::*;
+
+/// Builds a document from code instead of parsing it.
+/// Headings open a new section that contains all following blocks
+/// until a heading of the same or a smaller level is added.
+pub struct DocumentBuilder {
+    document: Document,
+    sections: Vec<Section>,
+}
+
+impl Document {
+    /// Returns a builder for a new document
+    pub fn builder() -> DocumentBuilder {
+        DocumentBuilder::new()
+    }
+
+    /// Returns the section with the given anchor or title
+    pub fn find_section_mut(&mut self, anchor: &str) -> Option<&mut Section> {
+        find_section_mut(&mut self.elements, &normalize_name(anchor))
+    }
+}
+
+impl DocumentBuilder {
+    pub fn new() -> Self {
+        Self {
+            document: Document::new(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Starts a new section with the header of the given level
+    pub fn heading(mut self, level: u8, title: &str) -> Self {
+        while self.sections.last().is_some_and(|s| s.header.size >= level) {
+            self.close_section();
+        }
+        let parent_anchor = self.sections.last().map(|s| s.header.anchor.as_str());
+        self.sections
+            .push(Section::with_title(level, title, parent_anchor));
+
+        self
+    }
+
+    /// Adds a paragraph with the given text
+    pub fn paragraph(self, text: Vec<Inline>) -> Self {
+        self.block(Block::Paragraph(Paragraph::with_text(text)))
+    }
+
+    pub fn code_block(self, language: &str, code: &str) -> Self {
+        self.block(Block::CodeBlock(CodeBlock::new(language, code)))
+    }
+
+    /// Adds a list with one item for each of the given texts
+    pub fn list(self, ordered: bool, items: Vec<Vec<Inline>>) -> Self {
+        self.block(Block::List(List::with_items(ordered, items)))
+    }
+
+    pub fn ruler(self) -> Self {
+        self.block(Block::Paragraph(Paragraph {
+            elements: vec![Line::Ruler(Ruler {
+                span: Span::default(),
+            })],
+            metadata: None,
+            span: Span::default(),
+        }))
+    }
+
+    /// Adds the block to the current section or the document if there's no section
+    pub fn block(mut self, block: Block) -> Self {
+        if let Some(section) = self.sections.last_mut() {
+            section.add_element(block);
+        } else {
+            self.document.add_element(block);
+        }
+
+        self
+    }
+
+    /// Returns the document with all references and numbers assigned
+    pub fn build(mut self) -> Document {
+        while !self.sections.is_empty() {
+            self.close_section();
+        }
+        self.document.post_process();
+
+        self.document
+    }
+
+    /// Adds the innermost section to its parent
+    fn close_section(&mut self) {
+        if let Some(section) = self.sections.pop() {
+            if let Some(parent) = self.sections.last_mut() {
+                parent.add_element(Block::Section(section));
+            } else {
+                self.document.add_element(Block::Section(section));
+            }
+        }
+    }
+}
+
+impl Default for DocumentBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Section {
+    /// Creates a section with a header of the given level.
+    /// The anchor is created from the title like the one of a parsed header
+    pub fn with_title(level: u8, title: &str, parent_anchor: Option<&str>) -> Self {
+        let mut anchor = title.to_string();
+        if let Some(parent) = parent_anchor {
+            anchor = format!("{}-{}", parent, anchor);
+        }
+        anchor.retain(|c| !c.is_whitespace());
+        let mut header = Header::new(
+            Line::Text(TextLine::with_text(vec![
+                Inline::plain(title),
+                Inline::LineBreak,
+            ])),
+            anchor,
+        );
+        header.size = level;
+
+        Self::new(header)
+    }
+
+    pub fn push_paragraph(&mut self, text: Vec<Inline>) {
+        self.add_element(Block::Paragraph(Paragraph::with_text(text)))
+    }
+
+    pub fn push_code_block(&mut self, language: &str, code: &str) {
+        self.add_element(Block::CodeBlock(CodeBlock::new(language, code)))
+    }
+
+    pub fn push_list(&mut self, ordered: bool, items: Vec<Vec<Inline>>) {
+        self.add_element(Block::List(List::with_items(ordered, items)))
+    }
+
+    /// Adds a subsection one level below the section and returns it
+    pub fn push_section(&mut self, title: &str) -> &mut Section {
+        let section = Section::with_title(self.header.size + 1, title, Some(&self.header.anchor));
+        self.add_element(Block::Section(section));
+
+        match self.elements.last_mut() {
+            Some(Block::Section(section)) => section,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Paragraph {
+    /// Creates a paragraph with a single line of text that ends with a paragraph break
+    /// like a paragraph that is followed by an empty line
+    pub fn with_text(mut text: Vec<Inline>) -> Self {
+        text.push(Inline::LineBreak);
+        Self {
+            elements: vec![Line::Text(TextLine::with_text(text))],
+            metadata: None,
+            span: Span::default(),
+        }
+    }
+}
+
+impl TextLine {
+    pub fn with_text(subtext: Vec<Inline>) -> Self {
+        Self {
+            subtext,
+            span: Span::default(),
+        }
+    }
+}
+
+impl List {
+    /// Creates a list with one item for each of the given texts
+    pub fn with_items(ordered: bool, items: Vec<Vec<Inline>>) -> Self {
+        let mut list = List::new();
+        list.ordered = ordered;
+        for text in items {
+            list.add_item(ListItem::new(
+                Line::Text(TextLine::with_text(text)),
+                0,
+                ordered,
+            ));
+        }
+
+        list
+    }
+}
+
+impl CodeBlock {
+    pub fn new(language: &str, code: &str) -> Self {
+        Self {
+            language: language.to_string(),
+            code: code.to_string(),
+            metadata: None,
+            span: Span::default(),
+        }
+    }
+}
+
+impl Html {
+    /// Creates raw html that is passed through to the output,
+    /// for example as the value of a custom container
+    pub fn new(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            metadata: None,
+            span: Span::default(),
+        }
+    }
+}
+
+impl Inline {
+    pub fn plain(text: &str) -> Self {
+        Inline::Plain(PlainText::new(text.to_string()))
+    }
+
+    pub fn bold(value: Vec<Inline>) -> Self {
+        Inline::Bold(BoldText {
+            value,
+            span: Span::default(),
+        })
+    }
+
+    pub fn italic(value: Vec<Inline>) -> Self {
+        Inline::Italic(ItalicText {
+            value,
+            span: Span::default(),
+        })
+    }
+
+    pub fn underlined(value: Vec<Inline>) -> Self {
+        Inline::Underlined(UnderlinedText {
+            value,
+            span: Span::default(),
+        })
+    }
+
+    pub fn striked(value: Vec<Inline>) -> Self {
+        Inline::Striked(StrikedText {
+            value,
+            span: Span::default(),
+        })
+    }
+
+    pub fn monospace(text: &str) -> Self {
+        Inline::Monospace(MonospaceText {
+            value: text.to_string(),
+            span: Span::default(),
+        })
+    }
+
+    /// Creates a link with the given description or the url as its text if it's empty
+    pub fn link(url: &str, description: Vec<Inline>) -> Self {
+        let description = if description.is_empty() {
+            None
+        } else {
+            Some(description)
+        };
+
+        Inline::Url(Url::new(description, url.to_string()))
+    }
+}
+
+/// Returns the section with the given normalized anchor or title
+fn find_section_mut<'a>(elements: &'a mut [Block], name: &str) -> Option<&'a mut Section> {
+    for element in elements {
+        if let Block::Section(section) = element {
+            if normalize_name(&section.header.anchor) == name
+                || normalize_name(&section.header.get_plain_text()) == name
+            {
+                return Some(section);
+            } else if let Some(section) = find_section_mut(&mut section.elements, name) {
+                return Some(section);
+            }
+        }
+    }
+
+    None
+}
@@ -0,0 +1,99 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::visitor::{walk_block_mut, VisitorMut};
+use crate::elements::{Block, CustomContainer, Document};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+/// Transforms custom containers with a given name.
+/// Implement this to define what a `:::name` container is rendered as.
+pub trait ContainerHandler: Send + Sync {
+    /// Returns the block the container is rendered as or None to render its content.
+    /// Nested containers have already been processed at this point.
+    fn process(&self, container: &CustomContainer) -> Option<Block>;
+}
+
+impl<F> ContainerHandler for F
+where
+    F: Fn(&CustomContainer) -> Option<Block> + Send + Sync,
+{
+    fn process(&self, container: &CustomContainer) -> Option<Block> {
+        self(container)
+    }
+}
+
+/// The custom container handlers by the name of the containers they transform
+#[derive(Clone, Default)]
+pub struct ContainerHandlers {
+    handlers: HashMap<String, Arc<dyn ContainerHandler>>,
+}
+
+impl ContainerHandlers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the handler for containers with the given name
+    pub fn register<H: ContainerHandler + 'static>(&mut self, name: &str, handler: H) {
+        self.handlers.insert(name.to_lowercase(), Arc::new(handler));
+    }
+
+    /// Adds the handlers of the other collection that don't exist in this one
+    pub(crate) fn extend(&mut self, other: &ContainerHandlers) {
+        for (name, handler) in &other.handlers {
+            self.handlers
+                .entry(name.clone())
+                .or_insert_with(|| Arc::clone(handler));
+        }
+    }
+
+    /// Returns the handler for containers with the given name
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn ContainerHandler>> {
+        self.handlers.get(&name.to_lowercase())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
+
+impl Debug for ContainerHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.handlers.keys()).finish()
+    }
+}
+
+impl Document {
+    /// Assigns the blocks the registered handlers transform the containers
+    /// of the document and its imports into
+    pub(crate) fn process_containers(&mut self) {
+        if self.container_handlers.is_empty() {
+            return;
+        }
+        let mut processor = ContainerProcessor {
+            handlers: self.container_handlers.clone(),
+        };
+        processor.visit_document(self);
+    }
+}
+
+struct ContainerProcessor {
+    handlers: ContainerHandlers,
+}
+
+impl VisitorMut for ContainerProcessor {
+    fn visit_block(&mut self, block: &mut Block) {
+        walk_block_mut(self, block);
+
+        if let Block::CustomContainer(container) = block {
+            if let Some(handler) = self.handlers.get(&container.name) {
+                container.value = handler.process(container).map(Box::new);
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Block, Document, Section, Span};
+use crate::format::markdown::ToMarkdown;
+use crate::parser::incremental::get_block_span;
+use std::mem::discriminant;
+
+/// A change of a block between two versions of a document
+#[derive(Clone, Debug)]
+pub enum Change {
+    Added {
+        block: Box<Block>,
+        span: Span,
+    },
+    Removed {
+        block: Box<Block>,
+        span: Span,
+    },
+    /// A block that was changed in place.
+    /// Modified sections only contain their header as the changes of
+    /// their content are listed separately
+    Modified {
+        old: Box<Block>,
+        new: Box<Block>,
+        old_span: Span,
+        new_span: Span,
+    },
+}
+
+/// Returns the blocks that were added, removed or modified in the new version of the document.
+/// Sections are matched by their anchor and compared block by block, other blocks are
+/// compared by their content so that changes of the formatting or position aren't reported.
+/// The changes are ordered by their position in the documents.
+pub fn diff(old: &Document, new: &Document) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_blocks(&old.elements, &new.elements, &mut changes);
+
+    changes
+}
+
+impl Change {
+    /// Returns the span of the block in the new document or in the old one if it was removed
+    pub fn get_span(&self) -> Span {
+        match self {
+            Change::Added { span, .. } | Change::Removed { span, .. } => *span,
+            Change::Modified { new_span, .. } => *new_span,
+        }
+    }
+}
+
+/// The value blocks are matched by
+#[derive(PartialEq)]
+enum BlockKey {
+    Section(String),
+    Content(String),
+}
+
+fn get_key(block: &Block) -> BlockKey {
+    match block {
+        Block::Section(section) => BlockKey::Section(section.header.anchor.clone()),
+        block => BlockKey::Content(block.to_markdown()),
+    }
+}
+
+fn diff_blocks(old: &[Block], new: &[Block], changes: &mut Vec<Change>) {
+    let old = old
+        .iter()
+        .filter(|b| !matches!(b, Block::Null))
+        .collect::<Vec<&Block>>();
+    let new = new
+        .iter()
+        .filter(|b| !matches!(b, Block::Null))
+        .collect::<Vec<&Block>>();
+    let old_keys = old.iter().map(|b| get_key(b)).collect::<Vec<BlockKey>>();
+    let new_keys = new.iter().map(|b| get_key(b)).collect::<Vec<BlockKey>>();
+    let (mut i, mut j) = (0, 0);
+
+    for (old_index, new_index) in longest_common_subsequence(&old_keys, &new_keys) {
+        diff_gap(&old[i..old_index], &new[j..new_index], changes);
+        if let (Block::Section(old_section), Block::Section(new_section)) =
+            (old[old_index], new[new_index])
+        {
+            diff_sections(old_section, new_section, changes);
+        }
+        i = old_index + 1;
+        j = new_index + 1;
+    }
+    diff_gap(&old[i..], &new[j..], changes);
+}
+
+/// Lists the blocks between two matching blocks as modified if they are
+/// of the same kind and as removed or added otherwise
+fn diff_gap(old: &[&Block], new: &[&Block], changes: &mut Vec<Change>) {
+    let mut old = old.iter().peekable();
+    let mut new = new.iter().peekable();
+
+    loop {
+        match (old.peek(), new.peek()) {
+            (Some(old_block), Some(new_block))
+                if discriminant(**old_block) == discriminant(**new_block) =>
+            {
+                if let (Block::Section(old_section), Block::Section(new_section)) =
+                    (old_block, new_block)
+                {
+                    diff_sections(old_section, new_section, changes);
+                } else {
+                    changes.push(Change::Modified {
+                        old: Box::new((**old_block).clone()),
+                        new: Box::new((**new_block).clone()),
+                        old_span: get_block_span(old_block).unwrap_or_default(),
+                        new_span: get_block_span(new_block).unwrap_or_default(),
+                    });
+                }
+                old.next();
+                new.next();
+            }
+            (Some(old_block), _) => {
+                changes.push(Change::Removed {
+                    block: Box::new((**old_block).clone()),
+                    span: get_block_span(old_block).unwrap_or_default(),
+                });
+                old.next();
+            }
+            (None, Some(new_block)) => {
+                changes.push(Change::Added {
+                    block: Box::new((**new_block).clone()),
+                    span: get_block_span(new_block).unwrap_or_default(),
+                });
+                new.next();
+            }
+            (None, None) => break,
+        }
+    }
+}
+
+/// Lists the header of the sections if it changed and the changes of their content
+fn diff_sections(old: &Section, new: &Section, changes: &mut Vec<Change>) {
+    let old_header = without_content(old);
+    let new_header = without_content(new);
+    if old_header.to_markdown() != new_header.to_markdown() {
+        changes.push(Change::Modified {
+            old: Box::new(Block::Section(old_header)),
+            new: Box::new(Block::Section(new_header)),
+            old_span: old.header.span,
+            new_span: new.header.span,
+        });
+    }
+    diff_blocks(&old.elements, &new.elements, changes);
+}
+
+/// Returns a copy of the section without its content
+fn without_content(section: &Section) -> Section {
+    Section {
+        header: section.header.clone(),
+        elements: Vec::new(),
+        metadata: section.metadata.clone(),
+        span: section.span,
+    }
+}
+
+/// Returns the pairs of indices of the longest common subsequence of both lists
+fn longest_common_subsequence<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(usize, usize)> {
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    pairs
+}
@@ -0,0 +1,126 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::*;
+use crate::references::placeholders::ProcessPlaceholders;
+use std::fmt::{self, Debug};
+
+/// Loads the document of an import that is only parsed when it's needed
+#[derive(Clone)]
+pub(crate) struct LazyDocument(Arc<dyn Fn() -> Document + Send + Sync>);
+
+impl LazyDocument {
+    pub(crate) fn new<F: Fn() -> Document + Send + Sync + 'static>(load: F) -> Self {
+        Self(Arc::new(load))
+    }
+}
+
+impl Debug for LazyDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LazyDocument")
+    }
+}
+
+impl ImportAnchor {
+    pub(crate) fn set_lazy(&mut self, lazy: LazyDocument) {
+        self.lazy = Some(lazy);
+    }
+
+    /// Returns if the imported document hasn't been parsed yet
+    pub fn is_lazy(&self) -> bool {
+        self.lazy.is_some()
+    }
+
+    /// Parses the document of a lazy import and assigns its references.
+    /// Returns false if the document was already parsed
+    pub fn materialize(&mut self) -> bool {
+        if let Some(lazy) = self.lazy.take() {
+            let mut document = (lazy.0)();
+            document.process_lazy_import();
+            self.document = Some(document);
+
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Document {
+    /// Parses all lazy imports of the document and includes them like the other imports.
+    /// Returns the number of imports that were parsed
+    pub fn materialize_imports(&mut self) -> usize {
+        let mut count = 0;
+
+        // the imports of materialized documents are lazy as well
+        loop {
+            self.elements = self
+                .elements
+                .drain(..)
+                .flat_map(split_at_lazy_import)
+                .collect();
+            let materialized = self
+                .elements
+                .iter()
+                .filter_map(|block| match block {
+                    Block::Import(import) => Some(import.anchor.write().unwrap().materialize()),
+                    _ => None,
+                })
+                .filter(|materialized| *materialized)
+                .count();
+            if materialized == 0 {
+                break;
+            }
+            self.postprocess_imports();
+            count += materialized;
+        }
+        if count > 0 && self.is_root {
+            if self.config.lock().features.numbered_headings {
+                number_sections(&mut self.elements, "");
+            }
+            self.links.lock().assign_numbers(&self.elements);
+        }
+
+        count
+    }
+
+    /// Assigns the references of a lazily imported document that is parsed
+    /// after the importing document has been processed
+    fn process_lazy_import(&mut self) {
+        self.process_definitions();
+        self.bibliography.assign_entries_to_references();
+        self.glossary.lock().assign_entries_to_references();
+        self.footnotes.lock().assign_definitions_to_references();
+        self.process_containers();
+        self.abbreviations
+            .lock()
+            .expand_abbreviations(&mut self.elements);
+        self.process_placeholders();
+        self.process_media();
+    }
+}
+
+/// Moves the first lazy import of a section and the blocks after it out of the section.
+/// Imports are added to the section they follow when parsing so that the imported
+/// blocks need to be nested again like the blocks of an import that is parsed eagerly
+fn split_at_lazy_import(block: Block) -> Vec<Block> {
+    match block {
+        Block::Section(mut section) => {
+            let index = section.elements.iter().position(|block| match block {
+                Block::Import(import) => import.anchor.read().unwrap().is_lazy(),
+                _ => false,
+            });
+            let rest = index
+                .map(|index| section.elements.split_off(index))
+                .unwrap_or_default();
+            let mut blocks = vec![Block::Section(section)];
+            blocks.extend(rest);
+
+            blocks
+        }
+        block => vec![block],
+    }
+}
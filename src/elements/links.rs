@@ -0,0 +1,257 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::tokens::URL_SCHEME_SEPARATOR;
+use crate::elements::visitor::{walk_block, walk_inline, walk_line, Visitor};
+use crate::elements::{Block, Document, Image, Inline, Line, Span};
+use crate::parser::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, Severity};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// A link of the document
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Link {
+    pub target: String,
+    pub kind: LinkKind,
+    pub span: Span,
+}
+
+/// What a link points to
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum LinkKind {
+    /// An url with a scheme like https://example.com
+    Url,
+    /// A path to a local file
+    File,
+    /// An anchor inside the document like #section
+    Anchor,
+}
+
+/// A file that is embedded into or imported by the document
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Asset {
+    pub path: String,
+    pub kind: AssetKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum AssetKind {
+    Image,
+    Import,
+}
+
+impl Document {
+    /// Returns all links of the document including the ones of imports
+    pub fn links(&self) -> impl Iterator<Item = Link> {
+        let mut collector = LinkCollector::default();
+        collector.visit_document(self);
+
+        collector.links.into_iter()
+    }
+
+    /// Returns all images and imported files of the document including the ones of imports.
+    /// Imports that are included into the document directly aren't listed, only their images.
+    pub fn assets(&self) -> impl Iterator<Item = Asset> {
+        let mut collector = LinkCollector::default();
+        collector.visit_document(self);
+
+        collector.assets.into_iter()
+    }
+}
+
+impl Document {
+    /// Checks that the local files the document links to exist
+    /// and that the anchors of links to sections resolve.
+    /// Remote urls are checked by `check_remote_links`
+    pub fn check_links(&self) -> Diagnostics {
+        let mut collector = LinkCollector::default();
+        collector.visit_document(self);
+        let dir = self
+            .path
+            .as_deref()
+            .and_then(|p| Path::new(p).parent())
+            .unwrap_or_else(|| Path::new(""));
+        let mut diagnostics = Diagnostics::new();
+
+        for link in &collector.links {
+            match link.kind {
+                LinkKind::Anchor => {
+                    let anchor = link.target.trim_start_matches('#');
+                    if !collector.anchors.contains(anchor) {
+                        diagnostics.add(self.create_link_diagnostic(
+                            Severity::Error,
+                            DiagnosticCode::BrokenAnchor,
+                            format!("The anchor '{}' doesn't exist", anchor),
+                            link.span,
+                        ))
+                    }
+                }
+                LinkKind::File => {
+                    let path = link.target.split(['#', '?']).next().unwrap_or_default();
+                    if !dir.join(path).exists() {
+                        diagnostics.add(self.create_link_diagnostic(
+                            Severity::Error,
+                            DiagnosticCode::BrokenLink,
+                            format!("The file '{}' doesn't exist", path),
+                            link.span,
+                        ))
+                    }
+                }
+                LinkKind::Url => {}
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Checks that the http(s) urls the document links to respond with a success status.
+    /// Every url is only requested once.
+    #[cfg(feature = "remote-links")]
+    pub fn check_remote_links(&self) -> Diagnostics {
+        let client = reqwest::blocking::Client::new();
+        let mut checked = HashSet::new();
+        let mut diagnostics = Diagnostics::new();
+
+        for link in self.links().filter(|l| l.kind == LinkKind::Url) {
+            if !link.target.starts_with("http://") && !link.target.starts_with("https://") {
+                continue;
+            }
+            if !checked.insert(link.target.clone()) {
+                continue;
+            }
+            let response = client
+                .head(&link.target)
+                .send()
+                .and_then(|r| match r.status() {
+                    reqwest::StatusCode::METHOD_NOT_ALLOWED => client.get(&link.target).send(),
+                    _ => Ok(r),
+                });
+            let message = match response {
+                Ok(r) if r.status().is_success() => continue,
+                Ok(r) => format!("'{}' responded with {}", link.target, r.status()),
+                Err(e) => format!("'{}' couldn't be reached: {}", link.target, e),
+            };
+            diagnostics.add(self.create_link_diagnostic(
+                Severity::Warning,
+                DiagnosticCode::UnreachableUrl,
+                message,
+                link.span,
+            ))
+        }
+
+        diagnostics
+    }
+
+    /// Returns the anchors of all sections, anchor elements and ids of blocks
+    pub(crate) fn get_anchors(&self) -> HashSet<String> {
+        let mut collector = LinkCollector::default();
+        collector.visit_document(self);
+
+        collector.anchors
+    }
+
+    fn create_link_diagnostic(
+        &self,
+        severity: Severity,
+        code: DiagnosticCode,
+        message: String,
+        span: Span,
+    ) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code,
+            message,
+            path: self.path.clone(),
+            span,
+        }
+    }
+}
+
+impl Link {
+    fn new(target: &str, span: Span) -> Self {
+        let kind = if target.starts_with('#') {
+            LinkKind::Anchor
+        } else if target.contains(URL_SCHEME_SEPARATOR) || target.starts_with("mailto:") {
+            LinkKind::Url
+        } else {
+            LinkKind::File
+        };
+
+        Self {
+            target: target.to_string(),
+            kind,
+            span,
+        }
+    }
+}
+
+impl Asset {
+    fn from_image(image: &Image) -> Self {
+        Self {
+            path: image.url.url.clone(),
+            kind: AssetKind::Image,
+            span: image.span,
+        }
+    }
+}
+
+#[derive(Default)]
+struct LinkCollector {
+    links: Vec<Link>,
+    assets: Vec<Asset>,
+    /// The anchors of sections, anchor elements and ids of blocks that links can point to
+    anchors: HashSet<String>,
+}
+
+impl Visitor for LinkCollector {
+    fn visit_block(&mut self, block: &Block) {
+        if let Some(id) = block.get_id() {
+            self.anchors.insert(id);
+        }
+        match block {
+            Block::Section(section) => {
+                self.anchors.insert(section.header.anchor.clone());
+            }
+            Block::Figure(figure) => self.assets.push(Asset::from_image(&figure.image)),
+            Block::Import(import) => self.assets.push(Asset {
+                path: import.path.clone(),
+                kind: AssetKind::Import,
+                span: import.span,
+            }),
+            _ => {}
+        }
+        walk_block(self, block)
+    }
+
+    fn visit_line(&mut self, line: &Line) {
+        match line {
+            Line::RefLink(link) => self.links.push(Link {
+                target: link.reference.clone(),
+                kind: LinkKind::Anchor,
+                span: link.span,
+            }),
+            Line::Anchor(anchor) => {
+                self.anchors.insert(anchor.key.clone());
+            }
+            _ => {}
+        }
+        walk_line(self, line)
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Url(url) => self.links.push(Link::new(&url.url, url.span)),
+            Inline::Image(image) => self.assets.push(Asset::from_image(image)),
+            Inline::Anchor(anchor) => {
+                self.anchors.insert(anchor.key.clone());
+            }
+            _ => {}
+        }
+        walk_inline(self, inline)
+    }
+}
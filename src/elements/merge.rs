@@ -0,0 +1,164 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::visitor::{walk_block_mut, walk_inline_mut, walk_line_mut, VisitorMut};
+use crate::elements::*;
+use std::collections::HashSet;
+
+impl Document {
+    /// Appends the blocks of the other document like an import at the end of the document.
+    ///
+    /// The headers of the other document are shifted by the given levels or below the last section.
+    /// Anchors that already exist get a unique name and the links pointing to them are changed.
+    /// The footnotes, glossary references, images and placeholders are moved into the document
+    /// and footnotes are numbered after the existing ones. Front matter entries and
+    /// placeholder handlers of the other document are only added if they don't exist yet.
+    pub fn merge(&mut self, mut other: Document, shift: Option<HeaderShift>) {
+        let levels = match shift {
+            Some(HeaderShift::Levels(levels)) => levels,
+            Some(HeaderShift::Nesting) => self.elements.last().map(get_nesting).unwrap_or(0),
+            None => 0,
+        };
+        shift_headers(&mut other.elements, levels);
+        self.rename_anchors(&mut other);
+
+        if !Arc::ptr_eq(&self.footnotes, &other.footnotes) {
+            self.footnotes.lock().append(&mut other.footnotes.lock());
+        }
+        if !Arc::ptr_eq(&self.glossary, &other.glossary) {
+            let mut glossary = self.glossary.lock();
+            glossary.append(&mut other.glossary.lock());
+            glossary.assign_entries_to_references();
+        }
+        if !Arc::ptr_eq(&self.images, &other.images) {
+            self.images.lock().append(&mut other.images.lock());
+        }
+        if !Arc::ptr_eq(&self.links, &other.links) {
+            self.links
+                .lock()
+                .add_document(other.path.as_deref(), &other.elements);
+        }
+        self.placeholders.append(&mut other.placeholders);
+        self.placeholder_handlers
+            .extend(&other.placeholder_handlers);
+        self.container_handlers.extend(&other.container_handlers);
+        for stylesheet in other.stylesheets {
+            let path = stylesheet.lock().path.clone();
+            if !self.stylesheets.iter().any(|s| s.lock().path == path) {
+                self.stylesheets.push(stylesheet);
+            }
+        }
+        match (&mut self.front_matter, other.front_matter) {
+            (Some(front_matter), Some(other)) => {
+                for (key, value) in other.data {
+                    front_matter.data.entry(key).or_insert(value);
+                }
+            }
+            (front_matter, other) if front_matter.is_none() => *front_matter = other,
+            _ => {}
+        }
+
+        self.elements.append(&mut other.elements);
+        self.postprocess_imports();
+        if self.is_root {
+            if self.config.lock().features.numbered_headings {
+                number_sections(&mut self.elements, "");
+            }
+            self.links.lock().assign_numbers(&self.elements);
+        }
+    }
+
+    /// Gives the sections and anchors of the other document that already exist
+    /// in this document a unique name and changes the links pointing to them
+    fn rename_anchors(&self, other: &mut Document) {
+        let mut renamer = AnchorRenamer {
+            anchors: self.get_anchors(),
+            renamed: HashMap::new(),
+        };
+        renamer.visit_document(other);
+
+        if !renamer.renamed.is_empty() {
+            LinkRewriter {
+                renamed: renamer.renamed,
+            }
+            .visit_document(other);
+        }
+    }
+}
+
+/// Renames the anchors that are already used
+struct AnchorRenamer {
+    anchors: HashSet<String>,
+    renamed: HashMap<String, String>,
+}
+
+impl AnchorRenamer {
+    fn rename(&mut self, anchor: &mut String) {
+        let mut name = anchor.clone();
+        let mut index = 1;
+        while self.anchors.contains(&name) {
+            index += 1;
+            name = format!("{}-{}", anchor, index);
+        }
+        if name != *anchor {
+            self.renamed.insert(anchor.clone(), name.clone());
+            *anchor = name.clone();
+        }
+        self.anchors.insert(name);
+    }
+}
+
+impl VisitorMut for AnchorRenamer {
+    fn visit_block(&mut self, block: &mut Block) {
+        if let Block::Section(section) = block {
+            self.rename(&mut section.header.anchor);
+        }
+        walk_block_mut(self, block)
+    }
+
+    fn visit_line(&mut self, line: &mut Line) {
+        if let Line::Anchor(anchor) = line {
+            self.rename(&mut anchor.key);
+        }
+        walk_line_mut(self, line)
+    }
+
+    fn visit_inline(&mut self, inline: &mut Inline) {
+        if let Inline::Anchor(anchor) = inline {
+            self.rename(&mut anchor.key);
+        }
+        walk_inline_mut(self, inline)
+    }
+}
+
+/// Changes the links to renamed anchors
+struct LinkRewriter {
+    renamed: HashMap<String, String>,
+}
+
+impl VisitorMut for LinkRewriter {
+    fn visit_line(&mut self, line: &mut Line) {
+        if let Line::RefLink(link) = line {
+            if let Some(anchor) = self.renamed.get(&link.reference) {
+                link.reference = anchor.clone();
+            }
+        }
+        walk_line_mut(self, line)
+    }
+
+    fn visit_inline(&mut self, inline: &mut Inline) {
+        if let Inline::Url(url) = inline {
+            let anchor = url
+                .url
+                .strip_prefix('#')
+                .and_then(|anchor| self.renamed.get(anchor));
+            if let Some(anchor) = anchor {
+                url.url = format!("#{}", anchor);
+            }
+        }
+        walk_inline_mut(self, inline)
+    }
+}
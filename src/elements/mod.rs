@@ -4,15 +4,37 @@
  * See LICENSE for more information.
  */
 
+pub mod builder;
+pub mod containers;
+pub mod diff;
+pub mod lazy;
+pub mod links;
+pub mod merge;
+pub mod selector;
+pub mod stats;
 pub mod tokens;
+pub mod transforms;
+pub mod visitor;
 
+use crate::elements::containers::{ContainerHandler, ContainerHandlers};
+use crate::elements::lazy::LazyDocument;
 use crate::format::PlaceholderTemplate;
+use crate::parser::diagnostics::Diagnostic;
+use crate::parser::events::{get_events, Event, Tag};
+use crate::parser::incremental::DocumentSource;
+use crate::parser::metrics::{measure, ParseMetrics};
+use crate::references::abbreviations::{Abbreviation, AbbreviationDefinition, AbbreviationManager};
+use crate::references::footnotes::{FootnoteDefinition, FootnoteManager, FootnoteReference};
 use crate::references::glossary::{GlossaryManager, GlossaryReference};
-use crate::references::placeholders::ProcessPlaceholders;
+use crate::references::links::{normalize_name, LinkManager};
+use crate::references::placeholders::{
+    PlaceholderHandler, PlaceholderHandlers, ProcessPlaceholders,
+};
 use crate::references::templates::{Template, TemplateVariable};
 use crate::settings::Settings;
 use crate::utils::downloads::{DownloadManager, PendingDownload};
 use crate::utils::image_converting::{ImageConverter, PendingImage};
+use crate::utils::interning::StringInterner;
 use asciimath_rs::elements::special::Expression;
 use bibliographix::bib_manager::BibManager;
 use bibliographix::bibliography::bibliography_entry::BibliographyEntryReference;
@@ -20,8 +42,11 @@ use bibliographix::references::bib_reference::BibRefAnchor;
 use image::ImageFormat;
 use mime::Mime;
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
 pub const SECTION: &str = "section";
@@ -32,7 +57,7 @@ pub const CODE_BLOCK: &str = "code_block";
 pub const QUOTE: &str = "quote";
 pub const IMPORT: &str = "import";
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum MetadataValue {
     String(String),
     Integer(i64),
@@ -42,28 +67,37 @@ pub enum MetadataValue {
     Template(Template),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Element {
     Block(Box<Block>),
     Line(Box<Line>),
     Inline(Box<Inline>),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Block {
     Section(Section),
     Paragraph(Paragraph),
     List(List),
     Table(Table),
     CodeBlock(CodeBlock),
+    Diagram(Diagram),
+    Html(Html),
+    Comment(Comment),
     MathBlock(MathBlock),
     Quote(Quote),
+    DefinitionList(DefinitionList),
+    Figure(Figure),
+    Aligned(Aligned),
+    Columns(Columns),
+    CustomContainer(CustomContainer),
+    PageBreak(PageBreak),
     Import(Import),
     Placeholder(Arc<RwLock<Placeholder>>),
     Null,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Line {
     Text(TextLine),
     Ruler(Ruler),
@@ -71,6 +105,20 @@ pub enum Line {
     Anchor(Anchor),
     Centered(Centered),
     BibEntry(BibEntry),
+    FootnoteDefinition(Arc<Mutex<FootnoteDefinition>>),
+    AbbreviationDefinition(AbbreviationDefinition),
+}
+
+/// The position of an element in the source text of its document.
+/// `start` and `end` are byte offsets, `line` and `column`
+/// describe the position of `start` and are zero based.
+/// The column is counted in characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
 }
 
 #[derive(Clone, Debug)]
@@ -85,94 +133,305 @@ pub struct Document {
     pub images: Arc<Mutex<ImageConverter>>,
     pub stylesheets: Vec<Arc<Mutex<PendingDownload>>>,
     pub glossary: Arc<Mutex<GlossaryManager>>,
+    pub footnotes: Arc<Mutex<FootnoteManager>>,
+    pub abbreviations: Arc<Mutex<AbbreviationManager>>,
+    pub links: Arc<Mutex<LinkManager>>,
+    pub placeholder_handlers: PlaceholderHandlers,
+    pub container_handlers: ContainerHandlers,
+    pub front_matter: Option<InlineMetadata>,
+    /// The html template the rendered document is inserted into
+    pub html_template: Option<String>,
+    /// The source the document was parsed from
+    pub(crate) source: Option<Box<DocumentSource>>,
+    /// The shared text of the inlines of the document and its imports
+    pub(crate) strings: Arc<Mutex<StringInterner>>,
+    /// Problems found while processing the document that the parser reports
+    pub(crate) diagnostics: Vec<Diagnostic>,
 }
 
-#[derive(Clone, Debug)]
+/// Options for the table of contents
+#[derive(Clone, Debug, Default)]
+pub struct TocOptions {
+    pub ordered: bool,
+    /// Prefixes the entries with their section number like 1.2
+    pub numbered: bool,
+    /// The maximum nesting depth of included sections
+    pub max_depth: Option<usize>,
+}
+
+/// An entry of the outline of a document
+#[derive(Clone, Debug, Serialize)]
+pub struct OutlineEntry {
+    /// The text of the header without any formatting
+    pub title: String,
+    pub anchor: String,
+    pub level: u8,
+    /// The hierarchical number of the section like 1.2
+    pub number: Option<String>,
+    pub span: Span,
+    pub children: Vec<OutlineEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Section {
     pub(crate) header: Header,
     pub(crate) elements: Vec<Block>,
     pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Header {
     pub(crate) size: u8,
     pub(crate) line: Line,
     pub(crate) anchor: String,
+    pub(crate) number: Option<String>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Paragraph {
     pub(crate) elements: Vec<Line>,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct List {
     pub(crate) ordered: bool,
     pub items: Vec<ListItem>,
+    /// If the items are separated by blank lines or contain blocks separated by blank lines.
+    /// The text of the items of loose lists is rendered as a paragraph
+    #[serde(default)]
+    pub loose: bool,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ListItem {
     pub(crate) text: Line,
     pub(crate) level: u16,
     pub(crate) ordered: bool,
     pub(crate) children: Vec<ListItem>,
+    /// The blocks following the text of the item that are indented to the text
+    #[serde(default)]
+    pub(crate) blocks: Vec<Block>,
+    pub checked: Option<bool>,
+    /// The character of the marker of unordered items
+    #[serde(default)]
+    pub bullet: Option<char>,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Table {
     pub(crate) header: Row,
     pub(crate) rows: Vec<Row>,
+    pub(crate) alignments: Vec<Alignment>,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+/// The alignment of a table column as defined by the colons in the delimiter row
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Row {
     pub(crate) cells: Vec<Cell>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Cell {
     pub(crate) text: Line,
+    pub(crate) colspan: usize,
+    pub(crate) rowspan: usize,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CodeBlock {
     pub(crate) language: String,
     pub(crate) code: String,
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+/// A code block with the source of a diagram
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Diagram {
+    pub(crate) kind: DiagramKind,
+    pub(crate) source: String,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
+}
+
+/// The language a diagram is written in
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DiagramKind {
+    Mermaid,
+    Graphviz,
+}
+
+/// Raw html that is passed through to the output
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Html {
+    pub(crate) value: String,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
+}
+
+/// A comment in the source that isn't rendered
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Comment {
+    pub(crate) value: String,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
+}
+
+/// An image that stands on its own line followed by a caption
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Figure {
+    pub(crate) image: Image,
+    pub(crate) caption: TextLine,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Quote {
     pub(crate) metadata: Option<InlineMetadata>,
-    pub(crate) text: Vec<TextLine>,
+    pub(crate) elements: Vec<Block>,
+    #[serde(default)]
+    pub attribution: Option<Attribution>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+/// Blocks that are aligned as a whole, like the content of a title page
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Aligned {
+    pub alignment: Alignment,
+    pub(crate) content: Vec<Block>,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
+}
+
+/// Blocks laid out in columns. The content is split into columns with `:::column` lines
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Columns {
+    /// The number of columns of the layout. Content that isn't split
+    /// flows over the columns
+    pub count: usize,
+    pub(crate) columns: Vec<Vec<Block>>,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
+}
+
+/// Blocks in a container with a custom name like `:::warning` that are
+/// transformed by the handler registered for the name
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CustomContainer {
+    pub name: String,
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub(crate) content: Vec<Block>,
+    /// The block the handler of the container transformed it into
+    #[serde(default)]
+    pub(crate) value: Option<Box<Block>>,
+    pub span: Span,
+}
+
+/// The author and source of a quote given with a `-- Author, Source` line at its end
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Attribution {
+    pub author: String,
+    pub source: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DefinitionList {
+    pub(crate) items: Vec<Definition>,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
+}
+
+/// A term with one or more definitions
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Definition {
+    pub(crate) term: TextLine,
+    pub(crate) definitions: Vec<TextLine>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Import {
     pub(crate) path: String,
     pub(crate) anchor: Arc<RwLock<ImportAnchor>>,
+    pub(crate) shift: Option<HeaderShift>,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+/// How the headers of an imported document are shifted
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum HeaderShift {
+    /// Shifts the headers below the section that contains the import
+    Nesting,
+    /// Shifts the headers by a fixed number of levels
+    Levels(u8),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ImportAnchor {
     pub(crate) document: Option<Document>,
+    /// Loads the document of an import that is parsed when it's rendered or materialized
+    #[serde(skip)]
+    pub(crate) lazy: Option<LazyDocument>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InlineMetadata {
     pub(crate) data: HashMap<String, MetadataValue>,
 }
 
-#[derive(Clone, Debug)]
-pub struct Ruler {}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ruler {
+    pub span: Span,
+}
 
-#[derive(Clone, Debug)]
+/// Starts a new page in paginated output like pdf or latex documents
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PageBreak {
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TextLine {
     pub subtext: Vec<Inline>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Inline {
     Plain(PlainText),
     Bold(BoldText),
@@ -181,6 +440,7 @@ pub enum Inline {
     Striked(StrikedText),
     Monospace(MonospaceText),
     Superscript(SuperscriptText),
+    Subscript(SubscriptText),
     Url(Url),
     Image(Image),
     Placeholder(Arc<RwLock<Placeholder>>),
@@ -190,118 +450,195 @@ pub enum Inline {
     Math(Math),
     BibReference(Arc<RwLock<BibReference>>),
     GlossaryReference(Arc<Mutex<GlossaryReference>>),
+    FootnoteReference(Arc<Mutex<FootnoteReference>>),
     TemplateVar(Arc<RwLock<TemplateVariable>>),
     CharacterCode(CharacterCode),
     LineBreak,
     Arrow(Arrow),
     Anchor(Anchor),
+    Html(Html),
+    Comment(Comment),
+    Abbreviation(Abbreviation),
+    Spoiler(SpoilerText),
+    Highlight(HighlightText),
+    Styled(StyledText),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PlainText {
-    pub(crate) value: String,
+    pub(crate) value: Arc<str>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BoldText {
     pub(crate) value: Vec<Inline>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ItalicText {
     pub(crate) value: Vec<Inline>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UnderlinedText {
     pub(crate) value: Vec<Inline>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StrikedText {
     pub(crate) value: Vec<Inline>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+/// Text that is hidden until the reader reveals it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpoilerText {
+    pub(crate) value: Vec<Inline>,
+    pub span: Span,
+}
+
+/// Highlighted text with an optional color set in its metadata
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HighlightText {
+    pub(crate) value: Vec<Inline>,
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MonospaceText {
     pub(crate) value: String,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SuperscriptText {
     pub(crate) value: Vec<Inline>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SubscriptText {
+    pub(crate) value: Vec<Inline>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Checkbox {
     pub(crate) value: bool,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Url {
     pub description: Option<Vec<Inline>>,
     pub url: String,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Image {
     pub(crate) url: Url,
     pub(crate) metadata: Option<InlineMetadata>,
     pub(crate) image_data: Arc<Mutex<PendingImage>>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Placeholder {
     pub(crate) name: String,
     pub(crate) value: Option<Element>,
     pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RefLink {
     pub(crate) description: TextLine,
     pub(crate) reference: String,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Anchor {
     pub(crate) inner: Box<Line>,
     pub(crate) key: String,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Centered {
     pub(crate) line: TextLine,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Emoji {
-    pub(crate) value: char,
+    pub(crate) value: String,
     pub(crate) name: String,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Colored {
     pub(crate) value: Box<Inline>,
     pub(crate) color: String,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+/// An inline span with metadata attached to it, e.g. *text*[color=red size=1.2em]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StyledText {
+    pub(crate) value: Box<Inline>,
+    pub(crate) metadata: InlineMetadata,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Math {
-    pub(crate) expression: Expression,
+    pub(crate) expression: MathExpression,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MathBlock {
-    pub(crate) expression: Expression,
+    pub(crate) expression: MathExpression,
+    #[serde(default)]
+    pub(crate) metadata: Option<InlineMetadata>,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+/// The content of a math element which is either parsed asciimath
+/// or raw TeX that is rendered by MathJax or KaTeX
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "MathSource", into = "MathSource")]
+pub enum MathExpression {
+    AsciiMath {
+        source: String,
+        expression: Expression,
+    },
+    Tex(String),
+}
+
+/// The serialized form of a math expression.
+/// Asciimath is stored as its source and parsed again when deserializing
+#[derive(Serialize, Deserialize)]
+enum MathSource {
+    AsciiMath(String),
+    Tex(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CharacterCode {
     pub(crate) code: String,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Arrow {
     RightArrow,
     LeftArrow,
@@ -327,6 +664,16 @@ impl Document {
             downloads: Arc::new(Mutex::new(DownloadManager::new())),
             images: Arc::new(Mutex::new(ImageConverter::new())),
             glossary: Arc::new(Mutex::new(GlossaryManager::new())),
+            footnotes: Arc::new(Mutex::new(FootnoteManager::new())),
+            abbreviations: Arc::new(Mutex::new(AbbreviationManager::new())),
+            links: Arc::new(Mutex::new(LinkManager::new())),
+            placeholder_handlers: PlaceholderHandlers::new(),
+            container_handlers: ContainerHandlers::new(),
+            front_matter: None,
+            html_template: None,
+            source: None,
+            strings: Arc::new(Mutex::new(StringInterner::new())),
+            diagnostics: Vec::new(),
         }
     }
 
@@ -343,6 +690,16 @@ impl Document {
             downloads: Arc::clone(&self.downloads),
             images: Arc::clone(&self.images),
             glossary: Arc::clone(&self.glossary),
+            footnotes: Arc::clone(&self.footnotes),
+            abbreviations: Arc::clone(&self.abbreviations),
+            links: Arc::clone(&self.links),
+            placeholder_handlers: self.placeholder_handlers.clone(),
+            container_handlers: self.container_handlers.clone(),
+            front_matter: None,
+            html_template: None,
+            source: None,
+            strings: Arc::clone(&self.strings),
+            diagnostics: Vec::new(),
         }
     }
 
@@ -354,30 +711,81 @@ impl Document {
         self.placeholders.push(placeholder);
     }
 
-    pub fn create_toc(&self, ordered: bool) -> List {
-        let mut list = List::new();
-        list.ordered = ordered;
-        self.elements.iter().for_each(|e| match e {
-            Block::Section(sec) => {
-                if !sec.is_hidden_in_toc() {
-                    let mut item =
-                        ListItem::new(Line::RefLink(sec.header.get_anchor()), 1, ordered);
-                    item.children.append(&mut sec.get_toc_list(ordered).items);
-                    list.add_item(item);
+    /// Registers a handler that resolves placeholders with the given name
+    pub fn register_placeholder_handler<H: PlaceholderHandler + 'static>(
+        &mut self,
+        name: &str,
+        handler: H,
+    ) {
+        self.placeholder_handlers.register(name, handler)
+    }
+
+    /// Registers a handler that transforms custom containers with the given name
+    pub fn register_container_handler<H: ContainerHandler + 'static>(
+        &mut self,
+        name: &str,
+        handler: H,
+    ) {
+        self.container_handlers.register(name, handler)
+    }
+
+    /// Returns the referenced footnotes ordered by their first occurrence
+    pub fn get_footnotes(&self) -> Vec<Arc<Mutex<FootnoteDefinition>>> {
+        self.footnotes.lock().get_footnotes()
+    }
+
+    /// Returns the number of words in the text of the document without code blocks
+    pub fn get_word_count(&self) -> usize {
+        let mut code_depth = 0;
+        let mut count = 0;
+
+        for event in get_events(&self.elements) {
+            match event {
+                Event::Start(Tag::CodeBlock { .. }) | Event::Start(Tag::Diagram { .. }) => {
+                    code_depth += 1
                 }
-            }
-            Block::Import(imp) => {
-                let anchor = imp.anchor.read().unwrap();
-                if let Some(doc) = &anchor.document {
-                    list.items.append(&mut doc.create_toc(ordered).items)
+                Event::End(Tag::CodeBlock { .. }) | Event::End(Tag::Diagram { .. }) => {
+                    code_depth -= 1
+                }
+                Event::Text(text) | Event::Code(text) if code_depth == 0 => {
+                    count += text.split_whitespace().count()
                 }
+                _ => {}
             }
-            _ => {}
-        });
+        }
+
+        count
+    }
+
+    /// Creates the table of contents of all sections including the ones of imports
+    pub fn create_toc(&self, options: &TocOptions) -> List {
+        let mut list = List::new();
+        list.ordered = options.ordered;
+        add_toc_items(&self.elements, options, 1, "", &mut 0, &mut list);
 
         list
     }
 
+    /// Returns the nested headers of all sections including the ones of imports.
+    /// The spans of entries from imported documents refer to the imported file
+    pub fn outline(&self) -> Vec<OutlineEntry> {
+        let mut entries = Vec::new();
+        add_outline_entries(&self.elements, &mut entries);
+
+        entries
+    }
+
+    /// Removes all elements except for the section with the given anchor or title and its subsections.
+    /// Returns false if the document doesn't contain the section in which case it becomes empty
+    pub fn retain_section(&mut self, anchor: &str) -> bool {
+        if let Some(section) = take_section(std::mem::take(&mut self.elements), anchor) {
+            self.elements = vec![Block::Section(section)];
+            true
+        } else {
+            false
+        }
+    }
+
     /// Processes section and import elements
     ///
     /// if it encounters a section it checks if the sections is of smaller order than the previous one
@@ -410,14 +818,28 @@ impl Document {
                     let anchor = &mut arc_anchor.write().unwrap();
 
                     if let Some(doc) = &mut anchor.document {
+                        let shift = match imp.shift {
+                            Some(HeaderShift::Levels(levels)) => levels,
+                            Some(HeaderShift::Nesting) => last_section
+                                .and_then(|(_, last_pos)| new_order.get(last_pos))
+                                .map(get_nesting)
+                                .unwrap_or(0),
+                            None => 0,
+                        };
+                        shift_headers(&mut doc.elements, shift);
                         self.placeholders.append(&mut doc.placeholders);
                         doc.elements.reverse();
                         self.elements.append(&mut doc.elements);
                         anchor.document = None;
                         continue;
-                    } else {
-                        new_order.push(Block::Import(imp));
                     }
+                    if let Some((_, last_pos)) = last_section {
+                        if let Block::Section(p_sec) = &mut new_order[last_pos] {
+                            p_sec.add_element(Block::Import(imp));
+                            continue;
+                        }
+                    }
+                    new_order.push(Block::Import(imp))
                 }
                 _ => {
                     if let Some((_, last_pos)) = last_section {
@@ -436,20 +858,78 @@ impl Document {
     }
 
     pub fn post_process(&mut self) {
+        self.post_process_with_metrics(&mut ParseMetrics::default())
+    }
+
+    /// Processes the document and records the time spent processing placeholders
+    pub(crate) fn post_process_with_metrics(&mut self, metrics: &mut ParseMetrics) {
+        self.links
+            .lock()
+            .add_document(self.path.as_deref(), &self.elements);
         self.postprocess_imports();
         if self.is_root {
+            if self.config.lock().features.numbered_headings {
+                number_sections(&mut self.elements, "");
+            }
+            self.links.lock().assign_numbers(&self.elements);
             self.process_definitions();
             self.bibliography.assign_entries_to_references();
             self.glossary.lock().assign_entries_to_references();
-            self.process_placeholders();
+            self.footnotes.lock().assign_definitions_to_references();
+            self.process_containers();
+            self.abbreviations
+                .lock()
+                .expand_abbreviations(&mut self.elements);
+            measure(&mut metrics.placeholders, || self.process_placeholders());
             self.process_media();
         }
     }
 
+    /// Returns the paths of all images that are referenced by the document and its imports
+    pub fn get_asset_paths(&self) -> Vec<PathBuf> {
+        self.images.lock().get_paths()
+    }
+
+    /// Copies the images into the assets directory next to the output directory
+    /// if one is configured. Otherwise the images stay embedded into the document.
+    pub fn collect_assets(&self, output_dir: &Path) -> io::Result<()> {
+        if let Some(assets) = self.config.lock().images.assets.clone() {
+            self.images
+                .lock()
+                .copy_all(&output_dir.join(&assets), &assets)?;
+        }
+
+        Ok(())
+    }
+
+    /// Downloads the media that is embedded into the document concurrently
+    /// so that it doesn't need to be downloaded when the document is processed
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn download_media_async(&self) {
+        let downloads = if self.config.lock().features.embed_external {
+            self.downloads.lock().get_downloads()
+        } else if self.config.lock().style.inline_css {
+            self.stylesheets.clone()
+        } else {
+            Vec::new()
+        };
+
+        futures::future::join_all(
+            downloads
+                .iter()
+                .map(|download| PendingDownload::download_async(download)),
+        )
+        .await;
+    }
+
     fn process_media(&self) {
         let downloads = Arc::clone(&self.downloads);
         if self.config.lock().features.embed_external {
             downloads.lock().download_all();
+        } else if self.config.lock().style.inline_css {
+            self.stylesheets
+                .iter()
+                .for_each(|stylesheet| stylesheet.lock().download());
         }
         if let Some(s) = &self.config.lock().images.format {
             if let Some(format) = ImageFormat::from_extension(s) {
@@ -478,12 +958,79 @@ impl Document {
     }
 }
 
+impl Block {
+    /// Returns the metadata of the block given with its own syntax
+    /// or with a `[key=value]` line before the block
+    pub fn get_metadata(&self) -> Option<&InlineMetadata> {
+        match self {
+            Block::Section(section) => section.metadata.as_ref(),
+            Block::Paragraph(paragraph) => paragraph.metadata.as_ref(),
+            Block::List(list) => list.metadata.as_ref(),
+            Block::Table(table) => table.metadata.as_ref(),
+            Block::CodeBlock(code_block) => code_block.metadata.as_ref(),
+            Block::Diagram(diagram) => diagram.metadata.as_ref(),
+            Block::Html(html) => html.metadata.as_ref(),
+            Block::Comment(comment) => comment.metadata.as_ref(),
+            Block::MathBlock(math_block) => math_block.metadata.as_ref(),
+            Block::Quote(quote) => quote.metadata.as_ref(),
+            Block::DefinitionList(list) => list.metadata.as_ref(),
+            Block::Figure(figure) => figure.metadata.as_ref(),
+            Block::Aligned(aligned) => aligned.metadata.as_ref(),
+            Block::Columns(columns) => columns.metadata.as_ref(),
+            Block::CustomContainer(container) => container.metadata.as_ref(),
+            Block::PageBreak(page_break) => page_break.metadata.as_ref(),
+            Block::Import(import) => import.metadata.as_ref(),
+            Block::Placeholder(_) | Block::Null => None,
+        }
+    }
+
+    /// Returns the id given with the `id` key
+    pub fn get_id(&self) -> Option<String> {
+        self.get_metadata()?.get_string("id")
+    }
+
+    /// Returns the classes given with the `class` key
+    pub fn get_class(&self) -> Option<String> {
+        self.get_metadata()?.get_string("class")
+    }
+
+    /// Returns the metadata and span of the block to attach attributes to it.
+    /// Placeholders use their metadata as arguments and don't take attributes
+    pub(crate) fn get_attribute_target(
+        &mut self,
+    ) -> Option<(&mut Option<InlineMetadata>, &mut Span)> {
+        match self {
+            Block::Section(section) => Some((&mut section.metadata, &mut section.span)),
+            Block::Paragraph(paragraph) => Some((&mut paragraph.metadata, &mut paragraph.span)),
+            Block::List(list) => Some((&mut list.metadata, &mut list.span)),
+            Block::Table(table) => Some((&mut table.metadata, &mut table.span)),
+            Block::CodeBlock(code_block) => Some((&mut code_block.metadata, &mut code_block.span)),
+            Block::Diagram(diagram) => Some((&mut diagram.metadata, &mut diagram.span)),
+            Block::Html(html) => Some((&mut html.metadata, &mut html.span)),
+            Block::Comment(comment) => Some((&mut comment.metadata, &mut comment.span)),
+            Block::MathBlock(math_block) => Some((&mut math_block.metadata, &mut math_block.span)),
+            Block::Quote(quote) => Some((&mut quote.metadata, &mut quote.span)),
+            Block::DefinitionList(list) => Some((&mut list.metadata, &mut list.span)),
+            Block::Figure(figure) => Some((&mut figure.metadata, &mut figure.span)),
+            Block::Aligned(aligned) => Some((&mut aligned.metadata, &mut aligned.span)),
+            Block::Columns(columns) => Some((&mut columns.metadata, &mut columns.span)),
+            Block::CustomContainer(container) => {
+                Some((&mut container.metadata, &mut container.span))
+            }
+            Block::PageBreak(page_break) => Some((&mut page_break.metadata, &mut page_break.span)),
+            Block::Import(import) => Some((&mut import.metadata, &mut import.span)),
+            Block::Placeholder(_) | Block::Null => None,
+        }
+    }
+}
+
 impl Section {
     pub fn new(header: Header) -> Self {
         Self {
             header,
             elements: Vec::new(),
             metadata: None,
+            span: Span::default(),
         }
     }
 
@@ -491,21 +1038,27 @@ impl Section {
         self.elements.push(element)
     }
 
-    pub fn get_toc_list(&self, ordered: bool) -> List {
-        let mut list = List::new();
+    /// Returns if the section starts on a new page because of `break=before`
+    pub fn breaks_before(&self) -> bool {
+        self.get_page_break().as_deref() == Some("before")
+    }
 
-        self.elements.iter().for_each(|e| {
-            if let Block::Section(sec) = e {
-                if !sec.is_hidden_in_toc() {
-                    let mut item =
-                        ListItem::new(Line::RefLink(sec.header.get_anchor()), 1, ordered);
-                    item.children.append(&mut sec.get_toc_list(ordered).items);
-                    list.add_item(item);
-                }
-            }
-        });
+    /// Returns if the content after the section starts on a new page because of `break=after`
+    pub fn breaks_after(&self) -> bool {
+        self.get_page_break().as_deref() == Some("after")
+    }
 
-        list
+    /// Returns if the section is printed on landscape pages because of `page=landscape`
+    pub fn is_landscape(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get_string("page"))
+            .as_deref()
+            == Some("landscape")
+    }
+
+    fn get_page_break(&self) -> Option<String> {
+        self.metadata.as_ref()?.get_string("break")
     }
 
     pub(crate) fn is_hidden_in_toc(&self) -> bool {
@@ -520,35 +1073,14 @@ impl Section {
     /// It either adds it directly to its elements or iterates through its children to
     /// add it to the fitting one
     pub(crate) fn add_section(&mut self, section: Section) {
-        if section.header.size == self.header.size + 1 {
-            self.elements.push(Block::Section(section))
-        } else {
-            let has_parent = RwLock::new(AtomicBool::new(true));
-            let iterator = self.elements.iter_mut().rev().filter(|e| {
-                if let Block::Section(sec) = e {
-                    if sec.header.size > section.header.size {
-                        has_parent.write().unwrap().store(true, Ordering::Relaxed);
-                        true
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            });
-
-            if has_parent.read().unwrap().load(Ordering::Relaxed) {
-                for sec in iterator {
-                    if let Block::Section(sec) = sec {
-                        if sec.header.size < section.header.size {
-                            sec.add_section(section);
-                            break;
-                        }
-                    }
-                }
-            } else {
-                self.elements.push(Block::Section(section));
-            }
+        let parent = self.elements.iter_mut().rev().find_map(|e| match e {
+            Block::Section(sec) => Some(sec),
+            _ => None,
+        });
+
+        match parent {
+            Some(parent) if parent.header.size < section.header.size => parent.add_section(section),
+            _ => self.elements.push(Block::Section(section)),
         }
     }
 }
@@ -559,21 +1091,43 @@ impl Header {
             size: 0,
             anchor,
             line: content,
+            number: None,
+            span: Span::default(),
         }
     }
 
+    /// Returns the hierarchical number of the header like 1.2
+    /// if headings are numbered
+    pub fn get_number(&self) -> Option<&String> {
+        self.number.as_ref()
+    }
+
     pub fn get_anchor(&self) -> RefLink {
         RefLink {
             description: self.line.as_raw_text().as_plain_line(),
             reference: self.anchor.clone(),
+            span: self.span,
         }
     }
+
+    /// Returns the text of the header without any formatting
+    pub fn get_plain_text(&self) -> String {
+        self.line
+            .as_raw_text()
+            .subtext
+            .iter()
+            .map(|i| i.as_plain_text().value)
+            .collect::<Vec<Arc<str>>>()
+            .join("")
+    }
 }
 
 impl Paragraph {
     pub fn new() -> Self {
         Self {
             elements: Vec::new(),
+            metadata: None,
+            span: Span::default(),
         }
     }
 
@@ -587,6 +1141,9 @@ impl List {
         Self {
             ordered: false,
             items: Vec::new(),
+            loose: false,
+            metadata: None,
+            span: Span::default(),
         }
     }
 
@@ -602,18 +1159,48 @@ impl ListItem {
             level,
             ordered,
             children: Vec::new(),
+            blocks: Vec::new(),
+            checked: None,
+            bullet: None,
+            metadata: None,
+            span: Span::default(),
         }
     }
 
     pub fn add_child(&mut self, child: ListItem) {
         self.children.push(child)
     }
+
+    /// Returns the custom bullet set with the `bullet` metadata key
+    pub fn get_custom_bullet(&self) -> Option<String> {
+        self.metadata.as_ref()?.get_string("bullet")
+    }
+
+    /// Returns the additional classes of the item
+    pub fn get_class(&self) -> Option<String> {
+        self.metadata.as_ref()?.get_string("class")
+    }
+
+    /// Returns the metadata values of the item as strings
+    /// except for the bullet and classes
+    pub fn get_attributes(&self) -> HashMap<String, String> {
+        let mut attributes = self
+            .metadata
+            .as_ref()
+            .map(|m| m.get_string_map())
+            .unwrap_or_default();
+        attributes.remove("bullet");
+        attributes.remove("class");
+
+        attributes
+    }
 }
 
 impl TextLine {
     pub fn new() -> Self {
         Self {
             subtext: Vec::new(),
+            span: Span::default(),
         }
     }
 
@@ -621,6 +1208,19 @@ impl TextLine {
         self.subtext.push(subtext)
     }
 
+    /// Returns if the text ends with a line break
+    pub fn ends_with_break(&self) -> bool {
+        matches!(self.subtext.last(), Some(Inline::LineBreak))
+    }
+
+    /// Returns the inline elements without the line break at the end
+    pub fn without_break(&self) -> &[Inline] {
+        match self.subtext.split_last() {
+            Some((Inline::LineBreak, subtext)) => subtext,
+            _ => &self.subtext,
+        }
+    }
+
     pub fn as_plain_line(&self) -> TextLine {
         TextLine {
             subtext: self
@@ -628,6 +1228,7 @@ impl TextLine {
                 .iter()
                 .map(|s| Inline::Plain(s.as_plain_text()))
                 .collect(),
+            span: self.span,
         }
     }
 }
@@ -637,17 +1238,70 @@ impl Table {
         Self {
             header,
             rows: Vec::new(),
+            alignments: Vec::new(),
+            metadata: None,
+            span: Span::default(),
         }
     }
 
     pub fn add_row(&mut self, row: Row) {
         self.rows.push(row)
     }
+
+    /// Returns the alignment of the column with the given index
+    pub fn get_alignment(&self, column: usize) -> Alignment {
+        self.alignments
+            .get(column)
+            .cloned()
+            .unwrap_or(Alignment::None)
+    }
+
+    /// Returns the column of every cell and the number of columns of the row
+    /// for the header followed by all rows.
+    /// Columns that are covered by a cell spanning multiple rows are skipped
+    pub fn get_layout(&self) -> Vec<(Vec<usize>, usize)> {
+        let mut covered_rows: Vec<usize> = Vec::new();
+        let mut layout = Vec::with_capacity(self.rows.len() + 1);
+
+        for row in std::iter::once(&self.header).chain(self.rows.iter()) {
+            let mut columns = Vec::with_capacity(row.cells.len());
+            let mut column = 0;
+
+            for cell in &row.cells {
+                while covered_rows.get(column).cloned().unwrap_or(0) > 0 {
+                    column += 1;
+                }
+                columns.push(column);
+                if covered_rows.len() < column + cell.colspan {
+                    covered_rows.resize(column + cell.colspan, 0);
+                }
+                for covered in &mut covered_rows[column..column + cell.colspan] {
+                    *covered = cell.rowspan;
+                }
+                column += cell.colspan;
+            }
+            let width = covered_rows
+                .iter()
+                .rposition(|c| *c > 0)
+                .map(|c| c + 1)
+                .unwrap_or(0)
+                .max(column);
+            covered_rows
+                .iter_mut()
+                .for_each(|c| *c = c.saturating_sub(1));
+            layout.push((columns, width));
+        }
+
+        layout
+    }
 }
 
 impl Row {
     pub fn new() -> Self {
-        Self { cells: Vec::new() }
+        Self {
+            cells: Vec::new(),
+            span: Span::default(),
+        }
     }
 
     pub fn add_cell(&mut self, cell: Cell) {
@@ -655,9 +1309,238 @@ impl Row {
     }
 }
 
+impl Cell {
+    pub fn new(text: Line) -> Self {
+        Self {
+            text,
+            colspan: 1,
+            rowspan: 1,
+        }
+    }
+}
+
+impl HighlightText {
+    /// Returns the color of the highlight if one is set
+    pub fn get_color(&self) -> Option<String> {
+        self.metadata.as_ref()?.get_string("color")
+    }
+}
+
+impl StyledText {
+    /// Returns the css style of the span built from the known metadata keys
+    pub fn get_style(&self) -> String {
+        [
+            ("color", "color"),
+            ("background", "background-color"),
+            ("size", "font-size"),
+            ("font", "font-family"),
+            ("weight", "font-weight"),
+        ]
+        .iter()
+        .filter_map(|(key, property)| {
+            self.metadata
+                .data
+                .get(*key)
+                .and_then(|value| value.as_style_value())
+                .map(|value| format!("{}: {};", property, value))
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+    }
+
+    /// Returns the additional classes of the span
+    pub fn get_class(&self) -> Option<String> {
+        self.metadata.get_string("class")
+    }
+
+    /// Returns all metadata values of the span as strings
+    pub fn get_attributes(&self) -> HashMap<String, String> {
+        self.metadata.get_string_map()
+    }
+}
+
+impl PlainText {
+    pub fn new(value: String) -> Self {
+        Self {
+            value: value.into(),
+            span: Span::default(),
+        }
+    }
+}
+
 impl Url {
     pub fn new(description: Option<Vec<Inline>>, url: String) -> Self {
-        Self { description, url }
+        Self {
+            description,
+            url,
+            span: Span::default(),
+        }
+    }
+}
+
+impl CodeBlock {
+    /// Returns the title given with the title attribute
+    pub fn get_title(&self) -> Option<String> {
+        self.metadata.as_ref()?.get_string("title")
+    }
+
+    /// Returns if line numbers should be displayed
+    pub fn has_line_numbers(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .map(|m| m.get_bool("lines"))
+            .unwrap_or(false)
+    }
+
+    /// Returns the line ranges given with the hl attribute
+    /// in the syntax hl=3 or hl="3-5,7"
+    pub fn get_highlighted_lines(&self) -> Vec<RangeInclusive<usize>> {
+        let ranges = match self.metadata.as_ref() {
+            Some(meta) => meta
+                .get_string("hl")
+                .or_else(|| meta.get_integer("hl").map(|i| i.to_string())),
+            None => None,
+        };
+
+        ranges
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|range| {
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                Some(start.trim().parse().ok()?..=end.trim().parse().ok()?)
+            })
+            .collect()
+    }
+
+    /// Returns if the given line is highlighted with the first line being 1
+    pub fn is_highlighted(&self, line: usize) -> bool {
+        self.get_highlighted_lines()
+            .iter()
+            .any(|range| range.contains(&line))
+    }
+}
+
+impl Diagram {
+    /// Creates a diagram from a code block if its language is the one of a diagram
+    pub fn from_code_block(code_block: &CodeBlock) -> Option<Self> {
+        Some(Self {
+            kind: DiagramKind::from_language(&code_block.language)?,
+            source: code_block.code.clone(),
+            metadata: code_block.metadata.clone(),
+            span: code_block.span,
+        })
+    }
+}
+
+impl DiagramKind {
+    /// Returns the kind of diagram for the language of a code block
+    pub fn from_language(language: &str) -> Option<Self> {
+        match language.trim().to_lowercase().as_str() {
+            "mermaid" => Some(Self::Mermaid),
+            "dot" | "graphviz" => Some(Self::Graphviz),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the diagram language
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mermaid => "mermaid",
+            Self::Graphviz => "dot",
+        }
+    }
+}
+
+impl CustomContainer {
+    pub fn new(name: String, metadata: Option<InlineMetadata>) -> Self {
+        Self {
+            name,
+            metadata,
+            content: Vec::new(),
+            value: None,
+            span: Span::default(),
+        }
+    }
+
+    /// Returns the blocks inside the container
+    pub fn get_content(&self) -> &[Block] {
+        &self.content
+    }
+
+    /// Returns the metadata given after the name of the container
+    pub fn get_metadata(&self) -> Option<&InlineMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Returns the title given with the title attribute
+    pub fn get_title(&self) -> Option<String> {
+        self.metadata.as_ref()?.get_string("title")
+    }
+
+    /// Returns the metadata values of the container as strings
+    pub fn get_attributes(&self) -> HashMap<String, String> {
+        self.metadata
+            .as_ref()
+            .map(|m| m.get_string_map())
+            .unwrap_or_default()
+    }
+
+    /// Returns the blocks the container is rendered as, which is the block
+    /// its handler transformed it into or its content
+    pub fn get_rendered_blocks(&self) -> &[Block] {
+        match &self.value {
+            Some(value) => std::slice::from_ref(value),
+            None => &self.content,
+        }
+    }
+}
+
+impl Columns {
+    pub fn new(count: usize) -> Self {
+        Self {
+            count,
+            columns: Vec::new(),
+            metadata: None,
+            span: Span::default(),
+        }
+    }
+
+    pub fn add_column(&mut self, column: Vec<Block>) {
+        self.columns.push(column)
+    }
+}
+
+impl Attribution {
+    /// Parses the attribution from the text after the dashes.
+    /// The author and source are separated by the first comma
+    pub fn parse(text: &str) -> Option<Self> {
+        let (author, source) = match text.split_once(',') {
+            Some((author, source)) => (author.trim(), Some(source.trim())),
+            None => (text.trim(), None),
+        };
+        if author.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            author: author.to_string(),
+            source: source.filter(|s| !s.is_empty()).map(String::from),
+        })
+    }
+}
+
+impl Aligned {
+    pub fn new(alignment: Alignment) -> Self {
+        Self {
+            alignment,
+            content: Vec::new(),
+            metadata: None,
+            span: Span::default(),
+        }
+    }
+
+    pub fn add_element(&mut self, element: Block) {
+        self.content.push(element)
     }
 }
 
@@ -665,27 +1548,51 @@ impl Quote {
     pub fn new(metadata: Option<InlineMetadata>) -> Self {
         Self {
             metadata,
-            text: Vec::new(),
+            elements: Vec::new(),
+            attribution: None,
+            span: Span::default(),
         }
     }
 
-    pub fn add_text(&mut self, text: TextLine) {
-        self.text.push(text)
+    pub fn add_element(&mut self, element: Block) {
+        self.elements.push(element)
+    }
+}
+
+impl DefinitionList {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    /// Strips a single linebreak from the end of the quote
-    pub fn strip_linebreak(&mut self) {
-        if let Some(last) = self.text.last_mut() {
-            if let Some(Inline::LineBreak) = last.subtext.last() {
-                last.subtext.pop();
-            }
+    pub fn add_item(&mut self, item: Definition) {
+        self.items.push(item)
+    }
+}
+
+impl Definition {
+    pub fn new(term: TextLine) -> Self {
+        Self {
+            term,
+            definitions: Vec::new(),
+            span: Span::default(),
         }
     }
+
+    /// Adds a definition and strips the linebreak from its end
+    pub fn add_definition(&mut self, mut definition: TextLine) {
+        if let Some(Inline::LineBreak) = definition.subtext.last() {
+            definition.subtext.pop();
+        }
+        self.definitions.push(definition)
+    }
 }
 
 impl ImportAnchor {
     pub fn new() -> Self {
-        Self { document: None }
+        Self {
+            document: None,
+            lazy: None,
+        }
     }
 
     pub fn set_document(&mut self, document: Document) {
@@ -705,12 +1612,28 @@ impl Placeholder {
             name,
             value: None,
             metadata,
+            span: Span::default(),
         }
     }
 
     pub fn set_value(&mut self, value: Element) {
         self.value = Some(value);
     }
+
+    /// Returns the name of the placeholder
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value the placeholder is rendered with or None if it wasn't resolved
+    pub fn get_value(&self) -> Option<&Element> {
+        self.value.as_ref()
+    }
+
+    /// Returns the metadata given to the placeholder
+    pub fn get_metadata(&self) -> Option<&InlineMetadata> {
+        self.metadata.as_ref()
+    }
 }
 
 pub trait Metadata {
@@ -721,6 +1644,15 @@ pub trait Metadata {
     fn get_string_map(&self) -> HashMap<String, String>;
 }
 
+impl InlineMetadata {
+    /// Adds the values of the other metadata with keys that aren't set yet
+    pub(crate) fn merge(&mut self, other: InlineMetadata) {
+        for (key, value) in other.data {
+            self.data.entry(key).or_insert(value);
+        }
+    }
+}
+
 impl Metadata for InlineMetadata {
     fn get_bool(&self, key: &str) -> bool {
         if let Some(MetadataValue::Bool(value)) = self.data.get(key) {
@@ -785,17 +1717,23 @@ impl Image {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BibEntry {
     pub key: String,
+    #[serde(with = "crate::references::bibliography::entry_serde")]
     pub entry: BibliographyEntryReference,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BibReference {
     pub(crate) key: String,
+    #[serde(with = "crate::references::bibliography::anchor_serde")]
     pub(crate) entry_anchor: Arc<Mutex<BibRefAnchor>>,
     pub(crate) display: Option<String>,
+    /// The text that is displayed if no entry with the key exists
+    pub(crate) fallback: Option<String>,
+    pub span: Span,
 }
 
 impl BibReference {
@@ -804,6 +1742,8 @@ impl BibReference {
             key: key.to_string(),
             display,
             entry_anchor: anchor,
+            fallback: None,
+            span: Span::default(),
         }
     }
 
@@ -829,6 +1769,34 @@ impl BibReference {
     }
 }
 
+impl MathExpression {
+    /// Parses the given asciimath source
+    pub fn from_ascii_math(source: String) -> Self {
+        Self::AsciiMath {
+            expression: asciimath_rs::parse(source.clone()),
+            source,
+        }
+    }
+}
+
+impl From<MathSource> for MathExpression {
+    fn from(source: MathSource) -> Self {
+        match source {
+            MathSource::AsciiMath(source) => Self::from_ascii_math(source),
+            MathSource::Tex(tex) => Self::Tex(tex),
+        }
+    }
+}
+
+impl From<MathExpression> for MathSource {
+    fn from(expression: MathExpression) -> Self {
+        match expression {
+            MathExpression::AsciiMath { source, .. } => Self::AsciiMath(source),
+            MathExpression::Tex(tex) => Self::Tex(tex),
+        }
+    }
+}
+
 impl MetadataValue {
     pub fn to_string(&self) -> String {
         match self {
@@ -840,9 +1808,28 @@ impl MetadataValue {
             MetadataValue::Template(_) => "".to_string(),
         }
     }
+
+    /// Returns the value if it can be used as a css property value
+    fn as_style_value(&self) -> Option<String> {
+        match self {
+            MetadataValue::String(s) => Some(s.clone()),
+            MetadataValue::Integer(i) => Some(i.to_string()),
+            MetadataValue::Float(f) => Some(f.to_string()),
+            _ => None,
+        }
+    }
 }
 
 impl Line {
+    /// Returns if the text of the line ends with a line break
+    pub fn ends_with_break(&self) -> bool {
+        match self {
+            Line::Text(text) => text.ends_with_break(),
+            Line::Centered(centered) => centered.line.ends_with_break(),
+            _ => false,
+        }
+    }
+
     pub fn as_raw_text(&self) -> TextLine {
         match self {
             Line::Text(t) => t.clone(),
@@ -851,6 +1838,8 @@ impl Line {
             Line::Anchor(a) => a.inner.as_raw_text().as_plain_line(),
             Line::Centered(c) => c.line.clone(),
             Line::BibEntry(_) => TextLine::new(),
+            Line::FootnoteDefinition(_) => TextLine::new(),
+            Line::AbbreviationDefinition(_) => TextLine::new(),
         }
     }
 }
@@ -861,51 +1850,239 @@ impl Inline {
             Inline::Plain(p) => p.clone(),
             Inline::Bold(b) => b.value.iter().fold(
                 PlainText {
-                    value: String::new(),
+                    value: "".into(),
+                    span: b.span,
                 },
                 |a, b| PlainText {
-                    value: format!("{} {}", a.value, b.as_plain_text().value),
+                    value: format!("{} {}", a.value, b.as_plain_text().value).into(),
+                    span: a.span,
                 },
             ),
             Inline::Italic(i) => i.value.iter().fold(
                 PlainText {
-                    value: String::new(),
+                    value: "".into(),
+                    span: i.span,
                 },
                 |a, b| PlainText {
-                    value: format!("{} {}", a.value, b.as_plain_text().value),
+                    value: format!("{} {}", a.value, b.as_plain_text().value).into(),
+                    span: a.span,
                 },
             ),
             Inline::Underlined(u) => u.value.iter().fold(
                 PlainText {
-                    value: String::new(),
+                    value: "".into(),
+                    span: u.span,
                 },
                 |a, b| PlainText {
-                    value: format!("{} {}", a.value, b.as_plain_text().value),
+                    value: format!("{} {}", a.value, b.as_plain_text().value).into(),
+                    span: a.span,
                 },
             ),
             Inline::Striked(s) => s.value.iter().fold(
                 PlainText {
-                    value: String::new(),
+                    value: "".into(),
+                    span: s.span,
                 },
                 |a, b| PlainText {
-                    value: format!("{} {}", a.value, b.as_plain_text().value),
+                    value: format!("{} {}", a.value, b.as_plain_text().value).into(),
+                    span: a.span,
                 },
             ),
             Inline::Monospace(m) => PlainText {
-                value: m.value.clone(),
+                value: m.value.as_str().into(),
+                span: m.span,
             },
             Inline::Superscript(s) => s.value.iter().fold(
                 PlainText {
-                    value: String::new(),
+                    value: "".into(),
+                    span: s.span,
+                },
+                |a, b| PlainText {
+                    value: format!("{} {}", a.value, b.as_plain_text().value).into(),
+                    span: a.span,
+                },
+            ),
+            Inline::Subscript(s) => s.value.iter().fold(
+                PlainText {
+                    value: "".into(),
+                    span: s.span,
+                },
+                |a, b| PlainText {
+                    value: format!("{} {}", a.value, b.as_plain_text().value).into(),
+                    span: a.span,
+                },
+            ),
+            Inline::Highlight(h) => h.value.iter().fold(
+                PlainText {
+                    value: "".into(),
+                    span: h.span,
+                },
+                |a, b| PlainText {
+                    value: format!("{} {}", a.value, b.as_plain_text().value).into(),
+                    span: a.span,
+                },
+            ),
+            Inline::Spoiler(s) => s.value.iter().fold(
+                PlainText {
+                    value: "".into(),
+                    span: s.span,
                 },
                 |a, b| PlainText {
-                    value: format!("{} {}", a.value, b.as_plain_text().value),
+                    value: format!("{} {}", a.value, b.as_plain_text().value).into(),
+                    span: a.span,
                 },
             ),
             Inline::Colored(c) => c.value.as_plain_text(),
+            Inline::Styled(s) => s.value.as_plain_text(),
+            Inline::Abbreviation(a) => PlainText {
+                value: a.short.as_str().into(),
+                span: a.span,
+            },
             _ => PlainText {
-                value: String::new(),
+                value: "".into(),
+                span: Span::default(),
             },
         }
     }
 }
+
+/// Adds the sections of the elements to the table of contents.
+/// The index counts the sections on the current level so that
+/// imported sections continue the numbering of the importing document.
+fn add_toc_items(
+    elements: &[Block],
+    options: &TocOptions,
+    depth: usize,
+    prefix: &str,
+    index: &mut usize,
+    list: &mut List,
+) {
+    for element in elements {
+        match element {
+            Block::Section(sec) => {
+                *index += 1;
+                let number = format!("{}{}", prefix, index);
+                if sec.is_hidden_in_toc() {
+                    continue;
+                }
+                let mut link = sec.header.get_anchor();
+                if options.numbered || sec.header.number.is_some() {
+                    link.description.subtext.insert(
+                        0,
+                        Inline::Plain(PlainText {
+                            value: format!("{} ", sec.header.number.as_ref().unwrap_or(&number))
+                                .into(),
+                            span: Span::default(),
+                        }),
+                    );
+                }
+                let mut item = ListItem::new(Line::RefLink(link), 1, options.ordered);
+                if options.max_depth.is_none_or(|max| depth < max) {
+                    let mut children = List::new();
+                    add_toc_items(
+                        &sec.elements,
+                        options,
+                        depth + 1,
+                        &format!("{}.", number),
+                        &mut 0,
+                        &mut children,
+                    );
+                    item.children.append(&mut children.items);
+                }
+                list.add_item(item);
+            }
+            Block::Import(imp) => {
+                let anchor = imp.anchor.read().unwrap();
+                if let Some(doc) = &anchor.document {
+                    add_toc_items(&doc.elements, options, depth, prefix, index, list)
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Adds the headers of the sections of the elements to the outline
+fn add_outline_entries(elements: &[Block], entries: &mut Vec<OutlineEntry>) {
+    for element in elements {
+        match element {
+            Block::Section(sec) => {
+                let mut children = Vec::new();
+                add_outline_entries(&sec.elements, &mut children);
+                entries.push(OutlineEntry {
+                    title: sec.header.get_plain_text(),
+                    anchor: sec.header.anchor.clone(),
+                    level: sec.header.size,
+                    number: sec.header.number.clone(),
+                    span: sec.header.span,
+                    children,
+                });
+            }
+            Block::Import(imp) => {
+                let anchor = imp.anchor.read().unwrap();
+                if let Some(doc) = &anchor.document {
+                    add_outline_entries(&doc.elements, entries)
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the header size of the innermost section that the next element of a block belongs to
+fn get_nesting(block: &Block) -> u8 {
+    match block {
+        Block::Section(section) => section
+            .elements
+            .last()
+            .filter(|e| matches!(e, Block::Section(_)))
+            .map(get_nesting)
+            .unwrap_or(section.header.size),
+        _ => 0,
+    }
+}
+
+/// Increases the size of all headers by the given number of levels up to the smallest header size
+fn shift_headers(elements: &mut [Block], levels: u8) {
+    if levels == 0 {
+        return;
+    }
+    for element in elements {
+        if let Block::Section(section) = element {
+            section.header.size = section.header.size.saturating_add(levels).min(6);
+            shift_headers(&mut section.elements, levels);
+        }
+    }
+}
+
+/// Returns the section with the given anchor or title from the elements
+fn take_section(elements: Vec<Block>, anchor: &str) -> Option<Section> {
+    let name = normalize_name(anchor);
+
+    for element in elements {
+        if let Block::Section(section) = element {
+            if normalize_name(&section.header.anchor) == name
+                || normalize_name(&section.header.get_plain_text()) == name
+            {
+                return Some(section);
+            } else if let Some(section) = take_section(section.elements, anchor) {
+                return Some(section);
+            }
+        }
+    }
+
+    None
+}
+
+/// Assigns the number of each section to its header
+fn number_sections(elements: &mut [Block], prefix: &str) {
+    let sections = elements.iter_mut().filter_map(|e| match e {
+        Block::Section(section) => Some(section),
+        _ => None,
+    });
+    for (index, section) in sections.enumerate() {
+        let number = format!("{}{}", prefix, index + 1);
+        number_sections(&mut section.elements, &format!("{}.", number));
+        section.header.number = Some(number);
+    }
+}
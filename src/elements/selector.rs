@@ -0,0 +1,711 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::visitor::{walk_block, walk_inline, walk_line, Visitor};
+use crate::elements::{Block, Document, Element, Inline, Line};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A selector for elements of the document tree similar to css selectors
+/// like `section[level=2] > codeblock[lang=rust]`.
+///
+/// Elements are selected by their name or `*` and filtered by attributes in brackets.
+/// Selectors separated by a space match descendants, selectors separated by `>`
+/// match direct children and multiple selectors are separated by commas.
+///
+/// | Name | Attributes |
+/// |------|------------|
+/// | any block | id, class |
+/// | section | level, anchor, title |
+/// | paragraph, table, quote, definitionlist, html, comment, math, ruler, centered, pagebreak | |
+/// | list | ordered, loose |
+/// | aligned | alignment |
+/// | columns | count |
+/// | container | name |
+/// | codeblock | lang |
+/// | diagram | kind |
+/// | figure, image, link | url |
+/// | import | path |
+/// | placeholder | name |
+/// | anchor | key |
+/// | footnote | label |
+/// | bold, italic, underlined, striked, monospace, superscript, subscript, spoiler, highlight | |
+/// | checkbox | checked |
+/// | emoji | name |
+/// | colored | color |
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selector {
+    alternatives: Vec<ComplexSelector>,
+}
+
+#[derive(Debug)]
+pub struct SelectorError {
+    message: String,
+    index: usize,
+}
+
+/// A chain of compound selectors joined by combinators.
+/// The combinator at index i joins the compounds at index i and i + 1
+#[derive(Clone, Debug, PartialEq)]
+struct ComplexSelector {
+    compounds: Vec<CompoundSelector>,
+    combinators: Vec<Combinator>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct CompoundSelector {
+    /// The name of the element or None for any element
+    name: Option<String>,
+    attributes: Vec<(String, Option<String>)>,
+}
+
+/// The names of all elements that can be selected
+const ELEMENT_NAMES: &[&str] = &[
+    "section",
+    "paragraph",
+    "list",
+    "table",
+    "codeblock",
+    "diagram",
+    "html",
+    "pagebreak",
+    "comment",
+    "math",
+    "quote",
+    "aligned",
+    "columns",
+    "container",
+    "definitionlist",
+    "figure",
+    "import",
+    "placeholder",
+    "ruler",
+    "anchor",
+    "centered",
+    "footnote",
+    "bold",
+    "italic",
+    "underlined",
+    "striked",
+    "monospace",
+    "superscript",
+    "subscript",
+    "spoiler",
+    "highlight",
+    "link",
+    "image",
+    "checkbox",
+    "emoji",
+    "colored",
+];
+
+/// The name and attributes of an element that selectors are matched against
+struct Node {
+    name: &'static str,
+    attributes: Vec<(&'static str, String)>,
+}
+
+impl Document {
+    /// Returns copies of all elements that match the selector including the ones of imports
+    /// in document order. See `Selector` for the syntax.
+    pub fn select(&self, selector: &str) -> Result<Vec<Element>, SelectorError> {
+        Ok(selector.parse::<Selector>()?.select(self))
+    }
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Self, SelectorError> {
+        SelectorParser {
+            chars: selector.char_indices().collect(),
+            index: 0,
+        }
+        .parse()
+    }
+
+    /// Returns copies of all elements of the document that match the selector
+    pub fn select(&self, document: &Document) -> Vec<Element> {
+        let mut collector = SelectCollector {
+            selector: self,
+            ancestors: Vec::new(),
+            matches: Vec::new(),
+        };
+        collector.visit_document(document);
+
+        collector.matches
+    }
+
+    fn matches(&self, node: &Node, ancestors: &[Node]) -> bool {
+        self.alternatives
+            .iter()
+            .any(|s| s.matches_at(s.compounds.len() - 1, node, ancestors))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl SelectorError {
+    /// Returns the byte index of the selector the error occurred on
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+}
+
+impl Display for SelectorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid selector at {}: {}", self.index, self.message)
+    }
+}
+
+impl Error for SelectorError {}
+
+impl ComplexSelector {
+    /// Returns if the compound at the index matches the node
+    /// and the compounds before it match its ancestors
+    fn matches_at(&self, index: usize, node: &Node, ancestors: &[Node]) -> bool {
+        if !self.compounds[index].matches(node) {
+            return false;
+        }
+        if index == 0 {
+            return true;
+        }
+        match self.combinators[index - 1] {
+            Combinator::Child => ancestors
+                .split_last()
+                .is_some_and(|(parent, ancestors)| self.matches_at(index - 1, parent, ancestors)),
+            Combinator::Descendant => (0..ancestors.len())
+                .rev()
+                .any(|i| self.matches_at(index - 1, &ancestors[i], &ancestors[..i])),
+        }
+    }
+}
+
+impl CompoundSelector {
+    fn matches(&self, node: &Node) -> bool {
+        self.name.as_ref().is_none_or(|name| name == node.name)
+            && self.attributes.iter().all(|(key, value)| {
+                node.attributes
+                    .iter()
+                    .any(|(k, v)| k == key && value.as_ref().is_none_or(|value| value == v))
+            })
+    }
+}
+
+struct SelectorParser {
+    chars: Vec<(usize, char)>,
+    index: usize,
+}
+
+impl SelectorParser {
+    fn parse(mut self) -> Result<Selector, SelectorError> {
+        let mut alternatives = vec![self.parse_complex()?];
+
+        while self.peek() == Some(',') {
+            self.index += 1;
+            alternatives.push(self.parse_complex()?);
+        }
+        if self.peek().is_some() {
+            return Err(self.error("unexpected character"));
+        }
+
+        Ok(Selector { alternatives })
+    }
+
+    fn parse_complex(&mut self) -> Result<ComplexSelector, SelectorError> {
+        self.skip_whitespace();
+        let mut compounds = vec![self.parse_compound()?];
+        let mut combinators = Vec::new();
+
+        loop {
+            let had_whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => {
+                    self.index += 1;
+                    self.skip_whitespace();
+                    Combinator::Child
+                }
+                Some(',') | None => break,
+                Some(_) if had_whitespace => Combinator::Descendant,
+                Some(_) => return Err(self.error("unexpected character")),
+            };
+            combinators.push(combinator);
+            compounds.push(self.parse_compound()?);
+        }
+
+        Ok(ComplexSelector {
+            compounds,
+            combinators,
+        })
+    }
+
+    fn parse_compound(&mut self) -> Result<CompoundSelector, SelectorError> {
+        let any = self.peek() == Some('*');
+        let start = self.index;
+        let name = if any {
+            self.index += 1;
+            None
+        } else {
+            Some(self.parse_identifier()).filter(|name| !name.is_empty())
+        };
+        if name
+            .as_ref()
+            .is_some_and(|name| !ELEMENT_NAMES.contains(&name.as_str()))
+        {
+            self.index = start;
+            return Err(self.error("unknown element name"));
+        }
+        let mut attributes = Vec::new();
+
+        while self.peek() == Some('[') {
+            self.index += 1;
+            let key = self.parse_identifier();
+            if key.is_empty() {
+                return Err(self.error("expected an attribute name"));
+            }
+            let value = if self.peek() == Some('=') {
+                self.index += 1;
+                Some(self.parse_value()?)
+            } else {
+                None
+            };
+            if self.peek() != Some(']') {
+                return Err(self.error("expected ]"));
+            }
+            self.index += 1;
+            attributes.push((key, value));
+        }
+        if !any && name.is_none() && attributes.is_empty() {
+            return Err(self.error("expected an element name"));
+        }
+
+        Ok(CompoundSelector { name, attributes })
+    }
+
+    /// Parses an attribute value that is either quoted or ends at the closing bracket
+    fn parse_value(&mut self) -> Result<String, SelectorError> {
+        let quote = self.peek().filter(|c| *c == '"' || *c == '\'');
+        let mut value = String::new();
+
+        if let Some(quote) = quote {
+            self.index += 1;
+            loop {
+                match self.peek() {
+                    Some(c) if c == quote => break,
+                    Some(c) => value.push(c),
+                    None => return Err(self.error("unterminated string")),
+                }
+                self.index += 1;
+            }
+            self.index += 1;
+        } else {
+            while let Some(c) = self.peek().filter(|c| *c != ']') {
+                value.push(c);
+                self.index += 1;
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn parse_identifier(&mut self) -> String {
+        let mut identifier = String::new();
+
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+        {
+            identifier.push(c.to_ascii_lowercase());
+            self.index += 1;
+        }
+
+        identifier
+    }
+
+    /// Skips whitespace and returns if there was any
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.index;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.index += 1;
+        }
+
+        self.index > start
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).map(|(_, c)| *c)
+    }
+
+    fn error(&self, message: &str) -> SelectorError {
+        SelectorError {
+            message: message.to_string(),
+            index: self
+                .chars
+                .get(self.index)
+                .map(|(i, _)| *i)
+                .unwrap_or_else(|| {
+                    self.chars
+                        .last()
+                        .map(|(i, c)| i + c.len_utf8())
+                        .unwrap_or(0)
+                }),
+        }
+    }
+}
+
+struct SelectCollector<'a> {
+    selector: &'a Selector,
+    ancestors: Vec<Node>,
+    matches: Vec<Element>,
+}
+
+impl SelectCollector<'_> {
+    /// Adds the element if it matches and visits its children with the node as their ancestor
+    fn visit_node<E, W>(&mut self, node: Option<Node>, element: E, walk: W)
+    where
+        E: FnOnce() -> Element,
+        W: FnOnce(&mut Self),
+    {
+        if let Some(node) = node {
+            if self.selector.matches(&node, &self.ancestors) {
+                self.matches.push(element());
+            }
+            self.ancestors.push(node);
+            walk(self);
+            self.ancestors.pop();
+        } else {
+            walk(self)
+        }
+    }
+}
+
+impl Visitor for SelectCollector<'_> {
+    fn visit_block(&mut self, block: &Block) {
+        let node = match block {
+            Block::Section(section) => Some(Node::new("section").with_attributes(vec![
+                ("level", section.header.size.to_string()),
+                ("anchor", section.header.anchor.clone()),
+                ("title", section.header.get_plain_text()),
+            ])),
+            Block::Paragraph(_) => Some(Node::new("paragraph")),
+            Block::List(list) => Some(Node::new("list").with_attributes(vec![
+                ("ordered", list.ordered.to_string()),
+                ("loose", list.loose.to_string()),
+            ])),
+            Block::Table(_) => Some(Node::new("table")),
+            Block::CodeBlock(code) => {
+                Some(Node::new("codeblock").with_attributes(vec![("lang", code.language.clone())]))
+            }
+            Block::Diagram(diagram) => Some(
+                Node::new("diagram")
+                    .with_attributes(vec![("kind", diagram.kind.as_str().to_string())]),
+            ),
+            Block::Html(_) => Some(Node::new("html")),
+            Block::PageBreak(_) => Some(Node::new("pagebreak")),
+            Block::Comment(_) => Some(Node::new("comment")),
+            Block::MathBlock(_) => Some(Node::new("math")),
+            Block::Quote(_) => Some(Node::new("quote")),
+            Block::Aligned(aligned) => Some(Node::new("aligned").with_attributes(vec![(
+                "alignment",
+                format!("{:?}", aligned.alignment).to_lowercase(),
+            )])),
+            Block::Columns(columns) => Some(
+                Node::new("columns").with_attributes(vec![("count", columns.count.to_string())]),
+            ),
+            Block::CustomContainer(container) => {
+                Some(Node::new("container").with_attributes(vec![("name", container.name.clone())]))
+            }
+            Block::DefinitionList(_) => Some(Node::new("definitionlist")),
+            Block::Figure(figure) => Some(
+                Node::new("figure").with_attributes(vec![("url", figure.image.url.url.clone())]),
+            ),
+            Block::Import(import) => {
+                Some(Node::new("import").with_attributes(vec![("path", import.path.clone())]))
+            }
+            Block::Placeholder(placeholder) => Some(
+                Node::new("placeholder")
+                    .with_attributes(vec![("name", placeholder.read().unwrap().name.clone())]),
+            ),
+            Block::Null => None,
+        };
+        self.visit_node(
+            node.map(|node| node.with_block_attributes(block)),
+            || Element::Block(Box::new(block.clone())),
+            |v| walk_block(v, block),
+        )
+    }
+
+    fn visit_line(&mut self, line: &Line) {
+        let node = match line {
+            Line::Ruler(_) => Some(Node::new("ruler")),
+            Line::Anchor(anchor) => {
+                Some(Node::new("anchor").with_attributes(vec![("key", anchor.key.clone())]))
+            }
+            Line::Centered(_) => Some(Node::new("centered")),
+            Line::FootnoteDefinition(definition) => Some(
+                Node::new("footnote")
+                    .with_attributes(vec![("label", definition.lock().label.clone())]),
+            ),
+            Line::Text(_)
+            | Line::RefLink(_)
+            | Line::BibEntry(_)
+            | Line::AbbreviationDefinition(_) => None,
+        };
+        self.visit_node(
+            node,
+            || Element::Line(Box::new(line.clone())),
+            |v| walk_line(v, line),
+        )
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        let node = match inline {
+            Inline::Bold(_) => Some(Node::new("bold")),
+            Inline::Italic(_) => Some(Node::new("italic")),
+            Inline::Underlined(_) => Some(Node::new("underlined")),
+            Inline::Striked(_) => Some(Node::new("striked")),
+            Inline::Monospace(_) => Some(Node::new("monospace")),
+            Inline::Superscript(_) => Some(Node::new("superscript")),
+            Inline::Subscript(_) => Some(Node::new("subscript")),
+            Inline::Spoiler(_) => Some(Node::new("spoiler")),
+            Inline::Highlight(_) => Some(Node::new("highlight")),
+            Inline::Url(url) => {
+                Some(Node::new("link").with_attributes(vec![("url", url.url.clone())]))
+            }
+            Inline::Image(image) => {
+                Some(Node::new("image").with_attributes(vec![("url", image.url.url.clone())]))
+            }
+            Inline::Placeholder(placeholder) => Some(
+                Node::new("placeholder")
+                    .with_attributes(vec![("name", placeholder.read().unwrap().name.clone())]),
+            ),
+            Inline::Checkbox(checkbox) => Some(
+                Node::new("checkbox")
+                    .with_attributes(vec![("checked", checkbox.value.to_string())]),
+            ),
+            Inline::Emoji(emoji) => {
+                Some(Node::new("emoji").with_attributes(vec![("name", emoji.name.clone())]))
+            }
+            Inline::Colored(colored) => {
+                Some(Node::new("colored").with_attributes(vec![("color", colored.color.clone())]))
+            }
+            Inline::Math(_) => Some(Node::new("math")),
+            Inline::Anchor(anchor) => {
+                Some(Node::new("anchor").with_attributes(vec![("key", anchor.key.clone())]))
+            }
+            Inline::Html(_) => Some(Node::new("html")),
+            Inline::Comment(_) => Some(Node::new("comment")),
+            _ => None,
+        };
+        self.visit_node(
+            node,
+            || Element::Inline(Box::new(inline.clone())),
+            |v| walk_inline(v, inline),
+        )
+    }
+}
+
+impl Node {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            attributes: Vec::new(),
+        }
+    }
+
+    fn with_attributes(mut self, attributes: Vec<(&'static str, String)>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Adds the id and each class given in the metadata of the block
+    fn with_block_attributes(mut self, block: &Block) -> Self {
+        if let Some(id) = block.get_id() {
+            self.attributes.push(("id", id));
+        }
+        if let Some(class) = block.get_class() {
+            self.attributes.extend(
+                class
+                    .split_whitespace()
+                    .map(|class| ("class", class.to_string())),
+            );
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    const DOCUMENT: &str = "\
+# Intro
+
+Some **bold** text.
+
+```rust
+fn main() {}
+```
+
+## Usage
+
+```python
+print()
+```
+
+1. first
+2. second
+
+Between the lists.
+
+- **item**
+";
+
+    fn select(selector: &str) -> Vec<Element> {
+        let document = Parser::new(DOCUMENT.to_string(), None).parse();
+
+        document.select(selector).unwrap()
+    }
+
+    /// Returns the languages of the selected code blocks
+    fn languages(elements: &[Element]) -> Vec<String> {
+        elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::Block(block) => match block.as_ref() {
+                    Block::CodeBlock(code) => Some(code.language.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn error(selector: &str) -> (String, usize) {
+        let error = Selector::parse(selector).unwrap_err();
+
+        (error.message.clone(), error.get_index())
+    }
+
+    #[test]
+    fn selects_by_name() {
+        assert_eq!(languages(&select("codeblock")), vec!["rust", "python"]);
+        assert_eq!(select("section").len(), 2);
+        assert!(select("*").len() > select("section").len() + select("codeblock").len());
+    }
+
+    #[test]
+    fn child_combinator_matches_direct_children() {
+        assert_eq!(
+            languages(&select("section[level=1] > codeblock")),
+            vec!["rust"]
+        );
+        assert_eq!(
+            languages(&select("section[level=2]>codeblock")),
+            vec!["python"]
+        );
+        assert!(select("section > bold").is_empty());
+    }
+
+    #[test]
+    fn descendant_combinator_matches_nested_elements() {
+        assert_eq!(
+            languages(&select("section[level=1] codeblock")),
+            vec!["rust", "python"]
+        );
+        assert_eq!(select("section paragraph bold").len(), 1);
+        assert_eq!(select("list bold").len(), 1);
+        assert_eq!(select("section > section list").len(), 2);
+    }
+
+    #[test]
+    fn comma_joins_alternatives_in_document_order() {
+        let elements = select("list[ordered=true], codeblock");
+
+        assert_eq!(elements.len(), 3);
+        assert_eq!(languages(&elements), vec!["rust", "python"]);
+        assert!(matches!(
+            &elements[2],
+            Element::Block(block) if matches!(block.as_ref(), Block::List(list) if list.ordered)
+        ));
+    }
+
+    #[test]
+    fn attribute_filters() {
+        assert_eq!(languages(&select("codeblock[lang=rust]")), vec!["rust"]);
+        assert_eq!(
+            languages(&select("codeblock[lang=\"python\"]")),
+            vec!["python"]
+        );
+        assert_eq!(languages(&select("[lang='rust']")), vec!["rust"]);
+        assert_eq!(languages(&select("codeblock[lang]")).len(), 2);
+        assert_eq!(select("section[title=Usage][level=2]").len(), 1);
+        assert!(select("section[title=Usage][level=1]").is_empty());
+        assert!(select("codeblock[lang=go]").is_empty());
+        assert!(select("codeblock[missing]").is_empty());
+    }
+
+    #[test]
+    fn names_are_case_insensitive() {
+        assert_eq!(
+            Selector::parse("CodeBlock").unwrap(),
+            Selector::parse("codeblock").unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_empty_selectors() {
+        assert_eq!(error(""), ("expected an element name".to_string(), 0));
+        assert_eq!(error("   "), ("expected an element name".to_string(), 3));
+    }
+
+    #[test]
+    fn rejects_trailing_commas() {
+        assert_eq!(
+            error("section,"),
+            ("expected an element name".to_string(), 8)
+        );
+        assert_eq!(error("a,"), ("unknown element name".to_string(), 0));
+    }
+
+    #[test]
+    fn rejects_unknown_element_names() {
+        assert_eq!(
+            error("section > sektion"),
+            ("unknown element name".to_string(), 10)
+        );
+        assert_eq!(error("div"), ("unknown element name".to_string(), 0));
+    }
+
+    #[test]
+    fn rejects_malformed_attributes() {
+        assert_eq!(
+            error("codeblock[]"),
+            ("expected an attribute name".to_string(), 10)
+        );
+        assert_eq!(error("codeblock[lang=rust"), ("expected ]".to_string(), 19));
+        assert_eq!(
+            error("codeblock[lang=\"rust]"),
+            ("unterminated string".to_string(), 21)
+        );
+        assert_eq!(
+            error("section+list"),
+            ("unexpected character".to_string(), 7)
+        );
+    }
+}
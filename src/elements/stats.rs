@@ -0,0 +1,136 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Block, Document};
+use crate::parser::events::{get_events, get_line_events, Event, Tag};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+/// The number of words that are read in one minute
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Statistics about the content of a document
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DocumentStats {
+    #[serde(flatten)]
+    pub counts: Counts,
+    /// The number of headers for each header size
+    pub headings: BTreeMap<u8, usize>,
+    pub sections: Vec<SectionStats>,
+}
+
+/// Statistics about a section including its subsections
+#[derive(Clone, Debug, Serialize)]
+pub struct SectionStats {
+    pub title: String,
+    pub anchor: String,
+    pub level: u8,
+    #[serde(flatten)]
+    pub counts: Counts,
+    pub children: Vec<SectionStats>,
+}
+
+/// Counts of the text and code of a part of the document.
+/// Words and characters are counted without the content of code blocks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Counts {
+    pub words: usize,
+    pub characters: usize,
+    pub code_blocks: usize,
+    pub code_lines: usize,
+}
+
+impl Document {
+    /// Returns statistics about the content of the document including its imports
+    pub fn stats(&self) -> DocumentStats {
+        let mut stats = DocumentStats::default();
+        stats.counts = count_blocks(&self.elements, &mut stats.headings, &mut stats.sections);
+
+        stats
+    }
+}
+
+impl DocumentStats {
+    /// Returns the estimated time it takes to read the document
+    pub fn reading_time(&self) -> Duration {
+        self.counts.reading_time()
+    }
+}
+
+impl Counts {
+    /// Returns the estimated time it takes to read the text
+    pub fn reading_time(&self) -> Duration {
+        Duration::from_secs((self.words * 60 / WORDS_PER_MINUTE) as u64)
+    }
+
+    fn add(&mut self, other: Counts) {
+        self.words += other.words;
+        self.characters += other.characters;
+        self.code_blocks += other.code_blocks;
+        self.code_lines += other.code_lines;
+    }
+
+    fn add_events(&mut self, events: VecDeque<Event>) {
+        let mut code_depth = 0;
+
+        for event in events {
+            match event {
+                Event::Start(Tag::CodeBlock { .. }) => {
+                    self.code_blocks += 1;
+                    code_depth += 1
+                }
+                Event::Start(Tag::Diagram { .. }) => code_depth += 1,
+                Event::End(Tag::CodeBlock { .. }) | Event::End(Tag::Diagram { .. }) => {
+                    code_depth -= 1
+                }
+                Event::Text(text) if code_depth > 0 => self.code_lines += text.lines().count(),
+                Event::Text(text) | Event::Code(text) => {
+                    self.words += text.split_whitespace().count();
+                    self.characters += text.chars().count();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Counts the content of the blocks and adds the statistics of their sections
+fn count_blocks(
+    blocks: &[Block],
+    headings: &mut BTreeMap<u8, usize>,
+    sections: &mut Vec<SectionStats>,
+) -> Counts {
+    let mut counts = Counts::default();
+
+    for block in blocks {
+        match block {
+            Block::Section(section) => {
+                *headings.entry(section.header.size).or_insert(0) += 1;
+                let mut children = Vec::new();
+                let mut section_counts = count_blocks(&section.elements, headings, &mut children);
+                section_counts.add_events(get_line_events(&section.header.line));
+                counts.add(section_counts);
+                sections.push(SectionStats {
+                    title: section.header.get_plain_text(),
+                    anchor: section.header.anchor.clone(),
+                    level: section.header.size,
+                    counts: section_counts,
+                    children,
+                });
+            }
+            Block::Import(import) => {
+                let anchor = import.anchor.read().unwrap();
+                if let Some(document) = &anchor.document {
+                    counts.add(count_blocks(&document.elements, headings, sections));
+                }
+            }
+            block => counts.add_events(get_events(std::slice::from_ref(block))),
+        }
+    }
+
+    counts
+}
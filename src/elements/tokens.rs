@@ -25,6 +25,7 @@ pub(crate) const X: char = 'x';
 pub(crate) const GT: char = '>';
 pub(crate) const LT: char = '<';
 pub(crate) const BANG: char = '!';
+pub(crate) const SLASH: char = '/';
 pub(crate) const SPACE: char = ' ';
 pub(crate) const EQ: char = '=';
 pub(crate) const DOUBLE_QUOTE: char = '"';
@@ -48,6 +49,8 @@ pub(crate) const QUESTION_MARK: char = '?';
 pub(crate) const SPECIAL_ESCAPE: char = BACKSLASH;
 pub(crate) const META_OPEN: char = R_BRACKET;
 pub(crate) const META_CLOSE: char = L_BRACKET;
+pub(crate) const ATTR_OPEN: char = R_BRACE;
+pub(crate) const ATTR_CLOSE: char = L_BRACE;
 pub(crate) const QUOTE_START: char = GT;
 pub(crate) const DESC_OPEN: char = R_BRACKET;
 pub(crate) const DESC_CLOSE: char = L_BRACKET;
@@ -71,15 +74,25 @@ pub(crate) const BIBREF_CLOSE: char = L_BRACKET;
 pub(crate) const BIB_KEY_OPEN: char = R_BRACKET;
 pub(crate) const BIB_KEY_CLOSE: char = L_BRACKET;
 pub(crate) const BIB_DATA_START: char = COLON;
+pub(crate) const FOOTNOTE_OPEN: char = R_BRACKET;
+pub(crate) const FOOTNOTE_MARK: char = UP;
+pub(crate) const FOOTNOTE_CLOSE: char = L_BRACKET;
+pub(crate) const FOOTNOTE_DATA_START: char = COLON;
 pub(crate) const TEMP_VAR_OPEN: char = R_BRACE;
 pub(crate) const TEMP_VAR_CLOSE: char = L_BRACE;
 pub(crate) const TEMPLATE: char = PERCENT;
+pub(crate) const DEFINITION_START: char = COLON;
+pub(crate) const URL_SCHEME_SEPARATOR: &str = "://";
 
 pub(crate) const ITALIC: char = ASTERISK;
 pub(crate) const MONOSPACE: char = BACKTICK;
 pub(crate) const STRIKED: &'static [char] = &[TILDE, TILDE];
+pub(crate) const SQ_SPOILER: &[char] = &[PIPE, PIPE];
+pub(crate) const SQ_HIGHLIGHT: &[char] = &[EQ, EQ];
 pub(crate) const UNDERLINED: char = UNDERSCR;
+pub(crate) const SQ_UNDERLINED: &[char] = &[UNDERSCR, UNDERSCR];
 pub(crate) const SUPER: char = UP;
+pub(crate) const SUB: char = TILDE;
 pub(crate) const EMOJI: char = COLON;
 pub(crate) const MATH_INLINE: &'static [char] = &[MATH, MATH];
 pub(crate) const BOLD: &'static [char] = &[ASTERISK, ASTERISK];
@@ -124,7 +137,10 @@ pub(crate) const BLOCK_SPECIAL_CHARS: &'static [&[char]] = &[
     &[META_OPEN],
     &[IMPORT_START, IMPORT_OPEN],
     &SQ_CENTERED_START,
+    &SQ_CONTAINER,
+    &SQ_PAGE_BREAK,
     &SQ_MATH,
+    SQ_TEX_BLOCK,
 ];
 
 pub(crate) const INLINE_SPECIAL_CHARS: &'static [char] = &[
@@ -151,6 +167,8 @@ pub(crate) const INLINE_SPECIAL_SEQUENCES: &'static [&'static [char]] = &[
     A_LEFT_RIGHT_ARROW,
     ANCHOR_START,
     REF_START,
+    SQ_SPOILER,
+    SQ_HIGHLIGHT,
 ];
 
 pub(crate) const LIST_SPECIAL_CHARS: [char; 14] = [
@@ -163,10 +181,31 @@ pub(crate) const INLINE_WHITESPACE: [char; 3] = [' ', '\t', '\r'];
 // sequences
 
 pub(crate) const SQ_CODE_BLOCK: [char; 3] = [BACKTICK, BACKTICK, BACKTICK];
+pub(crate) const SQ_ATTRIBUTION: &str = "-- ";
 pub(crate) const SQ_RULER: [char; 5] = [MINUS, SPACE, MINUS, SPACE, MINUS];
+pub(crate) const SQ_FRONT_MATTER: [char; 3] = [MINUS, MINUS, MINUS];
+pub(crate) const SQ_FRONT_MATTER_END: [char; 4] = [LB, MINUS, MINUS, MINUS];
 pub(crate) const SQ_PHOLDER_START: [char; 2] = [PHOLDER_OPEN, PHOLDER_OPEN];
 pub(crate) const SQ_PHOLDER_STOP: [char; 2] = [PHOLDER_CLOSE, PHOLDER_CLOSE];
 pub(crate) const SQ_CENTERED_START: [char; 2] = [PIPE, PIPE];
+pub(crate) const SQ_ALIGNED: [char; 3] = [PIPE, PIPE, PIPE];
+pub(crate) const SQ_CONTAINER: [char; 3] = [COLON, COLON, COLON];
+pub(crate) const SQ_PAGE_BREAK: [char; 3] = [LT, LT, LT];
 pub(crate) const SQ_COLOR_START: [char; 2] = [COLOR_START, COLOR_OPEN];
 pub(crate) const SQ_BIBREF_START: [char; 2] = [BIBREF_OPEN, BIBREF_REF];
+pub(crate) const SQ_FOOTNOTE_START: [char; 2] = [FOOTNOTE_OPEN, FOOTNOTE_MARK];
+pub(crate) const SQ_ABBREVIATION_START: [char; 2] = [ASTERISK, R_BRACKET];
 pub(crate) const SQ_MATH: &'static [char] = &[MATH, MATH, MATH];
+pub(crate) const SQ_TEX_BLOCK: &[char] = &[MATH, MATH, LB];
+
+// container names
+
+pub(crate) const CONTAINER_COLUMNS: &str = "columns";
+pub(crate) const CONTAINER_COLUMN: &str = "column";
+
+// html and comments
+
+pub(crate) const SQ_HTML_BLOCK_END: &str = "\n\n";
+pub(crate) const SQ_COMMENT_START: &[char] = &[LT, BANG, MINUS, MINUS];
+pub(crate) const SQ_COMMENT_END: &[char] = &[MINUS, MINUS, GT];
+pub(crate) const SQ_LINE_COMMENT: &[char] = &[SLASH, SLASH];
@@ -0,0 +1,234 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::visitor::{walk_inline_mut, walk_line_mut, VisitorMut};
+use crate::elements::{number_sections, Block, Document, Inline, Line, TocOptions};
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+/// Changes a document after it has been parsed and processed.
+/// Implement this to add your own steps to [Document::apply_transforms].
+pub trait Transform: Send + Sync {
+    fn apply(&self, document: &mut Document);
+}
+
+impl<F> Transform for F
+where
+    F: Fn(&mut Document) + Send + Sync,
+{
+    fn apply(&self, document: &mut Document) {
+        self(document)
+    }
+}
+
+impl Document {
+    /// Applies the transforms to the document in the given order
+    pub fn apply_transforms(&mut self, transforms: &[Box<dyn Transform>]) {
+        for transform in transforms {
+            transform.apply(self);
+        }
+    }
+}
+
+/// Replaces straight quotes with typographic ones and `--`, `---` and `...`
+/// with en dashes, em dashes and ellipses in the text of the document.
+/// Code, math and urls keep their characters.
+#[derive(Clone, Debug, Default)]
+pub struct SmartPunctuation;
+
+impl Transform for SmartPunctuation {
+    fn apply(&self, document: &mut Document) {
+        PunctuationReplacer { after_word: false }.visit_document(document);
+    }
+}
+
+struct PunctuationReplacer {
+    /// If the text before the current position ends with a word
+    /// so that a quote closes instead of opening
+    after_word: bool,
+}
+
+impl PunctuationReplacer {
+    fn replace(&mut self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.chars();
+
+        while let Some(ch) = chars.next() {
+            let replacement = match ch {
+                '"' if self.after_word => '\u{201D}',
+                '"' => '\u{201C}',
+                '\'' if self.after_word => '\u{2019}',
+                '\'' => '\u{2018}',
+                '.' if text_continues_with(&chars, "..") => {
+                    chars.nth(1);
+                    '\u{2026}'
+                }
+                '-' if text_continues_with(&chars, "--") => {
+                    chars.nth(1);
+                    '\u{2014}'
+                }
+                '-' if text_continues_with(&chars, "-")
+                    && !result.ends_with('<')
+                    && !text_continues_with(&chars, "->") =>
+                {
+                    chars.next();
+                    '\u{2013}'
+                }
+                ch => ch,
+            };
+            self.after_word = !(replacement.is_whitespace()
+                || matches!(
+                    replacement,
+                    '(' | '[' | '{' | '\u{2013}' | '\u{2014}' | '\u{201C}' | '\u{2018}'
+                ));
+            result.push(replacement);
+        }
+
+        result
+    }
+}
+
+/// Returns if the remaining chars start with the given text
+fn text_continues_with<I: Iterator<Item = char> + Clone>(chars: &I, text: &str) -> bool {
+    let mut chars = chars.clone();
+
+    text.chars().all(|c| chars.next() == Some(c))
+}
+
+impl VisitorMut for PunctuationReplacer {
+    fn visit_line(&mut self, line: &mut Line) {
+        self.after_word = false;
+        walk_line_mut(self, line);
+    }
+
+    fn visit_inline(&mut self, inline: &mut Inline) {
+        match inline {
+            Inline::Plain(plain) => {
+                let value = self.replace(&plain.value);
+                if *plain.value != value {
+                    plain.value = Arc::from(value);
+                }
+            }
+            Inline::LineBreak => self.after_word = false,
+            Inline::Url(_) => {
+                walk_inline_mut(self, inline);
+                self.after_word = true;
+            }
+            _ => {
+                walk_inline_mut(self, inline);
+                if !inline_contains_text(inline) {
+                    self.after_word = true;
+                }
+            }
+        }
+    }
+}
+
+/// Returns if the inline element contains text that was already replaced
+/// so that it decides how the quote after it is replaced
+fn inline_contains_text(inline: &Inline) -> bool {
+    !matches!(
+        inline,
+        Inline::Monospace(_)
+            | Inline::Math(_)
+            | Inline::Image(_)
+            | Inline::Checkbox(_)
+            | Inline::Emoji(_)
+            | Inline::CharacterCode(_)
+            | Inline::Arrow(_)
+            | Inline::BibReference(_)
+            | Inline::FootnoteReference(_)
+            | Inline::GlossaryReference(_)
+    )
+}
+
+/// Numbers the sections of the document like 1.2 as the `numbered_headings` feature does
+#[derive(Clone, Debug, Default)]
+pub struct NumberHeadings;
+
+impl Transform for NumberHeadings {
+    fn apply(&self, document: &mut Document) {
+        number_sections(&mut document.elements, "");
+        document.links.lock().assign_numbers(&document.elements);
+    }
+}
+
+/// Inserts the table of contents before the first section of the document.
+/// Documents without sections stay unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct InsertToc {
+    pub options: TocOptions,
+}
+
+impl InsertToc {
+    pub fn new(options: TocOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl Transform for InsertToc {
+    fn apply(&self, document: &mut Document) {
+        let position = document
+            .elements
+            .iter()
+            .position(|block| matches!(block, Block::Section(_)));
+
+        if let Some(position) = position {
+            let toc = document.create_toc(&self.options);
+            document.elements.insert(position, Block::List(toc));
+        }
+    }
+}
+
+/// Returns the new target for the target of a link
+type LinkRewrite = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+/// Changes the targets of the links in the document.
+/// The function returns the new target of a link or None to keep it.
+pub struct RewriteLinks {
+    rewrite: Box<LinkRewrite>,
+}
+
+impl RewriteLinks {
+    pub fn new<F>(rewrite: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        Self {
+            rewrite: Box::new(rewrite),
+        }
+    }
+}
+
+impl Debug for RewriteLinks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RewriteLinks").finish_non_exhaustive()
+    }
+}
+
+impl Transform for RewriteLinks {
+    fn apply(&self, document: &mut Document) {
+        LinkRewriter {
+            rewrite: &self.rewrite,
+        }
+        .visit_document(document);
+    }
+}
+
+struct LinkRewriter<'a> {
+    rewrite: &'a LinkRewrite,
+}
+
+impl VisitorMut for LinkRewriter<'_> {
+    fn visit_inline(&mut self, inline: &mut Inline) {
+        if let Inline::Url(url) = inline {
+            if let Some(target) = (self.rewrite)(&url.url) {
+                url.url = target;
+            }
+        }
+        walk_inline_mut(self, inline);
+    }
+}
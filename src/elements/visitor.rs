@@ -0,0 +1,369 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::*;
+
+/// A visitor that traverses the elements of a document.
+/// Every method walks into the children of the element by default,
+/// so an implementation only needs to override the methods it's interested in
+/// and call the matching `walk_*` function to continue the traversal.
+pub trait Visitor {
+    fn visit_document(&mut self, document: &Document) {
+        walk_document(self, document)
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block)
+    }
+
+    fn visit_line(&mut self, line: &Line) {
+        walk_line(self, line)
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        walk_inline(self, inline)
+    }
+}
+
+/// A visitor that traverses the elements of a document mutably
+/// so that they can be rewritten in place
+pub trait VisitorMut {
+    fn visit_document(&mut self, document: &mut Document) {
+        walk_document_mut(self, document)
+    }
+
+    fn visit_block(&mut self, block: &mut Block) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_line(&mut self, line: &mut Line) {
+        walk_line_mut(self, line)
+    }
+
+    fn visit_inline(&mut self, inline: &mut Inline) {
+        walk_inline_mut(self, inline)
+    }
+}
+
+/// Visits all top level blocks of the document
+pub fn walk_document<V: Visitor + ?Sized>(visitor: &mut V, document: &Document) {
+    document
+        .elements
+        .iter()
+        .for_each(|b| visitor.visit_block(b))
+}
+
+/// Visits the children of a block.
+/// Imported documents, placeholder values and the blocks containers were
+/// transformed into are visited as well
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    match block {
+        Block::Section(section) => {
+            visitor.visit_line(&section.header.line);
+            section.elements.iter().for_each(|b| visitor.visit_block(b));
+        }
+        Block::Paragraph(paragraph) => paragraph
+            .elements
+            .iter()
+            .for_each(|l| visitor.visit_line(l)),
+        Block::List(list) => walk_list_items(visitor, &list.items),
+        Block::Table(table) => {
+            for row in std::iter::once(&table.header).chain(table.rows.iter()) {
+                row.cells.iter().for_each(|c| visitor.visit_line(&c.text));
+            }
+        }
+        Block::Quote(quote) => quote.elements.iter().for_each(|b| visitor.visit_block(b)),
+        Block::Aligned(aligned) => aligned.content.iter().for_each(|b| visitor.visit_block(b)),
+        Block::Columns(columns) => columns
+            .columns
+            .iter()
+            .flatten()
+            .for_each(|b| visitor.visit_block(b)),
+        Block::CustomContainer(container) => {
+            container
+                .content
+                .iter()
+                .for_each(|b| visitor.visit_block(b));
+            if let Some(value) = &container.value {
+                visitor.visit_block(value);
+            }
+        }
+        Block::DefinitionList(list) => {
+            for item in &list.items {
+                walk_text(visitor, &item.term);
+                item.definitions.iter().for_each(|d| walk_text(visitor, d));
+            }
+        }
+        Block::Figure(figure) => walk_text(visitor, &figure.caption),
+        Block::Import(import) => {
+            if let Some(document) = &import.anchor.read().unwrap().document {
+                visitor.visit_document(document);
+            }
+        }
+        Block::Placeholder(placeholder) => {
+            if let Some(value) = &placeholder.read().unwrap().value {
+                walk_element(visitor, value);
+            }
+        }
+        Block::CodeBlock(_)
+        | Block::Diagram(_)
+        | Block::Html(_)
+        | Block::Comment(_)
+        | Block::MathBlock(_)
+        | Block::PageBreak(_)
+        | Block::Null => {}
+    }
+}
+
+/// Visits the children of a line
+pub fn walk_line<V: Visitor + ?Sized>(visitor: &mut V, line: &Line) {
+    match line {
+        Line::Text(text) => walk_text(visitor, text),
+        Line::Centered(centered) => walk_text(visitor, &centered.line),
+        Line::Anchor(anchor) => visitor.visit_line(&anchor.inner),
+        Line::RefLink(link) => walk_text(visitor, &link.description),
+        Line::FootnoteDefinition(footnote) => walk_text(visitor, &footnote.lock().text),
+        Line::Ruler(_) | Line::BibEntry(_) | Line::AbbreviationDefinition(_) => {}
+    }
+}
+
+/// Visits the children of an inline element.
+/// The values of placeholders and template variables are visited as well
+pub fn walk_inline<V: Visitor + ?Sized>(visitor: &mut V, inline: &Inline) {
+    match inline {
+        Inline::Bold(bold) => walk_inlines(visitor, &bold.value),
+        Inline::Italic(italic) => walk_inlines(visitor, &italic.value),
+        Inline::Underlined(underlined) => walk_inlines(visitor, &underlined.value),
+        Inline::Striked(striked) => walk_inlines(visitor, &striked.value),
+        Inline::Superscript(superscript) => walk_inlines(visitor, &superscript.value),
+        Inline::Subscript(subscript) => walk_inlines(visitor, &subscript.value),
+        Inline::Spoiler(spoiler) => walk_inlines(visitor, &spoiler.value),
+        Inline::Highlight(highlight) => walk_inlines(visitor, &highlight.value),
+        Inline::Styled(styled) => visitor.visit_inline(&styled.value),
+        Inline::Colored(colored) => visitor.visit_inline(&colored.value),
+        Inline::Url(url) => {
+            if let Some(description) = &url.description {
+                walk_inlines(visitor, description)
+            }
+        }
+        Inline::Anchor(anchor) => visitor.visit_line(&anchor.inner),
+        Inline::Placeholder(placeholder) => {
+            if let Some(value) = &placeholder.read().unwrap().value {
+                walk_element(visitor, value);
+            }
+        }
+        Inline::TemplateVar(variable) => {
+            if let Some(value) = &variable.read().unwrap().value {
+                walk_element(visitor, value);
+            }
+        }
+        Inline::Plain(_)
+        | Inline::Monospace(_)
+        | Inline::Image(_)
+        | Inline::Checkbox(_)
+        | Inline::Emoji(_)
+        | Inline::Math(_)
+        | Inline::BibReference(_)
+        | Inline::GlossaryReference(_)
+        | Inline::FootnoteReference(_)
+        | Inline::CharacterCode(_)
+        | Inline::LineBreak
+        | Inline::Arrow(_)
+        | Inline::Html(_)
+        | Inline::Comment(_)
+        | Inline::Abbreviation(_) => {}
+    }
+}
+
+fn walk_list_items<V: Visitor + ?Sized>(visitor: &mut V, items: &[ListItem]) {
+    for item in items {
+        visitor.visit_line(&item.text);
+        item.blocks.iter().for_each(|b| visitor.visit_block(b));
+        walk_list_items(visitor, &item.children);
+    }
+}
+
+fn walk_text<V: Visitor + ?Sized>(visitor: &mut V, text: &TextLine) {
+    walk_inlines(visitor, &text.subtext)
+}
+
+fn walk_inlines<V: Visitor + ?Sized>(visitor: &mut V, inlines: &[Inline]) {
+    inlines.iter().for_each(|i| visitor.visit_inline(i))
+}
+
+fn walk_element<V: Visitor + ?Sized>(visitor: &mut V, element: &Element) {
+    match element {
+        Element::Block(block) => visitor.visit_block(block),
+        Element::Line(line) => visitor.visit_line(line),
+        Element::Inline(inline) => visitor.visit_inline(inline),
+    }
+}
+
+/// Visits all top level blocks of the document mutably
+pub fn walk_document_mut<V: VisitorMut + ?Sized>(visitor: &mut V, document: &mut Document) {
+    document
+        .elements
+        .iter_mut()
+        .for_each(|b| visitor.visit_block(b))
+}
+
+/// Visits the children of a block mutably.
+/// Imported documents and placeholder values are visited as well
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, block: &mut Block) {
+    match block {
+        Block::Section(section) => {
+            visitor.visit_line(&mut section.header.line);
+            section
+                .elements
+                .iter_mut()
+                .for_each(|b| visitor.visit_block(b));
+        }
+        Block::Paragraph(paragraph) => paragraph
+            .elements
+            .iter_mut()
+            .for_each(|l| visitor.visit_line(l)),
+        Block::List(list) => walk_list_items_mut(visitor, &mut list.items),
+        Block::Table(table) => {
+            for row in std::iter::once(&mut table.header).chain(table.rows.iter_mut()) {
+                row.cells
+                    .iter_mut()
+                    .for_each(|c| visitor.visit_line(&mut c.text));
+            }
+        }
+        Block::Quote(quote) => quote
+            .elements
+            .iter_mut()
+            .for_each(|b| visitor.visit_block(b)),
+        Block::Aligned(aligned) => aligned
+            .content
+            .iter_mut()
+            .for_each(|b| visitor.visit_block(b)),
+        Block::Columns(columns) => columns
+            .columns
+            .iter_mut()
+            .flatten()
+            .for_each(|b| visitor.visit_block(b)),
+        Block::CustomContainer(container) => {
+            container
+                .content
+                .iter_mut()
+                .for_each(|b| visitor.visit_block(b));
+            if let Some(value) = &mut container.value {
+                visitor.visit_block(value);
+            }
+        }
+        Block::DefinitionList(list) => {
+            for item in &mut list.items {
+                walk_text_mut(visitor, &mut item.term);
+                item.definitions
+                    .iter_mut()
+                    .for_each(|d| walk_text_mut(visitor, d));
+            }
+        }
+        Block::Figure(figure) => walk_text_mut(visitor, &mut figure.caption),
+        Block::Import(import) => {
+            if let Some(document) = &mut import.anchor.write().unwrap().document {
+                visitor.visit_document(document);
+            }
+        }
+        Block::Placeholder(placeholder) => {
+            if let Some(value) = &mut placeholder.write().unwrap().value {
+                walk_element_mut(visitor, value);
+            }
+        }
+        Block::CodeBlock(_)
+        | Block::Diagram(_)
+        | Block::Html(_)
+        | Block::Comment(_)
+        | Block::MathBlock(_)
+        | Block::PageBreak(_)
+        | Block::Null => {}
+    }
+}
+
+/// Visits the children of a line mutably
+pub fn walk_line_mut<V: VisitorMut + ?Sized>(visitor: &mut V, line: &mut Line) {
+    match line {
+        Line::Text(text) => walk_text_mut(visitor, text),
+        Line::Centered(centered) => walk_text_mut(visitor, &mut centered.line),
+        Line::Anchor(anchor) => visitor.visit_line(&mut anchor.inner),
+        Line::RefLink(link) => walk_text_mut(visitor, &mut link.description),
+        Line::FootnoteDefinition(footnote) => walk_text_mut(visitor, &mut footnote.lock().text),
+        Line::Ruler(_) | Line::BibEntry(_) | Line::AbbreviationDefinition(_) => {}
+    }
+}
+
+/// Visits the children of an inline element mutably.
+/// The values of placeholders and template variables are visited as well
+pub fn walk_inline_mut<V: VisitorMut + ?Sized>(visitor: &mut V, inline: &mut Inline) {
+    match inline {
+        Inline::Bold(bold) => walk_inlines_mut(visitor, &mut bold.value),
+        Inline::Italic(italic) => walk_inlines_mut(visitor, &mut italic.value),
+        Inline::Underlined(underlined) => walk_inlines_mut(visitor, &mut underlined.value),
+        Inline::Striked(striked) => walk_inlines_mut(visitor, &mut striked.value),
+        Inline::Superscript(superscript) => walk_inlines_mut(visitor, &mut superscript.value),
+        Inline::Subscript(subscript) => walk_inlines_mut(visitor, &mut subscript.value),
+        Inline::Spoiler(spoiler) => walk_inlines_mut(visitor, &mut spoiler.value),
+        Inline::Highlight(highlight) => walk_inlines_mut(visitor, &mut highlight.value),
+        Inline::Styled(styled) => visitor.visit_inline(&mut styled.value),
+        Inline::Colored(colored) => visitor.visit_inline(&mut colored.value),
+        Inline::Url(url) => {
+            if let Some(description) = &mut url.description {
+                walk_inlines_mut(visitor, description)
+            }
+        }
+        Inline::Anchor(anchor) => visitor.visit_line(&mut anchor.inner),
+        Inline::Placeholder(placeholder) => {
+            if let Some(value) = &mut placeholder.write().unwrap().value {
+                walk_element_mut(visitor, value);
+            }
+        }
+        Inline::TemplateVar(variable) => {
+            if let Some(value) = &mut variable.write().unwrap().value {
+                walk_element_mut(visitor, value);
+            }
+        }
+        Inline::Plain(_)
+        | Inline::Monospace(_)
+        | Inline::Image(_)
+        | Inline::Checkbox(_)
+        | Inline::Emoji(_)
+        | Inline::Math(_)
+        | Inline::BibReference(_)
+        | Inline::GlossaryReference(_)
+        | Inline::FootnoteReference(_)
+        | Inline::CharacterCode(_)
+        | Inline::LineBreak
+        | Inline::Arrow(_)
+        | Inline::Html(_)
+        | Inline::Comment(_)
+        | Inline::Abbreviation(_) => {}
+    }
+}
+
+fn walk_list_items_mut<V: VisitorMut + ?Sized>(visitor: &mut V, items: &mut [ListItem]) {
+    for item in items {
+        visitor.visit_line(&mut item.text);
+        item.blocks.iter_mut().for_each(|b| visitor.visit_block(b));
+        walk_list_items_mut(visitor, &mut item.children);
+    }
+}
+
+fn walk_text_mut<V: VisitorMut + ?Sized>(visitor: &mut V, text: &mut TextLine) {
+    walk_inlines_mut(visitor, &mut text.subtext)
+}
+
+fn walk_inlines_mut<V: VisitorMut + ?Sized>(visitor: &mut V, inlines: &mut [Inline]) {
+    inlines.iter_mut().for_each(|i| visitor.visit_inline(i))
+}
+
+fn walk_element_mut<V: VisitorMut + ?Sized>(visitor: &mut V, element: &mut Element) {
+    match element {
+        Element::Block(block) => visitor.visit_block(block),
+        Element::Line(line) => visitor.visit_line(line),
+        Element::Inline(inline) => visitor.visit_inline(inline),
+    }
+}
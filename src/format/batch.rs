@@ -0,0 +1,182 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::Document;
+use crate::format::html::site::render_site;
+use crate::format::renderer::RenderResult;
+use crate::parser::diagnostics::Diagnostics;
+use crate::parser::ParserOptions;
+use crate::Parser;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Options for rendering multiple documents with [render_all]
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    /// The options every document is parsed with
+    pub parser_options: ParserOptions,
+    pub format: String,
+    /// The directory the outputs are written to.
+    /// Outputs are written next to their inputs if none is given.
+    pub output_dir: Option<PathBuf>,
+    /// The number of documents that are rendered at the same time.
+    /// Uses the number of cpus when set to 0.
+    pub threads: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            parser_options: ParserOptions::default(),
+            format: "html".to_string(),
+            output_dir: None,
+            threads: 0,
+        }
+    }
+}
+
+impl BatchOptions {
+    pub fn with_parser_options(mut self, parser_options: ParserOptions) -> Self {
+        self.parser_options = parser_options;
+
+        self
+    }
+
+    pub fn with_format(mut self, format: &str) -> Self {
+        self.format = format.to_string();
+
+        self
+    }
+
+    pub fn with_output_dir(mut self, output_dir: PathBuf) -> Self {
+        self.output_dir = Some(output_dir);
+
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+
+        self
+    }
+}
+
+/// The result of rendering one of the documents
+#[derive(Debug)]
+pub struct BatchOutput {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub diagnostics: Diagnostics,
+    pub result: RenderResult<()>,
+}
+
+/// Parses and renders the documents at the given paths concurrently.
+/// Every document is parsed independently with a copy of the parser options
+/// and its imports are still parsed in parallel as well.
+/// The outputs are returned in the order of the paths.
+pub fn render_all(paths: &[PathBuf], options: &BatchOptions) -> RenderResult<Vec<BatchOutput>> {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(options.threads)
+        .build()
+        .map_err(io::Error::other)?;
+
+    Ok(pool.install(|| {
+        paths
+            .par_iter()
+            .map(|path| render_document(path, options))
+            .collect()
+    }))
+}
+
+fn render_document(path: &Path, options: &BatchOptions) -> BatchOutput {
+    let output = get_output_path(path, options);
+
+    if !path.is_file() {
+        return BatchOutput {
+            input: path.to_path_buf(),
+            output,
+            diagnostics: Diagnostics::new(),
+            result: Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "The input file could not be found",
+            )
+            .into()),
+        };
+    }
+    log::debug!("Rendering {}", path.to_string_lossy());
+    let mut parser = Parser::with_defaults(
+        options
+            .parser_options
+            .detached()
+            .add_path(path.to_path_buf()),
+    );
+    let document = parser.parse();
+    let result = write_document(&document, &options.format, path, &output);
+
+    BatchOutput {
+        input: path.to_path_buf(),
+        output,
+        diagnostics: parser.get_diagnostics(),
+        result,
+    }
+}
+
+fn write_document(
+    document: &Document,
+    format: &str,
+    input: &Path,
+    output: &Path,
+) -> RenderResult<()> {
+    if format == "site" {
+        document.collect_assets(output)?;
+        return Ok(render_site(document, output)?);
+    }
+    if output == input {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "The output would replace the input file",
+        )
+        .into());
+    }
+    let dir = output.parent().unwrap_or_else(|| Path::new(""));
+    if !dir.as_os_str().is_empty() {
+        fs::create_dir_all(dir)?;
+    }
+    document.collect_assets(dir)?;
+
+    document.render(format, BufWriter::new(File::create(output)?))
+}
+
+/// Returns the path of the output for an input file.
+/// The site format is written into a directory named after the input.
+fn get_output_path(input: &Path, options: &BatchOptions) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let dir = options
+        .output_dir
+        .clone()
+        .or_else(|| input.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+
+    if options.format == "site" {
+        dir.join(stem.as_ref())
+    } else {
+        dir.join(format!("{}.{}", stem, get_extension(&options.format)))
+    }
+}
+
+/// Returns the file extension for the format
+fn get_extension(format: &str) -> &str {
+    match format {
+        "html" | "slides" => "html",
+        "markdown" => "md",
+        "latex" => "tex",
+        "man" => "1",
+        _ => format,
+    }
+}
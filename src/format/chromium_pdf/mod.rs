@@ -8,6 +8,8 @@ use crate::elements::Document;
 use crate::format::chromium_pdf::result::{PdfRenderingError, PdfRenderingResult};
 use crate::format::html::html_writer::HTMLWriter;
 use crate::format::html::to_html::ToHtml;
+use crate::settings::feature_settings::MathRenderer;
+use crate::settings::pdf_settings::PDFSettings;
 use crate::settings::Settings;
 use crate::utils::caching::CacheStorage;
 use bibliographix::Mutex;
@@ -36,7 +38,7 @@ pub fn render_to_pdf(document: Document) -> PdfRenderingResult<Vec<u8>> {
     }
 
     let config = document.config.clone();
-    let mathjax = config.lock().features.include_mathjax;
+    let features = config.lock().features.clone();
 
     let handle = thread::spawn({
         let file_path = file_path.clone();
@@ -63,8 +65,11 @@ pub fn render_to_pdf(document: Document) -> PdfRenderingResult<Vec<u8>> {
     tab.navigate_to(format!("file:///{}", file_path.to_string_lossy()).as_str())?;
     tab.wait_until_navigated()?;
 
-    if mathjax {
-        wait_for_mathjax(&tab, Duration::from_secs(60))?;
+    if let Some(script) = get_font_script(&config.lock().pdf) {
+        tab.evaluate(script.as_str(), false)?;
+    }
+    if features.include_mathjax {
+        wait_for_math(&tab, &features.math_renderer, Duration::from_secs(60))?;
     }
     log::info!("Rendering pdf...");
     let result = tab.print_to_pdf(Some(get_pdf_options(config)))?;
@@ -74,19 +79,48 @@ pub fn render_to_pdf(document: Document) -> PdfRenderingResult<Vec<u8>> {
     Ok(result)
 }
 
-/// Waits for mathjax to be finished
-fn wait_for_mathjax(tab: &Tab, timeout: Duration) -> PdfRenderingResult<()> {
+/// Returns a script that adds the configured fonts to the document
+fn get_font_script(config: &PDFSettings) -> Option<String> {
+    let mut css = String::new();
+    if let Some(family) = &config.font_family {
+        css.push_str(&format!("font-family: {};", family));
+    }
+    if let Some(size) = &config.font_size {
+        css.push_str(&format!("font-size: {};", size));
+    }
+    if css.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "var style = document.createElement('style');\
+        style.textContent = {:?};\
+        document.head.appendChild(style);",
+        format!("body, .content {{ {} }}", css)
+    ))
+}
+
+/// Waits for the math renderer to be loaded
+fn wait_for_math(tab: &Tab, renderer: &MathRenderer, timeout: Duration) -> PdfRenderingResult<()> {
     let start = Instant::now();
-    log::debug!("Waiting for mathjax...");
+    let global = match renderer {
+        MathRenderer::MathJax => "MathJax",
+        MathRenderer::KaTeX => "renderMathInElement",
+    };
+    log::debug!("Waiting for {}...", global);
     loop {
         let result = tab
             .evaluate(
-                "\
-            if (window.MathJax)\
-                !!window.MathJax;\
+                format!(
+                    "\
+            if (window.{0})\
+                !!window.{0};\
             else \
                 false;\
             ",
+                    global
+                )
+                .as_str(),
                 true,
             )?
             .value;
@@ -129,6 +163,7 @@ fn get_pdf_options(config: Arc<Mutex<Settings>>) -> PrintToPdfOptions {
         ignore_invalid_page_ranges: None,
         header_template: config.header_template,
         footer_template: config.footer_template,
-        prefer_css_page_size: None,
+        // landscape sections set the size of their pages with css
+        prefer_css_page_size: Some(true),
     }
 }
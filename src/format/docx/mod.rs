@@ -0,0 +1,1019 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::*;
+use crate::references::glossary::GlossaryDisplay;
+use crate::settings::Settings;
+use htmlescape::{encode_attribute, encode_minimal};
+use image::ImageFormat;
+use std::io::{Cursor, Write};
+use zip::result::ZipResult;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const CONTENT_TYPES_XML: &str = include_str!("assets/content-types.xml");
+const RELS_XML: &str = include_str!("assets/rels.xml");
+const STYLES_XML: &str = include_str!("assets/styles.xml");
+
+const NAMESPACES: &str =
+    "xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\" \
+    xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\" \
+    xmlns:wp=\"http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing\" \
+    xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" \
+    xmlns:pic=\"http://schemas.openxmlformats.org/drawingml/2006/picture\"";
+const RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+/// The width of the text area of an A4 page with margins of one inch in twentieths of a point
+const TEXT_WIDTH: usize = 9026;
+const EMU_PER_PIXEL: u64 = 9525;
+/// Images are scaled down to six inches if they are wider
+const MAX_IMAGE_WIDTH: u64 = 5486400;
+
+/// The formatting of a run of text that is inherited by nested inline elements
+#[derive(Clone, Default)]
+struct RunFormat {
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    striked: bool,
+    monospace: bool,
+    hyperlink: bool,
+    vertical_align: Option<&'static str>,
+    color: Option<String>,
+    highlight: Option<String>,
+}
+
+/// A relationship of the main document part to an external link or embedded image
+struct Relationship {
+    kind: &'static str,
+    target: String,
+    external: bool,
+}
+
+/// Writes the body of the document and collects the parts it references
+#[derive(Default)]
+struct DocxWriter {
+    body: String,
+    relationships: Vec<Relationship>,
+    media: Vec<(String, Vec<u8>)>,
+    /// the numbering instances of all lists with their ordering and level
+    lists: Vec<(bool, usize)>,
+    drawings: usize,
+    bookmarks: usize,
+    quote_depth: usize,
+    /// The alignment of the paragraphs inside of aligned blocks
+    alignment: Option<&'static str>,
+}
+
+/// Renders the document to a docx file and returns the resulting bytes.
+/// Code is written in monospaced styles, images that can be read are embedded.
+pub fn render_to_docx(document: &Document) -> ZipResult<Vec<u8>> {
+    let config = document.config.lock().clone();
+    let mut writer = DocxWriter::default();
+
+    if let Some(title) = &config.metadata.title {
+        writer.write_paragraph(
+            "<w:pStyle w:val=\"Title\"/>",
+            &run(title, &RunFormat::default()),
+        );
+    }
+    writer.write_blocks(&document.elements);
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+
+    zip.start_file("[Content_Types].xml", deflated)?;
+    zip.write_all(CONTENT_TYPES_XML.as_bytes())?;
+    zip.start_file("_rels/.rels", deflated)?;
+    zip.write_all(RELS_XML.as_bytes())?;
+    zip.start_file("docProps/core.xml", deflated)?;
+    zip.write_all(create_core_properties(&config).as_bytes())?;
+    zip.start_file("word/styles.xml", deflated)?;
+    zip.write_all(STYLES_XML.as_bytes())?;
+    zip.start_file("word/numbering.xml", deflated)?;
+    zip.write_all(create_numbering(&writer.lists).as_bytes())?;
+    zip.start_file("word/_rels/document.xml.rels", deflated)?;
+    zip.write_all(create_relationships(&writer.relationships).as_bytes())?;
+    zip.start_file("word/document.xml", deflated)?;
+    zip.write_all(create_document(&writer.body).as_bytes())?;
+
+    for (name, data) in &writer.media {
+        log::debug!("Embedding image {}", name);
+        zip.start_file(format!("word/media/{}", name), stored)?;
+        zip.write_all(data)?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+impl DocxWriter {
+    fn write_blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            self.write_block(block);
+        }
+    }
+
+    fn write_block(&mut self, block: &Block) {
+        match block {
+            Block::Section(section) => self.write_section(section),
+            Block::Paragraph(paragraph) => self.write_paragraph_block(paragraph),
+            Block::List(list) => self.write_list_items(&list.items, list.ordered, 0),
+            Block::Table(table) => self.write_table(table),
+            Block::CodeBlock(code) => {
+                if let Some(title) = code.get_title() {
+                    let format = RunFormat {
+                        bold: true,
+                        ..Default::default()
+                    };
+                    self.write_paragraph("<w:keepNext/>", &run(&title, &format));
+                }
+                self.write_code(&code.code)
+            }
+            Block::Diagram(diagram) => self.write_code(&diagram.source),
+            Block::MathBlock(math) => self.write_code(math.expression.source()),
+            Block::Quote(quote) => self.write_quote(quote),
+            Block::Aligned(aligned) => {
+                let previous = self.alignment;
+                self.alignment = match aligned.alignment {
+                    Alignment::Left => Some("left"),
+                    Alignment::Right => Some("right"),
+                    Alignment::Center | Alignment::None => Some("center"),
+                };
+                self.write_blocks(&aligned.content);
+                self.alignment = previous;
+            }
+            Block::Columns(columns) => columns
+                .columns
+                .iter()
+                .for_each(|column| self.write_blocks(column)),
+            Block::CustomContainer(container) => self.write_blocks(container.get_rendered_blocks()),
+            Block::DefinitionList(list) => self.write_definition_list(list),
+            Block::Figure(figure) => self.write_figure(figure),
+            Block::Placeholder(placeholder) => {
+                let placeholder = placeholder.read().unwrap();
+                match &placeholder.value {
+                    Some(Element::Block(block)) => self.write_block(block),
+                    Some(Element::Line(line)) => {
+                        let content = self.line_xml(line, &RunFormat::default());
+                        self.write_paragraph("", &content)
+                    }
+                    Some(Element::Inline(inline)) => {
+                        let content = self.inline_xml(inline, &RunFormat::default());
+                        self.write_paragraph("", &content)
+                    }
+                    None => self.write_paragraph(
+                        "",
+                        &run(&format!("[[{}]]", placeholder.name), &RunFormat::default()),
+                    ),
+                }
+            }
+            Block::PageBreak(_) => self.write_paragraph("", "<w:r><w:br w:type=\"page\"/></w:r>"),
+            Block::Html(_) | Block::Comment(_) | Block::Import(_) | Block::Null => {}
+        }
+    }
+
+    /// Writes a paragraph with the given properties. Paragraphs inside
+    /// of quotes get the quote style if they don't have a style of their own
+    /// and paragraphs inside of aligned blocks get their alignment.
+    fn write_paragraph(&mut self, properties: &str, content: &str) {
+        let style = if self.quote_depth > 0 && !properties.contains("w:pStyle") {
+            "<w:pStyle w:val=\"Quote\"/>"
+        } else {
+            ""
+        };
+        let alignment = match self.alignment {
+            Some(alignment) if !properties.contains("w:jc") => {
+                format!("<w:jc w:val=\"{}\"/>", alignment)
+            }
+            _ => String::new(),
+        };
+        self.body.push_str(&format!(
+            "<w:p><w:pPr>{}{}{}</w:pPr>{}</w:p>",
+            style, properties, alignment, content
+        ));
+    }
+
+    fn write_section(&mut self, section: &Section) {
+        let header = &section.header;
+        let mut content = self.bookmark(&header.anchor);
+        if let Some(number) = &header.number {
+            content.push_str(&run(&format!("{} ", number), &RunFormat::default()));
+        }
+        content.push_str(&self.line_xml(&header.line, &RunFormat::default()));
+        let page_break = if section.breaks_before() {
+            "<w:pageBreakBefore/>"
+        } else {
+            ""
+        };
+        self.write_paragraph(
+            &format!(
+                "<w:pStyle w:val=\"Heading{}\"/>{}",
+                header.size.clamp(1, 6),
+                page_break
+            ),
+            &content,
+        );
+        self.write_blocks(&section.elements);
+        if section.breaks_after() {
+            self.write_paragraph("", "<w:r><w:br w:type=\"page\"/></w:r>");
+        }
+    }
+
+    /// Writes the lines of a paragraph separated by spaces.
+    /// Rulers and centered lines are written as paragraphs of their own.
+    fn write_paragraph_block(&mut self, paragraph: &Paragraph) {
+        let format = RunFormat::default();
+        let mut content = String::new();
+        let mut previous_break = false;
+
+        for line in &paragraph.elements {
+            match line {
+                Line::Ruler(_) => {
+                    self.flush_paragraph(&mut content);
+                    previous_break = false;
+                    self.write_paragraph(
+                        "<w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" w:space=\"1\" w:color=\"auto\"/></w:pBdr>",
+                        "",
+                    );
+                }
+                Line::Centered(centered) => {
+                    self.flush_paragraph(&mut content);
+                    previous_break = false;
+                    let centered = self.text_xml(&centered.line, &format);
+                    self.write_paragraph("<w:jc w:val=\"center\"/>", &centered);
+                }
+                _ => {
+                    let line_content = self.line_xml(line, &format);
+                    if line_content.is_empty() {
+                        continue;
+                    }
+                    if previous_break {
+                        content.push_str("<w:r><w:br/></w:r>");
+                    } else if !content.is_empty() {
+                        content.push_str(&run(" ", &format));
+                    }
+                    content.push_str(&line_content);
+                    previous_break = line.ends_with_break();
+                }
+            }
+        }
+        self.flush_paragraph(&mut content);
+    }
+
+    /// Writes the collected content as a paragraph if there is any
+    fn flush_paragraph(&mut self, content: &mut String) {
+        if !content.is_empty() {
+            self.write_paragraph("", &std::mem::take(content));
+        }
+    }
+
+    /// Writes every item as a numbered paragraph. Each list gets a numbering
+    /// instance of its own so that ordered lists start at one.
+    fn write_list_items(&mut self, items: &[ListItem], ordered: bool, level: usize) {
+        self.lists.push((ordered, level));
+        let numbering = self.lists.len();
+
+        for item in items {
+            let mut content = String::new();
+            if let Some(checked) = item.checked {
+                content.push_str(&run(checkbox_text(checked), &RunFormat::default()));
+                content.push_str(&run(" ", &RunFormat::default()));
+            }
+            content.push_str(&self.line_xml(&item.text, &RunFormat::default()));
+            self.write_paragraph(
+                &format!(
+                    "<w:pStyle w:val=\"ListParagraph\"/><w:numPr><w:ilvl w:val=\"{}\"/><w:numId w:val=\"{}\"/></w:numPr>",
+                    level.min(8),
+                    numbering
+                ),
+                &content,
+            );
+            self.write_blocks(&item.blocks);
+            if let Some(first) = item.children.first() {
+                self.write_list_items(&item.children, first.ordered, level + 1);
+            }
+        }
+    }
+
+    /// Writes the table with merged cells for cells that span multiple rows or columns.
+    /// The header row is repeated on every page.
+    fn write_table(&mut self, table: &Table) {
+        let width = table
+            .get_layout()
+            .iter()
+            .map(|(_, width)| *width)
+            .max()
+            .unwrap_or(0);
+        if width == 0 {
+            return;
+        }
+        let column_width = TEXT_WIDTH / width;
+        self.body.push_str(
+            "<w:tbl><w:tblPr><w:tblStyle w:val=\"TableGrid\"/><w:tblW w:w=\"5000\" w:type=\"pct\"/></w:tblPr><w:tblGrid>",
+        );
+        for _ in 0..width {
+            self.body
+                .push_str(&format!("<w:gridCol w:w=\"{}\"/>", column_width));
+        }
+        self.body.push_str("</w:tblGrid>");
+
+        // the remaining rows and the width of cells that span into the following rows
+        let mut merges: Vec<Option<(usize, usize)>> = vec![None; width];
+        let rows = std::iter::once(&table.header).chain(table.rows.iter());
+
+        for (index, row) in rows.enumerate() {
+            let format = RunFormat {
+                bold: index == 0,
+                ..Default::default()
+            };
+            let mut cells = row.cells.iter();
+            let mut column = 0;
+
+            if index == 0 {
+                self.body.push_str("<w:tr><w:trPr><w:tblHeader/></w:trPr>");
+            } else {
+                self.body.push_str("<w:tr>");
+            }
+            while column < width {
+                let mut properties = String::new();
+                let mut content = String::new();
+                let mut colspan = 1;
+
+                if let Some((rows_left, span)) = merges[column] {
+                    properties.push_str("<w:vMerge/>");
+                    colspan = span;
+                    merges[column] = (rows_left > 1).then_some((rows_left - 1, span));
+                } else if let Some(cell) = cells.next() {
+                    colspan = cell.colspan.clamp(1, width - column);
+                    if cell.rowspan > 1 {
+                        properties.push_str("<w:vMerge w:val=\"restart\"/>");
+                        merges[column] = Some((cell.rowspan - 1, colspan));
+                    }
+                    content = self.line_xml(&cell.text, &format);
+                }
+                if colspan > 1 {
+                    properties.insert_str(0, &format!("<w:gridSpan w:val=\"{}\"/>", colspan));
+                }
+                let alignment = match table.get_alignment(column) {
+                    Alignment::Center => "<w:jc w:val=\"center\"/>",
+                    Alignment::Right => "<w:jc w:val=\"right\"/>",
+                    Alignment::None | Alignment::Left => "",
+                };
+                self.body.push_str(&format!(
+                    "<w:tc><w:tcPr><w:tcW w:w=\"{}\" w:type=\"dxa\"/>{}</w:tcPr>\
+                    <w:p><w:pPr><w:spacing w:after=\"0\"/>{}</w:pPr>{}</w:p></w:tc>",
+                    column_width * colspan,
+                    properties,
+                    alignment,
+                    content
+                ));
+                column += colspan;
+            }
+            self.body.push_str("</w:tr>");
+        }
+        self.body.push_str("</w:tbl>");
+        // tables that follow each other would be merged without a paragraph between them
+        self.write_paragraph("", "");
+    }
+
+    /// Writes the code in a single paragraph with the code style
+    fn write_code(&mut self, code: &str) {
+        let content = code
+            .trim_end_matches('\n')
+            .lines()
+            .map(|line| run(line, &RunFormat::default()))
+            .collect::<Vec<String>>()
+            .join("<w:r><w:br/></w:r>");
+
+        self.write_paragraph("<w:pStyle w:val=\"Code\"/>", &content);
+    }
+
+    fn write_quote(&mut self, quote: &Quote) {
+        self.quote_depth += 1;
+        self.write_blocks(&quote.elements);
+        if let Some(attribution) = &quote.attribution {
+            let mut content = run(&format!("— {}", attribution.author), &RunFormat::default());
+            if let Some(source) = &attribution.source {
+                let format = RunFormat {
+                    italic: true,
+                    ..Default::default()
+                };
+                content.push_str(&run(", ", &RunFormat::default()));
+                content.push_str(&run(source, &format));
+            }
+            self.write_paragraph("<w:jc w:val=\"right\"/>", &content);
+        }
+        if let Some(metadata) = &quote.metadata {
+            let text = format!("— {}", metadata_text(metadata));
+            self.write_paragraph(
+                "<w:jc w:val=\"right\"/>",
+                &run(&text, &RunFormat::default()),
+            );
+        }
+        self.quote_depth -= 1;
+    }
+
+    fn write_definition_list(&mut self, list: &DefinitionList) {
+        for item in &list.items {
+            let format = RunFormat {
+                bold: true,
+                ..Default::default()
+            };
+            let term = self.text_xml(&item.term, &format);
+            self.write_paragraph("<w:keepNext/><w:spacing w:after=\"0\"/>", &term);
+
+            for definition in &item.definitions {
+                let definition = self.text_xml(definition, &RunFormat::default());
+                self.write_paragraph("<w:pStyle w:val=\"Definition\"/>", &definition);
+            }
+        }
+    }
+
+    fn write_figure(&mut self, figure: &Figure) {
+        let image = self.image_xml(&figure.image);
+        self.write_paragraph("<w:keepNext/><w:jc w:val=\"center\"/>", &image);
+        let caption = self.text_xml(&figure.caption, &RunFormat::default());
+        self.write_paragraph("<w:pStyle w:val=\"Caption\"/>", &caption);
+    }
+
+    fn line_xml(&mut self, line: &Line, format: &RunFormat) -> String {
+        match line {
+            Line::Text(text) => self.text_xml(text, format),
+            Line::Centered(centered) => self.text_xml(&centered.line, format),
+            Line::RefLink(link) => {
+                let description = self.text_xml(&link.description, &link_format(format));
+                internal_link(&link.reference, &description)
+            }
+            Line::Anchor(anchor) => {
+                let mut xml = self.bookmark(&anchor.key);
+                xml.push_str(&self.line_xml(&anchor.inner, format));
+                xml
+            }
+            Line::Ruler(_)
+            | Line::BibEntry(_)
+            | Line::FootnoteDefinition(_)
+            | Line::AbbreviationDefinition(_) => String::new(),
+        }
+    }
+
+    /// Returns the runs of the text. A break at the end of the line is left out
+    /// as it is written as a break between the lines of a paragraph.
+    fn text_xml(&mut self, text: &TextLine, format: &RunFormat) -> String {
+        let subtext = text.without_break();
+
+        self.inlines_xml(subtext, format)
+    }
+
+    fn inlines_xml(&mut self, inlines: &[Inline], format: &RunFormat) -> String {
+        inlines
+            .iter()
+            .map(|inline| self.inline_xml(inline, format))
+            .collect()
+    }
+
+    fn inline_xml(&mut self, inline: &Inline, format: &RunFormat) -> String {
+        let mut format = format.clone();
+
+        match inline {
+            Inline::Plain(plain) => run(&plain.value, &format),
+            Inline::Bold(bold) => {
+                format.bold = true;
+                self.inlines_xml(&bold.value, &format)
+            }
+            Inline::Italic(italic) => {
+                format.italic = true;
+                self.inlines_xml(&italic.value, &format)
+            }
+            Inline::Underlined(under) => {
+                format.underlined = true;
+                self.inlines_xml(&under.value, &format)
+            }
+            Inline::Striked(striked) => {
+                format.striked = true;
+                self.inlines_xml(&striked.value, &format)
+            }
+            Inline::Spoiler(spoiler) => self.inlines_xml(&spoiler.value, &format),
+            Inline::Highlight(highlight) => {
+                format.highlight = Some(
+                    highlight
+                        .get_color()
+                        .and_then(|c| color_to_hex(&c))
+                        .unwrap_or_else(|| "FFFF00".to_string()),
+                );
+                self.inlines_xml(&highlight.value, &format)
+            }
+            Inline::Styled(styled) => {
+                if let Some(color) = styled
+                    .metadata
+                    .get_string("color")
+                    .and_then(|c| color_to_hex(&c))
+                {
+                    format.color = Some(color);
+                }
+                self.inline_xml(&styled.value, &format)
+            }
+            Inline::Colored(colored) => {
+                if let Some(color) = color_to_hex(&colored.color) {
+                    format.color = Some(color);
+                }
+                self.inline_xml(&colored.value, &format)
+            }
+            Inline::Monospace(mono) => {
+                format.monospace = true;
+                run(&mono.value, &format)
+            }
+            Inline::Superscript(sup) => {
+                format.vertical_align = Some("superscript");
+                self.inlines_xml(&sup.value, &format)
+            }
+            Inline::Subscript(sub) => {
+                format.vertical_align = Some("subscript");
+                self.inlines_xml(&sub.value, &format)
+            }
+            Inline::Url(url) => self.url_xml(url, &format),
+            Inline::Image(image) => self.image_xml(image),
+            Inline::Placeholder(placeholder) => {
+                let placeholder = placeholder.read().unwrap();
+                match &placeholder.value {
+                    Some(Element::Inline(inline)) => self.inline_xml(inline, &format),
+                    Some(Element::Line(line)) => self.line_xml(line, &format),
+                    Some(Element::Block(_)) => String::new(),
+                    None => run(&format!("[[{}]]", placeholder.name), &format),
+                }
+            }
+            Inline::Checkbox(checkbox) => run(checkbox_text(checkbox.value), &format),
+            Inline::Emoji(emoji) => run(&emoji.value, &format),
+            Inline::Math(math) => {
+                format.monospace = true;
+                run(math.expression.source(), &format)
+            }
+            Inline::BibReference(bib) => {
+                let bib = bib.read().unwrap();
+                if let Some(fallback) = &bib.fallback {
+                    if bib.entry_anchor.lock().entry.is_none() {
+                        return run(fallback, &format);
+                    }
+                }
+                format.vertical_align = Some("superscript");
+                let text = run(&bib.get_formatted(), &link_format(&format));
+                internal_link(&bib.key, &text)
+            }
+            Inline::GlossaryReference(gloss) => {
+                let gloss = gloss.lock();
+                if let Some(entry) = &gloss.entry {
+                    let entry = entry.lock();
+                    let text = match gloss.display {
+                        GlossaryDisplay::Short => &entry.short,
+                        GlossaryDisplay::Long => &entry.long,
+                    };
+                    internal_link(&gloss.short, &run(text, &link_format(&format)))
+                } else {
+                    run(&format!("~{}", gloss.short), &format)
+                }
+            }
+            Inline::FootnoteReference(footnote) => {
+                let footnote = footnote.lock();
+                if let Some(definition) = &footnote.definition {
+                    let definition = definition.lock();
+                    format.vertical_align = Some("superscript");
+                    let number = definition.number.unwrap_or_default().to_string();
+                    internal_link(
+                        &definition.get_anchor_key(),
+                        &run(&number, &link_format(&format)),
+                    )
+                } else {
+                    let citation = Inline::BibReference(footnote.citation.clone());
+                    self.inline_xml(&citation, &format)
+                }
+            }
+            Inline::TemplateVar(var) => {
+                let var = var.read().unwrap();
+                match &var.value {
+                    Some(value) => {
+                        let mut xml = run(&var.prefix, &format);
+                        xml.push_str(&self.element_xml(value, &format));
+                        xml.push_str(&run(&var.suffix, &format));
+                        xml
+                    }
+                    None => String::new(),
+                }
+            }
+            Inline::CharacterCode(code) => {
+                let text = htmlescape::decode_html(&format!("&{};", code.code))
+                    .unwrap_or_else(|_| format!("&{};", code.code));
+                run(&text, &format)
+            }
+            Inline::LineBreak => "<w:r><w:br/></w:r>".to_string(),
+            Inline::Arrow(arrow) => run(arrow_text(arrow), &format),
+            Inline::Anchor(anchor) => {
+                let mut xml = self.bookmark(&anchor.key);
+                xml.push_str(&self.line_xml(&anchor.inner, &format));
+                xml
+            }
+            Inline::Abbreviation(abbreviation) => run(&abbreviation.short, &format),
+            Inline::Html(_) | Inline::Comment(_) => String::new(),
+        }
+    }
+
+    /// Returns the runs of an element that is the value of a template variable
+    fn element_xml(&mut self, element: &Element, format: &RunFormat) -> String {
+        match element {
+            Element::Inline(inline) => self.inline_xml(inline, format),
+            Element::Line(line) => self.line_xml(line, format),
+            Element::Block(block) => match block.as_ref() {
+                Block::Paragraph(paragraph) => paragraph
+                    .elements
+                    .iter()
+                    .map(|line| self.line_xml(line, format))
+                    .collect::<Vec<String>>()
+                    .join(&run(" ", format)),
+                _ => String::new(),
+            },
+        }
+    }
+
+    fn url_xml(&mut self, url: &Url, format: &RunFormat) -> String {
+        let format = link_format(format);
+        let text = match &url.description {
+            Some(description) => self.inlines_xml(description, &format),
+            None => run(&url.url, &format),
+        };
+        if let Some(anchor) = url.url.strip_prefix('#') {
+            return internal_link(anchor, &text);
+        }
+        let id = self.add_relationship("hyperlink", url.url.clone(), true);
+
+        format!("<w:hyperlink r:id=\"{}\">{}</w:hyperlink>", id, text)
+    }
+
+    /// Returns a drawing that embeds the image into the document.
+    /// Images that can't be read are written as links.
+    fn image_xml(&mut self, image: &Image) -> String {
+        let data = image.get_content().or_else(|| {
+            let path = image.image_data.lock().path.clone();
+            std::fs::read(path).ok()
+        });
+        let embedded = data.and_then(|data| {
+            let extension = match image::guess_format(&data).ok()? {
+                ImageFormat::Png => "png",
+                ImageFormat::Jpeg => "jpeg",
+                ImageFormat::Gif => "gif",
+                ImageFormat::Bmp => "bmp",
+                ImageFormat::Tiff => "tiff",
+                _ => return None,
+            };
+            let dimensions = image::io::Reader::new(Cursor::new(&data))
+                .with_guessed_format()
+                .ok()?
+                .into_dimensions()
+                .ok()?;
+            Some((extension, dimensions, data))
+        });
+        let (extension, (width, height), data) = match embedded {
+            Some(embedded) => embedded,
+            None => {
+                log::warn!("Failed to embed image {}", image.url.url);
+                return self.url_xml(&image.url, &RunFormat::default());
+            }
+        };
+
+        let (mut width, mut height) = (width as u64 * EMU_PER_PIXEL, height as u64 * EMU_PER_PIXEL);
+        if let Some(target) = image.metadata.as_ref().and_then(|m| m.get_integer("width")) {
+            let target = target.max(1) as u64 * EMU_PER_PIXEL;
+            height = height * target / width.max(1);
+            width = target;
+        }
+        if width > MAX_IMAGE_WIDTH {
+            height = height * MAX_IMAGE_WIDTH / width;
+            width = MAX_IMAGE_WIDTH;
+        }
+        let name = format!("image{}.{}", self.media.len() + 1, extension);
+        let id = self.add_relationship("image", format!("media/{}", name), false);
+        self.media.push((name.clone(), data));
+        self.drawings += 1;
+        let description = image
+            .url
+            .description
+            .iter()
+            .flatten()
+            .map(|i| i.as_plain_text().value)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!(
+            "<w:r><w:drawing><wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\">\
+            <wp:extent cx=\"{w}\" cy=\"{h}\"/><wp:docPr id=\"{n}\" name=\"{name}\" descr=\"{descr}\"/>\
+            <a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">\
+            <pic:pic><pic:nvPicPr><pic:cNvPr id=\"{n}\" name=\"{name}\"/><pic:cNvPicPr/></pic:nvPicPr>\
+            <pic:blipFill><a:blip r:embed=\"{id}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>\
+            <pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{w}\" cy=\"{h}\"/></a:xfrm>\
+            <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic>\
+            </a:graphicData></a:graphic></wp:inline></w:drawing></w:r>",
+            w = width,
+            h = height,
+            n = self.drawings,
+            name = name,
+            descr = encode_attribute(&description),
+            id = id
+        )
+    }
+
+    fn bookmark(&mut self, key: &str) -> String {
+        self.bookmarks += 1;
+
+        format!(
+            "<w:bookmarkStart w:id=\"{0}\" w:name=\"{1}\"/><w:bookmarkEnd w:id=\"{0}\"/>",
+            self.bookmarks,
+            bookmark_name(key)
+        )
+    }
+
+    /// Adds a relationship of the document and returns its id.
+    /// The first ids are used by the styles and the numbering.
+    fn add_relationship(&mut self, kind: &'static str, target: String, external: bool) -> String {
+        self.relationships.push(Relationship {
+            kind,
+            target,
+            external,
+        });
+
+        format!("rId{}", self.relationships.len() + 2)
+    }
+}
+
+impl MathExpression {
+    /// Returns the source of the expression as it was written
+    fn source(&self) -> &str {
+        match self {
+            MathExpression::AsciiMath { source, .. } => source,
+            MathExpression::Tex(tex) => tex,
+        }
+    }
+}
+
+/// Returns a run of text with the given formatting
+fn run(text: &str, format: &RunFormat) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let mut properties = String::new();
+    if format.hyperlink {
+        properties.push_str("<w:rStyle w:val=\"Hyperlink\"/>");
+    } else if format.monospace {
+        properties.push_str("<w:rStyle w:val=\"CodeChar\"/>");
+    }
+    if format.bold {
+        properties.push_str("<w:b/>");
+    }
+    if format.italic {
+        properties.push_str("<w:i/>");
+    }
+    if format.striked {
+        properties.push_str("<w:strike/>");
+    }
+    if let Some(color) = &format.color {
+        properties.push_str(&format!("<w:color w:val=\"{}\"/>", color));
+    }
+    if format.underlined {
+        properties.push_str("<w:u w:val=\"single\"/>");
+    }
+    if let Some(highlight) = &format.highlight {
+        properties.push_str(&format!(
+            "<w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"{}\"/>",
+            highlight
+        ));
+    }
+    if let Some(align) = format.vertical_align {
+        properties.push_str(&format!("<w:vertAlign w:val=\"{}\"/>", align));
+    }
+
+    format!(
+        "<w:r><w:rPr>{}</w:rPr><w:t xml:space=\"preserve\">{}</w:t></w:r>",
+        properties,
+        encode_minimal(text)
+    )
+}
+
+fn link_format(format: &RunFormat) -> RunFormat {
+    RunFormat {
+        hyperlink: true,
+        ..format.clone()
+    }
+}
+
+fn internal_link(key: &str, content: &str) -> String {
+    format!(
+        "<w:hyperlink w:anchor=\"{}\">{}</w:hyperlink>",
+        bookmark_name(key),
+        content
+    )
+}
+
+/// Returns a valid bookmark name for an anchor key.
+/// Names are limited to 40 characters and are hidden by starting with an underscore.
+fn bookmark_name(key: &str) -> String {
+    let name = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .take(39)
+        .collect::<String>();
+
+    format!("_{}", name)
+}
+
+/// Returns the hex value of a css color for the colors that can be represented in a docx
+fn color_to_hex(color: &str) -> Option<String> {
+    let color = color.trim().to_lowercase();
+    if let Some(hex) = color.strip_prefix('#') {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        return match hex.len() {
+            6 => Some(hex.to_uppercase()),
+            3 => Some(
+                hex.chars()
+                    .flat_map(|c| [c, c])
+                    .collect::<String>()
+                    .to_uppercase(),
+            ),
+            _ => None,
+        };
+    }
+    let hex = match color.as_str() {
+        "black" => "000000",
+        "white" => "FFFFFF",
+        "red" => "FF0000",
+        "green" => "008000",
+        "lime" => "00FF00",
+        "blue" => "0000FF",
+        "yellow" => "FFFF00",
+        "orange" => "FFA500",
+        "purple" => "800080",
+        "magenta" | "fuchsia" => "FF00FF",
+        "cyan" | "aqua" => "00FFFF",
+        "gray" | "grey" => "808080",
+        "brown" => "A52A2A",
+        "pink" => "FFC0CB",
+        _ => return None,
+    };
+
+    Some(hex.to_string())
+}
+
+fn metadata_text(metadata: &InlineMetadata) -> String {
+    let mut entries = metadata
+        .get_string_map()
+        .into_iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<String>>();
+    entries.sort();
+
+    entries.join(", ")
+}
+
+fn checkbox_text(checked: bool) -> &'static str {
+    if checked {
+        "☒"
+    } else {
+        "☐"
+    }
+}
+
+fn arrow_text(arrow: &Arrow) -> &'static str {
+    match arrow {
+        Arrow::RightArrow => "→",
+        Arrow::LeftArrow => "←",
+        Arrow::LeftRightArrow => "↔",
+        Arrow::BigRightArrow => "⇒",
+        Arrow::BigLeftArrow => "⇐",
+        Arrow::BigLeftRightArrow => "⇔",
+    }
+}
+
+/// Creates the numbering definitions with a bullet and a decimal numbering
+/// and an instance for every list
+fn create_numbering(lists: &[(bool, usize)]) -> String {
+    let mut numbering = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?><w:numbering {}>",
+        NAMESPACES
+    );
+    for (id, ordered) in [(0, false), (1, true)] {
+        numbering.push_str(&format!(
+            "<w:abstractNum w:abstractNumId=\"{}\"><w:multiLevelType w:val=\"hybridMultilevel\"/>",
+            id
+        ));
+        for level in 0..9 {
+            let (format, text) = if ordered {
+                ("decimal", format!("%{}.", level + 1))
+            } else {
+                ("bullet", ["•", "◦", "▪"][level % 3].to_string())
+            };
+            numbering.push_str(&format!(
+                "<w:lvl w:ilvl=\"{0}\"><w:start w:val=\"1\"/><w:numFmt w:val=\"{1}\"/>\
+                <w:lvlText w:val=\"{2}\"/><w:lvlJc w:val=\"left\"/>\
+                <w:pPr><w:ind w:left=\"{3}\" w:hanging=\"360\"/></w:pPr></w:lvl>",
+                level,
+                format,
+                text,
+                720 * (level + 1)
+            ));
+        }
+        numbering.push_str("</w:abstractNum>");
+    }
+    for (index, (ordered, level)) in lists.iter().enumerate() {
+        numbering.push_str(&format!(
+            "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"{}\"/>\
+            <w:lvlOverride w:ilvl=\"{}\"><w:startOverride w:val=\"1\"/></w:lvlOverride></w:num>",
+            index + 1,
+            *ordered as u8,
+            level.min(&8)
+        ));
+    }
+    numbering.push_str("</w:numbering>");
+
+    numbering
+}
+
+fn create_relationships(relationships: &[Relationship]) -> String {
+    let mut rels = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+        <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+        <Relationship Id=\"rId1\" Type=\"{0}/styles\" Target=\"styles.xml\"/>\
+        <Relationship Id=\"rId2\" Type=\"{0}/numbering\" Target=\"numbering.xml\"/>",
+        RELATIONSHIP_TYPE
+    );
+    for (index, relationship) in relationships.iter().enumerate() {
+        let mode = if relationship.external {
+            " TargetMode=\"External\""
+        } else {
+            ""
+        };
+        rels.push_str(&format!(
+            "<Relationship Id=\"rId{}\" Type=\"{}/{}\" Target=\"{}\"{}/>",
+            index + 3,
+            RELATIONSHIP_TYPE,
+            relationship.kind,
+            encode_attribute(&relationship.target),
+            mode
+        ));
+    }
+    rels.push_str("</Relationships>");
+
+    rels
+}
+
+/// Creates the main document part with an A4 page
+fn create_document(body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+        <w:document {}><w:body>{}\
+        <w:sectPr><w:pgSz w:w=\"11906\" w:h=\"16838\"/>\
+        <w:pgMar w:top=\"1440\" w:right=\"1440\" w:bottom=\"1440\" w:left=\"1440\" w:header=\"708\" w:footer=\"708\" w:gutter=\"0\"/>\
+        </w:sectPr></w:body></w:document>",
+        NAMESPACES, body
+    )
+}
+
+fn create_core_properties(config: &Settings) -> String {
+    let metadata = &config.metadata;
+    let mut properties = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+        <cp:coreProperties xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" \
+        xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:dcterms=\"http://purl.org/dc/terms/\" \
+        xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">"
+        .to_string();
+
+    if let Some(title) = &metadata.title {
+        properties.push_str(&format!("<dc:title>{}</dc:title>", encode_minimal(title)));
+    }
+    if let Some(author) = &metadata.author {
+        properties.push_str(&format!(
+            "<dc:creator>{}</dc:creator>",
+            encode_minimal(author)
+        ));
+    }
+    if let Some(description) = &metadata.description {
+        properties.push_str(&format!(
+            "<dc:description>{}</dc:description>",
+            encode_minimal(description)
+        ));
+    }
+    if !metadata.keywords.is_empty() {
+        properties.push_str(&format!(
+            "<cp:keywords>{}</cp:keywords>",
+            encode_minimal(&metadata.keywords.join(", "))
+        ));
+    }
+    properties.push_str(&format!(
+        "<dc:language>{}</dc:language><dcterms:created xsi:type=\"dcterms:W3CDTF\">{}</dcterms:created>\
+        </cp:coreProperties>",
+        encode_minimal(&metadata.language),
+        chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ")
+    ));
+
+    properties
+}
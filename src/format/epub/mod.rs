@@ -0,0 +1,201 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Block, Document};
+use crate::format::html::html_writer::HTMLWriter;
+use crate::format::html::to_html::ToHtml;
+use crate::format::style::get_css_for_theme;
+use crate::settings::Settings;
+use htmlescape::{encode_attribute, encode_minimal};
+use parking_lot::Mutex;
+use sha2::Digest;
+use std::io::{self, Cursor, Write};
+use std::sync::Arc;
+use zip::result::ZipResult;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const CONTAINER_XML: &str = include_str!("assets/container.xml");
+
+/// A single chapter of the book that is stored as its own xhtml file
+struct Chapter<'a> {
+    title: String,
+    blocks: Vec<&'a Block>,
+}
+
+/// A writer that stores written data in a shared buffer
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Renders the document to an epub and returns the resulting bytes.
+/// Every top level section becomes a chapter of the book.
+pub fn render_to_epub(document: &Document) -> ZipResult<Vec<u8>> {
+    let config = document.config.lock().clone();
+    let chapters = get_chapters(document, &config);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(create_package(&config, chapters.len()).as_bytes())?;
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(create_navigation(&config, &chapters).as_bytes())?;
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(get_css_for_theme(config.style.theme.clone()).as_bytes())?;
+
+    for (index, chapter) in chapters.iter().enumerate() {
+        log::debug!("Rendering chapter {}", chapter.title);
+        zip.start_file(format!("OEBPS/{}", get_chapter_file(index)), deflated)?;
+        zip.write_all(create_chapter(&config, chapter)?.as_bytes())?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Splits the document into chapters at its top level sections
+fn get_chapters<'a>(document: &'a Document, config: &Settings) -> Vec<Chapter<'a>> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+
+    for block in &document.elements {
+        match block {
+            Block::Section(section) => chapters.push(Chapter {
+                title: section.header.get_plain_text(),
+                blocks: vec![block],
+            }),
+            _ => {
+                if let Some(chapter) = chapters.last_mut() {
+                    chapter.blocks.push(block)
+                } else {
+                    chapters.push(Chapter {
+                        title: config.metadata.title.clone().unwrap_or_default(),
+                        blocks: vec![block],
+                    })
+                }
+            }
+        }
+    }
+
+    chapters
+}
+
+fn get_chapter_file(index: usize) -> String {
+    format!("chapter-{}.xhtml", index)
+}
+
+/// Creates the opf package document
+fn create_package(config: &Settings, chapter_count: usize) -> String {
+    let metadata = &config.metadata;
+    let title = metadata
+        .title
+        .clone()
+        .unwrap_or_else(|| "Untitled".to_string());
+    let mut hasher = sha2::Sha256::default();
+    hasher.update(title.as_bytes());
+    hasher.update(metadata.author.clone().unwrap_or_default().as_bytes());
+
+    let mut package = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+        <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"uid\">\
+        <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+        <dc:identifier id=\"uid\">urn:snekdown:{:x}</dc:identifier>\
+        <dc:title>{}</dc:title>\
+        <dc:language>{}</dc:language>\
+        <meta property=\"dcterms:modified\">{}</meta>",
+        hasher.finalize(),
+        encode_minimal(&title),
+        encode_minimal(&metadata.language),
+        chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+    );
+    if let Some(author) = &metadata.author {
+        package.push_str(&format!(
+            "<dc:creator>{}</dc:creator>",
+            encode_minimal(author)
+        ));
+    }
+    if let Some(description) = &metadata.description {
+        package.push_str(&format!(
+            "<dc:description>{}</dc:description>",
+            encode_minimal(description)
+        ));
+    }
+    package.push_str(
+        "</metadata><manifest>\
+        <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\
+        <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>",
+    );
+    for index in 0..chapter_count {
+        package.push_str(&format!(
+            "<item id=\"chapter-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>",
+            index,
+            get_chapter_file(index)
+        ));
+    }
+    package.push_str("</manifest><spine>");
+    for index in 0..chapter_count {
+        package.push_str(&format!("<itemref idref=\"chapter-{}\"/>", index));
+    }
+    package.push_str("</spine></package>");
+
+    package
+}
+
+/// Creates the navigation document with links to all chapters
+fn create_navigation(config: &Settings, chapters: &[Chapter]) -> String {
+    let mut nav = get_xhtml_head(config, "Table of Contents");
+    nav.push_str("<nav epub:type=\"toc\" id=\"toc\"><ol>");
+    for (index, chapter) in chapters.iter().enumerate() {
+        nav.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>",
+            get_chapter_file(index),
+            encode_minimal(&chapter.title)
+        ));
+    }
+    nav.push_str("</ol></nav></body></html>");
+
+    nav
+}
+
+/// Renders a single chapter to xhtml
+fn create_chapter(config: &Settings, chapter: &Chapter) -> io::Result<String> {
+    let buffer = SharedBuffer::default();
+    let mut writer = HTMLWriter::new(Box::new(buffer.clone()), config.style.theme.clone());
+    writer.set_sanitize_html(config.features.sanitize_html);
+    writer.write(get_xhtml_head(config, &chapter.title))?;
+    writer.write("<div class=\"content\">".to_string())?;
+    for block in &chapter.blocks {
+        block.to_html(&mut writer)?;
+    }
+    writer.write("</div></body></html>".to_string())?;
+    writer.flush()?;
+    let content = buffer.0.lock().clone();
+
+    String::from_utf8(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn get_xhtml_head(config: &Settings, title: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><!DOCTYPE html>\
+        <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"{0}\" xml:lang=\"{0}\">\
+        <head><meta charset=\"UTF-8\"/><title>{1}</title>\
+        <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/></head><body>",
+        encode_attribute(&config.metadata.language),
+        encode_minimal(title)
+    )
+}
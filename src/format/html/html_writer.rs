@@ -8,15 +8,20 @@ use crate::settings::style_settings::Theme;
 use std::io;
 use std::io::Write;
 
-pub struct HTMLWriter {
-    inner: Box<dyn Write>,
+pub struct HTMLWriter<'a> {
+    inner: Box<dyn Write + 'a>,
     theme: Theme,
+    sanitize_html: bool,
 }
 
-impl HTMLWriter {
+impl<'a> HTMLWriter<'a> {
     /// Creates a new writer
-    pub fn new(inner: Box<dyn Write>, theme: Theme) -> Self {
-        Self { inner, theme }
+    pub fn new(inner: Box<dyn Write + 'a>, theme: Theme) -> Self {
+        Self {
+            inner,
+            theme,
+            sanitize_html: false,
+        }
     }
 
     /// Writes a raw string
@@ -40,7 +45,17 @@ impl HTMLWriter {
     }
 
     /// Return the theme of the html writer
-    pub fn get_theme(&mut self) -> Theme {
+    pub fn get_theme(&self) -> Theme {
         self.theme.clone()
     }
+
+    /// Sets if raw html is sanitized before it's written
+    pub fn set_sanitize_html(&mut self, sanitize: bool) {
+        self.sanitize_html = sanitize;
+    }
+
+    /// Returns if raw html is sanitized before it's written
+    pub fn get_sanitize_html(&self) -> bool {
+        self.sanitize_html
+    }
 }
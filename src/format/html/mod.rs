@@ -5,4 +5,6 @@
  */
 
 pub mod html_writer;
+pub mod site;
+pub mod slides;
 pub mod to_html;
@@ -0,0 +1,193 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Block, Document, TocOptions};
+use crate::format::html::html_writer::HTMLWriter;
+use crate::format::html::to_html::{render_to_string, write_head, ToHtml};
+use htmlescape::{decode_html, encode_attribute, encode_minimal};
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const INDEX_FILE: &str = "index.html";
+const NAVIGATION_STYLE: &str =
+    ".siteNavigation{display:flex;justify-content:space-between;margin:1em 0}";
+
+/// A single page of the site
+struct Page<'a> {
+    file_name: String,
+    title: String,
+    blocks: Vec<&'a Block>,
+}
+
+/// Renders the document as a static site into the given directory.
+/// Every top level section is written to its own page with links to the previous
+/// and next page. The index page contains the content before the first section
+/// and the table of contents of the whole document.
+pub fn render_site(document: &Document, output_dir: &Path) -> io::Result<()> {
+    let config = document.config.lock().clone();
+    let pages = get_pages(document);
+    let mut writer = HTMLWriter::new(Box::new(io::sink()), config.style.theme.clone());
+    writer.set_sanitize_html(config.features.sanitize_html);
+    let head = render_to_string(&writer, |w| write_head(document, w))?;
+
+    let mut contents = Vec::with_capacity(pages.len());
+    for (index, page) in pages.iter().enumerate() {
+        contents.push(render_to_string(&writer, |w| {
+            for block in &page.blocks {
+                block.to_html(w)?;
+            }
+            if index == 0 {
+                document.create_toc(&TocOptions::default()).to_html(w)?;
+            }
+            Ok(())
+        })?);
+    }
+    let contents = link_pages(&pages, contents);
+    fs::create_dir_all(output_dir)?;
+
+    for (index, (page, content)) in pages.iter().zip(contents).enumerate() {
+        let navigation = get_navigation(&pages, index);
+        let html = format!(
+            "<!DOCTYPE html><html lang=\"{}\"><head>{}<style>{}</style></head>\
+            <body><div class=\"content\">{}{}{}</div></body></html>",
+            encode_attribute(&config.metadata.language),
+            head,
+            NAVIGATION_STYLE,
+            navigation,
+            content,
+            navigation
+        );
+        log::debug!("Writing page {}", page.title);
+        fs::write(output_dir.join(&page.file_name), html)?;
+    }
+
+    Ok(())
+}
+
+/// Splits the document into the index page and one page per top level section
+fn get_pages(document: &Document) -> Vec<Page<'_>> {
+    let title = document.config.lock().metadata.title.clone();
+    let mut pages = vec![Page {
+        file_name: INDEX_FILE.to_string(),
+        title: title.unwrap_or_else(|| "Index".to_string()),
+        blocks: Vec::new(),
+    }];
+    let mut file_names = HashSet::new();
+    file_names.insert(INDEX_FILE.to_string());
+
+    for block in &document.elements {
+        match block {
+            Block::Section(section) => {
+                let name = get_file_stem(&section.header.anchor);
+                let mut file_name = format!("{}.html", name);
+                let mut count = 1;
+                while file_names.contains(&file_name) {
+                    count += 1;
+                    file_name = format!("{}-{}.html", name, count);
+                }
+                file_names.insert(file_name.clone());
+                pages.push(Page {
+                    file_name,
+                    title: section.header.get_plain_text(),
+                    blocks: vec![block],
+                })
+            }
+            _ => pages.last_mut().unwrap().blocks.push(block),
+        }
+    }
+
+    pages
+}
+
+/// Returns a file name for the anchor of a section
+fn get_file_stem(anchor: &str) -> String {
+    let stem: String = anchor
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let stem = stem.trim_matches('-');
+
+    if stem.is_empty() {
+        "page".to_string()
+    } else {
+        stem.to_string()
+    }
+}
+
+/// Changes links to anchors on other pages to point to the file of that page
+fn link_pages(pages: &[Page], contents: Vec<String>) -> Vec<String> {
+    lazy_static::lazy_static! {
+        static ref ID: Regex = Regex::new(r#"\sid="([^"]+)""#).unwrap();
+        static ref HREF: Regex = Regex::new(r##"href="#([^"]+)""##).unwrap();
+    }
+    let page_ids: Vec<HashSet<String>> = contents
+        .iter()
+        .map(|content| {
+            ID.captures_iter(content)
+                .map(|c| decode_anchor(&c[1]))
+                .collect()
+        })
+        .collect();
+    let mut anchor_pages: HashMap<&str, &str> = HashMap::new();
+    for (page, ids) in pages.iter().zip(&page_ids) {
+        for id in ids {
+            anchor_pages.entry(id).or_insert(&page.file_name);
+        }
+    }
+
+    contents
+        .iter()
+        .zip(&page_ids)
+        .map(|(content, ids)| {
+            HREF.replace_all(content, |c: &Captures| {
+                let anchor = decode_anchor(&c[1]);
+                match anchor_pages.get(anchor.as_str()) {
+                    Some(file_name) if !ids.contains(&anchor) => {
+                        format!("href=\"{}#{}\"", file_name, &c[1])
+                    }
+                    _ => c[0].to_string(),
+                }
+            })
+            .to_string()
+        })
+        .collect()
+}
+
+/// Decodes html entities of an anchor as ids and links encode them differently
+fn decode_anchor(anchor: &str) -> String {
+    decode_html(anchor).unwrap_or_else(|_| anchor.to_string())
+}
+
+/// Returns the links to the previous, index and next page
+fn get_navigation(pages: &[Page], index: usize) -> String {
+    let link = |page: Option<&Page>, format: &str| {
+        page.map(|page| {
+            format!(
+                "<a href=\"{}\">{}</a>",
+                page.file_name,
+                format.replace("{}", &encode_minimal(&page.title))
+            )
+        })
+        .unwrap_or_else(|| "<span></span>".to_string())
+    };
+    let previous = index.checked_sub(1).and_then(|i| pages.get(i));
+
+    format!(
+        "<nav class=\"siteNavigation\">{}{}{}</nav>",
+        link(previous, "&larr; {}"),
+        link(pages.first().filter(|_| index > 1), "{}"),
+        link(pages.get(index + 1), "{} &rarr;")
+    )
+}
@@ -0,0 +1,183 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Block, Document, Header, Inline, InlineMetadata, Line, Metadata};
+use crate::format::html::html_writer::HTMLWriter;
+use crate::format::html::to_html::{write_math_script, ToHtml};
+use crate::settings::style_settings::Theme;
+use std::io;
+
+const REVEAL_URL: &str = "https://cdn.jsdelivr.net/npm/reveal.js@4.1.2/dist";
+
+/// A single slide of the deck
+#[derive(Default)]
+struct Slide<'a> {
+    header: Option<&'a Header>,
+    metadata: Option<&'a InlineMetadata>,
+    content: Vec<SlideContent<'a>>,
+}
+
+/// The content of a slide which is either a whole block, the header of a
+/// nested section or the lines of a paragraph that was split at a ruler
+enum SlideContent<'a> {
+    Block(&'a Block),
+    Header(&'a Header),
+    Lines(&'a [Line]),
+}
+
+/// Renders the document as a reveal.js slide deck.
+/// Every top level section and every ruler starts a new slide. The background
+/// and transition of a slide are taken from the metadata of its section.
+pub fn render_slides(document: &Document, writer: &mut HTMLWriter) -> io::Result<()> {
+    let config = document.config.lock().clone();
+    let reveal_theme = match config.style.theme {
+        Theme::GitHub | Theme::SolarizedLight | Theme::OceanLight | Theme::Light => "white",
+        Theme::SolarizedDark | Theme::OceanDark | Theme::MagicDark | Theme::Dark => "black",
+        Theme::Print => "simple",
+    };
+    writer.set_sanitize_html(config.features.sanitize_html);
+
+    writer.write("<!DOCTYPE html><html lang=\"".to_string())?;
+    writer.write_attribute(config.metadata.language.clone())?;
+    writer.write("\"><head><meta charset=\"UTF-8\">".to_string())?;
+    writer.write(
+        "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">".to_string(),
+    )?;
+    if let Some(title) = &config.metadata.title {
+        writer.write("<title>".to_string())?;
+        writer.write_escaped(title.clone())?;
+        writer.write("</title>".to_string())?;
+    }
+    writer.write(format!(
+        "<link rel=\"stylesheet\" href=\"{0}/reset.css\"><link rel=\"stylesheet\" href=\"{0}/reveal.css\">\
+        <link rel=\"stylesheet\" href=\"{0}/theme/{1}.css\">",
+        REVEAL_URL, reveal_theme
+    ))?;
+    write_math_script(writer, &config.features)?;
+    writer.write("</head><body><div class=\"reveal\"><div class=\"slides\">".to_string())?;
+
+    for slide in get_slides(document) {
+        write_slide(&slide, writer)?;
+    }
+    writer.write(format!(
+        "</div></div><script src=\"{}/reveal.js\"></script>\
+        <script>Reveal.initialize({{hash: true}});</script></body></html>",
+        REVEAL_URL
+    ))
+}
+
+/// Splits the document into slides at top level sections and rulers
+fn get_slides(document: &Document) -> Vec<Slide<'_>> {
+    let mut slides = vec![Slide::default()];
+
+    for block in &document.elements {
+        match block {
+            Block::Section(section) => {
+                slides.push(Slide {
+                    header: Some(&section.header),
+                    metadata: section.metadata.as_ref(),
+                    content: Vec::new(),
+                });
+                for block in &section.elements {
+                    add_block(&mut slides, block);
+                }
+            }
+            _ => add_block(&mut slides, block),
+        }
+    }
+    slides.retain(|slide| slide.header.is_some() || !slide.content.is_empty());
+
+    slides
+}
+
+/// Adds the block to the last slide. Nested sections are added without
+/// their section so that reveal.js doesn't treat them as vertical slides.
+/// Paragraphs are split at rulers with the lines after a ruler starting a new slide.
+fn add_block<'a>(slides: &mut Vec<Slide<'a>>, block: &'a Block) {
+    let paragraph = match block {
+        Block::Paragraph(paragraph) => paragraph,
+        Block::Section(section) => {
+            let slide = slides.last_mut().unwrap();
+            slide.content.push(SlideContent::Header(&section.header));
+            for block in &section.elements {
+                add_block(slides, block);
+            }
+            return;
+        }
+        _ => {
+            let slide = slides.last_mut().unwrap();
+            slide.content.push(SlideContent::Block(block));
+            return;
+        }
+    };
+    for (index, lines) in paragraph
+        .elements
+        .split(|line| matches!(line, Line::Ruler(_)))
+        .enumerate()
+    {
+        if index > 0 {
+            slides.push(Slide::default());
+        }
+        // the line break after a ruler is parsed as an empty line
+        let start = lines
+            .iter()
+            .position(|line| !is_empty_line(line))
+            .unwrap_or(lines.len());
+        if start < lines.len() {
+            let slide = slides.last_mut().unwrap();
+            slide.content.push(SlideContent::Lines(&lines[start..]));
+        }
+    }
+}
+
+fn is_empty_line(line: &Line) -> bool {
+    match line {
+        Line::Text(text) => text
+            .subtext
+            .iter()
+            .all(|inline| matches!(inline, Inline::LineBreak)),
+        _ => false,
+    }
+}
+
+fn write_slide(slide: &Slide, writer: &mut HTMLWriter) -> io::Result<()> {
+    writer.write("<section".to_string())?;
+    if let Some(metadata) = slide.metadata {
+        for (key, attribute) in &[
+            ("background", "data-background"),
+            ("transition", "data-transition"),
+        ] {
+            if let Some(value) = metadata.get_string(key) {
+                writer.write(format!(" {}=\"", attribute))?;
+                writer.write_attribute(value)?;
+                writer.write("\"".to_string())?;
+            }
+        }
+    }
+    writer.write(">".to_string())?;
+
+    if let Some(header) = slide.header {
+        header.to_html(writer)?;
+    }
+    for content in &slide.content {
+        match content {
+            SlideContent::Block(block) => block.to_html(writer)?,
+            SlideContent::Header(header) => header.to_html(writer)?,
+            SlideContent::Lines(lines) => {
+                writer.write("<div class=\"paragraph\">".to_string())?;
+                for (index, line) in lines.iter().enumerate() {
+                    if index > 0 {
+                        writer.write(" ".to_string())?;
+                    }
+                    line.to_html(writer)?;
+                }
+                writer.write("</div>".to_string())?;
+            }
+        }
+    }
+
+    writer.write("</section>".to_string())
+}
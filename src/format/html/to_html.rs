@@ -8,15 +8,31 @@ use crate::elements::*;
 use crate::format::html::html_writer::HTMLWriter;
 use crate::format::style::{get_code_theme_for_theme, get_css_for_theme};
 use crate::format::PlaceholderTemplate;
+use crate::references::abbreviations::Abbreviation;
+use crate::references::footnotes::FootnoteReference;
 use crate::references::glossary::{GlossaryDisplay, GlossaryReference};
 use crate::references::templates::{Template, TemplateVariable};
+use crate::settings::feature_settings::{FeatureSettings, MathRenderer};
 use asciimath_rs::format::mathml::ToMathML;
-use htmlescape::encode_attribute;
+use htmlescape::{encode_attribute, encode_minimal};
 use minify::html::minify;
+use regex::Regex;
+use std::collections::HashMap;
 use std::io;
-use syntect::html::highlighted_html_for_string;
+use std::sync::Arc;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Theme};
+use syntect::html::{
+    highlighted_html_for_string, start_highlighted_html_snippet, styled_line_to_highlighted_html,
+    IncludeBackground,
+};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
 
 const MATHJAX_URL: &str = "https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-mml-chtml.js";
+const KATEX_CSS_URL: &str = "https://cdn.jsdelivr.net/npm/katex@0.13.11/dist/katex.min.css";
+const KATEX_URL: &str = "https://cdn.jsdelivr.net/npm/katex@0.13.11/dist/katex.min.js";
+const KATEX_AUTO_RENDER_URL: &str =
+    "https://cdn.jsdelivr.net/npm/katex@0.13.11/dist/contrib/auto-render.min.js";
 
 pub trait ToHtml {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()>;
@@ -51,6 +67,9 @@ impl ToHtml for Inline {
             Inline::Url(url) => url.to_html(writer),
             Inline::Monospace(mono) => mono.to_html(writer),
             Inline::Striked(striked) => striked.to_html(writer),
+            Inline::Spoiler(spoiler) => spoiler.to_html(writer),
+            Inline::Highlight(highlight) => highlight.to_html(writer),
+            Inline::Styled(styled) => styled.to_html(writer),
             Inline::Plain(plain) => plain.to_html(writer),
             Inline::Italic(italic) => italic.to_html(writer),
             Inline::Underlined(under) => under.to_html(writer),
@@ -58,35 +77,72 @@ impl ToHtml for Inline {
             Inline::Image(img) => img.to_html(writer),
             Inline::Placeholder(placeholder) => placeholder.read().unwrap().to_html(writer),
             Inline::Superscript(superscript) => superscript.to_html(writer),
+            Inline::Subscript(subscript) => subscript.to_html(writer),
             Inline::Checkbox(checkbox) => checkbox.to_html(writer),
             Inline::Emoji(emoji) => emoji.to_html(writer),
             Inline::Colored(colored) => colored.to_html(writer),
             Inline::BibReference(bibref) => bibref.read().unwrap().to_html(writer),
             Inline::TemplateVar(var) => var.read().unwrap().to_html(writer),
             Inline::Math(m) => m.to_html(writer),
-            Inline::LineBreak => writer.write("<br>".to_string()),
+            Inline::LineBreak => writer.write("<br/>".to_string()),
             Inline::CharacterCode(code) => code.to_html(writer),
             Inline::GlossaryReference(gloss) => gloss.lock().to_html(writer),
+            Inline::FootnoteReference(footnote) => footnote.lock().to_html(writer),
             Inline::Arrow(a) => a.to_html(writer),
             Inline::Anchor(a) => a.to_html(writer),
+            Inline::Html(html) => html.to_html(writer),
+            Inline::Comment(_) => Ok(()),
+            Inline::Abbreviation(a) => a.to_html(writer),
         }
     }
 }
 
 impl ToHtml for Block {
+    /// Blocks with an id or classes given in their metadata are wrapped in a div with them
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        match self {
-            Block::Paragraph(para) => para.to_html(writer),
-            Block::List(list) => list.to_html(writer),
-            Block::Table(table) => table.to_html(writer),
-            Block::CodeBlock(code) => code.to_html(writer),
-            Block::Quote(quote) => quote.to_html(writer),
-            Block::Section(section) => section.to_html(writer),
-            Block::Import(import) => import.to_html(writer),
-            Block::Placeholder(placeholder) => placeholder.read().unwrap().to_html(writer),
-            Block::MathBlock(m) => m.to_html(writer),
-            _ => Ok(()),
+        let id = self.get_id();
+        let class = self.get_class();
+        if id.is_none() && class.is_none() {
+            return block_to_html(self, writer);
+        }
+        writer.write("<div".to_string())?;
+        if let Some(id) = id {
+            writer.write(" id=\"".to_string())?;
+            writer.write_attribute(id)?;
+            writer.write("\"".to_string())?;
         }
+        if let Some(class) = class {
+            writer.write(" class=\"".to_string())?;
+            writer.write_attribute(class)?;
+            writer.write("\"".to_string())?;
+        }
+        writer.write(">".to_string())?;
+        block_to_html(self, writer)?;
+
+        writer.write("</div>".to_string())
+    }
+}
+
+fn block_to_html(block: &Block, writer: &mut HTMLWriter) -> io::Result<()> {
+    match block {
+        Block::Paragraph(para) => para.to_html(writer),
+        Block::List(list) => list.to_html(writer),
+        Block::Table(table) => table.to_html(writer),
+        Block::CodeBlock(code) => code.to_html(writer),
+        Block::Diagram(diagram) => diagram.to_html(writer),
+        Block::Html(html) => html.to_html(writer),
+        Block::Quote(quote) => quote.to_html(writer),
+        Block::Aligned(aligned) => aligned.to_html(writer),
+        Block::Columns(columns) => columns.to_html(writer),
+        Block::CustomContainer(container) => container.to_html(writer),
+        Block::PageBreak(_) => writer.write("<div class=\"pageBreak\"></div>".to_string()),
+        Block::DefinitionList(list) => list.to_html(writer),
+        Block::Figure(figure) => figure.to_html(writer),
+        Block::Section(section) => section.to_html(writer),
+        Block::Import(import) => import.to_html(writer),
+        Block::Placeholder(placeholder) => placeholder.read().unwrap().to_html(writer),
+        Block::MathBlock(m) => m.to_html(writer),
+        _ => Ok(()),
     }
 }
 
@@ -112,73 +168,18 @@ impl ToHtml for Document {
         };
 
         if self.is_root {
-            let metadata = self.config.lock().metadata.clone();
+            let features = self.config.lock().features.clone();
+            writer.set_sanitize_html(features.sanitize_html);
 
-            let style = minify(get_css_for_theme(writer.get_theme()).as_str());
+            if let Some(template) = &self.html_template {
+                return write_template(self, template, writer);
+            }
+            let language = self.config.lock().metadata.language.clone();
             writer.write("<!DOCTYPE html>".to_string())?;
             writer.write("<html lang=\"".to_string())?;
-            writer.write_attribute(metadata.language)?;
+            writer.write_attribute(language)?;
             writer.write("\"><head>".to_string())?;
-            writer.write("<meta charset=\"UTF-8\">".to_string())?;
-
-            if let Some(author) = metadata.author {
-                writer.write("<meta name=\"author\" content=\"".to_string())?;
-                writer.write_attribute(author)?;
-                writer.write("\">".to_string())?;
-            }
-
-            if let Some(title) = metadata.title {
-                writer.write("<title>".to_string())?;
-                writer.write_escaped(title.clone())?;
-                writer.write("</title>".to_string())?;
-                writer.write("<meta name=\"title\" content=\"".to_string())?;
-                writer.write_attribute(title)?;
-                writer.write("\">".to_string())?;
-            }
-
-            if let Some(description) = metadata.description {
-                writer.write("<meta name=\"description\" content=\"".to_string())?;
-                writer.write_attribute(description)?;
-                writer.write("\">".to_string())?;
-            }
-
-            if !metadata.keywords.is_empty() {
-                writer.write("<meta name=\"keywords\" content=\"".to_string())?;
-                writer.write_attribute(
-                    metadata
-                        .keywords
-                        .iter()
-                        .fold("".to_string(), |a, b| format!("{}, {}", a, b))
-                        .trim_start_matches(", ")
-                        .to_string(),
-                )?;
-                writer.write("\">".to_string())?;
-            }
-
-            writer.write("<style>".to_string())?;
-            writer.write(style)?;
-            writer.write("</style>".to_string())?;
-
-            if self.config.lock().features.include_mathjax {
-                writer.write(format!(
-                    "<script id=\"MathJax-script\" type=\"text/javascript\" async src={}></script>",
-                    MATHJAX_URL
-                ))?;
-            }
-
-            for stylesheet in &self.stylesheets {
-                let mut stylesheet = stylesheet.lock();
-                let data = std::mem::replace(&mut stylesheet.data, None);
-                if let Some(data) = data {
-                    writer.write("<style>".to_string())?;
-                    writer.write(minify(String::from_utf8(data).unwrap().as_str()))?;
-                    writer.write("</style>".to_string())?;
-                } else {
-                    writer.write("<link rel=\"stylsheet\" href=\"".to_string())?;
-                    writer.write_attribute(stylesheet.path.clone())?;
-                    writer.write("\">".to_string())?;
-                }
-            }
+            write_head(self, writer)?;
             writer.write("</head><body><div class=\"content\">".to_string())?;
             for element in &self.elements {
                 element.to_html(writer)?;
@@ -201,33 +202,76 @@ impl ToHtml for Document {
 
 impl ToHtml for Math {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        writer.write("<math xmlns='http://www.w3.org/1998/Math/MathML'>".to_string())?;
-        writer.write(self.expression.to_mathml())?;
+        match &self.expression {
+            MathExpression::AsciiMath { expression, .. } => {
+                writer.write("<math xmlns='http://www.w3.org/1998/Math/MathML'>".to_string())?;
+                writer.write(expression.to_mathml())?;
 
-        writer.write("</math>".to_string())
+                writer.write("</math>".to_string())
+            }
+            MathExpression::Tex(tex) => {
+                writer.write("<span class=\"math inline\">\\(".to_string())?;
+                writer.write_escaped(tex.clone())?;
+
+                writer.write("\\)</span>".to_string())
+            }
+        }
     }
 }
 
 impl ToHtml for MathBlock {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        writer.write(
-            "<math xmlns='http://www.w3.org/1998/Math/MathML' display='block'>".to_string(),
-        )?;
-        writer.write(self.expression.to_mathml())?;
+        match &self.expression {
+            MathExpression::AsciiMath { expression, .. } => {
+                writer.write(
+                    "<math xmlns='http://www.w3.org/1998/Math/MathML' display='block'>".to_string(),
+                )?;
+                writer.write(expression.to_mathml())?;
+
+                writer.write("</math>".to_string())
+            }
+            MathExpression::Tex(tex) => {
+                writer.write("<div class=\"math display\">\\[".to_string())?;
+                writer.write_escaped(tex.clone())?;
 
-        writer.write("</math>".to_string())
+                writer.write("\\]</div>".to_string())
+            }
+        }
     }
 }
 
 impl ToHtml for Import {
-    fn to_html(&self, _writer: &mut HTMLWriter) -> io::Result<()> {
+    /// Imports are included into the importing document while parsing
+    /// apart from lazy imports that are parsed when they're rendered
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        let mut anchor = self.anchor.write().unwrap();
+        anchor.materialize();
+        if let Some(document) = &anchor.document {
+            for element in &document.elements {
+                element.to_html(writer)?;
+            }
+        }
+
         Ok(())
     }
 }
 
 impl ToHtml for Section {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        writer.write("<section>".to_string())?;
+        let classes = [
+            (self.breaks_before(), "pageBreakBefore"),
+            (self.breaks_after(), "pageBreakAfter"),
+            (self.is_landscape(), "landscape"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, class)| *class)
+        .collect::<Vec<&str>>();
+        if classes.is_empty() {
+            writer.write("<section>".to_string())?;
+        } else {
+            writer.write(format!("<section class=\"{}\">", classes.join(" ")))?;
+        }
         self.header.to_html(writer)?;
         for element in &self.elements {
             element.to_html(writer)?;
@@ -242,6 +286,11 @@ impl ToHtml for Header {
         writer.write(" id=\"".to_string())?;
         writer.write_attribute(self.anchor.clone())?;
         writer.write("\">".to_string())?;
+        if let Some(number) = &self.number {
+            writer.write("<span class=\"header-number\">".to_string())?;
+            writer.write_escaped(number.clone())?;
+            writer.write("</span> ".to_string())?;
+        }
         self.line.to_html(writer)?;
 
         writer.write(format!("</h{}>", self.size))
@@ -268,60 +317,127 @@ impl ToHtml for Paragraph {
 
 impl ToHtml for List {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        if self.ordered {
-            writer.write("<ol>".to_string())?;
-            for item in &self.items {
-                item.to_html(writer)?;
-            }
-            writer.write("</ol>".to_string())
-        } else {
-            writer.write("<ul>".to_string())?;
-            for item in &self.items {
-                item.to_html(writer)?;
-            }
-
-            writer.write("</ul>".to_string())
-        }
+        list_items_to_html(&self.items, self.ordered, self.loose, writer)
     }
 }
 
 impl ToHtml for ListItem {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        writer.write("<li>".to_string())?;
-        self.text.to_html(writer)?;
+        list_item_to_html(self, false, writer)
+    }
+}
 
-        if let Some(first) = self.children.first() {
-            if first.ordered {
-                writer.write("<ol>".to_string())?;
-                for item in &self.children {
-                    item.to_html(writer)?;
-                }
-                writer.write("</ol>".to_string())?;
-            } else {
-                writer.write("<ul>".to_string())?;
-                for item in &self.children {
-                    item.to_html(writer)?;
-                }
-                writer.write("</ul>".to_string())?;
+fn list_items_to_html(
+    items: &[ListItem],
+    ordered: bool,
+    loose: bool,
+    writer: &mut HTMLWriter,
+) -> io::Result<()> {
+    writer.write(if ordered { "<ol>" } else { "<ul>" }.to_string())?;
+    for item in items {
+        list_item_to_html(item, loose, writer)?;
+    }
+
+    writer.write(if ordered { "</ol>" } else { "</ul>" }.to_string())
+}
+
+/// Renders a list item. The text of items of loose lists is rendered as a paragraph
+fn list_item_to_html(item: &ListItem, loose: bool, writer: &mut HTMLWriter) -> io::Result<()> {
+    write_list_item_start(item, writer)?;
+    if let Some(checked) = item.checked {
+        Checkbox {
+            value: checked,
+            span: item.span,
+        }
+        .to_html(writer)?;
+    }
+    match &item.text {
+        Line::Text(text) if loose => {
+            writer.write("<div class=\"paragraph\">".to_string())?;
+            // the break of the blank line after the item
+            let subtext = match text.subtext.split_last() {
+                Some((Inline::LineBreak, subtext)) => subtext,
+                _ => &text.subtext[..],
+            };
+            for inline in subtext {
+                inline.to_html(writer)?;
             }
+            writer.write("</div>".to_string())?;
         }
+        text => text.to_html(writer)?,
+    }
+    for block in &item.blocks {
+        block.to_html(writer)?;
+    }
+    if let Some(first) = item.children.first() {
+        list_items_to_html(&item.children, first.ordered, loose, writer)?;
+    }
+
+    writer.write("</li>".to_string())
+}
+
+/// Writes the opening tag of a list item with the classes, custom bullet
+/// and data attributes set with its metadata
+fn write_list_item_start(item: &ListItem, writer: &mut HTMLWriter) -> io::Result<()> {
+    let mut classes = Vec::new();
+    if item.checked.is_some() {
+        classes.push("taskListItem".to_string());
+    }
+    classes.extend(item.get_class());
+    writer.write("<li".to_string())?;
 
-        writer.write("</li>".to_string())
+    if !classes.is_empty() {
+        writer.write(" class=\"".to_string())?;
+        writer.write_attribute(classes.join(" "))?;
+        writer.write("\"".to_string())?;
     }
+    if let Some(bullet) = item.get_custom_bullet() {
+        writer.write(" style=\"list-style-type: ".to_string())?;
+        writer.write_attribute(format!(
+            "\"{}\"",
+            bullet.replace('\\', "\\\\").replace('"', "\\\"")
+        ))?;
+        writer.write("\"".to_string())?;
+    }
+    let mut attributes = item.get_attributes().into_iter().collect::<Vec<_>>();
+    attributes.sort();
+
+    for (key, value) in attributes {
+        let key = key
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect::<String>();
+        if key.is_empty() {
+            continue;
+        }
+        writer.write(format!(" data-{}=\"", key.to_lowercase()))?;
+        writer.write_attribute(value)?;
+        writer.write("\"".to_string())?;
+    }
+
+    writer.write(">".to_string())
 }
 
 impl ToHtml for Table {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        writer.write("<div class=\"tableWrapper\"><table><tr>".to_string())?;
+        writer.write("<div class=\"tableWrapper\"><table>".to_string())?;
+        let rows = std::iter::once(&self.header).chain(self.rows.iter());
 
-        for cell in &self.header.cells {
-            writer.write("<th>".to_string())?;
-            cell.text.to_html(writer)?;
-            writer.write("</th>".to_string())?;
-        }
-        writer.write("</tr>".to_string())?;
-        for row in &self.rows {
-            row.to_html(writer)?;
+        for (i, (row, (columns, _))) in rows.zip(self.get_layout()).enumerate() {
+            let tag = if i == 0 { "th" } else { "td" };
+            writer.write("<tr>".to_string())?;
+
+            for (cell, column) in row.cells.iter().zip(columns) {
+                writer.write(format!(
+                    "<{}{}{}>",
+                    tag,
+                    get_span_attributes(cell),
+                    get_alignment_style(self.get_alignment(column))
+                ))?;
+                cell.text.to_html(writer)?;
+                writer.write(format!("</{}>", tag))?;
+            }
+            writer.write("</tr>".to_string())?;
         }
 
         writer.write("</table></div>".to_string())
@@ -351,7 +467,15 @@ impl ToHtml for Cell {
 
 impl ToHtml for CodeBlock {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        writer.write("<div><code".to_string())?;
+        writer.write("<div>".to_string())?;
+
+        if let Some(title) = self.get_title() {
+            writer.write("<div class=\"codeTitle\">".to_string())?;
+            writer.write_escaped(title)?;
+            writer.write("</div>".to_string())?;
+        }
+        writer.write("<code".to_string())?;
+        let annotated = self.has_line_numbers() || !self.get_highlighted_lines().is_empty();
 
         if self.language.len() > 0 {
             writer.write(" lang=\"".to_string())?;
@@ -360,17 +484,29 @@ impl ToHtml for CodeBlock {
             let (theme, syntax_set) = get_code_theme_for_theme(writer.get_theme());
 
             if let Some(syntax) = syntax_set.find_syntax_by_token(self.language.as_str()) {
-                writer.write(highlighted_html_for_string(
-                    self.code.as_str(),
-                    &syntax_set,
-                    syntax,
-                    &theme,
-                ))?;
+                if annotated {
+                    writer.write(get_annotated_code_html(
+                        self,
+                        Some((syntax, &syntax_set, &theme)),
+                    ))?;
+                } else {
+                    writer.write(highlighted_html_for_string(
+                        self.code.as_str(),
+                        &syntax_set,
+                        syntax,
+                        &theme,
+                    ))?;
+                }
+            } else if annotated {
+                writer.write(get_annotated_code_html(self, None))?;
             } else {
                 writer.write("<pre>".to_string())?;
                 writer.write_escaped(self.code.clone())?;
                 writer.write("</pre>".to_string())?;
             }
+        } else if annotated {
+            writer.write(">".to_string())?;
+            writer.write(get_annotated_code_html(self, None))?;
         } else {
             writer.write("><pre>".to_string())?;
             writer.write_escaped(self.code.clone())?;
@@ -381,12 +517,53 @@ impl ToHtml for CodeBlock {
     }
 }
 
+/// Returns the html of a code block where each line is wrapped
+/// in its own element with an optional line number and highlighting
+fn get_annotated_code_html(
+    code_block: &CodeBlock,
+    highlighting: Option<(&SyntaxReference, &SyntaxSet, &Theme)>,
+) -> String {
+    let (mut html, background) = match highlighting {
+        Some((_, _, theme)) => start_highlighted_html_snippet(theme),
+        None => ("<pre>".to_string(), Color::WHITE),
+    };
+    let mut highlighter = highlighting.map(|(syntax, _, theme)| HighlightLines::new(syntax, theme));
+
+    for (index, line) in code_block.code.lines().enumerate() {
+        let number = index + 1;
+        if code_block.is_highlighted(number) {
+            html.push_str("<span class=\"codeLine highlighted\">");
+        } else {
+            html.push_str("<span class=\"codeLine\">");
+        }
+        if code_block.has_line_numbers() {
+            html.push_str(&format!("<span class=\"lineNumber\">{}</span>", number));
+        }
+        match (&mut highlighter, highlighting) {
+            (Some(highlighter), Some((_, syntax_set, _))) => {
+                let regions = highlighter.highlight(line, syntax_set);
+                html.push_str(&styled_line_to_highlighted_html(
+                    &regions,
+                    IncludeBackground::IfDifferent(background),
+                ));
+            }
+            _ => html.push_str(&encode_minimal(line)),
+        }
+        html.push_str("</span>");
+    }
+    html.push_str("</pre>");
+
+    html
+}
+
 impl ToHtml for Quote {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
         writer.write("<div class=\"quote\"><blockquote>".to_string())?;
-        for line in &self.text {
-            line.to_html(writer)?;
-            writer.write("<br/>".to_string())?;
+        for element in &self.elements {
+            element.to_html(writer)?;
+        }
+        if let Some(attribution) = &self.attribution {
+            attribution.to_html(writer)?;
         }
         if let Some(meta) = self.metadata.clone() {
             writer.write("<span class=\"metadata\">".to_string())?;
@@ -397,6 +574,94 @@ impl ToHtml for Quote {
     }
 }
 
+impl ToHtml for Aligned {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        writer.write(format!(
+            "<div class=\"aligned\"{}>",
+            get_alignment_style(self.alignment)
+        ))?;
+        for element in &self.content {
+            element.to_html(writer)?;
+        }
+        writer.write("</div>".to_string())
+    }
+}
+
+impl ToHtml for Columns {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        // content that isn't split into columns flows over the columns
+        if self.columns.len() == 1 {
+            writer.write(format!(
+                "<div class=\"columns flow\" style=\"column-count:{};\">",
+                self.count
+            ))?;
+        } else {
+            writer.write("<div class=\"columns\">".to_string())?;
+        }
+        for column in &self.columns {
+            writer.write("<div class=\"column\">".to_string())?;
+            for element in column {
+                element.to_html(writer)?;
+            }
+            writer.write("</div>".to_string())?;
+        }
+        writer.write("</div>".to_string())
+    }
+}
+
+impl ToHtml for CustomContainer {
+    /// Containers that weren't transformed by a handler are rendered
+    /// as a div with their name as class and their title as heading
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        if let Some(value) = &self.value {
+            return value.to_html(writer);
+        }
+        writer.write("<div class=\"container ".to_string())?;
+        writer.write_attribute(self.name.clone())?;
+        writer.write("\">".to_string())?;
+        if let Some(title) = self.get_title() {
+            writer.write("<div class=\"containerTitle\">".to_string())?;
+            writer.write_escaped(title)?;
+            writer.write("</div>".to_string())?;
+        }
+        for element in &self.content {
+            element.to_html(writer)?;
+        }
+        writer.write("</div>".to_string())
+    }
+}
+
+impl ToHtml for Attribution {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        writer.write("<footer class=\"attribution\">&mdash; ".to_string())?;
+        writer.write_escaped(self.author.clone())?;
+        if let Some(source) = &self.source {
+            writer.write(", <cite>".to_string())?;
+            writer.write_escaped(source.clone())?;
+            writer.write("</cite>".to_string())?;
+        }
+        writer.write("</footer>".to_string())
+    }
+}
+
+impl ToHtml for DefinitionList {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        writer.write("<dl>".to_string())?;
+        for item in &self.items {
+            writer.write("<dt>".to_string())?;
+            item.term.to_html(writer)?;
+            writer.write("</dt>".to_string())?;
+            for definition in &item.definitions {
+                writer.write("<dd>".to_string())?;
+                definition.to_html(writer)?;
+                writer.write("</dd>".to_string())?;
+            }
+        }
+
+        writer.write("</dl>".to_string())
+    }
+}
+
 impl ToHtml for Ruler {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
         writer.write("<hr/>".to_string())
@@ -415,26 +680,9 @@ impl ToHtml for TextLine {
 
 impl ToHtml for Image {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        let mut style = String::new();
+        let url = get_image_source(self);
+        let style = get_image_style(self);
 
-        let url = if let Some(content) = self.get_content() {
-            let mime_type = self.get_mime_type();
-            format!(
-                "data:{};base64,{}",
-                mime_type.to_string(),
-                base64::encode(content)
-            )
-        } else {
-            encode_attribute(self.url.url.as_str())
-        };
-        if let Some(meta) = &self.metadata {
-            if let Some(width) = meta.get_string("width") {
-                style = format!("{}width: {};", style, width)
-            }
-            if let Some(height) = meta.get_string("height") {
-                style = format!("{}height: {};", style, height)
-            }
-        }
         if let Some(description) = self.url.description.clone() {
             writer.write("<div class=\"figure\"><a href=\"".to_string())?;
             writer.write_attribute(url.clone())?;
@@ -442,7 +690,7 @@ impl ToHtml for Image {
             writer.write(url)?;
             writer.write("\" style=\"".to_string())?;
             writer.write(style)?;
-            writer.write("\"/></a><br><label class=\"imageDescripton\">".to_string())?;
+            writer.write("\"/></a><br/><label class=\"imageDescripton\">".to_string())?;
             for item in description {
                 item.to_html(writer)?;
                 writer.write("&#32;".to_string())?;
@@ -462,6 +710,239 @@ impl ToHtml for Image {
     }
 }
 
+impl ToHtml for Diagram {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        #[cfg(feature = "diagrams")]
+        match crate::utils::diagrams::render_svg(&self.kind, &self.source) {
+            Ok(svg) => {
+                writer.write("<div class=\"diagram\">".to_string())?;
+                writer.write(svg)?;
+                return writer.write("</div>".to_string());
+            }
+            Err(e) => log::warn!("Failed to render {} diagram: {}", self.kind.as_str(), e),
+        }
+        writer.write("<div class=\"diagram\"><pre class=\"".to_string())?;
+        writer.write(self.kind.as_str().to_string())?;
+        writer.write("\">".to_string())?;
+        writer.write_escaped(self.source.clone())?;
+
+        writer.write("</pre></div>".to_string())
+    }
+}
+
+impl ToHtml for Html {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        if writer.get_sanitize_html() {
+            writer.write(sanitize_html(&self.value))
+        } else {
+            writer.write(self.value.clone())
+        }
+    }
+}
+
+impl ToHtml for Figure {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        let url = get_image_source(&self.image);
+        let description = self
+            .image
+            .url
+            .description
+            .iter()
+            .flatten()
+            .map(|i| i.as_plain_text().value)
+            .collect::<Vec<Arc<str>>>()
+            .join(" ");
+
+        writer.write("<figure class=\"figure\"><a href=\"".to_string())?;
+        writer.write_attribute(url.clone())?;
+        writer.write("\"><img src=\"".to_string())?;
+        writer.write(url)?;
+        writer.write("\" alt=\"".to_string())?;
+        writer.write_attribute(description)?;
+        writer.write("\" style=\"".to_string())?;
+        writer.write(get_image_style(&self.image))?;
+        writer.write("\"/></a><figcaption>".to_string())?;
+        self.caption.to_html(writer)?;
+
+        writer.write("</figcaption></figure>".to_string())
+    }
+}
+
+/// Writes the script that renders TeX math if it is enabled
+/// Writes the meta tags, styles and scripts of the head of the document
+pub(crate) fn write_head(document: &Document, writer: &mut HTMLWriter) -> io::Result<()> {
+    let metadata = document.config.lock().metadata.clone();
+    let style = minify(get_css_for_theme(writer.get_theme()).as_str());
+    writer.write("<meta charset=\"UTF-8\">".to_string())?;
+
+    if let Some(author) = metadata.author {
+        writer.write("<meta name=\"author\" content=\"".to_string())?;
+        writer.write_attribute(author)?;
+        writer.write("\">".to_string())?;
+    }
+
+    if let Some(title) = metadata.title {
+        writer.write("<title>".to_string())?;
+        writer.write_escaped(title.clone())?;
+        writer.write("</title>".to_string())?;
+        writer.write("<meta name=\"title\" content=\"".to_string())?;
+        writer.write_attribute(title)?;
+        writer.write("\">".to_string())?;
+    }
+
+    if let Some(description) = metadata.description {
+        writer.write("<meta name=\"description\" content=\"".to_string())?;
+        writer.write_attribute(description)?;
+        writer.write("\">".to_string())?;
+    }
+
+    if !metadata.keywords.is_empty() {
+        writer.write("<meta name=\"keywords\" content=\"".to_string())?;
+        writer.write_attribute(metadata.keywords.join(", "))?;
+        writer.write("\">".to_string())?;
+    }
+
+    writer.write("<style>".to_string())?;
+    writer.write(style)?;
+    writer.write("</style>".to_string())?;
+
+    let features = document.config.lock().features.clone();
+    write_math_script(writer, &features)?;
+
+    let inline_css = document.config.lock().style.inline_css;
+    for stylesheet in &document.stylesheets {
+        let stylesheet = stylesheet.lock();
+        if let Some(data) = stylesheet.data.as_ref().filter(|_| inline_css) {
+            writer.write("<style>".to_string())?;
+            writer.write(minify(std::str::from_utf8(data).unwrap()))?;
+            writer.write("</style>".to_string())?;
+        } else {
+            writer.write("<link rel=\"stylesheet\" href=\"".to_string())?;
+            writer.write_attribute(stylesheet.path.clone())?;
+            writer.write("\">".to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Inserts the rendered document into the user supplied template.
+/// The template can use {{head}}, {{body}} and {{toc}} for the rendered html,
+/// {{title}}, {{author}}, {{description}}, {{keywords}} and {{language}} for the metadata
+/// and the name of any other front matter entry.
+fn write_template(document: &Document, template: &str, writer: &mut HTMLWriter) -> io::Result<()> {
+    let metadata = document.config.lock().metadata.clone();
+    let mut replacements: HashMap<String, String> = document
+        .front_matter
+        .as_ref()
+        .map(|front_matter| front_matter.get_string_map())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(key, value)| (key, encode_minimal(&value)))
+        .collect();
+    for (key, value) in [
+        ("title", metadata.title),
+        ("author", metadata.author),
+        ("description", metadata.description),
+        ("keywords", Some(metadata.keywords.join(", "))),
+        ("language", Some(metadata.language)),
+    ] {
+        replacements.insert(key.to_string(), encode_minimal(&value.unwrap_or_default()));
+    }
+    let toc = document.create_toc(&TocOptions::default());
+    replacements.insert(
+        "head".to_string(),
+        render_to_string(writer, |w| write_head(document, w))?,
+    );
+    replacements.insert(
+        "toc".to_string(),
+        render_to_string(writer, |w| toc.to_html(w))?,
+    );
+    replacements.insert(
+        "body".to_string(),
+        render_to_string(writer, |w| {
+            for element in &document.elements {
+                element.to_html(w)?;
+            }
+            Ok(())
+        })?,
+    );
+    let mut template = PlaceholderTemplate::new(template.to_string());
+    template.set_replacements(replacements);
+
+    writer.write(template.render())
+}
+
+/// Renders html into a string with the same settings as the given writer
+pub(crate) fn render_to_string<F>(writer: &HTMLWriter, render: F) -> io::Result<String>
+where
+    F: FnOnce(&mut HTMLWriter) -> io::Result<()>,
+{
+    let mut buffer = Vec::new();
+    {
+        let mut inner = HTMLWriter::new(Box::new(&mut buffer), writer.get_theme());
+        inner.set_sanitize_html(writer.get_sanitize_html());
+        render(&mut inner)?;
+    }
+
+    Ok(String::from_utf8_lossy(&buffer).to_string())
+}
+
+pub(crate) fn write_math_script(
+    writer: &mut HTMLWriter,
+    features: &FeatureSettings,
+) -> io::Result<()> {
+    if !features.include_mathjax {
+        return Ok(());
+    }
+    match features.math_renderer {
+        MathRenderer::MathJax => writer.write(format!(
+            "<script id=\"MathJax-script\" type=\"text/javascript\" async src={}></script>",
+            MATHJAX_URL
+        )),
+        MathRenderer::KaTeX => writer.write(format!(
+            "<link rel=\"stylesheet\" href=\"{}\"><script defer src=\"{}\"></script>\
+            <script defer src=\"{}\" onload=\"renderMathInElement(document.body);\"></script>",
+            KATEX_CSS_URL, KATEX_URL, KATEX_AUTO_RENDER_URL
+        )),
+    }
+}
+
+/// Returns the url of the image or the embedded data if it was loaded
+fn get_image_source(image: &Image) -> String {
+    if let Some(content) = image.get_content() {
+        let mime_type = image.get_mime_type();
+        format!("data:{};base64,{}", mime_type, base64::encode(content))
+    } else if let Some(url) = &image.image_data.lock().url {
+        encode_attribute(url)
+    } else {
+        encode_attribute(image.url.url.as_str())
+    }
+}
+
+/// Returns the css style of the image that is set with its metadata
+fn get_image_style(image: &Image) -> String {
+    let mut style = String::new();
+
+    if let Some(meta) = &image.metadata {
+        for key in &["width", "height"] {
+            if let Some(value) = meta.get_string(key) {
+                style = format!("{}{}: {};", style, key, value)
+            } else if let Some(value) = meta.get_integer(key) {
+                style = format!("{}{}: {}px;", style, key, value)
+            }
+        }
+        match meta.get_string("align").as_deref() {
+            Some("left") => style.push_str("float: left;"),
+            Some("right") => style.push_str("float: right;"),
+            Some("center") => style.push_str("display: block; margin: 0 auto;"),
+            _ => {}
+        }
+    }
+
+    style
+}
+
 impl ToHtml for BoldText {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
         writer.write("<b>".to_string())?;
@@ -502,6 +983,32 @@ impl ToHtml for StrikedText {
     }
 }
 
+impl ToHtml for SpoilerText {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        writer.write("<span class=\"spoiler\" tabindex=\"0\">".to_string())?;
+        for element in &self.value {
+            element.to_html(writer)?;
+        }
+        writer.write("</span>".to_string())
+    }
+}
+
+impl ToHtml for HighlightText {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        if let Some(color) = self.get_color() {
+            writer.write("<mark style=\"background-color: ".to_string())?;
+            writer.write_attribute(color)?;
+            writer.write("\">".to_string())?;
+        } else {
+            writer.write("<mark>".to_string())?;
+        }
+        for element in &self.value {
+            element.to_html(writer)?;
+        }
+        writer.write("</mark>".to_string())
+    }
+}
+
 impl ToHtml for SuperscriptText {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
         writer.write("<sup>".to_string())?;
@@ -512,10 +1019,20 @@ impl ToHtml for SuperscriptText {
     }
 }
 
+impl ToHtml for SubscriptText {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        writer.write("<sub>".to_string())?;
+        for element in &self.value {
+            element.to_html(writer)?;
+        }
+        writer.write("</sub>".to_string())
+    }
+}
+
 impl ToHtml for MonospaceText {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
         writer.write("<code class=\"inlineCode\">".to_string())?;
-        writer.write_escaped(self.value.clone())?;
+        writer.write_escaped(self.value.to_string())?;
 
         writer.write("</code>".to_string())
     }
@@ -540,7 +1057,7 @@ impl ToHtml for Url {
 
 impl ToHtml for PlainText {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        writer.write_escaped(self.value.clone())
+        writer.write_escaped(self.value.to_string())
     }
 }
 
@@ -598,9 +1115,9 @@ impl ToHtml for Centered {
 
 impl ToHtml for Checkbox {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        writer.write("<input type=\"checkbox\" disabled ".to_string())?;
+        writer.write("<input type=\"checkbox\" disabled=\"disabled\" ".to_string())?;
         if self.value {
-            writer.write("checked".to_string())?;
+            writer.write("checked=\"checked\"".to_string())?;
         }
 
         writer.write("/>".to_string())
@@ -612,12 +1129,23 @@ impl ToHtml for Emoji {
         writer.write("<span class=\"emoji\" emoji-name=\"".to_string())?;
         writer.write_attribute(self.name.clone())?;
         writer.write("\">".to_string())?;
-        writer.write(self.value.to_string())?;
+        writer.write(self.value.clone())?;
 
         writer.write("</span>".to_string())
     }
 }
 
+impl ToHtml for Abbreviation {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        writer.write("<abbr title=\"".to_string())?;
+        writer.write_attribute(self.long.clone())?;
+        writer.write("\">".to_string())?;
+        writer.write_escaped(self.short.clone())?;
+
+        writer.write("</abbr>".to_string())
+    }
+}
+
 impl ToHtml for Colored {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
         writer.write("<span class=\"colored\" style=\"color:".to_string())?;
@@ -629,8 +1157,29 @@ impl ToHtml for Colored {
     }
 }
 
+impl ToHtml for StyledText {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        writer.write("<span class=\"styled".to_string())?;
+        if let Some(class) = self.get_class() {
+            writer.write(" ".to_string())?;
+            writer.write_attribute(class)?;
+        }
+        writer.write("\" style=\"".to_string())?;
+        writer.write_attribute(self.get_style())?;
+        writer.write("\">".to_string())?;
+        self.value.to_html(writer)?;
+
+        writer.write("</span>".to_string())
+    }
+}
+
 impl ToHtml for BibReference {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        if let Some(fallback) = &self.fallback {
+            if self.entry_anchor.lock().entry.is_none() {
+                return writer.write_escaped(fallback.clone());
+            }
+        }
         writer.write("<sup><a href=\"#".to_string())?;
         writer.write_attribute(self.key.clone())?;
         writer.write("\">".to_string())?;
@@ -640,6 +1189,22 @@ impl ToHtml for BibReference {
     }
 }
 
+impl ToHtml for FootnoteReference {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        if let Some(definition) = &self.definition {
+            let definition = definition.lock();
+            writer.write("<sup><a class=\"footnoteReference\" href=\"#".to_string())?;
+            writer.write_attribute(definition.get_anchor_key())?;
+            writer.write("\">".to_string())?;
+            writer.write(definition.number.unwrap_or_default().to_string())?;
+
+            writer.write("</a></sup>".to_string())
+        } else {
+            self.citation.read().unwrap().to_html(writer)
+        }
+    }
+}
+
 impl ToHtml for Template {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
         for element in &self.text {
@@ -717,3 +1282,50 @@ impl ToHtml for Arrow {
         writer.write("</span>".to_string())
     }
 }
+
+/// Returns the colspan and rowspan attributes of a table cell
+fn get_span_attributes(cell: &Cell) -> String {
+    let mut attributes = String::new();
+    if cell.colspan > 1 {
+        attributes.push_str(&format!(" colspan=\"{}\"", cell.colspan));
+    }
+    if cell.rowspan > 1 {
+        attributes.push_str(&format!(" rowspan=\"{}\"", cell.rowspan));
+    }
+
+    attributes
+}
+
+/// Returns the style attribute for the alignment of a table cell or aligned blocks
+fn get_alignment_style(alignment: Alignment) -> &'static str {
+    match alignment {
+        Alignment::None => "",
+        Alignment::Left => " style=\"text-align:left;\"",
+        Alignment::Center => " style=\"text-align:center;\"",
+        Alignment::Right => " style=\"text-align:right;\"",
+    }
+}
+
+/// Removes scripts, embedded content, event handlers and javascript urls from raw html
+fn sanitize_html(html: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref UNSAFE_ELEMENTS: Vec<Regex> = ["script", "style", "iframe", "object", "embed"]
+            .iter()
+            .map(|tag| Regex::new(&format!(r"(?is)<{0}\b.*?(?:</{0}\s*>|$)", tag)).unwrap())
+            .collect();
+        static ref UNSAFE_TAGS: Regex =
+            Regex::new(r"(?i)</?(?:script|style|iframe|object|embed|frame|frameset|base|meta|link)\b[^>]*>").unwrap();
+        static ref EVENT_HANDLERS: Regex =
+            Regex::new(r#"(?i)\s+on\w+\s*=\s*(?:"[^"]*"|'[^']*'|[^\s>]+)"#).unwrap();
+        static ref JAVASCRIPT_URLS: Regex =
+            Regex::new(r#"(?i)((?:href|src|action)\s*=\s*["']?)\s*(?:javascript|vbscript):"#).unwrap();
+    }
+    let mut html = html.to_string();
+    for element in UNSAFE_ELEMENTS.iter() {
+        html = element.replace_all(&html, "").to_string();
+    }
+    let html = UNSAFE_TAGS.replace_all(&html, "");
+    let html = EVENT_HANDLERS.replace_all(&html, "");
+
+    JAVASCRIPT_URLS.replace_all(&html, "${1}#").to_string()
+}
@@ -0,0 +1,113 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Block, Document, InlineMetadata};
+use crate::settings::Settings;
+use crate::utils::downloads::PendingDownload;
+use parking_lot::Mutex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::Arc;
+
+/// The serialized part of a document.
+/// The managers of references, downloads and images are runtime state
+/// and are created again when deserializing.
+#[derive(Serialize)]
+struct DocumentRef<'a> {
+    elements: &'a Vec<Block>,
+    is_root: bool,
+    path: &'a Option<String>,
+    config: Settings,
+    front_matter: &'a Option<InlineMetadata>,
+    stylesheets: Vec<StylesheetData>,
+    html_template: &'a Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DocumentData {
+    elements: Vec<Block>,
+    is_root: bool,
+    path: Option<String>,
+    #[serde(default)]
+    config: Settings,
+    front_matter: Option<InlineMetadata>,
+    #[serde(default)]
+    stylesheets: Vec<StylesheetData>,
+    #[serde(default)]
+    html_template: Option<String>,
+}
+
+/// A stylesheet with its content if it was already loaded
+#[derive(Serialize, Deserialize)]
+struct StylesheetData {
+    path: String,
+    content: Option<String>,
+}
+
+impl Serialize for Document {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DocumentRef {
+            elements: &self.elements,
+            is_root: self.is_root,
+            path: &self.path,
+            config: self.config.lock().clone(),
+            front_matter: &self.front_matter,
+            stylesheets: self
+                .stylesheets
+                .iter()
+                .map(|s| {
+                    let stylesheet = s.lock();
+                    StylesheetData {
+                        path: stylesheet.path.clone(),
+                        content: stylesheet
+                            .data
+                            .as_ref()
+                            .map(|d| String::from_utf8_lossy(d).to_string()),
+                    }
+                })
+                .collect(),
+            html_template: &self.html_template,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Document {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = DocumentData::deserialize(deserializer)?;
+        let mut document = Document::new();
+        document.elements = data.elements;
+        document.is_root = data.is_root;
+        document.path = data.path;
+        *document.config.lock() = data.config;
+        document.front_matter = data.front_matter;
+        document.stylesheets = data
+            .stylesheets
+            .into_iter()
+            .map(|s| {
+                let mut stylesheet = PendingDownload::new(s.path);
+                stylesheet.data = s.content.map(String::into_bytes);
+                Arc::new(Mutex::new(stylesheet))
+            })
+            .collect();
+        document.html_template = data.html_template;
+
+        Ok(document)
+    }
+}
+
+impl Document {
+    /// Serializes the document tree with its source spans and metadata to json.
+    /// Embedded image data is not part of the json, images are referenced by their path or url.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Deserializes a document from json that was created with [Document::to_json]
+    /// so that it can be rendered again
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
@@ -0,0 +1,893 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::*;
+use crate::format::PlaceholderTemplate;
+use crate::references::abbreviations::Abbreviation;
+use crate::references::footnotes::FootnoteReference;
+use crate::references::glossary::{GlossaryDisplay, GlossaryReference};
+use crate::references::templates::{Template, TemplateVariable};
+use crate::settings::latex_settings::CodePackage;
+use crate::settings::Settings;
+
+const LINE_BREAK: &str = "\\\\\n";
+
+/// Languages that are known to the listings package by the name they are given in code blocks
+const LISTINGS_LANGUAGES: &[(&str, &str)] = &[
+    ("bash", "bash"),
+    ("sh", "sh"),
+    ("c", "C"),
+    ("cpp", "C++"),
+    ("c++", "C++"),
+    ("csharp", "[Sharp]C"),
+    ("cs", "[Sharp]C"),
+    ("go", "Go"),
+    ("haskell", "Haskell"),
+    ("html", "HTML"),
+    ("java", "Java"),
+    ("lisp", "Lisp"),
+    ("lua", "Lua"),
+    ("make", "make"),
+    ("matlab", "Matlab"),
+    ("perl", "Perl"),
+    ("php", "PHP"),
+    ("python", "Python"),
+    ("py", "Python"),
+    ("r", "R"),
+    ("ruby", "Ruby"),
+    ("sql", "SQL"),
+    ("tex", "TeX"),
+    ("latex", "[LaTeX]TeX"),
+    ("xml", "XML"),
+];
+
+/// Renders an element to LaTeX source that can be compiled with pdflatex.
+/// Code blocks are typeset with the package given in the settings.
+pub trait ToLatex {
+    fn to_latex(&self, settings: &Settings) -> String;
+}
+
+impl ToLatex for Document {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let body = blocks_to_latex(&self.elements, settings);
+        if !self.is_root {
+            return body;
+        }
+        let mut latex = format!("\\documentclass{{{}}}\n", settings.latex.document_class);
+        latex.push_str(
+            "\\usepackage[utf8]{inputenc}\n\
+            \\usepackage[T1]{fontenc}\n\
+            \\usepackage{amsmath}\n\
+            \\usepackage{amssymb}\n\
+            \\usepackage{graphicx}\n\
+            \\usepackage{multirow}\n\
+            \\usepackage{multicol}\n\
+            \\usepackage{pdflscape}\n\
+            \\usepackage[normalem]{ulem}\n\
+            \\usepackage{xcolor}\n",
+        );
+        match settings.latex.code_package {
+            CodePackage::Listings => latex.push_str("\\usepackage{listings}\n"),
+            CodePackage::Minted => latex.push_str("\\usepackage{minted}\n"),
+        }
+        latex.push_str("\\usepackage{hyperref}\n\n");
+
+        let metadata = &settings.metadata;
+        if let Some(title) = &metadata.title {
+            latex.push_str(&format!("\\title{{{}}}\n", escape_text(title)));
+        }
+        if let Some(author) = &metadata.author {
+            latex.push_str(&format!("\\author{{{}}}\n", escape_text(author)));
+        }
+        latex.push_str("\\date{}\n\n\\begin{document}\n");
+        if metadata.title.is_some() {
+            latex.push_str("\\maketitle\n");
+        }
+        latex.push('\n');
+        latex.push_str(&body);
+        latex.push_str("\\end{document}\n");
+
+        latex
+    }
+}
+
+impl ToLatex for Element {
+    fn to_latex(&self, settings: &Settings) -> String {
+        match self {
+            Element::Block(block) => block.to_latex(settings),
+            Element::Line(line) => line.to_latex(settings),
+            Element::Inline(inline) => inline.to_latex(settings),
+        }
+    }
+}
+
+impl ToLatex for Block {
+    /// Blocks with an id given in their metadata are labeled so that links can refer to them
+    fn to_latex(&self, settings: &Settings) -> String {
+        let latex = match self {
+            Block::Section(section) => section.to_latex(settings),
+            Block::Paragraph(paragraph) => paragraph.to_latex(settings),
+            Block::List(list) => list.to_latex(settings),
+            Block::Table(table) => table.to_latex(settings),
+            Block::CodeBlock(code) => code.to_latex(settings),
+            Block::Diagram(diagram) => verbatim(&diagram.source),
+            Block::Comment(comment) => comment.to_latex(settings),
+            Block::MathBlock(math) => math.to_latex(settings),
+            Block::Quote(quote) => quote.to_latex(settings),
+            Block::Aligned(aligned) => aligned.to_latex(settings),
+            Block::Columns(columns) => columns.to_latex(settings),
+            Block::CustomContainer(container) => {
+                blocks_to_latex(container.get_rendered_blocks(), settings)
+            }
+            Block::PageBreak(_) => "\\clearpage\n".to_string(),
+            Block::DefinitionList(list) => list.to_latex(settings),
+            Block::Figure(figure) => figure.to_latex(settings),
+            Block::Placeholder(placeholder) => placeholder.read().unwrap().to_latex(settings),
+            Block::Html(_) | Block::Import(_) | Block::Null => String::new(),
+        };
+
+        match self.get_id() {
+            Some(id) => format!("\\phantomsection\\label{{{}}}\n{}", id, latex),
+            None => latex,
+        }
+    }
+}
+
+impl ToLatex for Line {
+    fn to_latex(&self, settings: &Settings) -> String {
+        match self {
+            Line::Text(text) => text.to_latex(settings),
+            Line::Ruler(_) => "\\noindent\\rule{\\linewidth}{0.4pt}".to_string(),
+            Line::RefLink(link) => link.to_latex(settings),
+            Line::Anchor(anchor) => anchor_to_latex(&anchor.key, &anchor.inner, settings),
+            Line::Centered(centered) => format!(
+                "\\begin{{center}}\n{}\n\\end{{center}}",
+                centered.line.to_latex(settings)
+            ),
+            Line::BibEntry(_) | Line::FootnoteDefinition(_) | Line::AbbreviationDefinition(_) => {
+                String::new()
+            }
+        }
+    }
+}
+
+impl ToLatex for Inline {
+    fn to_latex(&self, settings: &Settings) -> String {
+        match self {
+            Inline::Plain(plain) => escape_text(&plain.value),
+            Inline::Bold(bold) => {
+                format!("\\textbf{{{}}}", inlines_to_latex(&bold.value, settings))
+            }
+            Inline::Italic(italic) => {
+                format!("\\emph{{{}}}", inlines_to_latex(&italic.value, settings))
+            }
+            Inline::Underlined(under) => {
+                format!("\\uline{{{}}}", inlines_to_latex(&under.value, settings))
+            }
+            Inline::Striked(striked) => {
+                format!("\\sout{{{}}}", inlines_to_latex(&striked.value, settings))
+            }
+            Inline::Spoiler(spoiler) => inlines_to_latex(&spoiler.value, settings),
+            Inline::Highlight(highlight) => highlight.to_latex(settings),
+            Inline::Styled(styled) => styled.to_latex(settings),
+            Inline::Monospace(mono) => format!("\\texttt{{{}}}", escape_text(&mono.value)),
+            Inline::Superscript(sup) => format!(
+                "\\textsuperscript{{{}}}",
+                inlines_to_latex(&sup.value, settings)
+            ),
+            Inline::Subscript(sub) => format!(
+                "\\textsubscript{{{}}}",
+                inlines_to_latex(&sub.value, settings)
+            ),
+            Inline::Url(url) => url.to_latex(settings),
+            Inline::Image(image) => image_to_latex(image),
+            Inline::Placeholder(placeholder) => placeholder.read().unwrap().to_latex(settings),
+            Inline::Checkbox(checkbox) => checkbox_to_latex(checkbox.value).to_string(),
+            Inline::Emoji(emoji) => escape_text(&format!(":{}:", emoji.name)),
+            Inline::Colored(colored) => format!(
+                "\\textcolor{}{{{}}}",
+                color_to_latex(&colored.color),
+                colored.value.to_latex(settings)
+            ),
+            Inline::Math(math) => math.to_latex(settings),
+            Inline::BibReference(bib) => bib.read().unwrap().to_latex(settings),
+            Inline::GlossaryReference(gloss) => gloss.lock().to_latex(settings),
+            Inline::FootnoteReference(footnote) => footnote.lock().to_latex(settings),
+            Inline::TemplateVar(var) => var.read().unwrap().to_latex(settings),
+            Inline::CharacterCode(code) => code.to_latex(settings),
+            Inline::LineBreak => LINE_BREAK.to_string(),
+            Inline::Arrow(arrow) => arrow.to_latex(settings),
+            Inline::Anchor(anchor) => anchor_to_latex(&anchor.key, &anchor.inner, settings),
+            Inline::Abbreviation(abbreviation) => abbreviation.to_latex(settings),
+            Inline::Html(_) | Inline::Comment(_) => String::new(),
+        }
+    }
+}
+
+impl ToLatex for MetadataValue {
+    fn to_latex(&self, settings: &Settings) -> String {
+        match self {
+            MetadataValue::String(string) => escape_text(string),
+            MetadataValue::Integer(num) => num.to_string(),
+            MetadataValue::Float(f) => f.to_string(),
+            MetadataValue::Bool(b) => b.to_string(),
+            MetadataValue::Placeholder(ph) => ph.read().unwrap().to_latex(settings),
+            MetadataValue::Template(t) => t.to_latex(settings),
+        }
+    }
+}
+
+impl ToLatex for Section {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let mut latex = format!(
+            "{}\n{}",
+            self.header.to_latex(settings),
+            blocks_to_latex(&self.elements, settings)
+        );
+        // landscape pages always start on a new page
+        if self.is_landscape() {
+            latex = format!("\\begin{{landscape}}\n{}\\end{{landscape}}\n", latex);
+        } else if self.breaks_before() {
+            latex.insert_str(0, "\\clearpage\n");
+        }
+        if self.breaks_after() {
+            latex.push_str("\\clearpage\n");
+        }
+
+        latex
+    }
+}
+
+impl ToLatex for Header {
+    /// Renders the header as the sectioning command of its level.
+    /// Headers are only numbered by LaTeX if numbered headings are enabled.
+    fn to_latex(&self, settings: &Settings) -> String {
+        let command = match self.size {
+            1 => "section",
+            2 => "subsection",
+            3 => "subsubsection",
+            4 => "paragraph",
+            _ => "subparagraph",
+        };
+        // unnumbered sections don't set an anchor for the label themselves
+        let (star, anchor) = if self.number.is_some() {
+            ("", "")
+        } else {
+            ("*", "\\phantomsection")
+        };
+
+        format!(
+            "\\{}{}{{{}}}{}\\label{{{}}}\n",
+            command,
+            star,
+            self.line.to_latex(settings),
+            anchor,
+            self.anchor
+        )
+    }
+}
+
+impl ToLatex for Paragraph {
+    /// Renders the lines of the paragraph. Breaks at the end of a line are only
+    /// written between lines as LaTeX doesn't allow them at the end of a paragraph.
+    fn to_latex(&self, settings: &Settings) -> String {
+        let lines = self
+            .elements
+            .iter()
+            .map(|line| {
+                (
+                    line.to_latex(settings),
+                    // the center environment already ends the line
+                    line.ends_with_break() && !matches!(line, Line::Centered(_)),
+                )
+            })
+            .filter(|(latex, _)| !latex.trim().is_empty())
+            .collect::<Vec<(String, bool)>>();
+        let mut latex = String::new();
+
+        for (i, (line, ends_with_break)) in lines.iter().enumerate() {
+            latex.push_str(line);
+            if *ends_with_break && i + 1 < lines.len() {
+                latex.push_str(LINE_BREAK);
+            } else {
+                latex.push('\n');
+            }
+        }
+
+        latex
+    }
+}
+
+impl ToLatex for List {
+    fn to_latex(&self, settings: &Settings) -> String {
+        list_items_to_latex(&self.items, self.ordered, settings)
+    }
+}
+
+impl ToLatex for ListItem {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let mut latex = match (self.checked, self.get_custom_bullet()) {
+            (Some(checked), _) => format!("\\item[{}] ", checkbox_to_latex(checked)),
+            (None, Some(bullet)) => format!("\\item[{}] ", escape_text(&bullet)),
+            (None, None) => "\\item ".to_string(),
+        };
+        latex.push_str(&self.text.to_latex(settings));
+        latex.push('\n');
+        for block in &self.blocks {
+            latex.push_str(&block.to_latex(settings));
+        }
+
+        if let Some(first) = self.children.first() {
+            latex.push_str(&list_items_to_latex(
+                &self.children,
+                first.ordered,
+                settings,
+            ));
+        }
+
+        latex
+    }
+}
+
+impl ToLatex for Table {
+    /// Renders the table as a tabular with the alignment of each column.
+    /// Cells that span multiple columns or rows use multicolumn and multirow,
+    /// the positions covered by a cell of a previous row are left empty.
+    fn to_latex(&self, settings: &Settings) -> String {
+        let layout = self.get_layout();
+        let width = layout.iter().map(|(_, width)| *width).max().unwrap_or(0);
+        let columns = (0..width)
+            .map(|column| alignment_to_latex(self.get_alignment(column)))
+            .collect::<String>();
+        let mut latex = format!("\\begin{{tabular}}{{{}}}\n\\hline\n", columns);
+
+        let rows = std::iter::once(&self.header).chain(self.rows.iter());
+        for (i, (row, (positions, _))) in rows.zip(layout).enumerate() {
+            let mut entries = Vec::new();
+            let mut column = 0;
+
+            for (cell, position) in row.cells.iter().zip(positions) {
+                while column < position {
+                    entries.push(String::new());
+                    column += 1;
+                }
+                entries.push(cell_to_latex(cell, self.get_alignment(column), settings));
+                column += cell.colspan;
+            }
+            while column < width {
+                entries.push(String::new());
+                column += 1;
+            }
+            latex.push_str(&entries.join(" & "));
+            latex.push_str(" \\\\\n");
+            if i == 0 {
+                latex.push_str("\\hline\n");
+            }
+        }
+        latex.push_str("\\hline\n\\end{tabular}\n");
+
+        latex
+    }
+}
+
+impl ToLatex for CodeBlock {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let mut options = Vec::new();
+        let mut latex = String::new();
+        let code = self.code.trim_end_matches('\n');
+
+        match settings.latex.code_package {
+            CodePackage::Listings => {
+                let language = self.language.to_lowercase();
+                if let Some((_, name)) = LISTINGS_LANGUAGES.iter().find(|(l, _)| *l == language) {
+                    options.push(format!("language={{{}}}", name));
+                }
+                if let Some(title) = self.get_title() {
+                    options.push(format!("title={{{}}}", escape_text(&title)));
+                }
+                if self.has_line_numbers() {
+                    options.push("numbers=left".to_string());
+                }
+                latex.push_str(&format!(
+                    "\\begin{{lstlisting}}{}\n{}\n\\end{{lstlisting}}\n",
+                    latex_options(&options),
+                    code
+                ));
+            }
+            CodePackage::Minted => {
+                if let Some(title) = self.get_title() {
+                    latex.push_str(&format!("\\noindent\\textbf{{{}}}\n", escape_text(&title)));
+                }
+                if self.has_line_numbers() {
+                    options.push("linenos".to_string());
+                }
+                let highlighted = self
+                    .get_highlighted_lines()
+                    .iter()
+                    .map(|range| format!("{}-{}", range.start(), range.end()))
+                    .collect::<Vec<String>>();
+                if !highlighted.is_empty() {
+                    options.push(format!("highlightlines={{{}}}", highlighted.join(",")));
+                }
+                let language = if self.language.is_empty() {
+                    "text"
+                } else {
+                    self.language.as_str()
+                };
+                latex.push_str(&format!(
+                    "\\begin{{minted}}{}{{{}}}\n{}\n\\end{{minted}}\n",
+                    latex_options(&options),
+                    language,
+                    code
+                ));
+            }
+        }
+
+        latex
+    }
+}
+
+impl ToLatex for Comment {
+    fn to_latex(&self, _: &Settings) -> String {
+        self.value
+            .lines()
+            .map(|line| format!("% {}\n", line.trim()))
+            .collect()
+    }
+}
+
+impl ToLatex for MathBlock {
+    /// Renders TeX verbatim in a display math environment.
+    /// AsciiMath can't be compiled by LaTeX so its source is kept as is.
+    fn to_latex(&self, _: &Settings) -> String {
+        match &self.expression {
+            MathExpression::Tex(tex) => format!("\\[\n{}\n\\]\n", tex.trim()),
+            MathExpression::AsciiMath { source, .. } => verbatim(source),
+        }
+    }
+}
+
+impl ToLatex for Quote {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let mut latex = "\\begin{quote}\n".to_string();
+        latex.push_str(&blocks_to_latex(&self.elements, settings));
+        if let Some(attribution) = &self.attribution {
+            let mut text = escape_text(&attribution.author);
+            if let Some(source) = &attribution.source {
+                text.push_str(&format!(", \\emph{{{}}}", escape_text(source)));
+            }
+            latex.push_str(&format!("\\hfill --- {}\n", text));
+        }
+        if let Some(metadata) = &self.metadata {
+            latex.push_str(&format!("\\hfill {}\n", metadata.to_latex(settings)));
+        }
+        latex.push_str("\\end{quote}\n");
+
+        latex
+    }
+}
+
+impl ToLatex for Aligned {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let environment = match self.alignment {
+            Alignment::Left => "flushleft",
+            Alignment::Right => "flushright",
+            Alignment::Center | Alignment::None => "center",
+        };
+
+        format!(
+            "\\begin{{{0}}}\n{1}\\end{{{0}}}\n",
+            environment,
+            blocks_to_latex(&self.content, settings)
+        )
+    }
+}
+
+impl ToLatex for Columns {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| blocks_to_latex(column, settings))
+            .collect::<Vec<String>>();
+
+        // multicols needs at least two columns
+        if self.count < 2 {
+            columns.concat()
+        } else {
+            format!(
+                "\\begin{{multicols}}{{{}}}\n{}\\end{{multicols}}\n",
+                self.count,
+                columns.join("\\columnbreak\n")
+            )
+        }
+    }
+}
+
+impl ToLatex for DefinitionList {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let mut latex = "\\begin{description}\n".to_string();
+        for item in &self.items {
+            let definitions = item
+                .definitions
+                .iter()
+                .map(|d| d.to_latex(settings))
+                .collect::<Vec<String>>();
+            latex.push_str(&format!(
+                "\\item[{}] {}\n",
+                item.term.to_latex(settings),
+                definitions.join("\n\n")
+            ));
+        }
+        latex.push_str("\\end{description}\n");
+
+        latex
+    }
+}
+
+impl ToLatex for Figure {
+    fn to_latex(&self, settings: &Settings) -> String {
+        format!(
+            "\\begin{{figure}}[h]\n\\centering\n{}\n\\caption{{{}}}\n\\end{{figure}}\n",
+            image_to_latex(&self.image),
+            self.caption.to_latex(settings)
+        )
+    }
+}
+
+impl ToLatex for Placeholder {
+    fn to_latex(&self, settings: &Settings) -> String {
+        if let Some(value) = &self.value {
+            value.to_latex(settings)
+        } else {
+            escape_text(&format!("[[{}]]", self.name))
+        }
+    }
+}
+
+impl ToLatex for TextLine {
+    fn to_latex(&self, settings: &Settings) -> String {
+        inlines_to_latex(self.without_break(), settings)
+    }
+}
+
+impl ToLatex for RefLink {
+    fn to_latex(&self, settings: &Settings) -> String {
+        format!(
+            "\\hyperref[{}]{{{}}}",
+            self.reference,
+            self.description.to_latex(settings)
+        )
+    }
+}
+
+impl ToLatex for InlineMetadata {
+    fn to_latex(&self, settings: &Settings) -> String {
+        if let Some(MetadataValue::String(format)) = self.data.get("display") {
+            let mut template = PlaceholderTemplate::new(format.clone());
+            self.data
+                .iter()
+                .for_each(|(k, v)| template.add_replacement(k, &v.to_string()));
+
+            escape_text(&template.render())
+        } else {
+            let mut entries = self
+                .data
+                .iter()
+                .map(|(k, v)| format!("{}={}", escape_text(k), v.to_latex(settings)))
+                .collect::<Vec<String>>();
+            entries.sort();
+
+            entries.join(", ")
+        }
+    }
+}
+
+impl ToLatex for HighlightText {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let color = self.get_color().unwrap_or_else(|| "yellow".to_string());
+
+        format!(
+            "\\colorbox{}{{{}}}",
+            color_to_latex(&color),
+            inlines_to_latex(&self.value, settings)
+        )
+    }
+}
+
+impl ToLatex for StyledText {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let value = self.value.to_latex(settings);
+
+        match self.metadata.get_string("color") {
+            Some(color) => format!("\\textcolor{}{{{}}}", color_to_latex(&color), value),
+            None => value,
+        }
+    }
+}
+
+impl ToLatex for Url {
+    fn to_latex(&self, settings: &Settings) -> String {
+        let url = escape_url(&self.url);
+
+        match &self.description {
+            Some(description) => format!(
+                "\\href{{{}}}{{{}}}",
+                url,
+                inlines_to_latex(description, settings)
+            ),
+            None => format!("\\url{{{}}}", url),
+        }
+    }
+}
+
+impl ToLatex for Math {
+    fn to_latex(&self, _: &Settings) -> String {
+        match &self.expression {
+            MathExpression::Tex(tex) => format!("${}$", tex.trim()),
+            MathExpression::AsciiMath { source, .. } => {
+                format!("\\texttt{{{}}}", escape_text(source))
+            }
+        }
+    }
+}
+
+impl ToLatex for BibReference {
+    fn to_latex(&self, _: &Settings) -> String {
+        if let Some(fallback) = &self.fallback {
+            if self.entry_anchor.lock().entry.is_none() {
+                return escape_text(fallback);
+            }
+        }
+
+        format!(
+            "\\textsuperscript{{\\hyperref[{}]{{{}}}}}",
+            self.key,
+            escape_text(&self.get_formatted())
+        )
+    }
+}
+
+impl ToLatex for GlossaryReference {
+    fn to_latex(&self, _: &Settings) -> String {
+        if let Some(entry) = &self.entry {
+            let entry = entry.lock();
+            let text = match self.display {
+                GlossaryDisplay::Short => &entry.short,
+                GlossaryDisplay::Long => &entry.long,
+            };
+
+            format!("\\hyperref[{}]{{{}}}", self.short, escape_text(text))
+        } else {
+            escape_text(&format!("~{}", self.short))
+        }
+    }
+}
+
+impl ToLatex for FootnoteReference {
+    fn to_latex(&self, settings: &Settings) -> String {
+        if let Some(definition) = &self.definition {
+            let definition = definition.lock();
+
+            format!(
+                "\\textsuperscript{{\\hyperref[{}]{{{}}}}}",
+                definition.get_anchor_key(),
+                definition.number.unwrap_or_default()
+            )
+        } else {
+            self.citation.read().unwrap().to_latex(settings)
+        }
+    }
+}
+
+impl ToLatex for Template {
+    fn to_latex(&self, settings: &Settings) -> String {
+        self.text.iter().map(|e| e.to_latex(settings)).collect()
+    }
+}
+
+impl ToLatex for TemplateVariable {
+    fn to_latex(&self, settings: &Settings) -> String {
+        match &self.value {
+            Some(value) => format!(
+                "{}{}{}",
+                escape_text(&self.prefix),
+                value.to_latex(settings),
+                escape_text(&self.suffix)
+            ),
+            None => String::new(),
+        }
+    }
+}
+
+impl ToLatex for CharacterCode {
+    fn to_latex(&self, _: &Settings) -> String {
+        match htmlescape::decode_html(&format!("&{};", self.code)) {
+            Ok(decoded) if decoded == "\u{a0}" => "~".to_string(),
+            Ok(decoded) => escape_text(&decoded),
+            Err(_) => escape_text(&format!("&{};", self.code)),
+        }
+    }
+}
+
+impl ToLatex for Arrow {
+    fn to_latex(&self, _: &Settings) -> String {
+        match self {
+            Arrow::RightArrow => "$\\rightarrow$",
+            Arrow::LeftArrow => "$\\leftarrow$",
+            Arrow::LeftRightArrow => "$\\leftrightarrow$",
+            Arrow::BigRightArrow => "$\\Rightarrow$",
+            Arrow::BigLeftArrow => "$\\Leftarrow$",
+            Arrow::BigLeftRightArrow => "$\\Leftrightarrow$",
+        }
+        .to_string()
+    }
+}
+
+impl ToLatex for Abbreviation {
+    fn to_latex(&self, _: &Settings) -> String {
+        escape_text(&self.short)
+    }
+}
+
+impl Document {
+    /// Renders the document as LaTeX source with the settings of the document
+    pub fn to_latex(&self) -> String {
+        let settings = self.config.lock().clone();
+
+        ToLatex::to_latex(self, &settings)
+    }
+}
+
+/// Renders the blocks separated by empty lines
+fn blocks_to_latex(blocks: &[Block], settings: &Settings) -> String {
+    blocks
+        .iter()
+        .map(|block| block.to_latex(settings))
+        .filter(|latex| !latex.is_empty())
+        .map(|latex| format!("{}\n", latex.trim_end_matches('\n')))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn inlines_to_latex(inlines: &[Inline], settings: &Settings) -> String {
+    inlines.iter().map(|i| i.to_latex(settings)).collect()
+}
+
+fn list_items_to_latex(items: &[ListItem], ordered: bool, settings: &Settings) -> String {
+    let environment = if ordered { "enumerate" } else { "itemize" };
+    let items = items
+        .iter()
+        .map(|item| item.to_latex(settings))
+        .collect::<String>();
+
+    format!("\\begin{{{0}}}\n{1}\\end{{{0}}}\n", environment, items)
+}
+
+fn anchor_to_latex(key: &str, inner: &Line, settings: &Settings) -> String {
+    format!(
+        "\\phantomsection\\label{{{}}}{}",
+        key,
+        inner.to_latex(settings)
+    )
+}
+
+fn cell_to_latex(cell: &Cell, alignment: Alignment, settings: &Settings) -> String {
+    let mut latex = cell.text.to_latex(settings).trim().to_string();
+    if cell.rowspan > 1 {
+        latex = format!("\\multirow{{{}}}{{*}}{{{}}}", cell.rowspan, latex);
+    }
+    if cell.colspan > 1 {
+        latex = format!(
+            "\\multicolumn{{{}}}{{{}}}{{{}}}",
+            cell.colspan,
+            alignment_to_latex(alignment),
+            latex
+        );
+    }
+
+    latex
+}
+
+fn alignment_to_latex(alignment: Alignment) -> &'static str {
+    match alignment {
+        Alignment::None | Alignment::Left => "l",
+        Alignment::Center => "c",
+        Alignment::Right => "r",
+    }
+}
+
+/// Returns an includegraphics command for the image.
+/// Images that are only available remotely are linked instead.
+fn image_to_latex(image: &Image) -> String {
+    let url = &image.url.url;
+    if url.starts_with("http://") || url.starts_with("https://") {
+        return format!("\\url{{{}}}", escape_url(url));
+    }
+    let path = image.image_data.lock().path.to_string_lossy().to_string();
+    let mut options = Vec::new();
+
+    if let Some(meta) = &image.metadata {
+        for key in &["width", "height"] {
+            if let Some(value) = meta.get_string(key) {
+                match value.strip_suffix('%').and_then(|v| v.parse::<f64>().ok()) {
+                    Some(percent) => {
+                        options.push(format!("{}={}\\linewidth", key, percent / 100.0))
+                    }
+                    None => options.push(format!("{}={}", key, value)),
+                }
+            } else if let Some(value) = meta.get_integer(key) {
+                options.push(format!("{}={}px", key, value))
+            }
+        }
+    }
+
+    format!("\\includegraphics{}{{{}}}", latex_options(&options), path)
+}
+
+fn checkbox_to_latex(checked: bool) -> &'static str {
+    if checked {
+        "$\\boxtimes$"
+    } else {
+        "$\\square$"
+    }
+}
+
+/// Returns the color argument for xcolor commands.
+/// Hex colors are passed with the HTML model, other values as color names.
+fn color_to_latex(color: &str) -> String {
+    let hex = color.trim_start_matches('#');
+    let is_hex = color.starts_with('#') && hex.chars().all(|c| c.is_ascii_hexdigit());
+
+    match hex.len() {
+        6 if is_hex => format!("[HTML]{{{}}}", hex.to_uppercase()),
+        3 if is_hex => {
+            let expanded = hex.chars().flat_map(|c| [c, c]).collect::<String>();
+            format!("[HTML]{{{}}}", expanded.to_uppercase())
+        }
+        _ => format!("{{{}}}", color),
+    }
+}
+
+fn latex_options(options: &[String]) -> String {
+    if options.is_empty() {
+        String::new()
+    } else {
+        format!("[{}]", options.join(", "))
+    }
+}
+
+fn verbatim(text: &str) -> String {
+    format!(
+        "\\begin{{verbatim}}\n{}\n\\end{{verbatim}}\n",
+        text.trim_end_matches('\n')
+    )
+}
+
+/// Escapes the characters that have a special meaning in LaTeX
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Escapes the characters of an url that would end the argument of href or url
+fn escape_url(url: &str) -> String {
+    url.replace('%', "\\%").replace('#', "\\#")
+}
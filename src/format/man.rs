@@ -0,0 +1,506 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::*;
+use crate::references::glossary::GlossaryDisplay;
+use crate::settings::Settings;
+
+/// Renders an element to roff source for the man macro package.
+/// Every returned string consists of complete lines.
+pub trait ToMan {
+    fn to_man(&self) -> String;
+}
+
+impl ToMan for Block {
+    fn to_man(&self) -> String {
+        match self {
+            Block::Section(section) => section.to_man(),
+            Block::Paragraph(paragraph) => paragraph.to_man(),
+            Block::List(list) => list_items_to_man(&list.items, list.ordered),
+            Block::Table(table) => table.to_man(),
+            Block::CodeBlock(code) => preformatted(&code.code),
+            Block::Diagram(diagram) => preformatted(&diagram.source),
+            Block::MathBlock(math) => preformatted(math_source(&math.expression)),
+            Block::Quote(quote) => quote.to_man(),
+            Block::Aligned(aligned) => aligned.to_man(),
+            // man pages don't have columns, so the columns are written after each other
+            Block::Columns(columns) => columns
+                .columns
+                .iter()
+                .flatten()
+                .map(|block| block.to_man())
+                .collect(),
+            Block::CustomContainer(container) => container
+                .get_rendered_blocks()
+                .iter()
+                .map(|block| block.to_man())
+                .collect(),
+            Block::DefinitionList(list) => list.to_man(),
+            Block::Figure(figure) => figure.to_man(),
+            Block::Placeholder(placeholder) => match &placeholder.read().unwrap().value {
+                Some(Element::Block(block)) => block.to_man(),
+                Some(Element::Line(line)) => format!(".PP\n{}", text_lines(&line_to_man(line))),
+                Some(Element::Inline(inline)) => {
+                    format!(".PP\n{}", text_lines(&inline_to_man(inline, Font::Roman)))
+                }
+                None => String::new(),
+            },
+            Block::PageBreak(_) => ".bp\n".to_string(),
+            Block::Html(_) | Block::Comment(_) | Block::Import(_) | Block::Null => String::new(),
+        }
+    }
+}
+
+impl ToMan for Section {
+    /// Renders top level headers as .SH and all nested headers as .SS
+    fn to_man(&self) -> String {
+        let macro_name = if self.header.size <= 1 { ".SH" } else { ".SS" };
+        let mut man = format!(
+            "{} \"{}\"\n",
+            macro_name,
+            escape_text(&self.header.get_plain_text()).replace('"', "\\(dq")
+        );
+        for block in &self.elements {
+            man.push_str(&block.to_man());
+        }
+
+        man
+    }
+}
+
+impl ToMan for Paragraph {
+    /// Renders the lines of the paragraph with forced breaks for line breaks.
+    /// Rulers and centered lines interrupt the filled text.
+    fn to_man(&self) -> String {
+        let mut man = ".PP\n".to_string();
+
+        for (index, line) in self.elements.iter().enumerate() {
+            match line {
+                Line::Ruler(_) => man.push_str(".sp\n\\l'\\n(.lu'\n.sp\n"),
+                Line::Centered(centered) => man.push_str(&format!(
+                    ".ce 1\n{}",
+                    text_lines(&text_to_man(&centered.line))
+                )),
+                _ => man.push_str(&text_lines(&line_to_man(line))),
+            }
+            if line.ends_with_break() && index + 1 < self.elements.len() {
+                man.push_str(".br\n");
+            }
+        }
+
+        man
+    }
+}
+
+impl ToMan for Table {
+    /// Renders the table for the tbl preprocessor with tab separated cells.
+    /// Cells that span multiple columns are followed by empty cells.
+    fn to_man(&self) -> String {
+        let layout = self.get_layout();
+        let width = layout.iter().map(|(_, width)| *width).max().unwrap_or(0);
+        if width == 0 {
+            return String::new();
+        }
+        let columns = (0..width)
+            .map(|column| match self.get_alignment(column) {
+                Alignment::None | Alignment::Left => "l",
+                Alignment::Center => "c",
+                Alignment::Right => "r",
+            })
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let mut man = format!(".TS\nallbox;\n{}.\n", columns);
+        let rows = std::iter::once(&self.header).chain(self.rows.iter());
+
+        for (row, (positions, _)) in rows.zip(layout) {
+            let mut entries = vec![String::new(); width];
+            for (cell, position) in row.cells.iter().zip(positions) {
+                if let Some(entry) = entries.get_mut(position) {
+                    *entry = line_to_man(&cell.text)
+                        .replace(".br", "")
+                        .replace(['\n', '\t'], " ")
+                        .trim()
+                        .to_string();
+                }
+            }
+            man.push_str(&escape_line_start(&entries.join("\t")));
+            man.push('\n');
+        }
+        man.push_str(".TE\n");
+
+        man
+    }
+}
+
+impl ToMan for Aligned {
+    fn to_man(&self) -> String {
+        let adjustment = match self.alignment {
+            Alignment::Left => "l",
+            Alignment::Right => "r",
+            Alignment::Center | Alignment::None => "c",
+        };
+        let mut man = format!(".ad {}\n", adjustment);
+        for block in &self.content {
+            man.push_str(&block.to_man());
+        }
+        man.push_str(".ad b\n");
+
+        man
+    }
+}
+
+impl ToMan for Quote {
+    fn to_man(&self) -> String {
+        let mut man = ".RS 4\n".to_string();
+        for block in &self.elements {
+            man.push_str(&block.to_man());
+        }
+        if let Some(attribution) = &self.attribution {
+            man.push_str(&format!(".PP\n\\(em {}", escape_text(&attribution.author)));
+            if let Some(source) = &attribution.source {
+                man.push_str(&format!(", \\fI{}\\fR", escape_text(source)));
+            }
+            man.push('\n');
+        }
+        if let Some(metadata) = &self.metadata {
+            let mut entries = metadata
+                .get_string_map()
+                .into_iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<String>>();
+            entries.sort();
+            man.push_str(&format!(
+                ".PP\n\\(em {}\n",
+                escape_text(&entries.join(", "))
+            ));
+        }
+        man.push_str(".RE\n");
+
+        man
+    }
+}
+
+impl ToMan for DefinitionList {
+    fn to_man(&self) -> String {
+        let mut man = String::new();
+        for item in &self.items {
+            man.push_str(".TP\n");
+            man.push_str(&text_lines(&with_font(
+                item.term.without_break(),
+                Font::Bold,
+                Font::Roman,
+            )));
+            let definitions = item
+                .definitions
+                .iter()
+                .map(|d| text_lines(&text_to_man(d)))
+                .collect::<Vec<String>>();
+            man.push_str(&definitions.join(".br\n"));
+        }
+
+        man
+    }
+}
+
+impl ToMan for Figure {
+    fn to_man(&self) -> String {
+        format!(".PP\n{}", text_lines(&text_to_man(&self.caption)))
+    }
+}
+
+impl Document {
+    /// Renders the document as a man page. The name of the page is taken from the title
+    /// and its section from the `section` entry of the front matter.
+    pub fn to_man(&self) -> String {
+        let settings = self.config.lock().clone();
+        let mut man = title_header(&settings);
+
+        for block in &self.elements {
+            man.push_str(&block.to_man());
+        }
+
+        man
+    }
+}
+
+/// The fonts of the man macros that are switched to with \f
+#[derive(Clone, Copy, PartialEq)]
+enum Font {
+    Roman,
+    Bold,
+    Italic,
+    BoldItalic,
+}
+
+impl Font {
+    fn escape(&self) -> &'static str {
+        match self {
+            Font::Roman => "\\fR",
+            Font::Bold => "\\fB",
+            Font::Italic => "\\fI",
+            Font::BoldItalic => "\\f(BI",
+        }
+    }
+
+    fn with_bold(self) -> Self {
+        match self {
+            Font::Roman | Font::Bold => Font::Bold,
+            Font::Italic | Font::BoldItalic => Font::BoldItalic,
+        }
+    }
+
+    fn with_italic(self) -> Self {
+        match self {
+            Font::Roman | Font::Italic => Font::Italic,
+            Font::Bold | Font::BoldItalic => Font::BoldItalic,
+        }
+    }
+}
+
+/// Returns the .TH line with the title, section and date of the page
+fn title_header(settings: &Settings) -> String {
+    let title = settings
+        .metadata
+        .title
+        .clone()
+        .unwrap_or_else(|| "UNTITLED".to_string());
+    let section = settings
+        .custom_attributes
+        .get("section")
+        .cloned()
+        .unwrap_or_else(|| "1".to_string());
+
+    // the comment in the first line tells man to run the tables through tbl
+    format!(
+        "'\\\" t\n.TH \"{}\" \"{}\" \"{}\"\n",
+        escape_text(&title.to_uppercase()).replace('"', "\\(dq"),
+        escape_text(&section),
+        chrono::Local::now().format("%Y-%m-%d")
+    )
+}
+
+fn list_items_to_man(items: &[ListItem], ordered: bool) -> String {
+    let mut man = String::new();
+
+    for (index, item) in items.iter().enumerate() {
+        let marker = match item.checked {
+            Some(true) => "[x]".to_string(),
+            Some(false) => "[ ]".to_string(),
+            None if ordered => format!("{}.", index + 1),
+            None => item
+                .get_custom_bullet()
+                .map(|bullet| escape_text(&bullet).replace('"', "\\(dq"))
+                .unwrap_or_else(|| "\\(bu".to_string()),
+        };
+        man.push_str(&format!(".IP \"{}\" 4\n", marker));
+        man.push_str(&text_lines(&line_to_man(&item.text)));
+        if !item.blocks.is_empty() {
+            man.push_str(".RS 4\n");
+            item.blocks.iter().for_each(|b| man.push_str(&b.to_man()));
+            man.push_str(".RE\n");
+        }
+
+        if let Some(first) = item.children.first() {
+            man.push_str(".RS 4\n");
+            man.push_str(&list_items_to_man(&item.children, first.ordered));
+            man.push_str(".RE\n");
+        }
+    }
+
+    man
+}
+
+/// Returns code that is written without filling and adjusting lines
+fn preformatted(code: &str) -> String {
+    let lines = code
+        .trim_end_matches('\n')
+        .lines()
+        .map(|line| escape_line_start(&escape_text(line)))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    format!(".PP\n.RS 4\n.nf\n{}\n.fi\n.RE\n", lines)
+}
+
+fn math_source(expression: &MathExpression) -> &str {
+    match expression {
+        MathExpression::AsciiMath { source, .. } => source,
+        MathExpression::Tex(tex) => tex,
+    }
+}
+
+fn line_to_man(line: &Line) -> String {
+    match line {
+        Line::Text(text) => text_to_man(text),
+        Line::Centered(centered) => text_to_man(&centered.line),
+        Line::RefLink(link) => text_to_man(&link.description),
+        Line::Anchor(anchor) => line_to_man(&anchor.inner),
+        Line::Ruler(_)
+        | Line::BibEntry(_)
+        | Line::FootnoteDefinition(_)
+        | Line::AbbreviationDefinition(_) => String::new(),
+    }
+}
+
+fn text_to_man(text: &TextLine) -> String {
+    inlines_to_man(text.without_break(), Font::Roman)
+}
+
+/// Renders the inline elements in the given font. Nested elements
+/// switch back to the font of their parent explicitly as roff only
+/// remembers the previous font.
+fn inlines_to_man(inlines: &[Inline], font: Font) -> String {
+    inlines.iter().map(|i| inline_to_man(i, font)).collect()
+}
+
+fn inline_to_man(inline: &Inline, font: Font) -> String {
+    match inline {
+        Inline::Plain(plain) => escape_text(&plain.value),
+        Inline::Bold(bold) => with_font(&bold.value, font.with_bold(), font),
+        Inline::Italic(italic) => with_font(&italic.value, font.with_italic(), font),
+        Inline::Underlined(under) => with_font(&under.value, font.with_italic(), font),
+        Inline::Monospace(mono) => format!(
+            "{}{}{}",
+            font.with_bold().escape(),
+            escape_text(&mono.value),
+            font.escape()
+        ),
+        Inline::Striked(striked) => inlines_to_man(&striked.value, font),
+        Inline::Spoiler(spoiler) => inlines_to_man(&spoiler.value, font),
+        Inline::Highlight(highlight) => inlines_to_man(&highlight.value, font),
+        Inline::Superscript(sup) => format!("^{}", inlines_to_man(&sup.value, font)),
+        Inline::Subscript(sub) => format!("_{}", inlines_to_man(&sub.value, font)),
+        Inline::Styled(styled) => inline_to_man(&styled.value, font),
+        Inline::Colored(colored) => inline_to_man(&colored.value, font),
+        Inline::Url(url) => match &url.description {
+            Some(description) => format!(
+                "{} <{}>",
+                inlines_to_man(description, font),
+                escape_text(&url.url)
+            ),
+            None => escape_text(&url.url),
+        },
+        Inline::Image(image) => image
+            .url
+            .description
+            .as_ref()
+            .map(|d| inlines_to_man(d, font))
+            .unwrap_or_default(),
+        Inline::Placeholder(placeholder) => match &placeholder.read().unwrap().value {
+            Some(Element::Inline(inline)) => inline_to_man(inline, font),
+            Some(Element::Line(line)) => line_to_man(line),
+            _ => String::new(),
+        },
+        Inline::Checkbox(checkbox) => {
+            if checkbox.value {
+                "[x]".to_string()
+            } else {
+                "[ ]".to_string()
+            }
+        }
+        Inline::Emoji(emoji) => emoji.value.clone(),
+        Inline::Math(math) => escape_text(math_source(&math.expression)),
+        Inline::BibReference(bib) => {
+            let bib = bib.read().unwrap();
+            match &bib.fallback {
+                Some(fallback) if bib.entry_anchor.lock().entry.is_none() => escape_text(fallback),
+                _ => format!("[{}]", escape_text(&bib.get_formatted())),
+            }
+        }
+        Inline::GlossaryReference(gloss) => {
+            let gloss = gloss.lock();
+            match &gloss.entry {
+                Some(entry) => {
+                    let entry = entry.lock();
+                    match gloss.display {
+                        GlossaryDisplay::Short => escape_text(&entry.short),
+                        GlossaryDisplay::Long => escape_text(&entry.long),
+                    }
+                }
+                None => escape_text(&format!("~{}", gloss.short)),
+            }
+        }
+        Inline::FootnoteReference(footnote) => {
+            let footnote = footnote.lock();
+            match &footnote.definition {
+                Some(definition) => format!("[{}]", definition.lock().number.unwrap_or_default()),
+                None => inline_to_man(&Inline::BibReference(footnote.citation.clone()), font),
+            }
+        }
+        Inline::TemplateVar(var) => {
+            let var = var.read().unwrap();
+            match &var.value {
+                Some(Element::Inline(inline)) => format!(
+                    "{}{}{}",
+                    escape_text(&var.prefix),
+                    inline_to_man(inline, font),
+                    escape_text(&var.suffix)
+                ),
+                Some(Element::Line(line)) => format!(
+                    "{}{}{}",
+                    escape_text(&var.prefix),
+                    line_to_man(line),
+                    escape_text(&var.suffix)
+                ),
+                _ => String::new(),
+            }
+        }
+        Inline::CharacterCode(code) => {
+            let text = htmlescape::decode_html(&format!("&{};", code.code))
+                .unwrap_or_else(|_| format!("&{};", code.code));
+            escape_text(&text)
+        }
+        Inline::LineBreak => "\n.br\n".to_string(),
+        Inline::Arrow(arrow) => match arrow {
+            Arrow::RightArrow => "\\(->",
+            Arrow::LeftArrow => "\\(<-",
+            Arrow::LeftRightArrow => "\\(<>",
+            Arrow::BigRightArrow => "\\(rA",
+            Arrow::BigLeftArrow => "\\(lA",
+            Arrow::BigLeftRightArrow => "\\(hA",
+        }
+        .to_string(),
+        Inline::Anchor(anchor) => line_to_man(&anchor.inner),
+        Inline::Abbreviation(abbreviation) => escape_text(&abbreviation.short),
+        Inline::Html(_) | Inline::Comment(_) => String::new(),
+    }
+}
+
+fn with_font(inlines: &[Inline], font: Font, parent: Font) -> String {
+    format!(
+        "{}{}{}",
+        font.escape(),
+        inlines_to_man(inlines, font),
+        parent.escape()
+    )
+}
+
+/// Returns the text as complete lines without empty lines
+/// as those would be rendered as vertical space
+fn text_lines(text: &str) -> String {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| match line {
+            ".br" => ".br\n".to_string(),
+            _ => format!("{}\n", escape_line_start(line)),
+        })
+        .collect()
+}
+
+/// Prevents a line that starts with a dot or quote from being read as a request
+fn escape_line_start(line: &str) -> String {
+    if line.starts_with('.') || line.starts_with('\'') {
+        format!("\\&{}", line)
+    } else {
+        line.to_string()
+    }
+}
+
+/// Escapes backslashes and hyphens so that they are printed as written
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
@@ -0,0 +1,989 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::*;
+use crate::parser::inline::get_emoji;
+use crate::references::abbreviations::AbbreviationDefinition;
+use crate::references::footnotes::{FootnoteDefinition, FootnoteReference};
+use crate::references::glossary::{GlossaryDisplay, GlossaryReference};
+use crate::references::templates::{Template, TemplateVariable};
+
+const LINE_BREAK: &str = "\\\n";
+const INDENT: &str = "  ";
+
+/// Characters in plain text that are escaped because they would start a formatted element
+const ESCAPED_CHARS: &[char] = &['\\', '*', '_', '~', '`', '^', '[', '§', '$'];
+
+/// Renders an element back into snekdown source text.
+/// The output is normalized: spacing is collapsed, list markers and
+/// table columns are aligned and metadata keys are sorted.
+pub trait ToMarkdown {
+    fn to_markdown(&self) -> String;
+}
+
+impl ToMarkdown for Document {
+    fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+
+        if let Some(front_matter) = &self.front_matter {
+            markdown.push_str("---\n");
+            for (key, value) in sorted_metadata(front_matter) {
+                markdown.push_str(&format!("{}: {}\n", key, front_matter_value(value)));
+            }
+            markdown.push_str("---\n");
+            // an empty line before a paragraph would be parsed as a break
+            if !matches!(self.elements.first(), Some(Block::Paragraph(_))) {
+                markdown.push('\n');
+            }
+        }
+        markdown.push_str(&blocks_to_markdown(&self.elements));
+        // the source is always read with a line break at the end
+        let mut markdown = markdown.trim_end_matches('\n').to_string();
+        markdown.push('\n');
+
+        markdown
+    }
+}
+
+impl ToMarkdown for Element {
+    fn to_markdown(&self) -> String {
+        match self {
+            Element::Block(block) => block.to_markdown(),
+            Element::Line(line) => line.to_markdown(),
+            Element::Inline(inline) => inline.to_markdown(),
+        }
+    }
+}
+
+impl ToMarkdown for Block {
+    /// The metadata of blocks without a syntax of their own for it
+    /// is written as attributes on the line before them
+    fn to_markdown(&self) -> String {
+        let markdown = match self {
+            Block::Section(section) => section.to_markdown(),
+            Block::Paragraph(paragraph) => paragraph.to_markdown(),
+            Block::List(list) => list.to_markdown(),
+            Block::Table(table) => table.to_markdown(),
+            Block::CodeBlock(code) => code.to_markdown(),
+            Block::Diagram(diagram) => diagram.to_markdown(),
+            Block::Html(html) => html.value.clone(),
+            Block::Comment(comment) => comment.to_markdown(),
+            Block::MathBlock(math) => math.to_markdown(),
+            Block::Quote(quote) => quote.to_markdown(),
+            Block::Aligned(aligned) => aligned.to_markdown(),
+            Block::Columns(columns) => columns.to_markdown(),
+            Block::CustomContainer(container) => container.to_markdown(),
+            Block::PageBreak(_) => "<<<".to_string(),
+            Block::DefinitionList(list) => list.to_markdown(),
+            Block::Figure(figure) => figure.to_markdown(),
+            Block::Import(import) => import.to_markdown(),
+            Block::Placeholder(placeholder) => placeholder.read().unwrap().to_markdown(),
+            Block::Null => String::new(),
+        };
+        let has_metadata_syntax = matches!(
+            self,
+            Block::Section(_)
+                | Block::CodeBlock(_)
+                | Block::Diagram(_)
+                | Block::Quote(_)
+                | Block::CustomContainer(_)
+                | Block::Import(_)
+        );
+
+        match self.get_metadata() {
+            Some(metadata) if !has_metadata_syntax => {
+                format!("{}\n{}", keyed_metadata_to_markdown(metadata), markdown)
+            }
+            _ => markdown,
+        }
+    }
+}
+
+impl ToMarkdown for Line {
+    fn to_markdown(&self) -> String {
+        match self {
+            Line::Text(text) => text.to_markdown(),
+            Line::Ruler(_) => "- - -".to_string(),
+            Line::RefLink(link) => link.description.to_markdown(),
+            Line::Anchor(anchor) => anchor.inner.to_markdown(),
+            Line::Centered(centered) => format!("||{}", centered.line.to_markdown()),
+            Line::BibEntry(entry) => entry.to_markdown(),
+            Line::FootnoteDefinition(footnote) => footnote.lock().to_markdown(),
+            Line::AbbreviationDefinition(abbreviation) => abbreviation.to_markdown(),
+        }
+    }
+}
+
+impl ToMarkdown for Inline {
+    fn to_markdown(&self) -> String {
+        match self {
+            Inline::Plain(plain) => escape_text(&plain.value),
+            Inline::Bold(bold) => format!("**{}**", inlines_to_markdown(&bold.value)),
+            Inline::Italic(italic) => format!("*{}*", inlines_to_markdown(&italic.value)),
+            Inline::Underlined(under) => format!("_{}_", inlines_to_markdown(&under.value)),
+            Inline::Striked(striked) => format!("~~{}~~", inlines_to_markdown(&striked.value)),
+            Inline::Spoiler(spoiler) => format!("||{}||", inlines_to_markdown(&spoiler.value)),
+            Inline::Highlight(highlight) => highlight.to_markdown(),
+            Inline::Styled(styled) => format!(
+                "{}{}",
+                styled.value.to_markdown(),
+                metadata_to_markdown(&styled.metadata)
+            ),
+            Inline::Monospace(mono) => format!("`{}`", mono.value.replace('`', "\\`")),
+            Inline::Superscript(sup) => format!("^{}^", inlines_to_markdown(&sup.value)),
+            Inline::Subscript(sub) => format!("~{}~", inlines_to_markdown(&sub.value)),
+            Inline::Url(url) => url.to_markdown(),
+            Inline::Image(image) => image.to_markdown(),
+            Inline::Placeholder(placeholder) => placeholder.read().unwrap().to_markdown(),
+            Inline::Checkbox(checkbox) => checkbox_to_markdown(checkbox.value),
+            Inline::Emoji(emoji) => format!(":{}:", emoji.name),
+            Inline::Colored(colored) => {
+                format!("§[{}]{}", colored.color, colored.value.to_markdown())
+            }
+            Inline::Math(math) => math.to_markdown(),
+            Inline::BibReference(bib) => bib.read().unwrap().to_markdown(),
+            Inline::GlossaryReference(gloss) => gloss.lock().to_markdown(),
+            Inline::FootnoteReference(footnote) => footnote.lock().to_markdown(),
+            Inline::TemplateVar(var) => var.read().unwrap().to_markdown(),
+            Inline::CharacterCode(code) => format!("&{};", code.code),
+            Inline::LineBreak => LINE_BREAK.to_string(),
+            Inline::Arrow(arrow) => arrow.to_markdown(),
+            Inline::Anchor(anchor) => format!("[?{}]{}", anchor.key, anchor.inner.to_markdown()),
+            Inline::Html(html) => html.value.clone(),
+            Inline::Comment(comment) => comment.to_markdown(),
+            Inline::Abbreviation(abbreviation) => escape_text(&abbreviation.short),
+        }
+    }
+}
+
+impl ToMarkdown for Section {
+    fn to_markdown(&self) -> String {
+        let mut markdown = "#".repeat(self.header.size as usize);
+        if let Some(metadata) = &self.metadata {
+            markdown.push_str(&metadata_to_markdown(metadata));
+        }
+        markdown.push(' ');
+        markdown.push_str(&self.header.line.to_markdown());
+        markdown.push('\n');
+        if self.header.line.ends_with_break() {
+            markdown.push('\n');
+        }
+        markdown.push_str(&blocks_to_markdown(&self.elements));
+
+        markdown
+    }
+}
+
+impl ToMarkdown for Paragraph {
+    /// Renders the lines of the paragraph with the line breaks that belong to it.
+    /// Breaks are written as empty lines, so the paragraph ends with the empty
+    /// lines that are parsed as part of it.
+    fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+
+        for line in &self.elements {
+            match line {
+                _ if is_empty_line(line) => {
+                    markdown.push('\n');
+                    continue;
+                }
+                Line::Text(text) => markdown.push_str(&escape_block_start(text.to_markdown())),
+                // the line break after a ruler is parsed as an empty line
+                Line::Ruler(_) => {
+                    markdown.push_str(&line.to_markdown());
+                    continue;
+                }
+                _ => markdown.push_str(&line.to_markdown()),
+            }
+            markdown.push('\n');
+            if line.ends_with_break() {
+                markdown.push('\n');
+            }
+        }
+
+        markdown
+    }
+}
+
+impl ToMarkdown for List {
+    fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        list_items_to_markdown(&self.items, 0, self.loose, &mut markdown);
+
+        markdown.trim_end_matches('\n').to_string()
+    }
+}
+
+impl ToMarkdown for Table {
+    fn to_markdown(&self) -> String {
+        let rows = std::iter::once(&self.header)
+            .chain(self.rows.iter())
+            .map(|row| row.cells.iter().map(cell_to_markdown).collect())
+            .collect::<Vec<Vec<String>>>();
+        let columns = rows
+            .iter()
+            .map(Vec::len)
+            .chain(std::iter::once(self.alignments.len()))
+            .max()
+            .unwrap_or(0);
+        let mut widths = vec![3; columns];
+
+        for row in &rows {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell.chars().count());
+            }
+        }
+        let mut lines = rows
+            .iter()
+            .map(|row| {
+                let cells = row
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cell)| pad_cell(cell, widths[i], self.alignment(i)))
+                    .collect::<Vec<String>>();
+                format!("| {} |", cells.join(" | "))
+            })
+            .collect::<Vec<String>>();
+
+        if !self.alignments.is_empty() {
+            let delimiters = widths
+                .iter()
+                .enumerate()
+                .map(|(i, width)| match self.alignment(i) {
+                    Alignment::None => "-".repeat(*width),
+                    Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                    Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+                    Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+                })
+                .collect::<Vec<String>>();
+            lines.insert(1, format!("| {} |", delimiters.join(" | ")));
+        }
+
+        lines.join("\n")
+    }
+}
+
+impl ToMarkdown for CodeBlock {
+    fn to_markdown(&self) -> String {
+        let attributes = self
+            .metadata
+            .as_ref()
+            .map(attributes_to_markdown)
+            .unwrap_or_default();
+
+        code_fence(&format!("{}{}", self.language, attributes), &self.code)
+    }
+}
+
+impl ToMarkdown for Diagram {
+    fn to_markdown(&self) -> String {
+        let attributes = self
+            .metadata
+            .as_ref()
+            .map(attributes_to_markdown)
+            .unwrap_or_default();
+
+        code_fence(
+            &format!("{}{}", self.kind.as_str(), attributes),
+            &self.source,
+        )
+    }
+}
+
+impl ToMarkdown for Comment {
+    fn to_markdown(&self) -> String {
+        format!("<!-- {} -->", self.value)
+    }
+}
+
+impl ToMarkdown for MathBlock {
+    fn to_markdown(&self) -> String {
+        match &self.expression {
+            MathExpression::AsciiMath { source, .. } => format!("$$${}$$$", source),
+            MathExpression::Tex(tex) => format!("$$\n{}\n$$", tex),
+        }
+    }
+}
+
+impl ToMarkdown for Quote {
+    fn to_markdown(&self) -> String {
+        let mut markdown = self
+            .metadata
+            .as_ref()
+            .map(metadata_to_markdown)
+            .unwrap_or_default();
+        let lines = blocks_to_markdown(&self.elements)
+            .trim_end_matches('\n')
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    ">".to_string()
+                } else {
+                    format!("> {}", line)
+                }
+            })
+            .collect::<Vec<String>>();
+        markdown.push_str(&lines.join("\n"));
+        if let Some(attribution) = &self.attribution {
+            markdown.push_str("\n>\n> -- ");
+            markdown.push_str(&attribution.author);
+            if let Some(source) = &attribution.source {
+                markdown.push_str(", ");
+                markdown.push_str(source);
+            }
+        }
+
+        markdown
+    }
+}
+
+impl ToMarkdown for Aligned {
+    fn to_markdown(&self) -> String {
+        // the alignment is always written so that nested blocks aren't closed by their start
+        let alignment = match self.alignment {
+            Alignment::Left => "left",
+            Alignment::Right => "right",
+            Alignment::Center | Alignment::None => "center",
+        };
+
+        format!(
+            "|||{}\n{}\n|||",
+            alignment,
+            blocks_to_markdown(&self.content).trim_end_matches('\n')
+        )
+    }
+}
+
+impl ToMarkdown for Columns {
+    fn to_markdown(&self) -> String {
+        let mut markdown = format!(":::columns {}\n", self.count);
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| {
+                blocks_to_markdown(column)
+                    .trim_end_matches('\n')
+                    .to_string()
+            })
+            .collect::<Vec<String>>();
+        markdown.push_str(&columns.join("\n:::column\n"));
+        markdown.push_str("\n:::");
+
+        markdown
+    }
+}
+
+impl ToMarkdown for CustomContainer {
+    fn to_markdown(&self) -> String {
+        let mut markdown = format!(":::{}", self.name);
+        if let Some(metadata) = &self.metadata {
+            markdown.push(' ');
+            markdown.push_str(&metadata_to_markdown(metadata));
+        }
+        markdown.push('\n');
+        let content = blocks_to_markdown(&self.content);
+        if !content.is_empty() {
+            markdown.push_str(content.trim_end_matches('\n'));
+            markdown.push('\n');
+        }
+        markdown.push_str(":::");
+
+        markdown
+    }
+}
+
+impl ToMarkdown for DefinitionList {
+    fn to_markdown(&self) -> String {
+        self.items
+            .iter()
+            .map(|item| {
+                let mut markdown = escape_block_start(item.term.to_markdown());
+                for definition in &item.definitions {
+                    markdown.push_str(&format!("\n: {}", definition.to_markdown()));
+                }
+                markdown
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl ToMarkdown for Figure {
+    fn to_markdown(&self) -> String {
+        format!(
+            "{}\n{}",
+            self.image.to_markdown(),
+            escape_block_start(self.caption.to_markdown())
+        )
+    }
+}
+
+impl ToMarkdown for Import {
+    fn to_markdown(&self) -> String {
+        let metadata = match (&self.metadata, self.shift) {
+            (Some(metadata), _) => metadata_to_markdown(metadata),
+            (None, Some(HeaderShift::Nesting)) => "[shift]".to_string(),
+            (None, Some(HeaderShift::Levels(levels))) => format!("[shift={}]", levels),
+            (None, None) => String::new(),
+        };
+
+        format!("<[{}]{}", self.path, metadata)
+    }
+}
+
+impl ToMarkdown for Placeholder {
+    fn to_markdown(&self) -> String {
+        let metadata = self
+            .metadata
+            .as_ref()
+            .map(metadata_to_markdown)
+            .unwrap_or_default();
+
+        format!("[[{}]]{}", self.name, metadata)
+    }
+}
+
+impl ToMarkdown for TextLine {
+    fn to_markdown(&self) -> String {
+        let subtext = self.without_break();
+
+        inlines_to_markdown(subtext)
+    }
+}
+
+impl ToMarkdown for BibEntry {
+    fn to_markdown(&self) -> String {
+        let entry = self.entry.lock();
+        let mut fields = entry
+            .raw_fields
+            .iter()
+            .filter(|(k, _)| k.as_str() != "key" && k.as_str() != "ord")
+            .collect::<Vec<(&String, &String)>>();
+        fields.sort();
+
+        // websites are written as [key]: url
+        if let [(k_type, kind), (k_url, url)] = fields[..] {
+            if k_type == "type" && kind == "website" && k_url == "url" {
+                return format!("[{}]: {}", self.key, url);
+            }
+        }
+        let fields = fields
+            .into_iter()
+            .map(|(k, v)| metadata_pair(k, &MetadataValue::String(v.clone())))
+            .collect::<Vec<String>>();
+
+        format!("[{}]: [{}]", self.key, fields.join(" "))
+    }
+}
+
+impl ToMarkdown for FootnoteDefinition {
+    fn to_markdown(&self) -> String {
+        format!("[^{}]: {}", self.label, self.text.to_markdown())
+    }
+}
+
+impl ToMarkdown for AbbreviationDefinition {
+    fn to_markdown(&self) -> String {
+        format!("*[{}]: {}", self.short, self.long)
+    }
+}
+
+impl ToMarkdown for HighlightText {
+    fn to_markdown(&self) -> String {
+        let metadata = self
+            .metadata
+            .as_ref()
+            .map(metadata_to_markdown)
+            .unwrap_or_default();
+
+        format!("=={}=={}", inlines_to_markdown(&self.value), metadata)
+    }
+}
+
+impl ToMarkdown for Url {
+    fn to_markdown(&self) -> String {
+        match &self.description {
+            Some(description) => format!("[{}]({})", inlines_to_markdown(description), self.url),
+            None if is_autolink(&self.url) => self.url.clone(),
+            None => format!("[]({})", self.url),
+        }
+    }
+}
+
+impl ToMarkdown for Image {
+    fn to_markdown(&self) -> String {
+        let description = self
+            .url
+            .description
+            .as_ref()
+            .map(|d| inlines_to_markdown(d))
+            .unwrap_or_default();
+        let metadata = self
+            .metadata
+            .as_ref()
+            .map(metadata_to_markdown)
+            .unwrap_or_default();
+
+        format!("![{}]({}){}", description, self.url.url, metadata)
+    }
+}
+
+impl ToMarkdown for Math {
+    fn to_markdown(&self) -> String {
+        match &self.expression {
+            MathExpression::AsciiMath { source, .. } => format!("$${}$$", source),
+            MathExpression::Tex(tex) => format!("${}$", tex),
+        }
+    }
+}
+
+impl ToMarkdown for BibReference {
+    fn to_markdown(&self) -> String {
+        if self.fallback.is_some() {
+            format!("[{}]", self.key)
+        } else {
+            format!("[^{}]", self.key)
+        }
+    }
+}
+
+impl ToMarkdown for GlossaryReference {
+    fn to_markdown(&self) -> String {
+        match self.display {
+            GlossaryDisplay::Short => format!("~{}", self.short),
+            GlossaryDisplay::Long => format!("~~{}", self.short),
+        }
+    }
+}
+
+impl ToMarkdown for FootnoteReference {
+    fn to_markdown(&self) -> String {
+        format!("[^{}]", self.label)
+    }
+}
+
+impl ToMarkdown for TemplateVariable {
+    fn to_markdown(&self) -> String {
+        format!("{{{}{{{}}}{}}}", self.prefix, self.name, self.suffix)
+    }
+}
+
+impl ToMarkdown for Template {
+    fn to_markdown(&self) -> String {
+        let elements = self
+            .text
+            .iter()
+            .map(|e| e.to_markdown())
+            .collect::<Vec<String>>()
+            .join("\n\n");
+
+        format!("%{}%", elements)
+    }
+}
+
+impl ToMarkdown for Arrow {
+    fn to_markdown(&self) -> String {
+        match self {
+            Arrow::RightArrow => "-->",
+            Arrow::LeftArrow => "<--",
+            Arrow::LeftRightArrow => "<-->",
+            Arrow::BigRightArrow => "==>",
+            Arrow::BigLeftArrow => "<==",
+            Arrow::BigLeftRightArrow => "<==>",
+        }
+        .to_string()
+    }
+}
+
+impl ToMarkdown for InlineMetadata {
+    fn to_markdown(&self) -> String {
+        metadata_to_markdown(self)
+    }
+}
+
+impl Table {
+    fn alignment(&self, column: usize) -> Alignment {
+        self.alignments
+            .get(column)
+            .copied()
+            .unwrap_or(Alignment::None)
+    }
+}
+
+impl Document {
+    /// Renders the document back into normalized snekdown source.
+    /// Imports are kept as import statements.
+    pub fn to_markdown(&self) -> String {
+        ToMarkdown::to_markdown(self)
+    }
+}
+
+/// Renders the blocks separated by empty lines.
+/// Blocks that are followed by text only get as many line breaks
+/// as they consume so that no breaks are added to the text.
+fn blocks_to_markdown(blocks: &[Block]) -> String {
+    let blocks = blocks
+        .iter()
+        .filter(|b| !matches!(b, Block::Null))
+        .collect::<Vec<&Block>>();
+    let mut markdown = String::new();
+
+    for (i, block) in blocks.iter().enumerate() {
+        markdown.push_str(&block.to_markdown());
+        if let Some(next) = blocks.get(i + 1) {
+            markdown.push_str(block_separator(block, next));
+        }
+    }
+
+    markdown
+}
+
+/// Returns the line breaks between the given block and the next one
+fn block_separator(block: &Block, next: &Block) -> &'static str {
+    let before_text = matches!(next, Block::Paragraph(_));
+
+    match block {
+        // sections and paragraphs end with the line breaks of their last line
+        Block::Section(section) => section
+            .elements
+            .iter()
+            .rev()
+            .find(|b| !matches!(b, Block::Null))
+            .map(|last| block_separator(last, next))
+            .unwrap_or(""),
+        Block::Paragraph(paragraph) => match paragraph.elements.last() {
+            Some(Line::Ruler(_)) => "\n",
+            _ => "",
+        },
+        Block::List(list) => {
+            let mut item = list.items.last();
+            while let Some(child) = item.and_then(|i| i.children.last()) {
+                item = Some(child);
+            }
+            if item.is_some_and(|i| !i.blocks.is_empty()) {
+                return "\n\n";
+            }
+            text_separator(item.is_some_and(|i| i.text.ends_with_break()))
+        }
+        Block::DefinitionList(list) => text_separator(
+            list.items
+                .last()
+                .and_then(|item| item.definitions.last())
+                .is_some_and(TextLine::ends_with_break),
+        ),
+        Block::Figure(figure) => text_separator(figure.caption.ends_with_break()),
+        Block::Table(_) | Block::CodeBlock(_) | Block::Diagram(_) if before_text => "\n",
+        Block::MathBlock(_) | Block::Placeholder(_) | Block::Import(_) if before_text => "",
+        _ => "\n\n",
+    }
+}
+
+/// Returns the line breaks after a block that ends with a line of text
+fn text_separator(ends_with_break: bool) -> &'static str {
+    if ends_with_break {
+        "\n\n"
+    } else {
+        "\n"
+    }
+}
+
+fn inlines_to_markdown(inlines: &[Inline]) -> String {
+    let mut markdown = String::new();
+    let mut previous: Option<&Inline> = None;
+
+    for inline in inlines {
+        match (previous, inline) {
+            // colored text only contains the next element, so the following text
+            // is only separated from it by the special character it starts with
+            (Some(Inline::Colored(_)), Inline::Plain(plain)) => {
+                let mut chars = plain.value.chars();
+                markdown.extend(chars.next());
+                markdown.push_str(&escape_text(chars.as_str()));
+            }
+            _ => markdown.push_str(&inline.to_markdown()),
+        }
+        previous = Some(inline);
+    }
+
+    markdown
+}
+
+/// Renders the list items with an indentation of two spaces per level.
+/// The blocks of an item are indented to the text of the item.
+fn list_items_to_markdown(items: &[ListItem], depth: usize, loose: bool, markdown: &mut String) {
+    for (i, item) in items.iter().enumerate() {
+        let marker = if item.ordered {
+            format!("{}. ", i + 1)
+        } else {
+            "- ".to_string()
+        };
+        markdown.push_str(&INDENT.repeat(depth));
+        markdown.push_str(&marker);
+        if let Some(checked) = item.checked {
+            markdown.push_str(&checkbox_to_markdown(checked));
+            markdown.push(' ');
+        }
+        if let Some(metadata) = &item.metadata {
+            markdown.push_str(&keyed_metadata_to_markdown(metadata));
+            markdown.push(' ');
+        }
+        markdown.push_str(&item.text.to_markdown());
+        markdown.push('\n');
+        if item.text.ends_with_break() || (loose && !item.blocks.is_empty()) {
+            markdown.push('\n');
+        }
+        if !item.blocks.is_empty() {
+            let indent = " ".repeat(INDENT.len() * depth + marker.len());
+
+            for line in blocks_to_markdown(&item.blocks)
+                .trim_end_matches('\n')
+                .lines()
+            {
+                if !line.is_empty() {
+                    markdown.push_str(&indent);
+                    markdown.push_str(line);
+                }
+                markdown.push('\n');
+            }
+            if loose {
+                markdown.push('\n');
+            }
+        }
+        list_items_to_markdown(&item.children, depth + 1, loose, markdown);
+    }
+}
+
+fn cell_to_markdown(cell: &Cell) -> String {
+    // only the pipes in plain text are escaped as they would end the cell
+    let text = match &cell.text {
+        Line::Text(text) => text
+            .subtext
+            .iter()
+            .map(|inline| match inline {
+                Inline::Plain(plain) => escape_pipes(&escape_text(&plain.value)),
+                _ => inline.to_markdown(),
+            })
+            .collect::<String>(),
+        line => line.to_markdown(),
+    };
+    let text = text.trim().to_string();
+
+    if cell.colspan > 1 || cell.rowspan > 1 {
+        format!(
+            "[colspan={} rowspan={}] {}",
+            cell.colspan, cell.rowspan, text
+        )
+    } else {
+        text
+    }
+}
+
+/// Pads the content of a cell to the width of its column
+fn pad_cell(cell: &str, width: usize, alignment: Alignment) -> String {
+    let padding = width.saturating_sub(cell.chars().count());
+
+    match alignment {
+        Alignment::Right => format!("{}{}", " ".repeat(padding), cell),
+        Alignment::Center => format!(
+            "{}{}{}",
+            " ".repeat(padding / 2),
+            cell,
+            " ".repeat(padding - padding / 2)
+        ),
+        _ => format!("{}{}", cell, " ".repeat(padding)),
+    }
+}
+
+fn code_fence(info: &str, code: &str) -> String {
+    let mut markdown = format!("```{}\n{}", info, code);
+    if !code.ends_with('\n') {
+        markdown.push('\n');
+    }
+    markdown.push_str("```");
+
+    markdown
+}
+
+fn checkbox_to_markdown(checked: bool) -> String {
+    if checked {
+        "[x]".to_string()
+    } else {
+        "[ ]".to_string()
+    }
+}
+
+/// Returns the metadata entries sorted by key so that the output is stable
+fn sorted_metadata(metadata: &InlineMetadata) -> Vec<(&String, &MetadataValue)> {
+    let mut entries = metadata
+        .data
+        .iter()
+        .collect::<Vec<(&String, &MetadataValue)>>();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    entries
+}
+
+fn metadata_to_markdown(metadata: &InlineMetadata) -> String {
+    format!("[{}]", metadata_pairs(metadata))
+}
+
+/// Renders the metadata of a list item or the attributes of a block. Metadata without
+/// a key=value pair would be parsed as text, so flags are written with their value in that case
+fn keyed_metadata_to_markdown(metadata: &InlineMetadata) -> String {
+    let markdown = metadata_to_markdown(metadata);
+
+    if markdown.contains('=') {
+        markdown
+    } else {
+        let pairs = sorted_metadata(metadata)
+            .into_iter()
+            .map(|(k, _)| format!("{}=true", k))
+            .collect::<Vec<String>>();
+        format!("[{}]", pairs.join(" "))
+    }
+}
+
+fn attributes_to_markdown(metadata: &InlineMetadata) -> String {
+    format!("{{{}}}", metadata_pairs(metadata))
+}
+
+fn metadata_pairs(metadata: &InlineMetadata) -> String {
+    sorted_metadata(metadata)
+        .into_iter()
+        .map(|(k, v)| metadata_pair(k, v))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn metadata_pair(key: &str, value: &MetadataValue) -> String {
+    match value {
+        MetadataValue::Bool(true) => key.to_string(),
+        MetadataValue::Bool(false) => format!("{}=false", key),
+        MetadataValue::Integer(i) => format!("{}={}", key, i),
+        MetadataValue::Float(f) => format!("{}={:?}", key, f),
+        MetadataValue::String(s) => format!("{}={}", key, quote_value(s)),
+        MetadataValue::Placeholder(p) => format!("{}={}", key, p.read().unwrap().to_markdown()),
+        MetadataValue::Template(t) => format!("{}={}", key, t.to_markdown()),
+    }
+}
+
+fn front_matter_value(value: &MetadataValue) -> String {
+    match value {
+        MetadataValue::String(s) if s.contains([':', '#']) || s.trim() != s => {
+            format!("{:?}", s)
+        }
+        MetadataValue::Float(f) => format!("{:?}", f),
+        _ => value.to_string(),
+    }
+}
+
+/// Quotes a string value of the metadata if it wouldn't be parsed as the same string otherwise
+fn quote_value(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.contains(|c: char| {
+            c.is_whitespace() || ['[', ']', '{', '}', ',', '=', '"', '\'', '%'].contains(&c)
+        })
+        || value.parse::<f64>().is_ok()
+        || ["true", "false"].contains(&value.to_lowercase().as_str());
+
+    if !needs_quotes {
+        value.to_string()
+    } else if value.contains('"') {
+        format!("'{}'", value)
+    } else {
+        format!("\"{}\"", value)
+    }
+}
+
+/// Escapes the characters of plain text that would otherwise be parsed as formatting
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|(_, c)| *c);
+        let rest = chars.clone().map(|(_, c)| c);
+        let is_sequence = match c {
+            '|' => next == Some('|'),
+            '=' => next == Some('='),
+            '-' | '<' => next == Some('-') || next == Some('='),
+            // emoji shortcodes and character codes
+            ':' => is_emoji_start(rest),
+            '&' => rest.take_while(|c| !c.is_whitespace()).any(|c| c == ';'),
+            // bare urls are parsed as links
+            // the parser splits plain text before urls, so the scheme may end the text
+            'h' => {
+                let rest = text[i..]
+                    .strip_prefix("https")
+                    .or_else(|| text[i..].strip_prefix("http"));
+                rest.is_some_and(|r| r.is_empty() || r.starts_with("://"))
+            }
+            _ => false,
+        };
+        if ESCAPED_CHARS.contains(&c) || is_sequence {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Returns if the text after a colon is the name of an emoji followed by a colon
+fn is_emoji_start<I: Iterator<Item = char>>(chars: I) -> bool {
+    let word = chars.take_while(|c| !c.is_whitespace()).collect::<String>();
+
+    word.split_once(':')
+        .is_some_and(|(name, _)| get_emoji(name).is_some())
+}
+
+/// Escapes the start of a text line that would otherwise be parsed as a block
+fn escape_block_start(line: String) -> String {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+    if digits > 0 && line[digits..].starts_with(". ") {
+        format!("{}\\{}", &line[..digits], &line[digits..])
+    } else if line.starts_with(['#', '|', '>'])
+        || line.starts_with("- ")
+        || line.starts_with("+ ")
+        || line.starts_with("o ")
+        || line.starts_with("<!--")
+    {
+        format!("\\{}", line)
+    } else {
+        line
+    }
+}
+
+/// Escapes the pipes in the text of a table cell that aren't escaped already
+fn escape_pipes(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut previous = None;
+
+    for c in text.chars() {
+        if c == '|' && previous != Some('\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        previous = Some(c);
+    }
+
+    escaped
+}
+
+fn is_autolink(url: &str) -> bool {
+    (url.starts_with("http://") || url.starts_with("https://"))
+        && !url.contains(char::is_whitespace)
+}
+
+/// Returns if the line only consists of a break
+fn is_empty_line(line: &Line) -> bool {
+    if let Line::Text(text) = line {
+        text.subtext.iter().all(|i| matches!(i, Inline::LineBreak))
+    } else {
+        false
+    }
+}
@@ -7,9 +7,19 @@
 use regex::Regex;
 use std::collections::HashMap;
 
+pub mod batch;
 #[cfg(feature = "pdf")]
 pub mod chromium_pdf;
+#[cfg(feature = "docx")]
+pub mod docx;
+#[cfg(feature = "epub")]
+pub mod epub;
 pub mod html;
+pub mod json;
+pub mod latex;
+pub mod man;
+pub mod markdown;
+pub mod renderer;
 pub mod style;
 
 pub struct PlaceholderTemplate {
@@ -43,17 +53,14 @@ impl PlaceholderTemplate {
 
     pub fn render(&self) -> String {
         lazy_static::lazy_static! { static ref RE_REP: Regex = Regex::new(r"\{\{([^}]*)}}").unwrap(); }
-        let mut ret_string = self.value.clone();
-        RE_REP.find_iter(&self.value).for_each(|m| {
-            let full_match = m.as_str();
-            let name = &full_match[2..full_match.len() - 2];
-            if let Some(val) = self.replacements.get(name) {
-                ret_string = ret_string.replace(full_match, val)
-            } else {
-                ret_string = ret_string.replace(full_match, "")
-            }
-        });
-
-        ret_string
+        // replacing all matches at once keeps placeholders inside of replaced values untouched
+        RE_REP
+            .replace_all(&self.value, |captures: &regex::Captures| {
+                self.replacements
+                    .get(&captures[1])
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .to_string()
     }
 }
@@ -0,0 +1,283 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::Document;
+use crate::format::html::html_writer::HTMLWriter;
+use crate::format::html::slides::render_slides;
+use crate::format::html::to_html::ToHtml;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+
+pub type RenderResult<T> = Result<T, RenderError>;
+
+#[derive(Debug)]
+pub enum RenderError {
+    IoError(io::Error),
+    JsonError(serde_json::Error),
+    UnknownFormat(String),
+    #[cfg(any(feature = "epub", feature = "docx"))]
+    ZipError(zip::result::ZipError),
+    #[cfg(feature = "pdf")]
+    PdfError(crate::format::chromium_pdf::result::PdfRenderingError),
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::IoError(e) => write!(f, "IO Error: {}", e),
+            RenderError::JsonError(e) => write!(f, "JSON Error: {}", e),
+            RenderError::UnknownFormat(name) => write!(f, "Unknown format {}", name),
+            #[cfg(any(feature = "epub", feature = "docx"))]
+            RenderError::ZipError(e) => write!(f, "Zip Error: {}", e),
+            #[cfg(feature = "pdf")]
+            RenderError::PdfError(e) => write!(f, "PDF Error: {}", e),
+        }
+    }
+}
+
+impl Error for RenderError {}
+
+impl From<io::Error> for RenderError {
+    fn from(other: io::Error) -> Self {
+        Self::IoError(other)
+    }
+}
+
+impl From<serde_json::Error> for RenderError {
+    fn from(other: serde_json::Error) -> Self {
+        Self::JsonError(other)
+    }
+}
+
+#[cfg(any(feature = "epub", feature = "docx"))]
+impl From<zip::result::ZipError> for RenderError {
+    fn from(other: zip::result::ZipError) -> Self {
+        Self::ZipError(other)
+    }
+}
+
+#[cfg(feature = "pdf")]
+impl From<crate::format::chromium_pdf::result::PdfRenderingError> for RenderError {
+    fn from(other: crate::format::chromium_pdf::result::PdfRenderingError) -> Self {
+        Self::PdfError(other)
+    }
+}
+
+/// An output format a document can be rendered to
+pub trait Renderer {
+    /// The name the format is selected with
+    fn name(&self) -> &str;
+
+    /// Renders the document into the given output
+    fn render(&mut self, document: &Document, out: &mut dyn Write) -> RenderResult<()>;
+}
+
+/// A collection of renderers that can be looked up by their name
+pub struct Renderers {
+    renderers: HashMap<String, Box<dyn Renderer>>,
+}
+
+impl Default for Renderers {
+    /// Creates the collection with all built-in renderers
+    fn default() -> Self {
+        let mut renderers = Self::empty();
+        renderers.register(Box::new(HtmlRenderer));
+        renderers.register(Box::new(SlidesRenderer));
+        renderers.register(Box::new(JsonRenderer));
+        renderers.register(Box::new(MarkdownRenderer));
+        renderers.register(Box::new(LatexRenderer));
+        renderers.register(Box::new(ManRenderer));
+        #[cfg(feature = "pdf")]
+        renderers.register(Box::new(PdfRenderer));
+        #[cfg(feature = "epub")]
+        renderers.register(Box::new(EpubRenderer));
+        #[cfg(feature = "docx")]
+        renderers.register(Box::new(DocxRenderer));
+
+        renderers
+    }
+}
+
+impl Renderers {
+    /// Creates a collection without any renderers
+    pub fn empty() -> Self {
+        Self {
+            renderers: HashMap::new(),
+        }
+    }
+
+    /// Adds a renderer replacing an existing one with the same name
+    pub fn register(&mut self, renderer: Box<dyn Renderer>) {
+        self.renderers.insert(renderer.name().to_string(), renderer);
+    }
+
+    /// Returns the renderer with the given name
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Box<dyn Renderer>> {
+        self.renderers.get_mut(name)
+    }
+
+    /// Returns the names of all registered renderers in alphabetical order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.renderers.keys().map(String::as_str).collect();
+        names.sort_unstable();
+
+        names
+    }
+
+    /// Renders the document with the renderer of the given name.
+    /// Returns None if there's no renderer with that name.
+    pub fn render(
+        &mut self,
+        name: &str,
+        document: &Document,
+        out: &mut dyn Write,
+    ) -> Option<RenderResult<()>> {
+        self.get_mut(name)
+            .map(|renderer| renderer.render(document, out))
+    }
+}
+
+impl Document {
+    /// Renders the document with the built-in renderer of the given format into any writer
+    pub fn render<W: Write>(&self, format: &str, mut out: W) -> RenderResult<()> {
+        Renderers::default()
+            .render(format, self, &mut out)
+            .unwrap_or_else(|| Err(RenderError::UnknownFormat(format.to_string())))
+    }
+}
+
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn render(&mut self, document: &Document, out: &mut dyn Write) -> RenderResult<()> {
+        let theme = document.config.lock().style.theme.clone();
+        let mut writer = HTMLWriter::new(Box::new(out), theme);
+        document.to_html(&mut writer)?;
+
+        Ok(writer.flush()?)
+    }
+}
+
+pub struct SlidesRenderer;
+
+impl Renderer for SlidesRenderer {
+    fn name(&self) -> &str {
+        "slides"
+    }
+
+    fn render(&mut self, document: &Document, out: &mut dyn Write) -> RenderResult<()> {
+        let theme = document.config.lock().style.theme.clone();
+        let mut writer = HTMLWriter::new(Box::new(out), theme);
+        render_slides(document, &mut writer)?;
+
+        Ok(writer.flush()?)
+    }
+}
+
+pub struct JsonRenderer;
+
+impl Renderer for JsonRenderer {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn render(&mut self, document: &Document, out: &mut dyn Write) -> RenderResult<()> {
+        write_output(out, document.to_json()?.as_bytes())
+    }
+}
+
+pub struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn render(&mut self, document: &Document, out: &mut dyn Write) -> RenderResult<()> {
+        write_output(out, document.to_markdown().as_bytes())
+    }
+}
+
+pub struct LatexRenderer;
+
+impl Renderer for LatexRenderer {
+    fn name(&self) -> &str {
+        "latex"
+    }
+
+    fn render(&mut self, document: &Document, out: &mut dyn Write) -> RenderResult<()> {
+        write_output(out, document.to_latex().as_bytes())
+    }
+}
+
+pub struct ManRenderer;
+
+impl Renderer for ManRenderer {
+    fn name(&self) -> &str {
+        "man"
+    }
+
+    fn render(&mut self, document: &Document, out: &mut dyn Write) -> RenderResult<()> {
+        write_output(out, document.to_man().as_bytes())
+    }
+}
+
+#[cfg(feature = "pdf")]
+pub struct PdfRenderer;
+
+#[cfg(feature = "pdf")]
+impl Renderer for PdfRenderer {
+    fn name(&self) -> &str {
+        "pdf"
+    }
+
+    fn render(&mut self, document: &Document, out: &mut dyn Write) -> RenderResult<()> {
+        let result = crate::format::chromium_pdf::render_to_pdf(document.clone())?;
+        write_output(out, &result)
+    }
+}
+
+#[cfg(feature = "epub")]
+pub struct EpubRenderer;
+
+#[cfg(feature = "epub")]
+impl Renderer for EpubRenderer {
+    fn name(&self) -> &str {
+        "epub"
+    }
+
+    fn render(&mut self, document: &Document, out: &mut dyn Write) -> RenderResult<()> {
+        let result = crate::format::epub::render_to_epub(document)?;
+        write_output(out, &result)
+    }
+}
+
+#[cfg(feature = "docx")]
+pub struct DocxRenderer;
+
+#[cfg(feature = "docx")]
+impl Renderer for DocxRenderer {
+    fn name(&self) -> &str {
+        "docx"
+    }
+
+    fn render(&mut self, document: &Document, out: &mut dyn Write) -> RenderResult<()> {
+        let result = crate::format::docx::render_to_docx(document)?;
+        write_output(out, &result)
+    }
+}
+
+fn write_output(out: &mut dyn Write, bytes: &[u8]) -> RenderResult<()> {
+    out.write_all(bytes)?;
+
+    Ok(out.flush()?)
+}
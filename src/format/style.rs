@@ -19,6 +19,9 @@ pub fn get_css_for_theme(theme: Theme) -> String {
         Theme::OceanDark => include_str!("assets/dark-ocean.scss"),
         Theme::OceanLight => include_str!("assets/light-ocean.scss"),
         Theme::MagicDark => include_str!("assets/dark-magic.scss"),
+        Theme::Light => include_str!("assets/light.scss"),
+        Theme::Dark => include_str!("assets/dark.scss"),
+        Theme::Print => include_str!("assets/print.scss"),
     };
     let style = format!("{}\n{}", vars, include_str!("assets/base.scss"));
 
@@ -41,6 +44,8 @@ pub fn get_code_theme_for_theme(theme: Theme) -> (syntect::highlighting::Theme,
         Theme::OceanDark => "base16-ocean.dark",
         Theme::OceanLight => "base16-ocean.light",
         Theme::MagicDark => "base16-ocean.dark",
+        Theme::Light | Theme::Print => "InspiredGitHub",
+        Theme::Dark => "base16-eighties.dark",
     };
 
     return (TS.themes[theme].clone(), PS.clone());
@@ -4,6 +4,7 @@
  * See LICENSE for more information.
  */
 
+pub mod config;
 pub mod elements;
 pub mod format;
 pub mod parser;
@@ -7,19 +7,24 @@
 use colored::Colorize;
 use env_logger::Env;
 use log::{Level, LevelFilter};
-use notify::{watcher, RecursiveMode, Watcher};
+use snekdown::config::Config;
+use snekdown::elements::stats::SectionStats;
 use snekdown::elements::Document;
-use snekdown::format::html::html_writer::HTMLWriter;
-use snekdown::format::html::to_html::ToHtml;
-use snekdown::parser::ParserOptions;
+use snekdown::format::batch;
+use snekdown::format::html::site::render_site;
+use snekdown::format::renderer::Renderers;
+use snekdown::parser::diagnostics::{DiagnosticCode, Diagnostics, Reporter, TerminalReporter};
+use snekdown::parser::import_cache::ImportCache;
+use snekdown::parser::{ImportErrorPolicy, InputNormalization, ParseError, ParserOptions};
 use snekdown::settings::Settings;
 use snekdown::utils::caching::CacheStorage;
+use snekdown::utils::watching::watch_document;
 use snekdown::Parser;
-use std::fs::{File, OpenOptions};
-use std::io::{stdout, BufWriter, Write};
-use std::path::PathBuf;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{stdin, stdout, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::sync::mpsc::channel;
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 
@@ -33,11 +38,30 @@ struct Opt {
 #[structopt()]
 enum SubCommand {
     /// Watch the document and its imports and render on change.
-    Watch(WatchOptions),
+    Watch(RenderOptions),
 
     /// Parse and render the document.
     Render(RenderOptions),
 
+    /// Parse the document and report the diagnostics without rendering.
+    Check(CheckOptions),
+
+    /// Write the document tree as json.
+    Ast(AstOptions),
+
+    /// Print statistics about the document.
+    Stats(StatsOptions),
+
+    /// Format the document as normalized snekdown source.
+    Fmt(FmtOptions),
+
+    /// Serve the rendered document and reload it in the browser on change.
+    #[cfg(feature = "serve")]
+    Serve(ServeOptions),
+
+    /// Parse and render multiple documents in parallel.
+    Batch(BatchOptions),
+
     /// Initializes the project with default settings
     Init,
 
@@ -48,11 +72,10 @@ enum SubCommand {
 #[derive(StructOpt, Debug, Clone)]
 #[structopt()]
 struct RenderOptions {
-    /// Path to the input file
-    #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    #[structopt(flatten)]
+    parse_options: ParseOptions,
 
-    /// Path for the output file
+    /// Path for the output file or - for stdout
     #[structopt(parse(from_os_str))]
     output: Option<PathBuf>,
 
@@ -63,17 +86,156 @@ struct RenderOptions {
     /// the output format
     #[structopt(short, long, default_value = "html")]
     format: String,
+
+    /// Renders the document again when it or one of its imports changes
+    #[structopt(long)]
+    watch: bool,
+
+    /// The amount of time in milliseconds to wait after changes before rendering
+    #[structopt(long, default_value = "500")]
+    debounce: u64,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt()]
+struct CheckOptions {
+    #[structopt(flatten)]
+    parse_options: ParseOptions,
+
+    /// Checks that linked files and anchors exist
+    #[structopt(long)]
+    links: bool,
+
+    /// Checks that linked http(s) urls respond
+    #[cfg(feature = "remote-links")]
+    #[structopt(long = "remote-links")]
+    remote_links: bool,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt()]
+struct AstOptions {
+    #[structopt(flatten)]
+    parse_options: ParseOptions,
+
+    /// Path for the json file. The json is written to stdout if none or - is given
+    #[structopt(parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt()]
+struct StatsOptions {
+    #[structopt(flatten)]
+    parse_options: ParseOptions,
+
+    /// Prints the statistics as json
+    #[structopt(long)]
+    json: bool,
 }
 
 #[derive(StructOpt, Debug, Clone)]
 #[structopt()]
-struct WatchOptions {
+struct FmtOptions {
+    /// Path to the input file or - to format stdin to stdout
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+
+    /// Writes the formatted document to stdout instead of replacing the file
+    #[structopt(long = "stdout")]
+    stdout: bool,
+
+    /// Fails if the document isn't formatted instead of replacing the file
+    #[structopt(long)]
+    check: bool,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt()]
+struct BatchOptions {
+    /// Paths or glob patterns of the input files like docs/**/*.md
+    #[structopt(required = true)]
+    inputs: Vec<String>,
+
+    #[structopt(flatten)]
+    parser_flags: ParserFlags,
+
+    /// Directory for the output files. Outputs are written next to their inputs if none is given
+    #[structopt(short, long = "output-dir", parse(from_os_str))]
+    output_dir: Option<PathBuf>,
+
+    /// the output format
+    #[structopt(short, long, default_value = "html")]
+    format: String,
+
+    /// The number of documents that are rendered at the same time. Defaults to the number of cpus
+    #[structopt(long, default_value = "0")]
+    threads: usize,
+}
+
+#[cfg(feature = "serve")]
+#[derive(StructOpt, Debug, Clone)]
+#[structopt()]
+struct ServeOptions {
+    #[structopt(flatten)]
+    parse_options: ParseOptions,
+
+    /// The address the preview server listens on
+    #[structopt(long, default_value = "127.0.0.1:8080")]
+    address: String,
+
     /// The amount of time in milliseconds to wait after changes before rendering
     #[structopt(long, default_value = "500")]
     debounce: u64,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt()]
+struct ParseOptions {
+    /// Path to the input file or - for stdin
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
 
     #[structopt(flatten)]
-    render_options: RenderOptions,
+    parser_flags: ParserFlags,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt()]
+struct ParserFlags {
+    /// A value for [[value:key]] placeholders in the form key=value
+    #[structopt(long = "value", parse(try_from_str = parse_key_value))]
+    values: Vec<(String, String)>,
+
+    /// Fails if a [[value:key]] or [[env:NAME]] placeholder has no value
+    #[structopt(long)]
+    strict: bool,
+
+    /// Passes raw html blocks and tags through to the output
+    #[structopt(long = "raw-html")]
+    raw_html: bool,
+
+    /// Treats lines starting with // as comments
+    #[structopt(long = "line-comments")]
+    line_comments: bool,
+
+    /// How failed imports are handled: error aborts rendering, warning leaves them out
+    /// and notice replaces them with a visible note
+    #[structopt(long = "import-errors")]
+    import_errors: Option<ImportErrorPolicy>,
+
+    /// Reads unchanged imported documents from the cache instead of parsing them again
+    #[structopt(long = "cache-imports")]
+    cache_imports: bool,
+
+    /// Replaces tabs in the indentation of lines with this number of spaces
+    #[structopt(long = "tab-width")]
+    tab_width: Option<usize>,
+
+    /// Path to the configuration file. Defaults to the snekdown.toml in the directory
+    /// of the input or one of its parents
+    #[structopt(long, parse(from_os_str))]
+    config: Option<PathBuf>,
 }
 
 fn main() {
@@ -100,10 +262,16 @@ fn main() {
         .init();
 
     match &opt.sub_command {
-        SubCommand::Render(opt) => {
-            let _ = render(&opt);
-        }
+        SubCommand::Render(opt) if opt.watch => watch(&opt),
+        SubCommand::Render(opt) => render(&opt),
+        SubCommand::Check(opt) => check(&opt),
+        SubCommand::Ast(opt) => ast(&opt),
+        SubCommand::Stats(opt) => stats(&opt),
+        SubCommand::Fmt(opt) => fmt(&opt),
+        #[cfg(feature = "serve")]
+        SubCommand::Serve(opt) => serve(&opt),
         SubCommand::Watch(opt) => watch(&opt),
+        SubCommand::Batch(opt) => batch(&opt),
         SubCommand::ClearCache => {
             let cache = CacheStorage::new();
             cache.clear().expect("Failed to clear cache");
@@ -152,26 +320,213 @@ fn init() {
 }
 
 /// Watches a file with all of its imports and renders on change
-fn watch(opt: &WatchOptions) {
-    let parser = render(&opt.render_options);
-    let (tx, rx) = channel();
-    let mut watcher = watcher(tx, Duration::from_millis(opt.debounce)).unwrap();
-
-    for path in parser.get_paths() {
-        watcher.watch(path, RecursiveMode::NonRecursive).unwrap();
-    }
-    while let Ok(_) = rx.recv() {
-        println!("---");
-        let parser = render(&opt.render_options);
-        for path in parser.get_paths() {
-            watcher.watch(path, RecursiveMode::NonRecursive).unwrap();
+fn watch(opt: &RenderOptions) {
+    require_input_file(&opt.parse_options);
+    let mut first_run = true;
+    let result = watch_document(
+        || create_parser(&opt.parse_options),
+        Duration::from_millis(opt.debounce),
+        |document, parser| {
+            if !first_run {
+                println!("---");
+            }
+            first_run = false;
+            if has_missing_values(&parser.get_diagnostics()) {
+                log::error!("Rendering aborted because of missing placeholder values");
+            } else {
+                render_document(opt, document);
+            }
+            true
+        },
+    );
+
+    if let Err(e) = result {
+        log::error!("Failed to watch the document: {}", e);
+        exit(1)
+    }
+}
+
+/// Serves the rendered document and renders it again when it or one of its imports changes
+#[cfg(feature = "serve")]
+fn serve(opt: &ServeOptions) {
+    require_input_file(&opt.parse_options);
+    use snekdown::format::renderer::{HtmlRenderer, Renderer};
+    use snekdown::utils::serving::PreviewServer;
+
+    let root = opt
+        .parse_options
+        .input
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    let server = PreviewServer::start(&opt.address, root).unwrap_or_else(|e| {
+        log::error!("Failed to start the preview server: {}", e);
+        exit(1)
+    });
+    log::info!("Serving the preview on http://{}", server.address());
+
+    let result = watch_document(
+        || create_parser(&opt.parse_options),
+        Duration::from_millis(opt.debounce),
+        |document, _| {
+            let mut html = Vec::new();
+            match HtmlRenderer.render(&document, &mut html) {
+                Ok(_) => server.set_html(String::from_utf8_lossy(&html).to_string()),
+                Err(e) => log::error!("Failed to render the document: {}", e),
+            }
+            true
+        },
+    );
+
+    if let Err(e) = result {
+        log::error!("Failed to watch the document: {}", e);
+        exit(1)
+    }
+}
+
+/// Exits if the input is read from stdin as it can't be watched
+fn require_input_file(opt: &ParseOptions) {
+    if is_stdio(&opt.input) {
+        log::error!("Watching requires an input file instead of stdin");
+        exit(1)
+    }
+}
+
+/// Parses the document and reports its diagnostics
+fn check(opt: &CheckOptions) {
+    let (parser, document) = parse(&opt.parse_options);
+    let mut diagnostics = parser.get_diagnostics();
+    let mut link_diagnostics = Vec::new();
+
+    if opt.links {
+        link_diagnostics.extend(document.check_links());
+    }
+    #[cfg(feature = "remote-links")]
+    if opt.remote_links {
+        link_diagnostics.extend(document.check_remote_links());
+    }
+    for diagnostic in link_diagnostics {
+        TerminalReporter.report(&diagnostic);
+        diagnostics.add(diagnostic);
+    }
+    let errors = diagnostics.errors().count();
+    log::info!(
+        "Found {} errors and {} warnings",
+        errors,
+        diagnostics.warnings().count()
+    );
+
+    if errors > 0 {
+        exit(1)
+    }
+}
+
+/// Writes the document tree as json to the output file or stdout
+fn ast(opt: &AstOptions) {
+    let (_, document) = parse(&opt.parse_options);
+    let json = document
+        .to_json()
+        .expect("Failed to serialize the document");
+
+    if let Some(output) = opt.output.as_deref().filter(|o| !is_stdio(o)) {
+        fs::write(output, json).expect("Failed to write the json file");
+    } else {
+        println!("{}", json);
+    }
+}
+
+/// Prints the statistics of the document
+fn stats(opt: &StatsOptions) {
+    let (_, document) = parse(&opt.parse_options);
+    let stats = document.stats();
+
+    if opt.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&stats).expect("Failed to serialize the statistics")
+        );
+        return;
+    }
+    let headings = stats
+        .headings
+        .iter()
+        .map(|(size, count)| format!("{} h{}", count, size))
+        .collect::<Vec<String>>()
+        .join(", ");
+    println!("Words: {}", stats.counts.words);
+    println!("Characters: {}", stats.counts.characters);
+    println!(
+        "Reading time: {} min",
+        (stats.reading_time().as_secs() + 59) / 60
+    );
+    println!("Headings: {}", headings);
+    println!(
+        "Code: {} blocks, {} lines",
+        stats.counts.code_blocks, stats.counts.code_lines
+    );
+
+    if !stats.sections.is_empty() {
+        println!("\nSections:");
+        print_section_stats(&stats.sections, 0);
+    }
+}
+
+/// Prints the word and code line counts of the sections indented by their depth
+fn print_section_stats(sections: &[SectionStats], depth: usize) {
+    for section in sections {
+        println!(
+            "{}{}: {} words, {} code lines",
+            "  ".repeat(depth),
+            section.title,
+            section.counts.words,
+            section.counts.code_lines
+        );
+        print_section_stats(&section.children, depth + 1);
+    }
+}
+
+/// Formats the document and replaces the file with the formatted source.
+/// Imports are written back as they are instead of including the imported documents.
+fn fmt(opt: &FmtOptions) {
+    let use_stdio = is_stdio(&opt.input);
+    let source = if use_stdio {
+        let mut source = String::new();
+        stdin().read_to_string(&mut source).map(|_| source)
+    } else {
+        fs::read_to_string(&opt.input)
+    };
+    let source = source.unwrap_or_else(|e| {
+        log::error!("Failed to read {}: {}", opt.input.to_string_lossy(), e);
+        exit(1)
+    });
+    let mut options = ParserOptions::default()
+        .with_reporter(TerminalReporter)
+        .with_imports(false)
+        .with_smart_punctuation(false);
+    if !use_stdio {
+        options = options.add_path(opt.input.clone());
+    }
+    let formatted = Parser::new(source.clone(), Some(options))
+        .parse()
+        .to_markdown();
+
+    if opt.stdout || (use_stdio && !opt.check) {
+        print!("{}", formatted);
+    } else if opt.check {
+        if formatted != source {
+            log::error!("{} isn't formatted", opt.input.to_string_lossy());
+            exit(1)
         }
+    } else if formatted != source {
+        fs::write(&opt.input, formatted).expect("Failed to write the formatted document");
+        log::info!("Formatted {}", opt.input.to_string_lossy());
     }
 }
 
-/// Renders the document to the output path
-fn render(opt: &RenderOptions) -> Parser {
-    if !opt.input.exists() {
+/// Creates the parser for the document with all of its imports
+fn create_parser(opt: &ParseOptions) -> Parser {
+    if !is_stdio(&opt.input) && !opt.input.exists() {
         log::error!(
             "The input file {} could not be found",
             opt.input.to_str().unwrap()
@@ -180,15 +535,143 @@ fn render(opt: &RenderOptions) -> Parser {
         exit(1)
     }
 
-    let start = Instant::now();
+    let config = load_config(&opt.parser_flags, get_input_dir(&opt.input));
+    let options = create_parser_options(&opt.parser_flags, &config);
+
+    if is_stdio(&opt.input) {
+        Parser::new_from_reader(stdin(), Some(options)).unwrap_or_else(|e| {
+            log::error!("Failed to read the document from stdin: {}", e);
+            exit(1)
+        })
+    } else {
+        Parser::with_defaults(options.add_path(opt.input.clone()))
+    }
+}
+
+/// Creates the parser options from the configuration with the flags of the command line applied
+fn create_parser_options(flags: &ParserFlags, config: &Config) -> ParserOptions {
+    let mut values = config.values.clone();
+    values.extend(flags.values.iter().cloned());
+
+    let options = config
+        .parser_options()
+        .with_reporter(TerminalReporter)
+        .with_values(values)
+        .with_strict_values(flags.strict || config.parser.strict_values)
+        .with_raw_html(flags.raw_html || config.parser.raw_html)
+        .with_line_comments(flags.line_comments || config.parser.line_comments)
+        .with_import_error_policy(flags.import_errors.unwrap_or(config.parser.import_errors));
+
+    let options = match flags.tab_width {
+        Some(tab_width) => options.with_normalization(InputNormalization {
+            tab_width: Some(tab_width),
+            ..InputNormalization::default()
+        }),
+        None => options,
+    };
+    if flags.cache_imports {
+        options.with_import_cache(ImportCache::new())
+    } else {
+        options
+    }
+}
+
+/// Returns the directory the configuration file is searched in for the input
+fn get_input_dir(input: &Path) -> Option<PathBuf> {
+    if is_stdio(input) {
+        env::current_dir().ok()
+    } else {
+        input.canonicalize().ok()?.parent().map(Path::to_path_buf)
+    }
+}
+
+/// Loads the configuration file given on the command line
+/// or the one found in the directory or one of its parents
+fn load_config(flags: &ParserFlags, dir: Option<PathBuf>) -> Config {
+    let path = flags.config.clone().or_else(|| Config::find(&dir?));
+
+    if let Some(path) = path {
+        log::debug!("Using the configuration {}", path.to_string_lossy());
+        Config::load(&path).unwrap_or_else(|e| {
+            log::error!(
+                "Failed to load the configuration {}: {}",
+                path.to_string_lossy(),
+                e
+            );
+            exit(1)
+        })
+    } else {
+        Config::default()
+    }
+}
+
+/// Parses the document with all of its imports
+fn parse(opt: &ParseOptions) -> (Parser, Document) {
+    let mut parser = create_parser(opt);
+    let (document, errors) = parser.parse_with_errors();
+
+    if has_missing_values(&parser.get_diagnostics()) {
+        log::error!("Rendering aborted because of missing placeholder values");
+        exit(1)
+    }
+    if errors
+        .iter()
+        .any(|e| matches!(e, ParseError::ImportError(_)))
+    {
+        log::error!("Rendering aborted because of failed imports");
+        exit(1)
+    }
+
+    (parser, document)
+}
 
-    let mut parser = Parser::with_defaults(ParserOptions::default().add_path(opt.input.clone()));
-    let document = parser.parse();
+/// Returns if a [[value:key]] or [[env:NAME]] placeholder had no value
+fn has_missing_values(diagnostics: &Diagnostics) -> bool {
+    diagnostics
+        .errors()
+        .any(|d| d.code == DiagnosticCode::MissingValue)
+}
 
+/// Parses the document and renders it to the output path
+fn render(opt: &RenderOptions) {
+    let start = Instant::now();
+    let (_, document) = parse(&opt.parse_options);
     log::info!("Parsing + Processing took: {:?}", start.elapsed());
+    render_document(opt, document);
+
+    log::info!("Total: {:?}", start.elapsed());
+}
+
+/// Renders the parsed document to the output path
+fn render_document(opt: &RenderOptions, document: Document) {
     let start_render = Instant::now();
 
-    if let Some(output) = &opt.output {
+    if opt.output.is_none() && !opt.stdout {
+        let parse_options = &opt.parse_options;
+        let outputs = load_config(
+            &parse_options.parser_flags,
+            get_input_dir(&parse_options.input),
+        )
+        .outputs;
+        if !outputs.is_empty() {
+            for output in outputs {
+                let opt = RenderOptions {
+                    output: Some(output.path),
+                    format: output.format,
+                    ..opt.clone()
+                };
+                render_document(&opt, document.clone());
+            }
+            return;
+        }
+    }
+
+    if opt.format == "site" {
+        render_site_format(opt, &document);
+    } else if let Some(output) = opt.output.as_deref().filter(|o| !is_stdio(o)) {
+        if let Err(e) = document.collect_assets(output.parent().unwrap_or(Path::new(""))) {
+            log::error!("Failed to copy the assets: {}", e);
+        }
         let file = OpenOptions::new()
             .read(true)
             .write(true)
@@ -199,7 +682,7 @@ fn render(opt: &RenderOptions) -> Parser {
 
         render_format(opt, document, BufWriter::new(file));
     } else {
-        if !opt.stdout {
+        if !opt.stdout && opt.output.is_none() {
             log::error!("No output file specified");
             exit(1)
         }
@@ -207,39 +690,127 @@ fn render(opt: &RenderOptions) -> Parser {
     }
 
     log::info!("Rendering took: {:?}", start_render.elapsed());
-    log::info!("Total: {:?}", start.elapsed());
-
-    parser
 }
 
-#[cfg(not(feature = "pdf"))]
-fn render_format<W: Write + 'static>(opt: &RenderOptions, document: Document, writer: W) {
-    match opt.format.as_str() {
-        "html" => render_html(document, writer),
-        _ => log::error!("Unknown format {}", opt.format),
+/// Renders all documents matching the input patterns in parallel
+fn batch(opt: &BatchOptions) {
+    let paths = expand_patterns(&opt.inputs);
+    if paths.is_empty() {
+        log::error!("No input files found");
+        exit(1)
+    }
+    let renderers = Renderers::default();
+    if opt.format != "site" && !renderers.names().contains(&opt.format.as_str()) {
+        log::error!(
+            "Unknown format {}. Available formats are: {}",
+            opt.format,
+            renderers.names().join(", ")
+        );
+        exit(1)
+    }
+    let config = load_config(&opt.parser_flags, env::current_dir().ok());
+    let mut options = batch::BatchOptions::default()
+        .with_parser_options(create_parser_options(&opt.parser_flags, &config))
+        .with_format(&opt.format)
+        .with_threads(opt.threads);
+    if let Some(output_dir) = &opt.output_dir {
+        options = options.with_output_dir(output_dir.clone());
+    }
+
+    let start = Instant::now();
+    let outputs = batch::render_all(&paths, &options).unwrap_or_else(|e| {
+        log::error!("Failed to start rendering: {}", e);
+        exit(1)
+    });
+    let mut failed = 0;
+
+    for output in &outputs {
+        let input = output.input.to_string_lossy();
+        match &output.result {
+            Err(e) => log::error!("Failed to render {}: {}", input, e),
+            Ok(_) if has_missing_values(&output.diagnostics) => {
+                log::error!("Rendered {} with missing placeholder values", input)
+            }
+            Ok(_) => {
+                log::debug!("Rendered {} to {}", input, output.output.to_string_lossy());
+                continue;
+            }
+        }
+        failed += 1;
+    }
+    log::info!(
+        "Rendered {} documents in {:?}",
+        outputs.len() - failed,
+        start.elapsed()
+    );
+
+    if failed > 0 {
+        log::error!("{} documents failed", failed);
+        exit(1)
     }
 }
 
-#[cfg(feature = "pdf")]
-fn render_format<W: Write + 'static>(opt: &RenderOptions, document: Document, writer: W) {
-    match opt.format.as_str() {
-        "html" => render_html(document, writer),
-        "pdf" => render_pdf(document, writer),
-        _ => log::error!("Unknown format {}", opt.format),
+/// Returns the files matching the glob patterns without duplicates
+fn expand_patterns(patterns: &[String]) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    for pattern in patterns {
+        let entries = glob::glob(pattern).unwrap_or_else(|e| {
+            log::error!("Invalid pattern {}: {}", pattern, e);
+            exit(1)
+        });
+        let count = paths.len();
+        for path in entries.filter_map(Result::ok) {
+            if path.is_file() && !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        if paths.len() == count {
+            log::warn!("No files match {}", pattern);
+        }
     }
+
+    paths
 }
 
-fn render_html<W: Write + 'static>(document: Document, writer: W) {
-    let mut writer = HTMLWriter::new(Box::new(writer), document.config.lock().style.theme.clone());
-    document.to_html(&mut writer).unwrap();
-    writer.flush().unwrap();
+/// Returns if the path is - which stands for stdin or stdout
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
 }
 
-#[cfg(feature = "pdf")]
-fn render_pdf<W: Write + 'static>(document: Document, mut writer: W) {
-    use snekdown::format::chromium_pdf::render_to_pdf;
+/// Parses a key-value pair in the form key=value
+fn parse_key_value(pair: &str) -> Result<(String, String), String> {
+    pair.split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("Expected key=value but got '{}'", pair))
+}
 
-    let result = render_to_pdf(document).expect("Failed to render pdf!");
-    writer.write_all(&result).unwrap();
-    writer.flush().unwrap();
+/// Renders the document as a multi page site into the output directory
+fn render_site_format(opt: &RenderOptions, document: &Document) {
+    let output = if let Some(output) = opt.output.as_deref().filter(|o| !is_stdio(o)) {
+        output
+    } else {
+        log::error!("The site format requires an output directory");
+        exit(1)
+    };
+    if let Err(e) = document.collect_assets(output) {
+        log::error!("Failed to copy the assets: {}", e);
+    }
+    if let Err(e) = render_site(document, output) {
+        log::error!("Failed to render the site: {}", e);
+    }
+}
+
+fn render_format<W: Write>(opt: &RenderOptions, document: Document, mut writer: W) {
+    let mut renderers = Renderers::default();
+
+    match renderers.render(&opt.format, &document, &mut writer) {
+        Some(Ok(_)) => {}
+        Some(Err(e)) => log::error!("Failed to render {}: {}", opt.format, e),
+        None => log::error!(
+            "Unknown format {}. Available formats are: {}",
+            opt.format,
+            renderers.names().join(", ")
+        ),
+    }
 }
@@ -7,28 +7,51 @@
 use super::ParseResult;
 use crate::elements::tokens::*;
 use crate::elements::{
-    Block, CodeBlock, Import, List, ListItem, MathBlock, Metadata, Paragraph, Quote, Section, Table,
+    Aligned, Alignment, Attribution, Block, CodeBlock, Columns, Comment, CustomContainer,
+    Definition, DefinitionList, Diagram, Figure, HeaderShift, Html, Import, ImportAnchor,
+    InlineMetadata, List, ListItem, MathBlock, MathExpression, Metadata, PageBreak, Paragraph,
+    Quote, Section, Span, Table,
 };
+use crate::parser::diagnostics::{DiagnosticCode, Severity};
 use crate::parser::inline::ParseInline;
 use crate::parser::line::ParseLine;
 use crate::parser::ImportType;
+use crate::utils::parsing::metadata_from_yaml;
 use crate::Parser;
+use regex::Regex;
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
 pub(crate) trait ParseBlock {
     fn parse_block(&mut self) -> ParseResult<Block>;
+    fn parse_block_content(&mut self) -> ParseResult<Block>;
+    fn parse_block_attributes(&mut self) -> ParseResult<(InlineMetadata, Span)>;
     fn parse_section(&mut self) -> ParseResult<Section>;
     fn parse_code_block(&mut self) -> ParseResult<CodeBlock>;
     fn parse_math_block(&mut self) -> ParseResult<MathBlock>;
+    fn parse_tex_block(&mut self) -> ParseResult<MathBlock>;
     fn parse_quote(&mut self) -> ParseResult<Quote>;
+    fn parse_aligned(&mut self) -> ParseResult<Aligned>;
+    fn parse_columns(&mut self) -> ParseResult<Columns>;
+    fn parse_custom_container(&mut self) -> ParseResult<CustomContainer>;
+    fn parse_page_break(&mut self) -> ParseResult<PageBreak>;
     fn parse_paragraph(&mut self) -> ParseResult<Paragraph>;
     fn parse_list(&mut self) -> ParseResult<List>;
+    fn parse_definition_list(&mut self) -> ParseResult<DefinitionList>;
+    fn parse_figure(&mut self) -> ParseResult<Figure>;
+    fn parse_definition(&mut self) -> ParseResult<Definition>;
+    fn check_definition_term(&mut self) -> bool;
+    fn check_setext_header(&mut self) -> Option<u8>;
     fn parse_table(&mut self) -> ParseResult<Table>;
-    fn parse_import(&mut self) -> ParseResult<Option<Import>>;
+    fn parse_import(&mut self) -> ParseResult<Option<Block>>;
+    fn parse_html_block(&mut self) -> ParseResult<Html>;
+    fn parse_comment_block(&mut self) -> ParseResult<Comment>;
+    fn check_line_comment(&mut self) -> bool;
+    fn parse_front_matter(&mut self) -> ParseResult<InlineMetadata>;
 }
 
 impl ParseBlock for Parser {
-    /// Parses a block Token
+    /// Parses a block Token with the attributes given on the line before it
     fn parse_block(&mut self) -> ParseResult<Block> {
         if let Some(section) = self.section_return {
             if section <= self.section_nesting && (self.section_nesting > 0) {
@@ -37,11 +60,49 @@ impl ParseBlock for Parser {
                 self.section_return = None;
             }
         }
+        let start_index = self.ctm.get_index();
+        let attributes = self.parse_block_attributes().ok();
+        let mut block = match self.parse_block_content() {
+            Ok(block) => block,
+            // the attributes belong to a block of an enclosing section
+            Err(e) if attributes.is_some() => {
+                self.ctm.rewind(start_index);
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        };
+
+        if let Some((attributes, attributes_span)) = attributes {
+            match block.get_attribute_target() {
+                Some((metadata, span)) => {
+                    match metadata {
+                        Some(metadata) => metadata.merge(attributes),
+                        None => *metadata = Some(attributes),
+                    }
+                    span.start = attributes_span.start;
+                    span.line = attributes_span.line;
+                    span.column = attributes_span.column;
+                }
+                None => log::warn!("Attributes can't be attached to placeholders and imports"),
+            }
+        }
+
+        Ok(block)
+    }
+
+    /// Parses the block without attributes
+    fn parse_block_content(&mut self) -> ParseResult<Block> {
         let token = if let Ok(section) = self.parse_section() {
             log::trace!("Block::Section");
             Block::Section(section)
         } else if let Some(_) = self.section_return {
             return Err(self.ctm.err().into());
+        } else if let Some(block) = self.parse_extension_block() {
+            log::trace!("Block from extension");
+            block
+        } else if let Ok(comment) = self.parse_comment_block() {
+            log::trace!("Block::Comment");
+            Block::Comment(comment)
         } else if let Ok(list) = self.parse_list() {
             log::trace!("Block::List");
             Block::List(list)
@@ -49,27 +110,56 @@ impl ParseBlock for Parser {
             log::trace!("Block::Table");
             Block::Table(table)
         } else if let Ok(code_block) = self.parse_code_block() {
-            log::trace!("Block::CodeBlock");
-            Block::CodeBlock(code_block)
+            if let Some(diagram) = Diagram::from_code_block(&code_block) {
+                log::trace!("Block::Diagram");
+                Block::Diagram(diagram)
+            } else {
+                log::trace!("Block::CodeBlock");
+                Block::CodeBlock(code_block)
+            }
         } else if let Ok(math_block) = self.parse_math_block() {
             log::trace!("Block::MathBlock");
             Block::MathBlock(math_block)
+        } else if let Ok(tex_block) = self.parse_tex_block() {
+            log::trace!("Block::MathBlock");
+            Block::MathBlock(tex_block)
         } else if let Ok(quote) = self.parse_quote() {
             log::trace!("Block::Quote");
             Block::Quote(quote)
+        } else if let Ok(aligned) = self.parse_aligned() {
+            log::trace!("Block::Aligned");
+            Block::Aligned(aligned)
+        } else if let Ok(columns) = self.parse_columns() {
+            log::trace!("Block::Columns");
+            Block::Columns(columns)
+        } else if let Ok(container) = self.parse_custom_container() {
+            log::trace!("Block::CustomContainer");
+            Block::CustomContainer(container)
+        } else if let Ok(page_break) = self.parse_page_break() {
+            log::trace!("Block::PageBreak");
+            Block::PageBreak(page_break)
         } else if let Ok(import) = self.parse_import() {
             if let Some(import) = import {
                 log::trace!("Block::Import");
-                Block::Import(import)
+                import
             } else {
                 log::trace!("Block::Null");
                 Block::Null
             }
         } else if let Some(_) = self.section_return {
             return Err(self.ctm.err().into());
+        } else if let Ok(html) = self.parse_html_block() {
+            log::trace!("Block::Html");
+            Block::Html(html)
         } else if let Ok(pholder) = self.parse_placeholder() {
             log::trace!("Block::Placeholder");
             Block::Placeholder(pholder)
+        } else if let Ok(figure) = self.parse_figure() {
+            log::trace!("Block::Figure");
+            Block::Figure(figure)
+        } else if let Ok(definition_list) = self.parse_definition_list() {
+            log::trace!("Block::DefinitionList");
+            Block::DefinitionList(definition_list)
         } else if let Ok(paragraph) = self.parse_paragraph() {
             log::trace!("Block::Paragraph");
             Block::Paragraph(paragraph)
@@ -80,32 +170,80 @@ impl ParseBlock for Parser {
         Ok(token)
     }
 
+    /// Parses the `[key=value]` attributes on their own line before a block.
+    /// Brackets without any key=value pair are left to the block
+    fn parse_block_attributes(&mut self) -> ParseResult<(InlineMetadata, Span)> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+        let metadata = self
+            .parse_inline_metadata()
+            .map_err(|_| self.ctm.rewind_with_error(start_index))?;
+        let slice = self.ctm.get_slice(span_start, self.ctm.get_index());
+
+        if !slice.contains(EQ) || !slice.ends_with(META_CLOSE) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let span = self.get_span(span_start);
+
+        if self.ctm.seek_any(&INLINE_WHITESPACE).is_err()
+            || !self.ctm.check_char(&LB)
+            || self.ctm.next_char().is_none()
+            || self.ctm.check_char(&LB)
+        {
+            // the attributes need to be followed by a block
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+
+        Ok((metadata, span))
+    }
+
     /// Parses a section that consists of a header and one or more blocks
     fn parse_section(&mut self) -> ParseResult<Section> {
         let start_index = self.ctm.get_index();
         self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+        let setext_size = self.check_setext_header();
 
-        if self.ctm.check_char(&HASH) {
+        if self.ctm.check_char(&HASH) || setext_size.is_some() {
             let mut size = 1;
-            while let Some(_) = self.ctm.next_char() {
-                if !self.ctm.check_char(&HASH) {
-                    break;
-                }
-                size += 1;
-            }
             let mut metadata = None;
-            if let Ok(meta) = self.parse_inline_metadata() {
-                metadata = Some(meta);
+
+            if let Some(setext_size) = setext_size {
+                size = setext_size;
+            } else {
+                while self.ctm.next_char().is_some() {
+                    if !self.ctm.check_char(&HASH) {
+                        break;
+                    }
+                    size = size.saturating_add(1);
+                }
+                if let Ok(meta) = self.parse_inline_metadata() {
+                    metadata = Some(meta);
+                }
             }
-            if size <= self.section_nesting || !self.ctm.get_current().is_whitespace() {
+            let is_header = setext_size.is_some() || self.ctm.get_current().is_whitespace();
+            if size <= self.section_nesting || !is_header {
                 if size <= self.section_nesting {
                     self.section_return = Some(size);
                 }
                 return Err(self.ctm.rewind_with_error(start_index).into());
             }
+            let max_depth = self.options.nesting_limits.max_section_depth;
+            if self.sections.len() >= max_depth {
+                let error = self.nesting_limit_exceeded("Sections", max_depth, span_start);
+                self.ctm.rewind(start_index);
+                return Err(error);
+            }
             self.ctm.seek_any(&INLINE_WHITESPACE)?;
             let mut header = self.parse_header()?;
+            if setext_size.is_some() {
+                // skip the line of = or - below the title
+                let _ = self.ctm.get_string_until_any(&[LB], &[]);
+                self.ctm.try_seek();
+            }
             header.size = size;
+            header.span = self.get_span(span_start);
             self.section_nesting = size;
             self.sections.push(size);
             self.section_anchors.push(header.anchor.clone());
@@ -118,6 +256,7 @@ impl ParseBlock for Parser {
                 section.add_element(block);
             }
 
+            section.span = self.get_span(span_start);
             self.sections.pop();
             self.section_anchors.pop();
             if let Some(sec) = self.sections.last() {
@@ -135,10 +274,21 @@ impl ParseBlock for Parser {
     fn parse_code_block(&mut self) -> ParseResult<CodeBlock> {
         let start_index = self.ctm.get_index();
         self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
         self.ctm
             .assert_sequence(&SQ_CODE_BLOCK, Some(start_index))?;
         self.ctm.seek_one()?;
-        let language = self.ctm.get_string_until_any(&[LB], &[])?;
+        let mut language = self.ctm.get_string_until_any(&[LB, ATTR_OPEN], &[])?;
+        let mut metadata = None;
+
+        if self.ctm.check_char(&ATTR_OPEN) {
+            if let Ok(meta) = self.parse_attributes() {
+                metadata = Some(meta);
+                self.ctm.get_string_until_any(&[LB], &[])?;
+            } else {
+                language.push_str(&self.ctm.get_string_until_any(&[LB], &[])?);
+            }
+        }
         self.ctm.seek_one()?;
         let text = self.ctm.get_string_until_sequence(&[&SQ_CODE_BLOCK], &[])?;
 
@@ -147,8 +297,10 @@ impl ParseBlock for Parser {
         }
 
         Ok(CodeBlock {
-            language,
+            language: language.trim().to_string(),
             code: text,
+            metadata,
+            span: self.get_span(span_start),
         })
     }
 
@@ -156,6 +308,7 @@ impl ParseBlock for Parser {
     fn parse_math_block(&mut self) -> ParseResult<MathBlock> {
         let start_index = self.ctm.get_index();
         self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
         self.ctm.assert_sequence(SQ_MATH, Some(start_index))?;
         self.ctm.seek_one()?;
         let text = self.ctm.get_string_until_sequence(&[SQ_MATH], &[])?;
@@ -163,7 +316,34 @@ impl ParseBlock for Parser {
             self.ctm.try_seek();
         }
         Ok(MathBlock {
-            expression: asciimath_rs::parse(text),
+            expression: MathExpression::from_ascii_math(text),
+            metadata: None,
+            span: self.get_span(span_start),
+        })
+    }
+
+    /// parses a TeX math block that starts with a line containing only $$
+    /// and ends with $$
+    fn parse_tex_block(&mut self) -> ParseResult<MathBlock> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+        self.ctm.assert_sequence(MATH_INLINE, Some(start_index))?;
+        self.ctm.seek_one()?;
+        self.ctm.seek_any(&INLINE_WHITESPACE)?;
+        self.ctm.assert_char(&LB, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let text = self.ctm.get_string_until_sequence(&[MATH_INLINE], &[])?;
+
+        if self.ctm.check_eof() || !self.ctm.check_char(&MATH) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        self.ctm.try_seek();
+
+        Ok(MathBlock {
+            expression: MathExpression::Tex(text.trim().to_string()),
+            metadata: None,
+            span: self.get_span(span_start),
         })
     }
 
@@ -171,6 +351,7 @@ impl ParseBlock for Parser {
     fn parse_quote(&mut self) -> ParseResult<Quote> {
         let start_index = self.ctm.get_index();
         self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
 
         let metadata = if let Ok(meta) = self.parse_inline_metadata() {
             Some(meta)
@@ -183,32 +364,241 @@ impl ParseBlock for Parser {
             }
         }
         let mut quote = Quote::new(metadata);
+        let mut text = String::new();
+        let mut source_starts = Vec::new();
 
         while self.ctm.check_char(&QUOTE_START)
-            && self.ctm.next_char() != None
-            && (self.ctm.check_any(&WHITESPACE))
+            && self.ctm.next_char().is_some()
+            && self.ctm.check_any(WHITESPACE)
         {
-            self.ctm.seek_any(&INLINE_WHITESPACE)?;
-            if let Ok(text) = self.parse_text_line() {
-                if text.subtext.len() > 0 {
-                    quote.add_text(text);
-                }
-            } else {
+            if self.ctm.check_any(&INLINE_WHITESPACE) {
+                self.ctm.try_seek();
+            }
+            source_starts.push(self.ctm.get_index());
+            text.push_str(&self.ctm.get_string_until_any(&[LB], &[])?);
+            text.push(LB);
+            if !self.ctm.try_seek() {
                 break;
             }
+            // an empty line between two quoted lines doesn't end the quote
+            if self.ctm.check_char(&LB) && self.ctm.peek_char() == Some(QUOTE_START) {
+                source_starts.push(self.ctm.get_index());
+                text.push(LB);
+                self.ctm.try_seek();
+            }
+        }
+        quote.attribution = take_attribution(&mut text, &mut source_starts);
+        source_starts.push(self.ctm.get_index());
+        if self.ctm.check_char(&LB) {
+            self.ctm.try_seek();
         }
 
-        quote.strip_linebreak();
-        if quote.text.len() == 0 {
+        if text.trim().is_empty() {
             return Err(self.ctm.rewind_with_error(start_index).into());
         }
+        let max_depth = self.options.nesting_limits.max_quote_depth;
+        if self.nested.len() >= max_depth {
+            let error = self.nesting_limit_exceeded("Quotes", max_depth, span_start);
+            self.ctm.rewind(start_index);
+            return Err(error);
+        }
+        quote.elements = self.parse_nested(text, source_starts);
+        quote.span = self.get_span(span_start);
 
         Ok(quote)
     }
 
+    /// Parses blocks that are aligned as a whole. They start with a line of three pipes
+    /// followed by the alignment and end with a line of three pipes
+    fn parse_aligned(&mut self) -> ParseResult<Aligned> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+        self.ctm.assert_sequence(&SQ_ALIGNED, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let alignment = match self.ctm.get_string_until_any(&[LB], &[])?.trim() {
+            "" | "center" => Alignment::Center,
+            "left" => Alignment::Left,
+            "right" => Alignment::Right,
+            _ => return Err(self.ctm.rewind_with_error(start_index).into()),
+        };
+        if !self.ctm.try_seek() {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let content_start = self.ctm.get_index();
+        let mut text = String::new();
+        let mut source_starts = Vec::new();
+        let mut line_start = content_start;
+        let mut depth = 0;
+        let mut end = None;
+
+        for line in self.ctm.get_remaining().split_inclusive(LB) {
+            let index = line_start;
+            line_start += line.len();
+            let line_content = line.trim();
+
+            if line_content.chars().eq(SQ_ALIGNED.iter().copied()) {
+                if depth == 0 {
+                    end = Some((index, line_start));
+                    break;
+                }
+                depth -= 1;
+            } else if is_aligned_start(line_content) {
+                depth += 1;
+            }
+            source_starts.push(index);
+            text.push_str(line);
+        }
+        let (content_end, end) = match end {
+            Some(end) => end,
+            None => return Err(self.ctm.rewind_with_error(start_index).into()),
+        };
+        let max_depth = self.options.nesting_limits.max_container_depth;
+        if self.nested.len() >= max_depth {
+            let error = self.nesting_limit_exceeded("Aligned blocks", max_depth, span_start);
+            self.ctm.rewind(start_index);
+            return Err(error);
+        }
+        source_starts.push(content_end);
+        while self.ctm.get_index() < end && self.ctm.try_seek() {}
+        if self.ctm.check_char(&LB) {
+            self.ctm.try_seek();
+        }
+
+        let mut aligned = Aligned::new(alignment);
+        aligned.content = self.parse_nested(text, source_starts);
+        aligned.span = self.get_span(span_start);
+
+        Ok(aligned)
+    }
+
+    /// Parses a column layout that starts with `:::columns` followed by the optional
+    /// number of columns and ends with a line of three colons.
+    /// The content is split into columns with `:::column` lines
+    fn parse_columns(&mut self) -> ParseResult<Columns> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+        self.ctm.assert_sequence(&SQ_CONTAINER, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let header = self.ctm.get_string_until_any(&[LB], &[])?;
+        let mut arguments = header.split_whitespace();
+        if arguments.next() != Some(CONTAINER_COLUMNS) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let count = match arguments.next().map(|c| c.parse::<usize>()) {
+            Some(Ok(count)) if count > 0 => Some(count),
+            Some(_) => return Err(self.ctm.rewind_with_error(start_index).into()),
+            None => None,
+        };
+        if arguments.next().is_some() || !self.ctm.try_seek() {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let (parts, end) =
+            match split_container(self.ctm.get_remaining(), self.ctm.get_index(), true) {
+                Some(content) => content,
+                None => return Err(self.ctm.rewind_with_error(start_index).into()),
+            };
+        let max_depth = self.options.nesting_limits.max_container_depth;
+        if self.nested.len() >= max_depth {
+            let error = self.nesting_limit_exceeded("Columns", max_depth, span_start);
+            self.ctm.rewind(start_index);
+            return Err(error);
+        }
+        while self.ctm.get_index() < end && self.ctm.try_seek() {}
+        if self.ctm.check_char(&LB) {
+            self.ctm.try_seek();
+        }
+
+        let mut columns = Columns::new(count.unwrap_or(parts.len()));
+        for (text, source_starts) in parts {
+            let column = self.parse_nested(text, source_starts);
+            columns.add_column(column);
+        }
+        columns.span = self.get_span(span_start);
+
+        Ok(columns)
+    }
+
+    /// Parses a container with a custom name that starts with `:::name [metadata]`
+    /// and ends with a line of three colons
+    fn parse_custom_container(&mut self) -> ParseResult<CustomContainer> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+        self.ctm.assert_sequence(&SQ_CONTAINER, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let name = self.ctm.get_string_until_any(&[META_OPEN, LB], &[])?;
+        let name = name.trim();
+        if name.is_empty()
+            || name == CONTAINER_COLUMNS
+            || name == CONTAINER_COLUMN
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == MINUS || c == UNDERSCR)
+        {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let metadata = self.parse_inline_metadata().ok();
+        if self.ctm.seek_any(&INLINE_WHITESPACE).is_err()
+            || !self.ctm.check_char(&LB)
+            || !self.ctm.try_seek()
+        {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let (mut parts, end) =
+            match split_container(self.ctm.get_remaining(), self.ctm.get_index(), false) {
+                Some(content) => content,
+                None => return Err(self.ctm.rewind_with_error(start_index).into()),
+            };
+        let max_depth = self.options.nesting_limits.max_container_depth;
+        if self.nested.len() >= max_depth {
+            let error = self.nesting_limit_exceeded("Containers", max_depth, span_start);
+            self.ctm.rewind(start_index);
+            return Err(error);
+        }
+        while self.ctm.get_index() < end && self.ctm.try_seek() {}
+        if self.ctm.check_char(&LB) {
+            self.ctm.try_seek();
+        }
+
+        let mut container = CustomContainer::new(name.to_string(), metadata);
+        if let Some((text, source_starts)) = parts.pop() {
+            container.content = self.parse_nested(text, source_starts);
+        }
+        container.span = self.get_span(span_start);
+
+        Ok(container)
+    }
+
+    /// Parses a page break that is written as three less-than signs on a line of their own
+    fn parse_page_break(&mut self) -> ParseResult<PageBreak> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+        self.ctm
+            .assert_sequence(&SQ_PAGE_BREAK, Some(start_index))?;
+        self.ctm.seek_one()?;
+        while self.ctm.check_any(&INLINE_WHITESPACE) && self.ctm.try_seek() {}
+        if !self.ctm.check_eof() && !self.ctm.check_char(&LB) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let span = self.get_span(span_start);
+        self.ctm.try_seek();
+        if self.ctm.check_char(&LB) {
+            self.ctm.try_seek();
+        }
+
+        Ok(PageBreak {
+            metadata: None,
+            span,
+        })
+    }
+
     /// Parses a paragraph
     fn parse_paragraph(&mut self) -> ParseResult<Paragraph> {
         let mut paragraph = Paragraph::new();
+        let start_index = self.ctm.get_index();
 
         while let Ok(element) = self.parse_line() {
             paragraph.add_element(element);
@@ -216,6 +606,12 @@ impl ParseBlock for Parser {
 
             if self.ctm.check_any_sequence(&BLOCK_SPECIAL_CHARS)
                 || self.ctm.check_any(&self.block_break_at)
+                || self.check_definition_term()
+                || self.check_setext_header().is_some()
+                || (self.options.raw_html && is_html_block_start(self.ctm.get_remaining()))
+                || self.check_line_comment()
+                || self.ctm.check_sequence(SQ_COMMENT_START)
+                || self.check_extension_block()
             {
                 self.ctm.rewind(start_index);
                 break;
@@ -226,24 +622,186 @@ impl ParseBlock for Parser {
         }
 
         if paragraph.elements.len() > 0 {
+            paragraph.span = self.get_span(start_index);
             Ok(paragraph)
         } else {
             Err(self.ctm.err().into())
         }
     }
 
+    /// parses an image that stands on its own line and is followed by a caption line
+    fn parse_figure(&mut self) -> ParseResult<Figure> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_any(&INLINE_WHITESPACE)?;
+        let span_start = self.ctm.get_index();
+        let image = if let Ok(image) = self.parse_image() {
+            image
+        } else {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        };
+        self.ctm.seek_any(&INLINE_WHITESPACE)?;
+
+        if self.ctm.check_char(&LB) && self.ctm.try_seek() {
+            self.ctm.seek_any(&INLINE_WHITESPACE)?;
+            let caption_start = self.ctm.get_index();
+
+            if !self.ctm.check_char(&LB)
+                && !self.ctm.check_any_sequence(BLOCK_SPECIAL_CHARS)
+                && !self.ctm.check_char(&IMG_START)
+            {
+                if let Ok(caption) = self.parse_text_line() {
+                    return Ok(Figure {
+                        image,
+                        caption,
+                        metadata: None,
+                        span: self.get_span(span_start),
+                    });
+                }
+            }
+            self.ctm.rewind(caption_start);
+        }
+        self.options
+            .document
+            .images
+            .lock()
+            .remove_image(&image.image_data);
+
+        Err(self.ctm.rewind_with_error(start_index).into())
+    }
+
+    /// parses a list of terms that are each followed by one or more
+    /// lines starting with `: ` that define them
+    fn parse_definition_list(&mut self) -> ParseResult<DefinitionList> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+        let mut list = DefinitionList::new();
+
+        while let Ok(definition) = self.parse_definition() {
+            list.add_item(definition);
+        }
+
+        if !list.items.is_empty() {
+            list.span = self.get_span(span_start);
+            Ok(list)
+        } else {
+            Err(self.ctm.rewind_with_error(start_index).into())
+        }
+    }
+
+    /// parses a single term with its definitions
+    fn parse_definition(&mut self) -> ParseResult<Definition> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+
+        if self.ctm.check_any_sequence(BLOCK_SPECIAL_CHARS) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        self.ctm.rewind(span_start);
+        let mut definition = Definition::new(self.parse_text_line()?);
+
+        while self.ctm.check_char(&DEFINITION_START)
+            && self.ctm.next_char().is_some()
+            && self.ctm.check_any(&INLINE_WHITESPACE)
+        {
+            self.ctm.seek_any(&INLINE_WHITESPACE)?;
+            if let Ok(text) = self.parse_text_line() {
+                definition.add_definition(text);
+            } else {
+                break;
+            }
+        }
+
+        if definition.definitions.is_empty() {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        definition.span = self.get_span(span_start);
+
+        Ok(definition)
+    }
+
+    /// checks if the current line is the term of a definition
+    /// by looking for a definition at the start of the next line
+    fn check_definition_term(&mut self) -> bool {
+        let start_index = self.ctm.get_index();
+        let is_term = self.ctm.get_string_until_any(&[LB], &[]).is_ok()
+            && self.ctm.try_seek()
+            && self.ctm.check_char(&DEFINITION_START)
+            && self.ctm.try_seek()
+            && self.ctm.check_any(&INLINE_WHITESPACE);
+        self.ctm.rewind(start_index);
+
+        is_term
+    }
+
+    /// Checks if the current line is the title of a header that is underlined
+    /// with = or - in the next line and returns the size of the header
+    fn check_setext_header(&mut self) -> Option<u8> {
+        let mut lines = self.ctm.get_remaining().splitn(3, LB);
+        let title = lines.next()?.trim();
+        let underline = lines.next()?.trim_end();
+
+        let size = match underline.chars().next()? {
+            EQ => 1,
+            MINUS => 2,
+            _ => return None,
+        };
+        // a single minus is more likely the start of a list item than an underline
+        if !underline
+            .chars()
+            .all(|c| c == underline.chars().next().unwrap())
+            || (size == 2 && underline.len() < 2)
+            || title.is_empty()
+            || is_list_item(title)
+        {
+            return None;
+        }
+        let start_index = self.ctm.get_index();
+        let is_block =
+            self.ctm.check_any_sequence(BLOCK_SPECIAL_CHARS) || self.ctm.check_sequence(&SQ_RULER);
+        self.ctm.rewind(start_index);
+
+        (!is_block).then_some(size)
+    }
+
     /// parses a list which consists of one or more list items
     /// The parsing is done iterative to resolve nested items
     fn parse_list(&mut self) -> ParseResult<List> {
         let mut list = List::new();
         let start_index = self.ctm.get_index();
         self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
 
         let ordered = self.ctm.get_current().is_numeric();
         list.ordered = ordered;
         let mut list_hierarchy: Vec<ListItem> = Vec::new();
+        let max_depth = self.options.nesting_limits.max_list_depth;
+        let mut blank_after_item = false;
 
-        while let Ok(mut item) = self.parse_list_item() {
+        loop {
+            let item_start = self.ctm.get_index();
+            let mut item = match self.parse_list_item() {
+                Ok(item) => item,
+                Err(_) => break,
+            };
+            // lists are loose if their items are separated by blank lines
+            // or contain blocks separated by blank lines
+            let item_source = self.ctm.get_slice(item_start, self.ctm.get_index());
+            if blank_after_item || has_blank_line(item_source.trim_end()) {
+                list.loose = true;
+            }
+            blank_after_item = ends_with_blank_line(item_source);
+            // items that would be nested deeper are added to the deepest level instead
+            match list_hierarchy.last() {
+                Some(deepest)
+                    if list_hierarchy.len() >= max_depth && item.level > deepest.level =>
+                {
+                    item.level = deepest.level;
+                    self.nesting_limit_exceeded("List items", max_depth, item_start);
+                }
+                _ => {}
+            }
             while let Some(parent_item) = list_hierarchy.pop() {
                 if parent_item.level < item.level {
                     // the parent item is the actual parent of the next item
@@ -286,7 +844,8 @@ impl ParseBlock for Parser {
         }
         list.items.append(&mut list_hierarchy);
 
-        if list.items.len() > 0 {
+        if !list.items.is_empty() {
+            list.span = self.get_span(span_start);
             Ok(list)
         } else {
             return Err(self.ctm.rewind_with_error(start_index).into());
@@ -295,37 +854,57 @@ impl ParseBlock for Parser {
 
     /// parses a markdown table
     fn parse_table(&mut self) -> ParseResult<Table> {
+        let start_index = self.ctm.get_index();
         let header = self.parse_row()?;
         if self.ctm.check_char(&LB) {
             self.ctm.seek_one()?;
         }
-        let seek_index = self.ctm.get_index();
         let mut table = Table::new(header);
 
-        while let Ok(_) = self.ctm.seek_one() {
-            self.ctm.seek_any(&INLINE_WHITESPACE)?;
-            if !self.ctm.check_any(&[MINUS, PIPE]) || self.ctm.check_char(&LB) {
-                break;
-            }
-        }
-
-        if !self.ctm.check_char(&LB) {
-            self.ctm.rewind(seek_index);
+        if let Ok(alignments) = self.parse_table_alignments() {
+            table.alignments = alignments;
+        } else {
+            table.span = self.get_span(start_index);
             return Ok(table);
         }
 
         self.ctm.seek_whitespace();
-        while let Ok(row) = self.parse_row() {
-            table.add_row(row);
+        let mut row_starts = Vec::new();
+        loop {
+            let row_start = self.ctm.get_index();
+            if let Ok(row) = self.parse_row() {
+                row_starts.push(row_start);
+                table.add_row(row);
+            } else {
+                break;
+            }
         }
+        let layout = table.get_layout();
+        let header_width = layout[0].1;
+
+        for (row_start, (_, width)) in row_starts.into_iter().zip(layout.iter().skip(1)) {
+            if *width != header_width {
+                self.add_diagnostic(
+                    Severity::Warning,
+                    DiagnosticCode::TableColumnMismatch,
+                    format!(
+                        "Table row has {} columns but the header has {}",
+                        width, header_width
+                    ),
+                    row_start,
+                );
+            }
+        }
+        table.span = self.get_span(start_index);
 
         Ok(table)
     }
 
     /// parses an import and starts a new task to parse the document of the import
-    fn parse_import(&mut self) -> ParseResult<Option<Import>> {
+    fn parse_import(&mut self) -> ParseResult<Option<Block>> {
         let start_index = self.ctm.get_index();
         self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
         self.ctm
             .assert_any_sequence(&[&[IMPORT_START, IMPORT_OPEN]], Some(start_index))?;
         let mut path = String::new();
@@ -347,18 +926,318 @@ impl ParseBlock for Parser {
             self.section_return = Some(0);
             return Err(self.ctm.rewind_with_error(start_index).into());
         }
-        let metadata = self
-            .parse_inline_metadata()
-            .ok()
+        let inline_metadata = self.parse_inline_metadata().ok();
+        let shift = inline_metadata.as_ref().and_then(|m| {
+            m.get_integer("shift")
+                .map(|levels| HeaderShift::Levels(levels.clamp(0, 6) as u8))
+                .or_else(|| m.get_bool("shift").then_some(HeaderShift::Nesting))
+        });
+        if !self.options.allow_imports {
+            self.add_diagnostic(
+                Severity::Warning,
+                DiagnosticCode::ImportFailed,
+                format!("Import of \"{}\" skipped: Imports are disabled.", path),
+                span_start,
+            );
+            // the import stays in the document without content so that it can be written back
+            return Ok(Some(Block::Import(Import {
+                path,
+                anchor: Arc::new(RwLock::new(ImportAnchor::new())),
+                shift,
+                metadata: inline_metadata,
+                span: self.get_span(span_start),
+            })));
+        }
+        let metadata = inline_metadata
+            .as_ref()
             .map(|m| m.get_string_map())
             .unwrap_or(HashMap::new());
 
         match self.import(path.clone(), &metadata) {
-            ImportType::Document(Ok(anchor)) => Ok(Some(Import { path, anchor })),
+            ImportType::Document(Ok(anchor)) => Ok(Some(Block::Import(Import {
+                path,
+                anchor,
+                shift,
+                metadata: inline_metadata,
+                span: self.get_span(span_start),
+            }))),
+            ImportType::Code(Ok(code_block)) => Ok(Some(Block::CodeBlock(CodeBlock {
+                metadata: inline_metadata,
+                span: self.get_span(span_start),
+                ..code_block
+            }))),
+            ImportType::Table(Ok(table)) => Ok(Some(Block::Table(Table {
+                span: self.get_span(span_start),
+                ..table
+            }))),
             ImportType::Stylesheet(_) => Ok(None),
             ImportType::Bibliography(_) => Ok(None),
             ImportType::Manifest(_) => Ok(None),
-            _ => Err(self.ctm.err().into()),
+            ImportType::Template(_) => Ok(None),
+            ImportType::Glossary(_) => Ok(None),
+            ImportType::None => Err(self.ctm.err().into()),
+            _ => Ok(Some(Block::Import(Import {
+                anchor: self.create_failed_import_anchor(&path),
+                path,
+                shift,
+                metadata: inline_metadata,
+                span: self.get_span(span_start),
+            }))),
         }
     }
+
+    /// Parses raw html that starts with a block level tag.
+    /// The html reaches until the next empty line
+    fn parse_html_block(&mut self) -> ParseResult<Html> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+
+        if !self.options.raw_html
+            || self.ctm.check_escaped()
+            || !is_html_block_start(self.ctm.get_remaining())
+        {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let remaining = self.ctm.get_remaining();
+        let end = remaining.find(SQ_HTML_BLOCK_END);
+        let value = remaining[..end.unwrap_or(remaining.len())]
+            .trim_end()
+            .to_string();
+
+        if let Some(end) = end {
+            // skip the empty line after the html
+            self.ctm.rewind(span_start + end + LB.len_utf8());
+            self.ctm.try_seek();
+        } else {
+            while self.ctm.next_char().is_some() {}
+        }
+
+        Ok(Html {
+            value,
+            metadata: None,
+            span: self.get_span(span_start),
+        })
+    }
+
+    /// Parses a comment enclosed in <!-- --> that stands on its own lines
+    /// or one or more lines starting with // if line comments are enabled
+    fn parse_comment_block(&mut self) -> ParseResult<Comment> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+        let span_start = self.ctm.get_index();
+        let mut lines = Vec::new();
+
+        if self.ctm.check_sequence(SQ_COMMENT_START) {
+            self.ctm.seek_one()?;
+            let value = self
+                .ctm
+                .get_string_until_sequence(&[SQ_COMMENT_END], &[])
+                .map_err(|_| self.ctm.rewind_with_error(start_index))?;
+            if self.ctm.check_eof() {
+                // the comment isn't closed
+                return Err(self.ctm.rewind_with_error(start_index).into());
+            }
+            self.ctm.seek_one()?;
+            // the rest of the line has to be empty
+            self.ctm.seek_any(&INLINE_WHITESPACE)?;
+            if !self.ctm.check_eof() && !self.ctm.check_char(&LB) {
+                return Err(self.ctm.rewind_with_error(start_index).into());
+            }
+            self.ctm.try_seek();
+            lines.push(value);
+        } else {
+            while self.check_line_comment() {
+                self.ctm.seek_any(&INLINE_WHITESPACE)?;
+                self.ctm.check_sequence(SQ_LINE_COMMENT);
+                self.ctm.seek_one()?;
+                lines.push(
+                    self.ctm
+                        .get_string_until_any(&[LB], &[])
+                        .unwrap_or_default(),
+                );
+                self.ctm.try_seek();
+            }
+        }
+        if lines.is_empty() {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let span = self.get_span(span_start);
+        // an empty line after the comment would be rendered as a break
+        if self.ctm.check_char(&LB) {
+            self.ctm.try_seek();
+        }
+
+        Ok(Comment {
+            value: lines.join("\n").trim().to_string(),
+            metadata: None,
+            span,
+        })
+    }
+
+    /// Checks if the current line is a // comment
+    fn check_line_comment(&mut self) -> bool {
+        if !self.options.line_comments {
+            return false;
+        }
+        let start_index = self.ctm.get_index();
+        let is_comment = self.ctm.seek_any(&INLINE_WHITESPACE).is_ok()
+            && self.ctm.check_sequence(SQ_LINE_COMMENT);
+        self.ctm.rewind(start_index);
+
+        is_comment
+    }
+
+    /// parses a yaml front matter block enclosed in --- lines
+    fn parse_front_matter(&mut self) -> ParseResult<InlineMetadata> {
+        let start_index = self.ctm.get_index();
+        self.ctm
+            .assert_sequence(&SQ_FRONT_MATTER, Some(start_index))?;
+        self.ctm.seek_one()?;
+        self.ctm.seek_any(&INLINE_WHITESPACE)?;
+        self.ctm.assert_char(&LB, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let content = self
+            .ctm
+            .get_string_until_sequence(&[&SQ_FRONT_MATTER_END], &[])?;
+
+        if self.ctm.check_eof() || !self.ctm.check_char(&MINUS) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        self.ctm.seek_one()?;
+        self.ctm.seek_any(&INLINE_WHITESPACE)?;
+        self.ctm.assert_char(&LB, Some(start_index))?;
+        self.ctm.seek_one()?;
+
+        match metadata_from_yaml(&content) {
+            Ok(metadata) => Ok(metadata),
+            Err(e) => {
+                self.add_diagnostic(
+                    Severity::Error,
+                    DiagnosticCode::InvalidFrontMatter,
+                    format!("Failed to parse front matter: {}", e),
+                    start_index,
+                );
+                Err(self.ctm.rewind_with_error(start_index).into())
+            }
+        }
+    }
+}
+
+/// Returns if any line of the text is empty or contains only whitespace
+fn has_blank_line(text: &str) -> bool {
+    text.lines().any(|line| line.trim().is_empty())
+}
+
+/// Returns if the last line of the text is empty or contains only whitespace
+fn ends_with_blank_line(text: &str) -> bool {
+    text.strip_suffix(LB)
+        .and_then(|text| text.rsplit(LB).next())
+        .is_some_and(|line| line.trim().is_empty())
+}
+
+/// Returns if the line starts like the item of a list
+pub(crate) fn is_list_item(line: &str) -> bool {
+    let marker = line.trim_start_matches(|c: char| c.is_ascii_digit());
+
+    if marker.len() < line.len() {
+        marker.starts_with(". ")
+    } else {
+        [MINUS, PLUS, ASTERISK, O]
+            .iter()
+            .any(|c| marker.starts_with(*c) && marker[1..].starts_with(INLINE_WHITESPACE))
+    }
+}
+
+/// The text of nested blocks with the indices its lines start at
+type NestedText = (String, Vec<usize>);
+
+/// Splits the content of a container that ends with a line of three colons into parts
+/// at `:::column` lines if the container is split into columns. Returns the text of each part
+/// with the indices its lines start at and the index after the closing line.
+/// Nested containers are closed by their own closing line
+fn split_container(
+    remaining: &str,
+    content_start: usize,
+    split_columns: bool,
+) -> Option<(Vec<NestedText>, usize)> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut source_starts = Vec::new();
+    let mut line_start = content_start;
+    let mut depth = 0;
+    let fence = SQ_CONTAINER.iter().collect::<String>();
+
+    for line in remaining.split_inclusive(LB) {
+        let index = line_start;
+        line_start += line.len();
+        let name = line
+            .trim()
+            .strip_prefix(fence.as_str())
+            .map(|name| name.trim());
+
+        match name {
+            Some("") if depth == 0 => {
+                source_starts.push(index);
+                parts.push((text, source_starts));
+                return Some((parts, line_start));
+            }
+            Some("") => depth -= 1,
+            Some(CONTAINER_COLUMN) if depth == 0 && split_columns => {
+                source_starts.push(index);
+                parts.push((
+                    std::mem::take(&mut text),
+                    std::mem::take(&mut source_starts),
+                ));
+                continue;
+            }
+            // the separators of nested columns don't start a container
+            Some(name) if name != CONTAINER_COLUMN && !name.starts_with(COLON) => depth += 1,
+            _ => {}
+        }
+        source_starts.push(index);
+        text.push_str(line);
+    }
+
+    None
+}
+
+/// Returns if the line starts an aligned block with an explicit alignment
+fn is_aligned_start(line: &str) -> bool {
+    let alignment = line.trim_start_matches(PIPE);
+
+    line.len() - alignment.len() == SQ_ALIGNED.len()
+        && ["left", "center", "right"].contains(&alignment.trim())
+}
+
+/// Removes a `-- Author, Source` line at the end of the text of a quote
+/// together with the empty lines before it and returns the parsed attribution
+fn take_attribution(text: &mut String, source_starts: &mut Vec<usize>) -> Option<Attribution> {
+    let lines = text.trim_end_matches(LB);
+    let line_start = lines.rfind(LB).map_or(0, |i| i + 1);
+    let attribution = lines[line_start..].strip_prefix(SQ_ATTRIBUTION)?;
+    let remaining = lines[..line_start].trim_end_matches(LB);
+
+    if remaining.trim().is_empty() {
+        return None;
+    }
+    let attribution = Attribution::parse(attribution)?;
+    let line_count = remaining.split(LB).count();
+    text.truncate(remaining.len());
+    text.push(LB);
+    source_starts.truncate(line_count);
+
+    Some(attribution)
+}
+
+/// Returns if the text starts with a tag of a block level html element
+fn is_html_block_start(text: &str) -> bool {
+    lazy_static::lazy_static! {
+        static ref HTML_BLOCK_START: Regex = Regex::new(
+            r"(?i)^</?(?:address|article|aside|blockquote|details|dialog|div|dl|fieldset|figcaption|figure|footer|form|h[1-6]|header|hr|iframe|main|nav|ol|p|pre|script|section|style|summary|table|tbody|td|tfoot|th|thead|tr|ul|video|audio|canvas|svg)(?:[\s/>]|$)"
+        )
+        .unwrap();
+    }
+
+    HTML_BLOCK_START.is_match(text)
 }
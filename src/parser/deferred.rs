@@ -0,0 +1,128 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use super::diagnostics::{DiagnosticCode, Severity};
+use super::metrics::ParseMetrics;
+use super::{Parser, ParserOptions};
+use crate::elements::{Document, ImportAnchor};
+use futures::future::join_all;
+use futures::Future;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+
+/// An import that failed with its index in the importing document
+type ImportFailure = (usize, PathBuf, io::Error);
+
+/// A document import that is loaded and parsed after the importing document
+pub(crate) struct DeferredImport {
+    options: ParserOptions,
+    path: PathBuf,
+    anchor: Arc<RwLock<ImportAnchor>>,
+    section: Option<String>,
+    index: usize,
+}
+
+impl DeferredImport {
+    /// Loads and parses the imported document with its own imports
+    fn parse(self) -> Pin<Box<dyn Future<Output = Result<(), ImportFailure>> + Send>> {
+        Box::pin(async move {
+            let text = match self.options.resolver.read_async(&self.path).await {
+                Ok(text) => text,
+                Err(e) => return Err((self.index, self.path, e)),
+            };
+            if let Err(message) = self.options.import_limits.check_file_size(&text) {
+                let e = io::Error::new(io::ErrorKind::FileTooLarge, message);
+                return Err((self.index, self.path, e));
+            }
+            let mut parser = Parser::new(text, Some(self.options));
+            let mut document = parser.parse_async().await;
+            if let Some(section) = &self.section {
+                parser.retain_import_section(&mut document, section);
+            }
+            self.anchor.write().unwrap().set_document(document);
+
+            Ok(())
+        })
+    }
+}
+
+impl Parser {
+    /// Creates a new parser for the document at the path of the options
+    /// and reads it without blocking the executor
+    pub async fn with_defaults_async(options: ParserOptions) -> io::Result<Self> {
+        let text = if let Some(path) = &options.path {
+            options.resolver.read_async(path).await?
+        } else {
+            String::new()
+        };
+
+        Ok(Self::new(text, Some(options)))
+    }
+
+    /// Parses the document like [Parser::parse] but awaits the imported documents and
+    /// embedded downloads instead of waiting for threads. Imported documents are loaded
+    /// and parsed concurrently after the importing document is parsed
+    /// or one after another with deterministic imports.
+    /// Settings files like the Manifest.toml are still read synchronously.
+    pub async fn parse_async(&mut self) -> Document {
+        self.deferred_imports = Some(Vec::new());
+        self.parse_blocks();
+        let imports = self.deferred_imports.take().unwrap_or_default();
+
+        let results = if self.options.deterministic_imports {
+            let mut results = Vec::new();
+            for import in imports {
+                results.push(import.parse().await);
+            }
+            results
+        } else {
+            join_all(imports.into_iter().map(DeferredImport::parse)).await
+        };
+        for (index, path, e) in results.into_iter().filter_map(Result::err) {
+            let code = match e.kind() {
+                io::ErrorKind::NotFound => DiagnosticCode::ImportNotFound,
+                io::ErrorKind::FileTooLarge => DiagnosticCode::ImportLimitExceeded,
+                _ => DiagnosticCode::ImportFailed,
+            };
+            self.add_diagnostic(
+                Severity::Error,
+                code,
+                format!("Import of \"{}\" failed: {}", path.to_string_lossy(), e),
+                index,
+            );
+        }
+        self.import_settings();
+        if !self.options.is_child {
+            self.options.document.download_media_async().await;
+        }
+
+        self.finish_document(&mut ParseMetrics::default())
+    }
+
+    /// Registers an imported document to be parsed after this document
+    pub(super) fn defer_import(
+        &mut self,
+        path: PathBuf,
+        args: &HashMap<String, String>,
+    ) -> Arc<RwLock<ImportAnchor>> {
+        let anchor = Arc::new(RwLock::new(ImportAnchor::new()));
+        let import = DeferredImport {
+            options: self.create_child_options(path.clone()),
+            path,
+            anchor: Arc::clone(&anchor),
+            section: args.get("section").cloned(),
+            index: self.ctm.get_index(),
+        };
+        if let Some(imports) = &mut self.deferred_imports {
+            imports.push(import);
+        }
+
+        anchor
+    }
+}
@@ -0,0 +1,218 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::Span;
+#[cfg(feature = "cli")]
+use colored::Colorize;
+use std::fmt;
+use std::fmt::Debug;
+
+/// The severity of a diagnostic
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// The kind of problem a diagnostic describes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiagnosticCode {
+    SyntaxError,
+    ImportNotFound,
+    ImportFailed,
+    DuplicateImport,
+    InvalidFrontMatter,
+    InvalidBibEntry,
+    InvalidGlossary,
+    TableColumnMismatch,
+    MissingValue,
+    BrokenLink,
+    BrokenAnchor,
+    ImportLimitExceeded,
+    ImportOutsideSandbox,
+    NestingLimitExceeded,
+    ImportCycle,
+    UnreachableUrl,
+    BrokenWikiLink,
+}
+
+/// A warning or error that was encountered while parsing a document
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub message: String,
+    pub path: Option<String>,
+    pub span: Span,
+}
+
+/// A collection of all diagnostics of a document and its imports
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl DiagnosticCode {
+    /// Returns the short identifier of the code
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::SyntaxError => "E001",
+            DiagnosticCode::ImportNotFound => "E002",
+            DiagnosticCode::ImportFailed => "E003",
+            DiagnosticCode::InvalidFrontMatter => "E004",
+            DiagnosticCode::InvalidBibEntry => "E005",
+            DiagnosticCode::InvalidGlossary => "E006",
+            DiagnosticCode::MissingValue => "E007",
+            DiagnosticCode::BrokenLink => "E008",
+            DiagnosticCode::BrokenAnchor => "E009",
+            DiagnosticCode::ImportLimitExceeded => "E010",
+            DiagnosticCode::ImportOutsideSandbox => "E011",
+            DiagnosticCode::NestingLimitExceeded => "E012",
+            DiagnosticCode::ImportCycle => "E013",
+            DiagnosticCode::DuplicateImport => "W001",
+            DiagnosticCode::TableColumnMismatch => "W002",
+            DiagnosticCode::UnreachableUrl => "W003",
+            DiagnosticCode::BrokenWikiLink => "W004",
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Diagnostic {
+    /// Returns the location of the diagnostic as path:line:column
+    pub fn get_location(&self) -> String {
+        if let Some(path) = &self.path {
+            format!("{}:{}:{}", path, self.span.line, self.span.column)
+        } else {
+            format!("{}:{}", self.span.line, self.span.column)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {}\n\t--> {}\n",
+            self.severity,
+            self.code,
+            self.message,
+            self.get_location()
+        )
+    }
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic)
+    }
+
+    /// Returns if the same diagnostic was already added
+    pub fn contains(&self, diagnostic: &Diagnostic) -> bool {
+        self.diagnostics.contains(diagnostic)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    /// Returns all diagnostics with the error severity
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.iter().filter(|d| d.severity == Severity::Error)
+    }
+
+    /// Returns all diagnostics with the warning severity
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.iter().filter(|d| d.severity == Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.into_iter()
+    }
+}
+
+/// Receives the diagnostics of a document as soon as they are encountered.
+/// Implement this to decide how parse errors and failed imports are surfaced.
+pub trait Reporter: Debug + Send + Sync {
+    fn report(&self, diagnostic: &Diagnostic);
+}
+
+/// The default reporter that writes diagnostics to the log
+#[derive(Clone, Debug, Default)]
+pub struct LogReporter;
+
+impl Reporter for LogReporter {
+    fn report(&self, diagnostic: &Diagnostic) {
+        let message = format!(
+            "[{}] {}\n\t--> {}\n",
+            diagnostic.code,
+            diagnostic.message,
+            diagnostic.get_location()
+        );
+        match diagnostic.severity {
+            Severity::Error => log::error!("{}", message),
+            Severity::Warning => log::warn!("{}", message),
+        }
+    }
+}
+
+/// A reporter that prints colored diagnostics to stderr
+#[cfg(feature = "cli")]
+#[derive(Clone, Debug, Default)]
+pub struct TerminalReporter;
+
+#[cfg(feature = "cli")]
+impl Reporter for TerminalReporter {
+    fn report(&self, diagnostic: &Diagnostic) {
+        let severity = format!("{}[{}]", diagnostic.severity, diagnostic.code);
+        let severity = match diagnostic.severity {
+            Severity::Error => severity.red(),
+            Severity::Warning => severity.yellow(),
+        };
+        eprintln!(
+            "{}: {}\n\t{} {}\n",
+            severity.bold(),
+            diagnostic.message.bold(),
+            "-->".blue(),
+            diagnostic.get_location()
+        );
+    }
+}
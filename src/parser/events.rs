@@ -0,0 +1,535 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::*;
+use crate::parser::block::ParseBlock;
+use crate::Parser;
+use std::collections::{HashMap, VecDeque};
+
+/// A single event of the document.
+/// Containers are surrounded by a `Start` and `End` event with the same tag.
+#[derive(Clone, Debug)]
+pub enum Event {
+    Start(Tag),
+    End(Tag),
+    Text(String),
+    Code(String),
+    Math(MathExpression),
+    Emoji(String),
+    CharacterCode(String),
+    Arrow(Arrow),
+    Checkbox(bool),
+    Placeholder(String),
+    TemplateVariable(String),
+    BibReference(String),
+    GlossaryReference(String),
+    FootnoteReference(String),
+    BibEntry(String),
+    Import(String),
+    FrontMatter(InlineMetadata),
+    Html(String),
+    Comment(String),
+    LineBreak,
+    Ruler,
+    PageBreak,
+}
+
+/// The container element of a `Start` or `End` event
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Section,
+    Header {
+        size: u8,
+        anchor: String,
+    },
+    Paragraph,
+    List {
+        ordered: bool,
+    },
+    ListItem {
+        checked: Option<bool>,
+        bullet: Option<char>,
+        attributes: HashMap<String, String>,
+    },
+    Table {
+        alignments: Vec<Alignment>,
+    },
+    TableHeader,
+    Row,
+    Cell {
+        colspan: usize,
+        rowspan: usize,
+    },
+    CodeBlock {
+        language: String,
+    },
+    Diagram {
+        language: String,
+    },
+    MathBlock,
+    Quote {
+        attribution: Option<Attribution>,
+    },
+    Aligned {
+        alignment: Alignment,
+    },
+    Columns {
+        count: usize,
+    },
+    Column,
+    Container {
+        name: String,
+        attributes: HashMap<String, String>,
+    },
+    DefinitionList,
+    DefinitionTerm,
+    DefinitionDescription,
+    Figure,
+    FigureCaption,
+    Centered,
+    FootnoteDefinition {
+        label: String,
+    },
+    RefLink {
+        reference: String,
+    },
+    Anchor {
+        key: String,
+    },
+    Bold,
+    Italic,
+    Underlined,
+    Striked,
+    Superscript,
+    Subscript,
+    Link {
+        url: String,
+    },
+    Image {
+        url: String,
+    },
+    Colored {
+        color: String,
+    },
+    Abbreviation {
+        long: String,
+    },
+    Spoiler,
+    Highlight {
+        color: Option<String>,
+    },
+    Styled {
+        attributes: HashMap<String, String>,
+    },
+}
+
+/// An iterator over the events of a document that is parsed one top level block at a time.
+/// References, placeholders and imports are not resolved, they are reported
+/// by their name and it's up to the consumer to handle them.
+pub struct Events<'a> {
+    parser: &'a mut Parser,
+    pending: VecDeque<Event>,
+}
+
+impl<'a> Events<'a> {
+    pub(crate) fn new(parser: &'a mut Parser) -> Self {
+        let mut pending = VecDeque::new();
+        if let Ok(front_matter) = parser.parse_front_matter() {
+            pending.push_back(Event::FrontMatter(front_matter));
+        }
+
+        Self { parser, pending }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            let block = self.parser.parse_next_block()?;
+            block.to_events(&mut self.pending);
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+/// Returns the events of the given blocks
+pub(crate) fn get_events(blocks: &[Block]) -> VecDeque<Event> {
+    let mut events = VecDeque::new();
+    blocks.iter().for_each(|b| b.to_events(&mut events));
+
+    events
+}
+
+/// Returns the events of the inline elements of a line
+pub(crate) fn get_line_events(line: &Line) -> VecDeque<Event> {
+    let mut events = VecDeque::new();
+    line.to_events(&mut events);
+
+    events
+}
+
+trait ToEvents {
+    fn to_events(&self, events: &mut VecDeque<Event>);
+}
+
+/// Pushes the events of the inner elements surrounded by the start and end of the tag
+fn surround<F: FnOnce(&mut VecDeque<Event>)>(events: &mut VecDeque<Event>, tag: Tag, inner: F) {
+    events.push_back(Event::Start(tag.clone()));
+    inner(events);
+    events.push_back(Event::End(tag));
+}
+
+impl<T: ToEvents> ToEvents for Vec<T> {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        self.iter().for_each(|e| e.to_events(events))
+    }
+}
+
+impl ToEvents for Block {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        match self {
+            Block::Section(section) => section.to_events(events),
+            Block::Paragraph(paragraph) => {
+                surround(events, Tag::Paragraph, |e| paragraph.elements.to_events(e))
+            }
+            Block::List(list) => list.to_events(events),
+            Block::Table(table) => table.to_events(events),
+            Block::CodeBlock(code) => surround(
+                events,
+                Tag::CodeBlock {
+                    language: code.language.clone(),
+                },
+                |e| e.push_back(Event::Text(code.code.clone())),
+            ),
+            Block::Diagram(diagram) => surround(
+                events,
+                Tag::Diagram {
+                    language: diagram.kind.as_str().to_string(),
+                },
+                |e| e.push_back(Event::Text(diagram.source.clone())),
+            ),
+            Block::Html(html) => events.push_back(Event::Html(html.value.clone())),
+            Block::Comment(comment) => events.push_back(Event::Comment(comment.value.clone())),
+            Block::MathBlock(math) => surround(events, Tag::MathBlock, |e| {
+                e.push_back(Event::Math(math.expression.clone()))
+            }),
+            Block::Quote(quote) => surround(
+                events,
+                Tag::Quote {
+                    attribution: quote.attribution.clone(),
+                },
+                |e| quote.elements.to_events(e),
+            ),
+            Block::Aligned(aligned) => surround(
+                events,
+                Tag::Aligned {
+                    alignment: aligned.alignment,
+                },
+                |e| aligned.content.to_events(e),
+            ),
+            Block::Columns(columns) => surround(
+                events,
+                Tag::Columns {
+                    count: columns.count,
+                },
+                |e| {
+                    for column in &columns.columns {
+                        surround(e, Tag::Column, |e| column.to_events(e))
+                    }
+                },
+            ),
+            Block::CustomContainer(container) => surround(
+                events,
+                Tag::Container {
+                    name: container.name.clone(),
+                    attributes: container.get_attributes(),
+                },
+                |e| container.content.to_events(e),
+            ),
+            Block::DefinitionList(list) => surround(events, Tag::DefinitionList, |e| {
+                for item in &list.items {
+                    surround(e, Tag::DefinitionTerm, |e| item.term.to_events(e));
+                    for definition in &item.definitions {
+                        surround(e, Tag::DefinitionDescription, |e| definition.to_events(e))
+                    }
+                }
+            }),
+            Block::Figure(figure) => surround(events, Tag::Figure, |e| {
+                figure.image.to_events(e);
+                surround(e, Tag::FigureCaption, |e| figure.caption.to_events(e))
+            }),
+            Block::Import(import) => events.push_back(Event::Import(import.path.clone())),
+            Block::Placeholder(placeholder) => {
+                events.push_back(Event::Placeholder(placeholder.read().unwrap().name.clone()))
+            }
+            Block::PageBreak(_) => events.push_back(Event::PageBreak),
+            Block::Null => {}
+        }
+    }
+}
+
+impl ToEvents for Section {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        surround(events, Tag::Section, |e| {
+            surround(
+                e,
+                Tag::Header {
+                    size: self.header.size,
+                    anchor: self.header.anchor.clone(),
+                },
+                |e| self.header.line.to_events(e),
+            );
+            self.elements.to_events(e);
+        })
+    }
+}
+
+impl ToEvents for List {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        surround(
+            events,
+            Tag::List {
+                ordered: self.ordered,
+            },
+            |e| self.items.to_events(e),
+        )
+    }
+}
+
+impl ToEvents for ListItem {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        surround(
+            events,
+            Tag::ListItem {
+                checked: self.checked,
+                bullet: self.bullet,
+                attributes: self
+                    .metadata
+                    .as_ref()
+                    .map(|m| m.get_string_map())
+                    .unwrap_or_default(),
+            },
+            |e| {
+                self.text.to_events(e);
+                self.blocks.to_events(e);
+                if !self.children.is_empty() {
+                    surround(
+                        e,
+                        Tag::List {
+                            ordered: self.children[0].ordered,
+                        },
+                        |e| self.children.to_events(e),
+                    )
+                }
+            },
+        )
+    }
+}
+
+impl ToEvents for Table {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        surround(
+            events,
+            Tag::Table {
+                alignments: self.alignments.clone(),
+            },
+            |e| {
+                surround(e, Tag::TableHeader, |e| self.header.to_events(e));
+                self.rows.to_events(e);
+            },
+        )
+    }
+}
+
+impl ToEvents for Row {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        surround(events, Tag::Row, |e| {
+            self.cells.iter().for_each(|cell| {
+                surround(
+                    e,
+                    Tag::Cell {
+                        colspan: cell.colspan,
+                        rowspan: cell.rowspan,
+                    },
+                    |e| cell.text.to_events(e),
+                )
+            })
+        })
+    }
+}
+
+impl ToEvents for Line {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        match self {
+            Line::Text(text) => text.to_events(events),
+            Line::Ruler(_) => events.push_back(Event::Ruler),
+            Line::RefLink(link) => link.to_events(events),
+            Line::Anchor(anchor) => surround(
+                events,
+                Tag::Anchor {
+                    key: anchor.key.clone(),
+                },
+                |e| anchor.inner.to_events(e),
+            ),
+            Line::Centered(centered) => {
+                surround(events, Tag::Centered, |e| centered.line.to_events(e))
+            }
+            Line::BibEntry(entry) => events.push_back(Event::BibEntry(entry.key.clone())),
+            Line::AbbreviationDefinition(_) => {}
+            Line::FootnoteDefinition(definition) => {
+                let definition = definition.lock();
+                surround(
+                    events,
+                    Tag::FootnoteDefinition {
+                        label: definition.label.clone(),
+                    },
+                    |e| definition.text.to_events(e),
+                )
+            }
+        }
+    }
+}
+
+impl ToEvents for RefLink {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        surround(
+            events,
+            Tag::RefLink {
+                reference: self.reference.clone(),
+            },
+            |e| self.description.to_events(e),
+        )
+    }
+}
+
+impl ToEvents for TextLine {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        self.subtext.to_events(events)
+    }
+}
+
+impl ToEvents for Inline {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        match self {
+            Inline::Plain(plain) => events.push_back(Event::Text(plain.value.to_string())),
+            Inline::Bold(bold) => surround(events, Tag::Bold, |e| bold.value.to_events(e)),
+            Inline::Italic(italic) => surround(events, Tag::Italic, |e| italic.value.to_events(e)),
+            Inline::Underlined(underlined) => {
+                surround(events, Tag::Underlined, |e| underlined.value.to_events(e))
+            }
+            Inline::Striked(striked) => {
+                surround(events, Tag::Striked, |e| striked.value.to_events(e))
+            }
+            Inline::Styled(styled) => surround(
+                events,
+                Tag::Styled {
+                    attributes: styled.get_attributes(),
+                },
+                |e| styled.value.to_events(e),
+            ),
+            Inline::Highlight(highlight) => surround(
+                events,
+                Tag::Highlight {
+                    color: highlight.get_color(),
+                },
+                |e| highlight.value.to_events(e),
+            ),
+            Inline::Spoiler(spoiler) => {
+                surround(events, Tag::Spoiler, |e| spoiler.value.to_events(e))
+            }
+            Inline::Monospace(mono) => events.push_back(Event::Code(mono.value.clone())),
+            Inline::Superscript(sup) => {
+                surround(events, Tag::Superscript, |e| sup.value.to_events(e))
+            }
+            Inline::Subscript(sub) => surround(events, Tag::Subscript, |e| sub.value.to_events(e)),
+            Inline::Url(url) => url.to_events(events),
+            Inline::Image(image) => image.to_events(events),
+            Inline::Placeholder(placeholder) => {
+                events.push_back(Event::Placeholder(placeholder.read().unwrap().name.clone()))
+            }
+            Inline::Checkbox(checkbox) => events.push_back(Event::Checkbox(checkbox.value)),
+            Inline::Emoji(emoji) => events.push_back(Event::Emoji(emoji.value.clone())),
+            Inline::Colored(colored) => surround(
+                events,
+                Tag::Colored {
+                    color: colored.color.clone(),
+                },
+                |e| colored.value.to_events(e),
+            ),
+            Inline::Math(math) => events.push_back(Event::Math(math.expression.clone())),
+            Inline::BibReference(bib_ref) => {
+                events.push_back(Event::BibReference(bib_ref.read().unwrap().key.clone()))
+            }
+            Inline::GlossaryReference(gloss_ref) => {
+                events.push_back(Event::GlossaryReference(gloss_ref.lock().short.clone()))
+            }
+            Inline::FootnoteReference(footnote_ref) => {
+                events.push_back(Event::FootnoteReference(footnote_ref.lock().label.clone()))
+            }
+            Inline::TemplateVar(var) => {
+                events.push_back(Event::TemplateVariable(var.read().unwrap().name.clone()))
+            }
+            Inline::CharacterCode(code) => {
+                events.push_back(Event::CharacterCode(code.code.clone()))
+            }
+            Inline::LineBreak => events.push_back(Event::LineBreak),
+            Inline::Arrow(arrow) => events.push_back(Event::Arrow(arrow.clone())),
+            Inline::Anchor(anchor) => surround(
+                events,
+                Tag::Anchor {
+                    key: anchor.key.clone(),
+                },
+                |e| anchor.inner.to_events(e),
+            ),
+            Inline::Html(html) => events.push_back(Event::Html(html.value.clone())),
+            Inline::Comment(comment) => events.push_back(Event::Comment(comment.value.clone())),
+            Inline::Abbreviation(abbreviation) => surround(
+                events,
+                Tag::Abbreviation {
+                    long: abbreviation.long.clone(),
+                },
+                |e| e.push_back(Event::Text(abbreviation.short.clone())),
+            ),
+        }
+    }
+}
+
+impl ToEvents for Image {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        surround(
+            events,
+            Tag::Image {
+                url: self.url.url.clone(),
+            },
+            |e| {
+                if let Some(description) = &self.url.description {
+                    description.to_events(e)
+                }
+            },
+        )
+    }
+}
+
+impl ToEvents for Url {
+    fn to_events(&self, events: &mut VecDeque<Event>) {
+        surround(
+            events,
+            Tag::Link {
+                url: self.url.clone(),
+            },
+            |e| {
+                if let Some(description) = &self.description {
+                    description.to_events(e)
+                } else {
+                    e.push_back(Event::Text(self.url.clone()))
+                }
+            },
+        )
+    }
+}
@@ -0,0 +1,169 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::tokens::LB;
+use crate::elements::{Block, Document, Inline};
+use crate::parser::Parser;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+/// Adds syntax to the parser without changing the built in elements.
+/// Extensions are asked for a block or inline element before the built in
+/// syntax is tried and can change the document after it has been processed.
+pub trait Extension: Send + Sync {
+    /// Returns the name the extension is listed with in debug output
+    fn name(&self) -> &str {
+        "extension"
+    }
+
+    /// Parses a block at the start of the given text which begins at the start of a line.
+    /// Returns the block together with the number of bytes it spans or None
+    /// to let the next extension or the built in syntax parse the text.
+    fn try_parse_block(&self, _text: &str) -> Option<(Block, usize)> {
+        None
+    }
+
+    /// Parses an inline element at the start of the given text which ends with the current line.
+    /// Returns the element together with the number of bytes it spans or None
+    /// to let the next extension or the built in syntax parse the text.
+    fn try_parse_inline(&self, _text: &str) -> Option<(Inline, usize)> {
+        None
+    }
+
+    /// Changes the root document after it and its imports have been parsed and processed
+    fn post_process_document(&self, _document: &mut Document) {}
+}
+
+/// The extensions of the parser in the order they were registered
+#[derive(Clone, Default)]
+pub struct Extensions {
+    extensions: Vec<Arc<dyn Extension>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the extension after the already registered ones
+    pub fn register<E: Extension + 'static>(&mut self, extension: E) {
+        self.extensions.push(Arc::new(extension));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    /// Returns the first block an extension parses from the text
+    fn parse_block(&self, text: &str) -> Option<(Block, usize)> {
+        self.extensions
+            .iter()
+            .filter_map(|extension| extension.try_parse_block(text))
+            .find(|(_, length)| is_valid_length(text, *length))
+    }
+
+    /// Returns the first inline element an extension parses from the text
+    fn parse_inline(&self, text: &str) -> Option<(Inline, usize)> {
+        self.extensions
+            .iter()
+            .filter_map(|extension| extension.try_parse_inline(text))
+            .find(|(_, length)| is_valid_length(text, *length))
+    }
+
+    /// Lets every extension change the document in the order they were registered
+    pub(crate) fn post_process_document(&self, document: &mut Document) {
+        for extension in &self.extensions {
+            extension.post_process_document(document);
+        }
+    }
+}
+
+impl Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.extensions.iter().map(|extension| extension.name()))
+            .finish()
+    }
+}
+
+/// Elements that don't consume any text would be parsed over and over again
+fn is_valid_length(text: &str, length: usize) -> bool {
+    length > 0 && length <= text.len() && text.is_char_boundary(length)
+}
+
+impl Parser {
+    /// Parses a block with the registered extensions
+    pub(crate) fn parse_extension_block(&mut self) -> Option<Block> {
+        if self.options.extensions.is_empty() {
+            return None;
+        }
+        let start_index = self.ctm.get_index();
+        let (mut block, length) = self
+            .options
+            .extensions
+            .parse_block(self.ctm.get_remaining())?;
+        self.seek_bytes(start_index + length);
+
+        if let Some((_, span)) = block.get_attribute_target() {
+            *span = self.get_span(start_index);
+        }
+
+        Some(block)
+    }
+
+    /// Parses an inline element with the registered extensions
+    pub(crate) fn parse_extension_inline(&mut self) -> Option<Inline> {
+        if self.options.extensions.is_empty() {
+            return None;
+        }
+        let start_index = self.ctm.get_index();
+        let (inline, length) = self
+            .options
+            .extensions
+            .parse_inline(self.get_remaining_line())?;
+        self.seek_bytes(start_index + length);
+
+        Some(inline)
+    }
+
+    /// Checks if an extension parses a block at the current position
+    /// so that paragraphs end before it
+    pub(crate) fn check_extension_block(&self) -> bool {
+        !self.options.extensions.is_empty()
+            && self
+                .options
+                .extensions
+                .parse_block(self.ctm.get_remaining())
+                .is_some()
+    }
+
+    /// Checks if an extension parses an inline element at the current position
+    /// so that plain text ends before it
+    pub(crate) fn check_extension_inline(&self) -> bool {
+        !self.options.extensions.is_empty()
+            && self
+                .options
+                .extensions
+                .parse_inline(self.get_remaining_line())
+                .is_some()
+    }
+
+    /// Returns the text from the current position to the end of the line
+    fn get_remaining_line(&self) -> &str {
+        let remaining = self.ctm.get_remaining();
+
+        &remaining[..remaining.find(LB).unwrap_or(remaining.len())]
+    }
+
+    /// Moves the tape forward to the given byte index which may be the end of the text
+    fn seek_bytes(&mut self, index: usize) {
+        while self.ctm.get_index() < index {
+            if self.ctm.next_char().is_none() {
+                break;
+            }
+        }
+    }
+}
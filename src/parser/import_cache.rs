@@ -0,0 +1,263 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use super::incremental::RegisteredElements;
+use super::ParserOptions;
+use crate::elements::visitor::{walk_block, walk_document, walk_inline, walk_line, Visitor};
+use crate::elements::{Block, Document, Inline, Line};
+use crate::utils::caching::CacheStorage;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A cache of parsed imported documents on disk so that unchanged imports
+/// don't need to be parsed again by later runs or in watch mode.
+/// An entry is only used while the imported document, the files it imports
+/// and the parser options are unchanged.
+#[derive(Clone, Debug)]
+pub struct ImportCache {
+    storage: CacheStorage,
+}
+
+/// A cached document with the hashes it was created from.
+/// Entries are written with a reference to the document so that it doesn't need to be cloned
+#[derive(Serialize, Deserialize)]
+struct CacheEntry<D> {
+    version: String,
+    options: String,
+    hash: String,
+    /// The files imported by the document and its imports
+    dependencies: Vec<Dependency>,
+    document: D,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Dependency {
+    path: PathBuf,
+    hash: String,
+}
+
+impl ImportCache {
+    /// Creates a cache in the cache directory of snekdown
+    pub fn new() -> Self {
+        Self {
+            storage: CacheStorage::new(),
+        }
+    }
+
+    /// Removes the cached document of the given path
+    pub fn invalidate(&self, path: &Path) -> io::Result<()> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+        match self.storage.remove(&get_key(&path)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Returns the cached document of the path if it was parsed from the same text and options
+    /// and the files it imports haven't changed, together with the paths of these files
+    pub(crate) fn get(
+        &self,
+        path: &Path,
+        text: &str,
+        options: &ParserOptions,
+    ) -> Option<(Document, Vec<PathBuf>)> {
+        let key = get_key(&canonicalize(path, options));
+        if !self.storage.has_file(&key) {
+            return None;
+        }
+        let data = self.storage.read(&key).ok()?;
+        let entry: CacheEntry<Document> = serde_json::from_slice(&data).ok()?;
+
+        let valid = entry.version == env!("CARGO_PKG_VERSION")
+            && entry.options == get_options_hash(options)
+            && entry.hash == get_hash(text)
+            && entry.dependencies.iter().all(|dependency| {
+                options
+                    .resolver
+                    .read_to_string(&dependency.path)
+                    .map(|text| get_hash(&text) == dependency.hash)
+                    .unwrap_or(false)
+            });
+        if !valid {
+            return None;
+        }
+        log::debug!("Read the import {:?} from the cache", path);
+        let dependencies = entry.dependencies.into_iter().map(|d| d.path).collect();
+
+        Some((entry.document, dependencies))
+    }
+
+    /// Stores the parsed document of the path with the files it imports
+    pub(crate) fn insert(
+        &self,
+        path: &Path,
+        text: &str,
+        options: &ParserOptions,
+        dependencies: &[PathBuf],
+        document: &Document,
+    ) {
+        if !is_cacheable(document) {
+            return;
+        }
+        let dependencies = dependencies
+            .iter()
+            .map(|path| {
+                options
+                    .resolver
+                    .read_to_string(path)
+                    .map(|text| Dependency {
+                        path: path.clone(),
+                        hash: get_hash(&text),
+                    })
+            })
+            .collect::<io::Result<Vec<Dependency>>>();
+        let dependencies = match dependencies {
+            Ok(dependencies) => dependencies,
+            Err(_) => return,
+        };
+        let entry = CacheEntry {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            options: get_options_hash(options),
+            hash: get_hash(text),
+            dependencies,
+            document,
+        };
+        let result = serde_json::to_vec(&entry)
+            .map_err(io::Error::from)
+            .and_then(|data| {
+                let key = get_key(&canonicalize(path, options));
+                self.storage.write(&key, data)
+            });
+
+        if let Err(e) = result {
+            log::warn!("Failed to write the import {:?} to the cache: {}", path, e);
+        }
+    }
+}
+
+impl Default for ImportCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Document {
+    /// Registers the elements of a document that was read from the cache
+    /// in the managers of this document like they're registered when parsing
+    pub(crate) fn register_cached(&mut self, cached: &Document) {
+        let mut elements = RegisteredElements::default();
+        walk_document(&mut elements, cached);
+
+        self.placeholders.append(&mut elements.placeholders);
+        let mut footnotes = self.footnotes.lock();
+        elements
+            .footnote_definitions
+            .into_iter()
+            .for_each(|definition| footnotes.insert_definition(definition));
+        elements
+            .footnote_references
+            .into_iter()
+            .for_each(|reference| footnotes.insert_reference(reference));
+        let mut glossary = self.glossary.lock();
+        elements
+            .glossary_references
+            .into_iter()
+            .for_each(|reference| glossary.insert_reference(reference));
+        let mut images = self.images.lock();
+        elements
+            .images
+            .into_iter()
+            .for_each(|image| images.insert_image(image));
+        let mut abbreviations = self.abbreviations.lock();
+        elements
+            .abbreviation_definitions
+            .iter()
+            .for_each(|definition| abbreviations.add_definition(definition));
+        self.links
+            .lock()
+            .add_document(cached.path.as_deref(), &cached.elements);
+    }
+}
+
+/// Returns the name of the cache file for the imported document
+fn get_key(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.import.json", path.to_string_lossy()))
+}
+
+fn canonicalize(path: &Path, options: &ParserOptions) -> PathBuf {
+    options
+        .resolver
+        .canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn get_hash(text: &str) -> String {
+    let mut hasher = sha2::Sha256::default();
+    hasher.update(text.as_bytes());
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// Returns a hash of the options that change the parsed document
+fn get_options_hash(options: &ParserOptions) -> String {
+    let mut values = options.values.iter().collect::<Vec<_>>();
+    values.sort();
+
+    get_hash(&format!(
+        "{}{}{}{}{}{:?}{:?}{:?}{:?}{:?}",
+        options.autolinks,
+        options.smart_punctuation,
+        options.raw_html,
+        options.line_comments,
+        options.strict_values,
+        values,
+        options.import_roots,
+        options.import_errors,
+        options.nesting_limits,
+        options.normalization
+    ))
+}
+
+/// Returns if the document can be restored from the cache. Bibliography entries and references
+/// are stored in the bibliography of the importing document and can't be registered again.
+fn is_cacheable(document: &Document) -> bool {
+    let mut checker = CacheableChecker { cacheable: true };
+    walk_document(&mut checker, document);
+
+    checker.cacheable
+}
+
+struct CacheableChecker {
+    cacheable: bool,
+}
+
+impl Visitor for CacheableChecker {
+    fn visit_block(&mut self, block: &Block) {
+        if let Block::Import(import) = block {
+            if import.anchor.read().unwrap().is_lazy() {
+                self.cacheable = false;
+            }
+        }
+        walk_block(self, block)
+    }
+
+    fn visit_line(&mut self, line: &Line) {
+        if let Line::BibEntry(_) = line {
+            self.cacheable = false;
+        }
+        walk_line(self, line)
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        if let Inline::BibReference(_) = inline {
+            self.cacheable = false;
+        }
+        walk_inline(self, inline)
+    }
+}
@@ -0,0 +1,251 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+#[cfg(feature = "remote-imports")]
+use crate::utils::caching::CacheStorage;
+#[cfg(feature = "remote-imports")]
+use crate::utils::downloads::download_path;
+#[cfg(all(feature = "remote-imports", feature = "tokio"))]
+use crate::utils::downloads::download_path_async;
+#[cfg(feature = "remote-imports")]
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+#[cfg(feature = "remote-imports")]
+use std::time::Duration;
+
+/// The time remote imports are read from the cache before they are fetched again
+#[cfg(feature = "remote-imports")]
+const DEFAULT_REMOTE_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// The future returned by [ImportResolver::read_async]
+#[cfg(feature = "tokio")]
+pub type ReadFuture<'a> = Pin<Box<dyn futures::Future<Output = io::Result<String>> + Send + 'a>>;
+
+/// Loads the content of the parsed document and its imports.
+/// Implement this to parse documents that don't live on the filesystem.
+pub trait ImportResolver: Debug + Send + Sync {
+    /// Returns if there's a file at the given path
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Returns the text content of the file at the given path
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+
+    /// Returns the canonical form of the given path
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(path.to_path_buf())
+    }
+
+    /// Returns the text content of the file at the given path without blocking the executor.
+    /// Reads the file with [ImportResolver::read_to_string] by default.
+    #[cfg(feature = "tokio")]
+    fn read_async<'a>(&'a self, path: &'a Path) -> ReadFuture<'a> {
+        Box::pin(futures::future::ready(self.read_to_string(path)))
+    }
+}
+
+/// The default resolver that reads files from the filesystem
+#[derive(Clone, Debug, Default)]
+pub struct FileSystemResolver;
+
+impl ImportResolver for FileSystemResolver {
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    #[cfg(feature = "tokio")]
+    fn read_async<'a>(&'a self, path: &'a Path) -> ReadFuture<'a> {
+        Box::pin(tokio::fs::read_to_string(path))
+    }
+}
+
+/// A resolver that serves files from memory
+#[derive(Clone, Debug, Default)]
+pub struct MemoryResolver {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemoryResolver {
+    /// Creates a new empty memory resolver
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file with the given content
+    pub fn add_file<P: Into<PathBuf>, S: ToString>(mut self, path: P, content: S) -> Self {
+        self.files.insert(path.into(), content.to_string());
+
+        self
+    }
+}
+
+impl ImportResolver for MemoryResolver {
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        self.files.get(path).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found", path.to_string_lossy()),
+            )
+        })
+    }
+}
+
+/// A resolver that fetches http(s) urls and caches the responses on disk.
+/// All other paths are loaded with the inner resolver
+#[cfg(feature = "remote-imports")]
+#[derive(Debug)]
+pub struct RemoteResolver<R: ImportResolver> {
+    inner: R,
+    cache: CacheStorage,
+    ttl: Duration,
+    fetched: Mutex<HashMap<PathBuf, String>>,
+}
+
+#[cfg(feature = "remote-imports")]
+impl<R: ImportResolver> RemoteResolver<R> {
+    /// Creates a new remote resolver wrapping the given resolver
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            cache: CacheStorage::new(),
+            ttl: DEFAULT_REMOTE_TTL,
+            fetched: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the time a cached response is used before the url is fetched again
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+
+        self
+    }
+
+    /// Returns the content of the url from memory, the cache or the remote
+    fn fetch(&self, path: &Path) -> io::Result<String> {
+        if let Some(content) = self.read_cached(path) {
+            return Ok(content);
+        }
+        let data = download_path(path.to_string_lossy().to_string());
+
+        self.store(path, data)
+    }
+
+    /// Returns the content of the url from memory, the cache or the remote
+    /// without blocking the executor
+    #[cfg(feature = "tokio")]
+    async fn fetch_async(&self, path: &Path) -> io::Result<String> {
+        if let Some(content) = self.read_cached(path) {
+            return Ok(content);
+        }
+        let data = download_path_async(path.to_string_lossy().to_string()).await;
+
+        self.store(path, data)
+    }
+
+    /// Returns the content of the url if it was already fetched
+    /// or there's a cached response that isn't older than the ttl
+    fn read_cached(&self, path: &Path) -> Option<String> {
+        let path = path.to_path_buf();
+        if let Some(content) = self.fetched.lock().get(&path) {
+            return Some(content.clone());
+        }
+        let is_fresh = self
+            .cache
+            .get_age(&path)
+            .map(|age| age < self.ttl)
+            .unwrap_or(false);
+
+        if is_fresh {
+            let data = self.cache.read(&path).ok()?;
+            log::debug!("Read {:?} from cache.", path);
+            let content = String::from_utf8(data).ok()?;
+            self.fetched.lock().insert(path, content.clone());
+
+            Some(content)
+        } else {
+            None
+        }
+    }
+
+    /// Stores the fetched data of the url in memory and in the cache
+    fn store(&self, path: &Path, data: Option<Vec<u8>>) -> io::Result<String> {
+        let path = path.to_path_buf();
+        let data = data.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Failed to fetch {}", path.to_string_lossy()),
+            )
+        })?;
+        self.cache
+            .write(&path, &data)
+            .unwrap_or_else(|_| log::warn!("Failed to write file to cache: {:?}", path));
+        let content =
+            String::from_utf8(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.fetched.lock().insert(path, content.clone());
+
+        Ok(content)
+    }
+}
+
+#[cfg(feature = "remote-imports")]
+impl<R: ImportResolver> ImportResolver for RemoteResolver<R> {
+    fn exists(&self, path: &Path) -> bool {
+        if is_url(path) {
+            self.fetch(path).is_ok()
+        } else {
+            self.inner.exists(path)
+        }
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        if is_url(path) {
+            self.fetch(path)
+        } else {
+            self.inner.read_to_string(path)
+        }
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        if is_url(path) {
+            Ok(path.to_path_buf())
+        } else {
+            self.inner.canonicalize(path)
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    fn read_async<'a>(&'a self, path: &'a Path) -> ReadFuture<'a> {
+        if is_url(path) {
+            Box::pin(self.fetch_async(path))
+        } else {
+            self.inner.read_async(path)
+        }
+    }
+}
+
+/// Returns if the given path is a http(s) url
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .map(|p| p.starts_with("http://") || p.starts_with("https://"))
+        .unwrap_or(false)
+}
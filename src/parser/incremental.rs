@@ -0,0 +1,639 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use super::block::ParseBlock;
+use super::{Parser, ParserOptions};
+use crate::elements::transforms::{SmartPunctuation, Transform};
+use crate::elements::visitor::{
+    walk_block, walk_block_mut, walk_document, walk_inline, walk_inline_mut, walk_line,
+    walk_line_mut, Visitor, VisitorMut,
+};
+use crate::elements::{Block, Document, Inline, Line, ListItem, Placeholder, Section, Span};
+use crate::references::abbreviations::AbbreviationDefinition;
+use crate::references::footnotes::{FootnoteDefinition, FootnoteReference};
+use crate::references::glossary::GlossaryReference;
+use crate::references::links::LinkManager;
+use crate::utils::image_converting::PendingImage;
+use parking_lot::Mutex;
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+/// A change of the source text of a document
+#[derive(Clone, Debug)]
+pub struct TextEdit {
+    /// The byte range of the replaced text in the previous source
+    pub range: Range<usize>,
+    /// The text the range is replaced with
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: &str) -> Self {
+        Self {
+            range,
+            text: text.to_string(),
+        }
+    }
+}
+
+/// The source text of a root document and the options it was parsed with
+#[derive(Clone, Debug)]
+pub(crate) struct DocumentSource {
+    text: String,
+    options: ParserOptions,
+    /// Documents with imports are always parsed again completely
+    /// as the content of the imported documents is merged into their sections
+    incremental: bool,
+}
+
+impl DocumentSource {
+    /// Keeps a copy of the options before the settings of the document are applied to them
+    pub(crate) fn new(text: String, options: &ParserOptions) -> Self {
+        Self {
+            text,
+            options: parser_options(options),
+            incremental: true,
+        }
+    }
+
+    /// Checks if the parsed document can be parsed again partially
+    pub(crate) fn scan(mut self, document: &Document) -> Self {
+        let mut elements = GlobalElements::default();
+        walk_document(&mut elements, document);
+        self.incremental = !elements.imports;
+
+        self
+    }
+}
+
+impl Document {
+    /// Applies the edit to the source of the document and parses the changed part again.
+    /// Only the innermost section that contains the edit is parsed again, all other blocks
+    /// are kept and the spans behind the edit are moved. The whole document is parsed again
+    /// if the edit changes the structure of the sections around it, if it changes imports,
+    /// bibliography or footnote references, abbreviations or settings,
+    /// or if the document imports other documents.
+    /// Returns false without changing the document if it wasn't created by the parser
+    /// or the range isn't valid for its source.
+    pub fn reparse_range(&mut self, edit: TextEdit) -> bool {
+        let source = match &self.source {
+            Some(source) if is_valid_range(&source.text, &edit.range) => source,
+            _ => return false,
+        };
+        let replacement = source.options.normalization.normalize(edit.text);
+        let delta = replacement.len() as isize - edit.range.len() as isize;
+        let mut text = source.text.clone();
+        text.replace_range(edit.range.clone(), &replacement);
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        // an edit can turn the start of the document into front matter or back into sections
+        let front_matter_end = get_front_matter_end(&source.text).max(get_front_matter_end(&text));
+        let source = DocumentSource {
+            text,
+            ..source.as_ref().clone()
+        };
+
+        if source.incremental
+            && edit.range.start >= front_matter_end
+            && self.reparse_section(&source, &edit.range, delta)
+        {
+            self.source = Some(Box::new(source));
+        } else {
+            log::debug!("Parsing the whole document again");
+            let mut parser = Parser::from_text(source.text, parser_options(&source.options));
+            *self = parser.parse();
+        }
+
+        true
+    }
+
+    /// Parses the innermost section around the range again that can be replaced
+    /// without changing the rest of the document.
+    /// Returns false if the whole document needs to be parsed again.
+    fn reparse_section(
+        &mut self,
+        source: &DocumentSource,
+        range: &Range<usize>,
+        delta: isize,
+    ) -> bool {
+        let path = find_sections(&self.elements, range);
+
+        for depth in (1..=path.len()).rev() {
+            let path = &path[..depth];
+            let new_section = match self.parse_section_again(source, path, delta) {
+                Some(section) => section,
+                None => continue,
+            };
+            let old_section = section_chain(&self.elements, path).pop().unwrap();
+            if has_global_elements(old_section) || has_global_elements(&new_section) {
+                return false;
+            }
+            log::debug!("Parsing section '{}' again", new_section.header.anchor);
+
+            let mut shift = SpanShift::new(&source.text, old_section.span.end, delta);
+            self.elements
+                .iter_mut()
+                .for_each(|block| shift.visit_block(block));
+            if let Some(section) = section_mut(&mut self.elements, path) {
+                *section = new_section;
+            }
+            self.register_elements();
+            self.post_process();
+            if source.options.smart_punctuation {
+                SmartPunctuation.apply(self);
+            }
+            source.options.extensions.post_process_document(self);
+            source
+                .options
+                .report_diagnostics(std::mem::take(&mut self.diagnostics));
+
+            return true;
+        }
+
+        false
+    }
+
+    /// Parses the section at the path with the new source.
+    /// Returns None if the section doesn't end at the same position of the source anymore.
+    fn parse_section_again(
+        &self,
+        source: &DocumentSource,
+        path: &[usize],
+        delta: isize,
+    ) -> Option<Section> {
+        let chain = section_chain(&self.elements, path);
+        let (section, parents) = chain.split_last()?;
+        if !is_self_contained(section) {
+            return None;
+        }
+        let mut options = parser_options(&source.options);
+        options.document = self.create_view();
+        let mut parser = Parser::from_text(source.text.clone(), options);
+        parser.sections = parents.iter().map(|s| s.header.size).collect();
+        parser.section_nesting = parser.sections.last().copied().unwrap_or(0);
+        parser.section_anchors = parents.iter().map(|s| s.header.anchor.clone()).collect();
+        parser.ctm.rewind(section.span.start);
+
+        // the section starts with the attributes given before its header
+        let new_section = match parser.parse_block().ok()? {
+            Block::Section(section) => section,
+            _ => return None,
+        };
+        let end = section.span.end.saturating_add_signed(delta);
+
+        (new_section.span.start == section.span.start && new_section.span.end == end)
+            .then_some(new_section)
+    }
+
+    /// Creates a document that registers parsed elements in the managers of this document
+    fn create_view(&self) -> Document {
+        Document {
+            is_root: false,
+            config: Arc::clone(&self.config),
+            bibliography: self.bibliography.clone(),
+            downloads: Arc::clone(&self.downloads),
+            images: Arc::clone(&self.images),
+            glossary: Arc::clone(&self.glossary),
+            footnotes: Arc::clone(&self.footnotes),
+            abbreviations: Arc::clone(&self.abbreviations),
+            links: Arc::clone(&self.links),
+            placeholder_handlers: self.placeholder_handlers.clone(),
+            container_handlers: self.container_handlers.clone(),
+            strings: Arc::clone(&self.strings),
+            ..Document::new()
+        }
+    }
+
+    /// Registers the elements of the document in its managers again
+    /// so that removed elements aren't processed anymore
+    fn register_elements(&mut self) {
+        let mut elements = RegisteredElements::default();
+        walk_document(&mut elements, self);
+
+        self.placeholders = elements.placeholders;
+        let mut footnotes = self.footnotes.lock();
+        footnotes.clear();
+        elements
+            .footnote_definitions
+            .into_iter()
+            .for_each(|definition| footnotes.insert_definition(definition));
+        elements
+            .footnote_references
+            .into_iter()
+            .for_each(|reference| footnotes.insert_reference(reference));
+        let mut glossary = self.glossary.lock();
+        glossary.clear_references();
+        elements
+            .glossary_references
+            .into_iter()
+            .for_each(|reference| glossary.insert_reference(reference));
+        let mut images = self.images.lock();
+        images.clear();
+        elements
+            .images
+            .into_iter()
+            .for_each(|image| images.insert_image(image));
+        *self.links.lock() = LinkManager::new();
+    }
+}
+
+/// Returns a copy of the options with its own diagnostics and settings
+fn parser_options(options: &ParserOptions) -> ParserOptions {
+    ParserOptions {
+        path: options.path.clone(),
+        ..options.detached()
+    }
+}
+
+fn is_valid_range(text: &str, range: &Range<usize>) -> bool {
+    range.start <= range.end
+        && range.end <= text.len()
+        && text.is_char_boundary(range.start)
+        && text.is_char_boundary(range.end)
+}
+
+/// Returns the end of the text at the start of the document that can become its front matter
+fn get_front_matter_end(text: &str) -> usize {
+    if !text.starts_with("---") {
+        return 0;
+    }
+
+    text[3..]
+        .find("\n---")
+        .map(|index| index + 3 + "\n---".len())
+        .unwrap_or(text.len())
+}
+
+/// Returns the indices of the nested sections that contain the range, outermost first
+fn find_sections(mut blocks: &[Block], range: &Range<usize>) -> Vec<usize> {
+    let mut path = Vec::new();
+
+    while let Some((index, section)) =
+        blocks
+            .iter()
+            .enumerate()
+            .find_map(|(index, block)| match block {
+                Block::Section(section)
+                    if section.span.start <= range.start && range.end <= section.span.end =>
+                {
+                    Some((index, section))
+                }
+                _ => None,
+            })
+    {
+        path.push(index);
+        blocks = &section.elements;
+    }
+
+    path
+}
+
+/// Returns the sections along the path, outermost first
+fn section_chain<'a>(mut blocks: &'a [Block], path: &[usize]) -> Vec<&'a Section> {
+    path.iter()
+        .map_while(|index| match blocks.get(*index) {
+            Some(Block::Section(section)) => {
+                blocks = &section.elements;
+                Some(section)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn section_mut<'a>(mut blocks: &'a mut [Block], path: &[usize]) -> Option<&'a mut Section> {
+    let (index, parents) = path.split_last()?;
+
+    for parent in parents {
+        blocks = match blocks.get_mut(*parent)? {
+            Block::Section(section) => &mut section.elements,
+            _ => return None,
+        };
+    }
+
+    match blocks.get_mut(*index)? {
+        Block::Section(section) => Some(section),
+        _ => None,
+    }
+}
+
+/// Returns if all elements of the section were parsed from its source.
+/// Sections can contain elements that followed them in the source
+/// when their order was changed after parsing.
+fn is_self_contained(section: &Section) -> bool {
+    section
+        .elements
+        .iter()
+        .filter_map(get_block_span)
+        .all(|span| span.start >= section.span.start && span.end <= section.span.end)
+}
+
+/// Returns the span of the block or None for null blocks
+pub(crate) fn get_block_span(block: &Block) -> Option<Span> {
+    match block {
+        Block::Section(section) => Some(section.span),
+        Block::Paragraph(paragraph) => Some(paragraph.span),
+        Block::List(list) => Some(list.span),
+        Block::Table(table) => Some(table.span),
+        Block::CodeBlock(code_block) => Some(code_block.span),
+        Block::Diagram(diagram) => Some(diagram.span),
+        Block::Html(html) => Some(html.span),
+        Block::Comment(comment) => Some(comment.span),
+        Block::MathBlock(math_block) => Some(math_block.span),
+        Block::Quote(quote) => Some(quote.span),
+        Block::Aligned(aligned) => Some(aligned.span),
+        Block::Columns(columns) => Some(columns.span),
+        Block::CustomContainer(container) => Some(container.span),
+        Block::PageBreak(page_break) => Some(page_break.span),
+        Block::DefinitionList(list) => Some(list.span),
+        Block::Figure(figure) => Some(figure.span),
+        Block::Import(import) => Some(import.span),
+        Block::Placeholder(placeholder) => Some(placeholder.read().unwrap().span),
+        Block::Null => None,
+    }
+}
+
+fn has_global_elements(section: &Section) -> bool {
+    let mut elements = GlobalElements::default();
+    elements.visit_line(&section.header.line);
+    section
+        .elements
+        .iter()
+        .for_each(|block| elements.visit_block(block));
+
+    elements.found
+}
+
+fn is_definition(placeholder: &RwLock<Placeholder>) -> bool {
+    let name = placeholder.read().unwrap().name.to_lowercase();
+
+    name == "set" || name.starts_with("set:")
+}
+
+/// Finds elements that change the state of the whole document when they're parsed
+/// and can't be removed again by parsing a part of the document
+#[derive(Default)]
+struct GlobalElements {
+    imports: bool,
+    found: bool,
+}
+
+impl Visitor for GlobalElements {
+    fn visit_block(&mut self, block: &Block) {
+        match block {
+            Block::Import(_) => {
+                self.imports = true;
+                self.found = true;
+            }
+            // imports of stylesheets, bibliographies and glossaries
+            Block::Null => self.found = true,
+            Block::Placeholder(placeholder) => self.found |= is_definition(placeholder),
+            _ => walk_block(self, block),
+        }
+    }
+
+    fn visit_line(&mut self, line: &Line) {
+        match line {
+            Line::BibEntry(_) | Line::AbbreviationDefinition(_) => self.found = true,
+            _ => walk_line(self, line),
+        }
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::BibReference(_) | Inline::FootnoteReference(_) => self.found = true,
+            Inline::Placeholder(placeholder) => self.found |= is_definition(placeholder),
+            _ => walk_inline(self, inline),
+        }
+    }
+}
+
+/// Collects the elements that are registered in the managers of the document while parsing.
+/// Placeholder values are skipped as they're created when processing the document.
+#[derive(Default)]
+pub(crate) struct RegisteredElements {
+    pub(crate) placeholders: Vec<Arc<RwLock<Placeholder>>>,
+    pub(crate) footnote_definitions: Vec<Arc<Mutex<FootnoteDefinition>>>,
+    pub(crate) footnote_references: Vec<Arc<Mutex<FootnoteReference>>>,
+    pub(crate) glossary_references: Vec<Arc<Mutex<GlossaryReference>>>,
+    pub(crate) abbreviation_definitions: Vec<AbbreviationDefinition>,
+    pub(crate) images: Vec<Arc<Mutex<PendingImage>>>,
+}
+
+impl Visitor for RegisteredElements {
+    fn visit_block(&mut self, block: &Block) {
+        match block {
+            Block::Placeholder(placeholder) => self.placeholders.push(Arc::clone(placeholder)),
+            Block::Figure(figure) => {
+                self.images.push(Arc::clone(&figure.image.image_data));
+                walk_block(self, block);
+            }
+            _ => walk_block(self, block),
+        }
+    }
+
+    fn visit_line(&mut self, line: &Line) {
+        match line {
+            Line::FootnoteDefinition(definition) => {
+                self.footnote_definitions.push(Arc::clone(definition))
+            }
+            Line::AbbreviationDefinition(definition) => {
+                self.abbreviation_definitions.push(definition.clone())
+            }
+            _ => {}
+        }
+        walk_line(self, line);
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Placeholder(placeholder) => self.placeholders.push(Arc::clone(placeholder)),
+            Inline::Image(image) => self.images.push(Arc::clone(&image.image_data)),
+            Inline::GlossaryReference(reference) => {
+                self.glossary_references.push(Arc::clone(reference))
+            }
+            Inline::FootnoteReference(reference) => {
+                self.footnote_references.push(Arc::clone(reference))
+            }
+            _ => walk_inline(self, inline),
+        }
+    }
+}
+
+/// Moves the spans behind the section that was parsed again by the difference in length of the edit
+struct SpanShift<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+    /// The end of the section in the previous source
+    from: usize,
+    delta: isize,
+}
+
+impl<'a> SpanShift<'a> {
+    fn new(text: &'a str, from: usize, delta: isize) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+
+        Self {
+            text,
+            line_starts,
+            from,
+            delta,
+        }
+    }
+
+    fn shift(&self, span: &mut Span) {
+        if span.end >= self.from {
+            span.end = span.end.saturating_add_signed(self.delta);
+        }
+        if span.start >= self.from {
+            span.start = span.start.saturating_add_signed(self.delta);
+            span.line = self
+                .line_starts
+                .partition_point(|start| *start <= span.start)
+                .saturating_sub(1);
+            span.column = self
+                .text
+                .get(self.line_starts[span.line]..span.start)
+                .map(|line| line.chars().count())
+                .unwrap_or_default();
+        }
+    }
+
+    fn shift_items(&self, items: &mut [ListItem]) {
+        for item in items {
+            self.shift(&mut item.span);
+            self.shift_items(&mut item.children);
+        }
+    }
+}
+
+impl VisitorMut for SpanShift<'_> {
+    fn visit_block(&mut self, block: &mut Block) {
+        match block {
+            Block::Section(section) => {
+                self.shift(&mut section.span);
+                self.shift(&mut section.header.span);
+            }
+            Block::Paragraph(paragraph) => self.shift(&mut paragraph.span),
+            Block::List(list) => {
+                self.shift(&mut list.span);
+                self.shift_items(&mut list.items);
+            }
+            Block::Table(table) => {
+                self.shift(&mut table.span);
+                self.shift(&mut table.header.span);
+                table
+                    .rows
+                    .iter_mut()
+                    .for_each(|row| self.shift(&mut row.span));
+            }
+            Block::CodeBlock(code_block) => self.shift(&mut code_block.span),
+            Block::Diagram(diagram) => self.shift(&mut diagram.span),
+            Block::Html(html) => self.shift(&mut html.span),
+            Block::Comment(comment) => self.shift(&mut comment.span),
+            Block::MathBlock(math_block) => self.shift(&mut math_block.span),
+            Block::Quote(quote) => self.shift(&mut quote.span),
+            Block::Aligned(aligned) => self.shift(&mut aligned.span),
+            Block::Columns(columns) => self.shift(&mut columns.span),
+            Block::CustomContainer(container) => self.shift(&mut container.span),
+            Block::PageBreak(page_break) => self.shift(&mut page_break.span),
+            Block::DefinitionList(list) => {
+                self.shift(&mut list.span);
+                for item in &mut list.items {
+                    self.shift(&mut item.span);
+                    self.shift(&mut item.term.span);
+                    item.definitions
+                        .iter_mut()
+                        .for_each(|definition| self.shift(&mut definition.span));
+                }
+            }
+            Block::Figure(figure) => {
+                self.shift(&mut figure.span);
+                self.shift(&mut figure.image.span);
+                self.shift(&mut figure.image.url.span);
+                self.shift(&mut figure.caption.span);
+            }
+            // the spans of imported documents and placeholder values belong to other sources
+            Block::Import(import) => return self.shift(&mut import.span),
+            Block::Placeholder(placeholder) => {
+                return self.shift(&mut placeholder.write().unwrap().span)
+            }
+            Block::Null => {}
+        }
+        walk_block_mut(self, block);
+    }
+
+    fn visit_line(&mut self, line: &mut Line) {
+        match line {
+            Line::Text(text) => self.shift(&mut text.span),
+            Line::Ruler(ruler) => self.shift(&mut ruler.span),
+            Line::RefLink(link) => {
+                self.shift(&mut link.span);
+                self.shift(&mut link.description.span);
+            }
+            Line::Anchor(anchor) => self.shift(&mut anchor.span),
+            Line::Centered(centered) => {
+                self.shift(&mut centered.span);
+                self.shift(&mut centered.line.span);
+            }
+            Line::BibEntry(entry) => self.shift(&mut entry.span),
+            Line::FootnoteDefinition(definition) => {
+                let mut definition = definition.lock();
+                self.shift(&mut definition.span);
+                self.shift(&mut definition.text.span);
+            }
+            Line::AbbreviationDefinition(definition) => self.shift(&mut definition.span),
+        }
+        walk_line_mut(self, line);
+    }
+
+    fn visit_inline(&mut self, inline: &mut Inline) {
+        match inline {
+            Inline::Plain(plain) => self.shift(&mut plain.span),
+            Inline::Bold(bold) => self.shift(&mut bold.span),
+            Inline::Italic(italic) => self.shift(&mut italic.span),
+            Inline::Underlined(underlined) => self.shift(&mut underlined.span),
+            Inline::Striked(striked) => self.shift(&mut striked.span),
+            Inline::Monospace(monospace) => self.shift(&mut monospace.span),
+            Inline::Superscript(superscript) => self.shift(&mut superscript.span),
+            Inline::Subscript(subscript) => self.shift(&mut subscript.span),
+            Inline::Url(url) => self.shift(&mut url.span),
+            Inline::Image(image) => {
+                self.shift(&mut image.span);
+                self.shift(&mut image.url.span);
+            }
+            Inline::Checkbox(checkbox) => self.shift(&mut checkbox.span),
+            Inline::Emoji(emoji) => self.shift(&mut emoji.span),
+            Inline::Colored(colored) => self.shift(&mut colored.span),
+            Inline::Math(math) => self.shift(&mut math.span),
+            Inline::BibReference(reference) => self.shift(&mut reference.write().unwrap().span),
+            Inline::GlossaryReference(reference) => self.shift(&mut reference.lock().span),
+            Inline::FootnoteReference(reference) => {
+                let mut reference = reference.lock();
+                self.shift(&mut reference.span);
+                self.shift(&mut reference.citation.write().unwrap().span);
+            }
+            Inline::CharacterCode(code) => self.shift(&mut code.span),
+            Inline::Anchor(anchor) => self.shift(&mut anchor.span),
+            Inline::Html(html) => self.shift(&mut html.span),
+            Inline::Comment(comment) => self.shift(&mut comment.span),
+            Inline::Abbreviation(abbreviation) => self.shift(&mut abbreviation.span),
+            Inline::Spoiler(spoiler) => self.shift(&mut spoiler.span),
+            Inline::Highlight(highlight) => self.shift(&mut highlight.span),
+            Inline::Styled(styled) => self.shift(&mut styled.span),
+            Inline::Placeholder(placeholder) => {
+                return self.shift(&mut placeholder.write().unwrap().span)
+            }
+            Inline::TemplateVar(variable) => {
+                return self.shift(&mut variable.write().unwrap().span)
+            }
+            Inline::LineBreak | Inline::Arrow(_) => {}
+        }
+        walk_inline_mut(self, inline);
+    }
+}
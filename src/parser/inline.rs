@@ -9,39 +9,55 @@ use crate::elements::tokens::*;
 use crate::elements::BibReference;
 use crate::elements::*;
 use crate::parser::block::ParseBlock;
+use crate::references::footnotes::FootnoteReference;
 use crate::references::glossary::GlossaryDisplay;
 use crate::references::glossary::GlossaryReference;
+use crate::references::placeholders::BLOCK_PLACEHOLDERS;
 use crate::references::templates::{GetTemplateVariables, Template, TemplateVariable};
 use crate::utils::parsing::remove_single_backlslash;
 use crate::Parser;
-use bibliographix::references::bib_reference::BibRef;
 use parking_lot::Mutex;
+use regex::Regex;
 use std::collections::HashMap;
-use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
 pub(crate) trait ParseInline {
     fn parse_surrounded(&mut self, surrounding: &char) -> ParseResult<Vec<Inline>>;
+    fn parse_surrounded_sequence(&mut self, surrounding: &[char]) -> ParseResult<Vec<Inline>>;
     fn parse_inline(&mut self) -> ParseResult<Inline>;
+    fn parse_inline_element(&mut self) -> ParseResult<Inline>;
     fn parse_image(&mut self) -> ParseResult<Image>;
     fn parse_url(&mut self, short_syntax: bool) -> ParseResult<Url>;
+    fn parse_autolink(&mut self) -> ParseResult<Url>;
     fn parse_checkbox(&mut self) -> ParseResult<Checkbox>;
     fn parse_bold(&mut self) -> ParseResult<BoldText>;
     fn parse_italic(&mut self) -> ParseResult<ItalicText>;
     fn parse_striked(&mut self) -> ParseResult<StrikedText>;
+    fn parse_spoiler(&mut self) -> ParseResult<SpoilerText>;
+    fn parse_highlight(&mut self) -> ParseResult<HighlightText>;
     fn parse_math(&mut self) -> ParseResult<Math>;
+    fn parse_tex_math(&mut self) -> ParseResult<Math>;
     fn parse_monospace(&mut self) -> ParseResult<MonospaceText>;
     fn parse_underlined(&mut self) -> ParseResult<UnderlinedText>;
     fn parse_superscript(&mut self) -> ParseResult<SuperscriptText>;
+    fn parse_subscript(&mut self) -> ParseResult<SubscriptText>;
     fn parse_emoji(&mut self) -> ParseResult<Emoji>;
     fn parse_colored(&mut self) -> ParseResult<Colored>;
     fn parse_bibref(&mut self) -> ParseResult<Arc<RwLock<BibReference>>>;
+    fn parse_citation(&mut self) -> ParseResult<Arc<RwLock<BibReference>>>;
+    fn parse_footnote_reference(&mut self) -> ParseResult<Arc<Mutex<FootnoteReference>>>;
     fn parse_template_variable(&mut self) -> ParseResult<Arc<RwLock<TemplateVariable>>>;
     fn parse_glossary_reference(&mut self) -> ParseResult<Arc<Mutex<GlossaryReference>>>;
     fn parse_plain(&mut self) -> ParseResult<PlainText>;
+    fn parse_hard_break(&mut self) -> ParseResult<()>;
+    fn parse_html(&mut self) -> ParseResult<Html>;
+    fn parse_comment(&mut self) -> ParseResult<Comment>;
     fn parse_inline_metadata(&mut self) -> ParseResult<InlineMetadata>;
-    fn parse_metadata_pair(&mut self) -> ParseResult<(String, MetadataValue)>;
+    fn parse_attributes(&mut self) -> ParseResult<InlineMetadata>;
+    fn parse_metadata_block(&mut self, open: char, close: char) -> ParseResult<InlineMetadata>;
+    fn parse_metadata_pair(&mut self, close: char) -> ParseResult<(String, MetadataValue)>;
     fn parse_placeholder(&mut self) -> ParseResult<Arc<RwLock<Placeholder>>>;
+    fn parse_inline_placeholder(&mut self) -> ParseResult<Arc<RwLock<Placeholder>>>;
     fn parse_template(&mut self) -> ParseResult<Template>;
     fn parse_character_code(&mut self) -> ParseResult<CharacterCode>;
     fn parse_arrow(&mut self) -> ParseResult<Arrow>;
@@ -70,16 +86,94 @@ impl ParseInline for Parser {
         Ok(inline)
     }
 
+    /// parses Inline surrounded by a sequence of characters.
+    /// The closing sequence must not follow whitespace
+    fn parse_surrounded_sequence(&mut self, surrounding: &[char]) -> ParseResult<Vec<Inline>> {
+        let start_index = self.ctm.get_index();
+        self.ctm.assert_sequence(surrounding, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let mut inline = vec![self.parse_inline()?];
+
+        while !self.ctm.check_sequence(surrounding) {
+            if let Ok(result) = self.parse_inline() {
+                inline.push(result);
+            } else {
+                return Err(self.ctm.rewind_with_error(start_index).into());
+            }
+        }
+        self.ctm.rewind_chars(surrounding.len());
+        if self.ctm.check_any(WHITESPACE) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        for _ in 0..(surrounding.len() + 1) {
+            self.ctm.seek_one()?;
+        }
+
+        Ok(inline)
+    }
+
     /// parses Inline, the formatting parts of a line (Text)
+    /// and the metadata that can follow formatted spans
     fn parse_inline(&mut self) -> ParseResult<Inline> {
+        let start_index = self.ctm.get_index();
+        let max_depth = self.options.nesting_limits.max_inline_depth;
+        if self.inline_depth >= max_depth {
+            return Err(self.nesting_limit_exceeded("Inline elements", max_depth, start_index));
+        }
+        self.inline_depth += 1;
+        let inline = self.parse_inline_element();
+        self.inline_depth -= 1;
+        let inline = inline?;
+
+        if !is_styleable(&inline) {
+            return Ok(inline);
+        }
+        if let Ok(metadata) = self
+            .parse_inline_metadata()
+            .or_else(|_| self.parse_attributes())
+        {
+            log::trace!("Inline::Styled");
+            Ok(Inline::Styled(StyledText {
+                value: Box::new(inline),
+                metadata,
+                span: self.get_span(start_index),
+            }))
+        } else {
+            Ok(inline)
+        }
+    }
+
+    /// parses a single inline element
+    fn parse_inline_element(&mut self) -> ParseResult<Inline> {
+        if !self.ctm.check_char(&LB) {
+            if let Some(inline) = self.parse_extension_inline() {
+                log::trace!("Inline from extension");
+                return Ok(inline);
+            }
+        }
         if self.parse_variables {
             if let Ok(var) = self.parse_template_variable() {
                 log::trace!("Inline::TemplateVar");
                 return Ok(Inline::TemplateVar(var));
             }
         }
-        if self.ctm.check_char(&PIPE) || self.ctm.check_char(&LB) {
+        if let Ok(spoiler) = self.parse_spoiler() {
+            log::trace!("Inline::Spoiler");
+            return Ok(Inline::Spoiler(spoiler));
+        }
+        if (self.ctm.check_char(&PIPE) && self.inline_break_at.contains(&PIPE))
+            || self.ctm.check_char(&LB)
+        {
             Err(self.ctm.err().into())
+        } else if self.parse_hard_break().is_ok() {
+            log::trace!("Inline::LineBreak");
+            Ok(Inline::LineBreak)
+        } else if let Ok(comment) = self.parse_comment() {
+            log::trace!("Inline::Comment");
+            Ok(Inline::Comment(comment))
+        } else if let Ok(html) = self.parse_html() {
+            log::trace!("Inline::Html {}", html.value);
+            Ok(Inline::Html(html))
         } else if self.ctm.check_eof() {
             log::trace!("EOF");
             Err(self.ctm.err().into())
@@ -89,7 +183,7 @@ impl ParseInline for Parser {
         } else if let Ok(url) = self.parse_url(false) {
             log::trace!("Inline::Url {:?}", url);
             Ok(Inline::Url(url))
-        } else if let Ok(pholder) = self.parse_placeholder() {
+        } else if let Ok(pholder) = self.parse_inline_placeholder() {
             log::trace!("Inline::Placeholder {:?}", pholder);
             Ok(Inline::Placeholder(pholder))
         } else if let Ok(bold) = self.parse_bold() {
@@ -104,9 +198,15 @@ impl ParseInline for Parser {
         } else if let Ok(mono) = self.parse_monospace() {
             log::trace!("Inline::Monospace {}", mono.value);
             Ok(Inline::Monospace(mono))
+        } else if let Ok(highlight) = self.parse_highlight() {
+            log::trace!("Inline::Highlight");
+            Ok(Inline::Highlight(highlight))
         } else if let Ok(striked) = self.parse_striked() {
             log::trace!("Inline::Striked");
             Ok(Inline::Striked(striked))
+        } else if let Ok(subscript) = self.parse_subscript() {
+            log::trace!("Inline::Subscript");
+            Ok(Inline::Subscript(subscript))
         } else if let Ok(gloss) = self.parse_glossary_reference() {
             log::trace!("Inline::GlossaryReference {}", gloss.lock().short);
             Ok(Inline::GlossaryReference(gloss))
@@ -122,12 +222,15 @@ impl ParseInline for Parser {
         } else if let Ok(colored) = self.parse_colored() {
             log::trace!("Inline::Colored");
             Ok(Inline::Colored(colored))
-        } else if let Ok(bibref) = self.parse_bibref() {
-            log::trace!("Inline::BibReference {:?}", bibref);
-            Ok(Inline::BibReference(bibref))
+        } else if let Ok(footnote) = self.parse_footnote_reference() {
+            log::trace!("Inline::FootnoteReference {}", footnote.lock().label);
+            Ok(Inline::FootnoteReference(footnote))
         } else if let Ok(math) = self.parse_math() {
             log::trace!("Inline::Math");
             Ok(Inline::Math(math))
+        } else if let Ok(math) = self.parse_tex_math() {
+            log::trace!("Inline::Math");
+            Ok(Inline::Math(math))
         } else if let Ok(char_code) = self.parse_character_code() {
             log::trace!("Inline::CharacterCode {}", char_code.code);
             Ok(Inline::CharacterCode(char_code))
@@ -137,6 +240,12 @@ impl ParseInline for Parser {
         } else if let Ok(anchor) = self.parse_anchor() {
             log::trace!("Inline::Anchor {:?}", anchor);
             Ok(Inline::Anchor(anchor))
+        } else if let Ok(citation) = self.parse_citation() {
+            log::trace!("Inline::BibReference {}", citation.read().unwrap().key);
+            Ok(Inline::BibReference(citation))
+        } else if let Ok(url) = self.parse_autolink() {
+            log::trace!("Inline::Url {:?}", url);
+            Ok(Inline::Url(url))
         } else {
             let plain = self.parse_plain()?;
             log::trace!("Inline::Plain {}", plain.value);
@@ -151,15 +260,13 @@ impl ParseInline for Parser {
         self.ctm.seek_one()?;
 
         if let Ok(url) = self.parse_url(true) {
-            let metadata = self.parse_inline_metadata().ok();
+            let metadata = self
+                .parse_inline_metadata()
+                .or_else(|_| self.parse_attributes())
+                .ok();
 
-            let path = url.url.clone();
-            let pending_image = self
-                .options
-                .document
-                .images
-                .lock()
-                .add_image(PathBuf::from(path));
+            let path = self.transform_path(url.url.clone());
+            let pending_image = self.options.document.images.lock().add_image(path);
 
             if let Some(meta) = &metadata {
                 pending_image.lock().assign_from_meta(meta)
@@ -169,6 +276,7 @@ impl ParseInline for Parser {
                 url,
                 metadata,
                 image_data: pending_image,
+                span: self.get_span(start_index),
             })
         } else {
             Err(self.ctm.rewind_with_error(start_index).into())
@@ -183,6 +291,7 @@ impl ParseInline for Parser {
         if self.ctm.check_char(&DESC_OPEN) {
             self.ctm.seek_one()?;
             self.inline_break_at.push(DESC_CLOSE);
+            let parse_autolinks = std::mem::replace(&mut self.parse_autolinks, false);
 
             // only parse the description as inline if there is a description
             if !self.ctm.check_char(&DESC_CLOSE) {
@@ -193,6 +302,7 @@ impl ParseInline for Parser {
                     }
                 }
             }
+            self.parse_autolinks = parse_autolinks;
             self.inline_break_at.pop();
             self.ctm.seek_one()?;
         } else if !short_syntax {
@@ -212,10 +322,46 @@ impl ParseInline for Parser {
             url = url_path.to_str().unwrap().to_string();
         }
 
-        if description.len() > 0 {
-            Ok(Url::new(Some(description), url))
+        let description = if !description.is_empty() {
+            Some(description)
         } else {
-            Ok(Url::new(None, url))
+            None
+        };
+
+        Ok(Url {
+            description,
+            url,
+            span: self.get_span(start_index),
+        })
+    }
+
+    /// parses a bare http(s) url or email address
+    fn parse_autolink(&mut self) -> ParseResult<Url> {
+        let start_index = self.ctm.get_index();
+        if !self.parse_autolinks || self.ctm.check_escaped() {
+            return Err(self.ctm.err().into());
+        }
+        let link = match_autolink(self.ctm.get_remaining())
+            .ok_or_else(|| self.ctm.err())?
+            .to_string();
+        self.ctm.rewind(start_index + link.len());
+        let span = self.get_span(start_index);
+
+        if link.contains(URL_SCHEME_SEPARATOR) {
+            Ok(Url {
+                description: None,
+                url: link,
+                span,
+            })
+        } else {
+            Ok(Url {
+                url: format!("mailto:{}", link),
+                description: Some(vec![Inline::Plain(PlainText {
+                    value: link.into(),
+                    span,
+                })]),
+                span,
+            })
         }
     }
 
@@ -235,7 +381,10 @@ impl ParseInline for Parser {
         self.ctm.assert_char(&CHECK_CLOSE, Some(start_index))?;
         self.ctm.seek_one()?;
 
-        Ok(Checkbox { value: checked })
+        Ok(Checkbox {
+            value: checked,
+            span: self.get_span(start_index),
+        })
     }
 
     /// parses bold text with must start with two asterisks
@@ -254,37 +403,54 @@ impl ParseInline for Parser {
         }
         self.ctm.seek_one()?;
 
-        Ok(BoldText { value: inline })
+        Ok(BoldText {
+            value: inline,
+            span: self.get_span(start_index),
+        })
     }
 
     fn parse_italic(&mut self) -> ParseResult<ItalicText> {
+        let start_index = self.ctm.get_index();
+
         Ok(ItalicText {
             value: self.parse_surrounded(&ITALIC)?,
+            span: self.get_span(start_index),
         })
     }
 
     fn parse_striked(&mut self) -> ParseResult<StrikedText> {
         let start_index = self.ctm.get_index();
-        self.ctm.assert_sequence(&STRIKED, Some(start_index))?;
-        self.ctm.seek_one()?;
-        let mut inline = vec![self.parse_inline()?];
 
-        while !self.ctm.check_sequence(&STRIKED) {
-            if let Ok(result) = self.parse_inline() {
-                inline.push(result);
-            } else {
-                return Err(self.ctm.rewind_with_error(start_index).into());
-            }
-        }
-        self.ctm.rewind(self.ctm.get_index() - STRIKED.len());
-        if self.ctm.check_any(WHITESPACE) {
-            return Err(self.ctm.rewind_with_error(start_index).into());
-        }
-        for _ in 0..(STRIKED.len() + 1) {
-            self.ctm.seek_one()?;
-        }
+        Ok(StrikedText {
+            value: self.parse_surrounded_sequence(STRIKED)?,
+            span: self.get_span(start_index),
+        })
+    }
 
-        Ok(StrikedText { value: inline })
+    /// parses text hidden between ||
+    fn parse_spoiler(&mut self) -> ParseResult<SpoilerText> {
+        let start_index = self.ctm.get_index();
+
+        Ok(SpoilerText {
+            value: self.parse_surrounded_sequence(SQ_SPOILER)?,
+            span: self.get_span(start_index),
+        })
+    }
+
+    /// parses text highlighted with == and optional metadata
+    fn parse_highlight(&mut self) -> ParseResult<HighlightText> {
+        let start_index = self.ctm.get_index();
+        let value = self.parse_surrounded_sequence(SQ_HIGHLIGHT)?;
+        let metadata = self
+            .parse_inline_metadata()
+            .or_else(|_| self.parse_attributes())
+            .ok();
+
+        Ok(HighlightText {
+            value,
+            metadata,
+            span: self.get_span(start_index),
+        })
     }
 
     fn parse_math(&mut self) -> ParseResult<Math> {
@@ -297,7 +463,33 @@ impl ParseInline for Parser {
         self.ctm.seek_one()?;
 
         Ok(Math {
-            expression: asciimath_rs::parse(content),
+            expression: MathExpression::from_ascii_math(content),
+            span: self.get_span(start_index),
+        })
+    }
+
+    /// parses inline TeX math $tex$. The content must not start or end with whitespace
+    /// and the closing $ must not be followed by a digit so that prices aren't matched
+    fn parse_tex_math(&mut self) -> ParseResult<Math> {
+        let start_index = self.ctm.get_index();
+        self.ctm.assert_char(&MATH, Some(start_index))?;
+        self.ctm.seek_one()?;
+        if self.ctm.check_any(WHITESPACE) || self.ctm.check_char(&MATH) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let content = self
+            .ctm
+            .get_string_until_any_or_rewind(&[MATH], &[LB], start_index)?;
+        if !self.ctm.check_char(&MATH) || content.ends_with(char::is_whitespace) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        if self.ctm.try_seek() && self.ctm.get_current().is_ascii_digit() {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+
+        Ok(Math {
+            expression: MathExpression::Tex(content),
+            span: self.get_span(start_index),
         })
     }
 
@@ -313,21 +505,60 @@ impl ParseInline for Parser {
         self.ctm.assert_char(&BACKTICK, Some(start_index))?;
         self.ctm.seek_one()?;
 
-        Ok(MonospaceText { value: content })
+        Ok(MonospaceText {
+            value: content,
+            span: self.get_span(start_index),
+        })
     }
 
     fn parse_underlined(&mut self) -> ParseResult<UnderlinedText> {
+        let start_index = self.ctm.get_index();
+        let value = if let Ok(value) = self.parse_surrounded_sequence(SQ_UNDERLINED) {
+            value
+        } else {
+            self.parse_surrounded(&UNDERLINED)?
+        };
+
         Ok(UnderlinedText {
-            value: self.parse_surrounded(&UNDERLINED)?,
+            value,
+            span: self.get_span(start_index),
         })
     }
 
     fn parse_superscript(&mut self) -> ParseResult<SuperscriptText> {
+        let start_index = self.ctm.get_index();
+
         Ok(SuperscriptText {
             value: self.parse_surrounded(&SUPER)?,
+            span: self.get_span(start_index),
+        })
+    }
+
+    /// parses subscript text ~sub~ which must not contain whitespace
+    /// so that it doesn't collide with glossary references
+    fn parse_subscript(&mut self) -> ParseResult<SubscriptText> {
+        let start_index = self.ctm.get_index();
+        self.ctm.assert_char(&SUB, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let value = self
+            .ctm
+            .get_string_until_any_or_rewind(&[SUB], WHITESPACE, start_index)?;
+        if value.is_empty() {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let value_span = self.get_span(start_index + 1);
+        self.ctm.seek_one()?;
+
+        Ok(SubscriptText {
+            value: vec![Inline::Plain(PlainText {
+                value: remove_single_backlslash(value).into(),
+                span: value_span,
+            })],
+            span: self.get_span(start_index),
         })
     }
 
+    /// parses an emoji shortcode like :rocket:
     fn parse_emoji(&mut self) -> ParseResult<Emoji> {
         let start_index = self.ctm.get_index();
         self.ctm.assert_char(&EMOJI, Some(start_index))?;
@@ -336,11 +567,11 @@ impl ParseInline for Parser {
             .ctm
             .get_string_until_any_or_rewind(&[EMOJI], &[SPACE, LB], start_index)?;
         self.ctm.seek_one()?;
-        if let Some(emoji) = gh_emoji::get(name.as_str()) {
-            let emoji_char = *emoji.chars().collect::<Vec<char>>().first().unwrap();
+        if let Some(emoji) = get_emoji(&name) {
             Ok(Emoji {
-                value: emoji_char,
+                value: emoji,
                 name,
+                span: self.get_span(start_index),
             })
         } else {
             Err(self.ctm.rewind_with_error(start_index).into())
@@ -362,9 +593,12 @@ impl ParseInline for Parser {
         if color.is_empty() {
             return Err(self.ctm.err().into());
         }
+        let value = Box::new(self.parse_inline()?);
+
         Ok(Colored {
-            value: Box::new(self.parse_inline()?),
+            value,
             color,
+            span: self.get_span(start_index),
         })
     }
 
@@ -377,28 +611,54 @@ impl ParseInline for Parser {
             self.ctm
                 .get_string_until_any_or_rewind(&[BIBREF_CLOSE], &[SPACE, LB], start_index)?;
         self.ctm.seek_one()?;
-        let bib_ref = BibRef::new(key.clone());
-        let ref_entry = Arc::new(RwLock::new(BibReference::new(
-            key,
-            Some(
-                self.options
-                    .document
-                    .config
-                    .lock()
-                    .style
-                    .bib_ref_display
-                    .clone(),
-            ),
-            bib_ref.anchor(),
-        )));
-        self.options
+
+        Ok(self.create_bib_reference(key, start_index))
+    }
+
+    /// Parses a citation [key] without the footnote marker.
+    /// If no entry with the key exists the citation is displayed as written.
+    fn parse_citation(&mut self) -> ParseResult<Arc<RwLock<BibReference>>> {
+        let start_index = self.ctm.get_index();
+        self.ctm.assert_char(&BIBREF_OPEN, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let key = self.ctm.get_string_until_any_or_rewind(
+            &[BIBREF_CLOSE],
+            &[SPACE, LB, BIBREF_OPEN],
+            start_index,
+        )?;
+        if !is_citation_key(&key) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        self.ctm.seek_one()?;
+        if self.ctm.check_any(&[URL_OPEN, META_OPEN, BIB_DATA_START]) {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        let citation = self.create_bib_reference(key, start_index);
+        citation.write().unwrap().fallback = Some(
+            self.ctm
+                .get_slice(start_index, self.ctm.get_index())
+                .to_string(),
+        );
+
+        Ok(citation)
+    }
+
+    /// Parses a reference [^label] to a footnote.
+    /// Bibliography references use the same syntax, so the citation
+    /// is kept in case no footnote with that label gets defined.
+    fn parse_footnote_reference(&mut self) -> ParseResult<Arc<Mutex<FootnoteReference>>> {
+        let start_index = self.ctm.get_index();
+        let citation = self.parse_bibref()?;
+        let label = citation.read().unwrap().key.clone();
+        let mut reference = FootnoteReference::new(label, citation);
+        reference.span = self.get_span(start_index);
+
+        Ok(self
+            .options
             .document
-            .bibliography
-            .root_ref_anchor()
+            .footnotes
             .lock()
-            .insert(bib_ref);
-
-        Ok(ref_entry)
+            .add_reference(reference))
     }
 
     /// parses a template variable {prefix{name}suffix}
@@ -423,6 +683,7 @@ impl ParseInline for Parser {
             name,
             prefix,
             suffix,
+            span: self.get_span(start_index),
         })))
     }
 
@@ -446,10 +707,11 @@ impl ParseInline for Parser {
             return Err(self.ctm.rewind_with_error(start_index).into());
         }
         while !key.is_empty() && !key.chars().last().unwrap().is_alphabetic() {
-            self.ctm.rewind(self.ctm.get_index() - 1);
-            key = key[..key.len() - 1].to_string();
+            self.ctm.rewind_chars(1);
+            key.pop();
         }
-        let reference = GlossaryReference::with_display(key, display);
+        let mut reference = GlossaryReference::with_display(key, display);
+        reference.span = self.get_span(start_index);
 
         Ok(self
             .options
@@ -464,6 +726,7 @@ impl ParseInline for Parser {
         if self.ctm.check_char(&LB) {
             return Err(self.ctm.err().into());
         }
+        let start_index = self.ctm.get_index();
         let mut characters = String::new();
         if !self.ctm.check_char(&SPECIAL_ESCAPE) {
             characters.push(self.ctm.get_current());
@@ -475,37 +738,147 @@ impl ParseInline for Parser {
                 || self.ctm.check_any(&self.inline_break_at)
                 || self.ctm.check_any_sequence(&INLINE_SPECIAL_SEQUENCES)
                 || (self.parse_variables && self.ctm.check_char(&TEMP_VAR_OPEN))
+                || self.parse_hard_break().is_ok()
+                || self.ctm.check_sequence(SQ_COMMENT_START)
+                || (self.options.raw_html
+                    && self.ctm.check_char(&LT)
+                    && match_html_tag(self.ctm.get_remaining()).is_some())
+                || (self.parse_autolinks
+                    && characters.ends_with(|c: char| c.is_whitespace() || c == '(')
+                    && match_autolink(self.ctm.get_remaining()).is_some())
+                || self.check_extension_inline()
             {
                 self.ctm.rewind(index);
                 break;
             }
-            if !self.ctm.check_char(&SPECIAL_ESCAPE) {
+            if self.join_escaped_lines && ch == LB {
+                // an escaped line break continues the text on the next line
+                if !characters.ends_with(char::is_whitespace) {
+                    characters.push(SPACE);
+                }
+                while self
+                    .ctm
+                    .peek_char()
+                    .map(|c| INLINE_WHITESPACE.contains(&c))
+                    .unwrap_or(false)
+                {
+                    self.ctm.next_char();
+                }
+            } else if !self.ctm.check_char(&SPECIAL_ESCAPE) {
                 characters.push(ch)
             }
         }
 
         if characters.len() > 0 {
-            Ok(PlainText { value: characters })
+            Ok(PlainText {
+                value: self.options.document.strings.lock().intern(&characters),
+                span: self.get_span(start_index),
+            })
         } else {
             Err(self.ctm.err().into())
         }
     }
 
+    /// Parses a hard line break written as a backslash or at least
+    /// two spaces at the end of a line
+    fn parse_hard_break(&mut self) -> ParseResult<()> {
+        if self.join_escaped_lines || self.ctm.check_eof() {
+            return Err(self.ctm.err().into());
+        }
+        let start_index = self.ctm.get_index();
+        let remaining = self.ctm.get_remaining();
+        let spaces = remaining.len() - remaining.trim_start_matches(SPACE).len();
+
+        let length = if self.ctm.get_current() == LB && self.ctm.check_escaped() {
+            LB.len_utf8()
+        } else if self.ctm.check_char(&BACKSLASH) && remaining[1..].starts_with(LB) {
+            BACKSLASH.len_utf8() + LB.len_utf8()
+        } else if spaces >= 2 && remaining[spaces..].starts_with(LB) {
+            spaces + LB.len_utf8()
+        } else {
+            return Err(self.ctm.err().into());
+        };
+        // a break at the end of a paragraph has no effect
+        let next = &remaining[length..];
+        if next.is_empty() || next.starts_with(LB) {
+            return Err(self.ctm.err().into());
+        }
+        self.ctm.rewind(start_index + length);
+        self.ctm.seek_any(&INLINE_WHITESPACE)?;
+
+        Ok(())
+    }
+
+    /// Parses a raw html tag
+    fn parse_html(&mut self) -> ParseResult<Html> {
+        let start_index = self.ctm.get_index();
+        if !self.options.raw_html || !self.ctm.check_char(&LT) {
+            return Err(self.ctm.err().into());
+        }
+        let value = match_html_tag(self.ctm.get_remaining())
+            .ok_or_else(|| self.ctm.err())?
+            .to_string();
+        self.ctm.rewind(start_index + value.len() - GT.len_utf8());
+        self.ctm.try_seek();
+
+        Ok(Html {
+            value,
+            metadata: None,
+            span: self.get_span(start_index),
+        })
+    }
+
+    /// Parses a comment enclosed in <!-- -->
+    fn parse_comment(&mut self) -> ParseResult<Comment> {
+        let start_index = self.ctm.get_index();
+        self.ctm
+            .assert_sequence(SQ_COMMENT_START, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let value = self
+            .ctm
+            .get_string_until_sequence(&[SQ_COMMENT_END], &[])
+            .map_err(|_| self.ctm.rewind_with_error(start_index))?;
+        if self.ctm.check_eof() {
+            // the comment isn't closed
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        self.ctm.try_seek();
+
+        Ok(Comment {
+            value: value.trim().to_string(),
+            metadata: None,
+            span: self.get_span(start_index),
+        })
+    }
+
     /// Parses metadata
     fn parse_inline_metadata(&mut self) -> ParseResult<InlineMetadata> {
+        self.parse_metadata_block(META_OPEN, META_CLOSE)
+    }
+
+    /// Parses metadata in the attribute syntax {key=value}
+    fn parse_attributes(&mut self) -> ParseResult<InlineMetadata> {
+        if self.ctm.peek_char() == Some(ATTR_OPEN) {
+            return Err(self.ctm.err().into());
+        }
+        self.parse_metadata_block(ATTR_OPEN, ATTR_CLOSE)
+    }
+
+    /// Parses metadata surrounded by the given characters
+    fn parse_metadata_block(&mut self, open: char, close: char) -> ParseResult<InlineMetadata> {
         let start_index = self.ctm.get_index();
-        self.ctm.assert_char(&META_OPEN, Some(start_index))?;
+        self.ctm.assert_char(&open, Some(start_index))?;
         self.ctm.seek_one()?;
 
         let mut values = HashMap::new();
-        while let Ok((key, value)) = self.parse_metadata_pair() {
+        while let Ok((key, value)) = self.parse_metadata_pair(close) {
             values.insert(key, value);
-            if self.ctm.check_char(&META_CLOSE) || self.ctm.check_char(&LB) {
+            if self.ctm.check_char(&close) || self.ctm.check_char(&LB) {
                 // abort the parser of the inner content when encountering a closing tag or linebreak
                 break;
             }
         }
-        if self.ctm.check_char(&META_CLOSE) {
+        if self.ctm.check_char(&close) {
             self.ctm.seek_one()?;
         }
         if values.len() == 0 {
@@ -518,15 +891,19 @@ impl ParseInline for Parser {
     }
 
     /// parses a key-value metadata pair
-    fn parse_metadata_pair(&mut self) -> Result<(String, MetadataValue), ParseError> {
+    fn parse_metadata_pair(&mut self, close: char) -> Result<(String, MetadataValue), ParseError> {
         self.ctm.seek_any(&INLINE_WHITESPACE)?;
         let name = self
             .ctm
-            .get_string_until_any(&[META_CLOSE, EQ, SPACE, LB], &[])?;
+            .get_string_until_any(&[close, EQ, SPACE, LB, COMMA], &[])?;
 
         self.ctm.seek_any(&INLINE_WHITESPACE)?;
         let mut value = MetadataValue::Bool(true);
 
+        if self.ctm.check_char(&COMMA) {
+            self.ctm.seek_one()?;
+        }
+
         if self.ctm.check_char(&EQ) {
             self.ctm.seek_one()?;
             self.ctm.seek_any(&INLINE_WHITESPACE)?;
@@ -540,9 +917,9 @@ impl ParseInline for Parser {
                 let parse_until = if quoted_string {
                     let quote_start = self.ctm.get_current();
                     self.ctm.seek_one()?;
-                    vec![quote_start, META_CLOSE, LB]
+                    vec![quote_start, close, LB]
                 } else {
-                    vec![META_CLOSE, LB, SPACE]
+                    vec![close, LB, SPACE, COMMA]
                 };
 
                 let raw_value = self.ctm.get_string_until_any(&parse_until, &[])?;
@@ -594,8 +971,17 @@ impl ParseInline for Parser {
         }
 
         let metadata = self.parse_inline_metadata().ok();
-
-        let placeholder = Arc::new(RwLock::new(Placeholder::new(name, metadata)));
+        let value = self.get_placeholder_value(&name, start_index);
+
+        let mut placeholder = Placeholder::new(name, metadata);
+        placeholder.span = self.get_span(start_index);
+        if let Some(value) = value {
+            placeholder.set_value(Element::Inline(Box::new(Inline::Plain(PlainText {
+                value: value.into(),
+                span: placeholder.span,
+            }))));
+        }
+        let placeholder = Arc::new(RwLock::new(placeholder));
         self.options
             .document
             .add_placeholder(Arc::clone(&placeholder));
@@ -603,6 +989,22 @@ impl ParseInline for Parser {
         Ok(placeholder)
     }
 
+    /// Parses a placeholder in a line of text. Placeholders that are replaced
+    /// with blocks can only be used on their own line and stay text otherwise
+    fn parse_inline_placeholder(&mut self) -> ParseResult<Arc<RwLock<Placeholder>>> {
+        let remaining = self.ctm.get_remaining();
+        let is_block = BLOCK_PLACEHOLDERS.iter().any(|name| {
+            remaining
+                .get(..name.len() + 4)
+                .is_some_and(|text| text.eq_ignore_ascii_case(&format!("[[{}]]", name)))
+        });
+        if is_block {
+            return Err(self.ctm.err().into());
+        }
+
+        self.parse_placeholder()
+    }
+
     /// parses a template
     fn parse_template(&mut self) -> ParseResult<Template> {
         let start_index = self.ctm.get_index();
@@ -661,12 +1063,17 @@ impl ParseInline for Parser {
                 .get_string_until_any_or_rewind(&[CHARACTER_STOP], &[LB], start_index)?;
         self.ctm.seek_one()?;
 
-        Ok(CharacterCode { code })
+        Ok(CharacterCode {
+            code,
+            span: self.get_span(start_index),
+        })
     }
 
     /// Parses an arrow
     fn parse_arrow(&mut self) -> ParseResult<Arrow> {
-        if !self.options.document.config.lock().features.smart_arrows {
+        if !self.options.smart_punctuation
+            || !self.options.document.config.lock().features.smart_arrows
+        {
             Err(self.ctm.err().into())
         } else if self.ctm.check_sequence(A_LEFT_RIGHT_ARROW) {
             self.ctm.seek_one()?;
@@ -706,6 +1113,81 @@ impl ParseInline for Parser {
         Ok(Anchor {
             inner: Box::new(Line::Text(TextLine::new())),
             key,
+            span: self.get_span(start_index),
         })
     }
 }
+
+/// Returns if the text can be used as the key of a citation
+fn is_citation_key(key: &str) -> bool {
+    key.starts_with(char::is_alphabetic)
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == ':')
+}
+
+/// Returns the bare url or email address at the start of the text
+fn match_autolink(text: &str) -> Option<&str> {
+    lazy_static::lazy_static! {
+        static ref AUTOLINK: Regex = Regex::new(
+            r#"^(?:https?://[^\s<>]*[^\s<>.,;:!?'")\]]|[\w.%+-]+@[\w-]+(?:\.[\w-]+)+)"#
+        )
+        .unwrap();
+    }
+    let first = text.chars().next()?;
+    if !first.is_alphanumeric() {
+        return None;
+    }
+
+    AUTOLINK.find(text).map(|m| m.as_str())
+}
+
+/// Returns the html tag at the start of the text
+fn match_html_tag(text: &str) -> Option<&str> {
+    lazy_static::lazy_static! {
+        static ref HTML_TAG: Regex = Regex::new(
+            r#"^(?:</?[A-Za-z][A-Za-z0-9-]*(?:\s+[A-Za-z_:][\w:.-]*(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*\s*/?>)"#
+        )
+        .unwrap();
+    }
+
+    HTML_TAG.find(text).map(|m| m.as_str())
+}
+
+/// Returns the emoji for a shortcode like rocket
+#[cfg(feature = "emoji")]
+pub(crate) fn get_emoji(name: &str) -> Option<String> {
+    let is_shortcode = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-');
+
+    if is_shortcode {
+        gh_emoji::get(&name.to_lowercase()).map(String::from)
+    } else {
+        None
+    }
+}
+
+/// Emoji shortcodes are left as text without the emoji table
+#[cfg(not(feature = "emoji"))]
+pub(crate) fn get_emoji(_name: &str) -> Option<String> {
+    None
+}
+
+/// Returns if the inline is a formatted span that metadata can be attached to
+fn is_styleable(inline: &Inline) -> bool {
+    matches!(
+        inline,
+        Inline::Bold(_)
+            | Inline::Italic(_)
+            | Inline::Underlined(_)
+            | Inline::Striked(_)
+            | Inline::Monospace(_)
+            | Inline::Superscript(_)
+            | Inline::Subscript(_)
+            | Inline::Spoiler(_)
+            | Inline::Colored(_)
+            | Inline::Math(_)
+    )
+}
@@ -7,25 +7,40 @@
 use super::ParseResult;
 use crate::elements::tokens::*;
 use crate::elements::Inline::LineBreak;
-use crate::elements::{BibEntry, Metadata};
+use crate::elements::{Alignment, BibEntry, Block, Metadata};
 use crate::elements::{Cell, Centered, Header, Line, ListItem, Row, Ruler, TextLine};
+use crate::parser::block::is_list_item;
+use crate::parser::diagnostics::{DiagnosticCode, Severity};
 use crate::parser::inline::ParseInline;
+use crate::references::abbreviations::AbbreviationDefinition;
+use crate::references::footnotes::FootnoteDefinition;
+use crate::utils::parsing::{get_indentation_offset, get_indentation_width};
 use crate::Parser;
 use bibliographix::bibliography::bibliography_entry::BibliographyEntry;
 use bibliographix::bibliography::keys::{K_KEY, K_TYPE, K_URL, T_WEBSITE};
 use bibliographix::bibliography::FromHashMap;
+use parking_lot::Mutex;
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The width of tabs in the indentation of list items if tabs aren't expanded before parsing
+const DEFAULT_TAB_WIDTH: usize = 4;
 
 pub(crate) trait ParseLine {
     fn parse_line(&mut self) -> ParseResult<Line>;
     fn parse_header(&mut self) -> ParseResult<Header>;
     fn parse_list_item(&mut self) -> ParseResult<ListItem>;
+    fn parse_list_item_blocks(&mut self, content_column: usize, tab_width: usize) -> Vec<Block>;
     fn parse_row(&mut self) -> ParseResult<Row>;
+    fn parse_table_alignments(&mut self) -> ParseResult<Vec<Alignment>>;
+    fn parse_cell_spans(&mut self) -> Option<(usize, usize)>;
     fn parse_centered(&mut self) -> ParseResult<Centered>;
     fn parse_ruler(&mut self) -> ParseResult<Ruler>;
     fn parse_paragraph_break(&mut self) -> ParseResult<TextLine>;
     fn parse_text_line(&mut self) -> ParseResult<TextLine>;
     fn parse_bib_entry(&mut self) -> ParseResult<BibEntry>;
+    fn parse_footnote_definition(&mut self) -> ParseResult<Arc<Mutex<FootnoteDefinition>>>;
+    fn parse_abbreviation_definition(&mut self) -> ParseResult<AbbreviationDefinition>;
 }
 
 impl ParseLine for Parser {
@@ -41,6 +56,12 @@ impl ParseLine for Parser {
             } else if let Ok(centered) = self.parse_centered() {
                 log::trace!("Line::Centered");
                 Ok(Line::Centered(centered))
+            } else if let Ok(footnote) = self.parse_footnote_definition() {
+                log::trace!("Line::FootnoteDefinition");
+                Ok(Line::FootnoteDefinition(footnote))
+            } else if let Ok(abbreviation) = self.parse_abbreviation_definition() {
+                log::trace!("Line::AbbreviationDefinition {}", abbreviation.short);
+                Ok(Line::AbbreviationDefinition(abbreviation))
             } else if let Ok(bib) = self.parse_bib_entry() {
                 log::trace!("Line::BibEntry");
                 Ok(Line::BibEntry(bib))
@@ -60,10 +81,10 @@ impl ParseLine for Parser {
     fn parse_header(&mut self) -> ParseResult<Header> {
         let start_index = self.ctm.get_index();
         let line = self.parse_line()?;
-        let mut anchor = String::new();
-        self.ctm.get_text()[start_index..self.ctm.get_index()]
-            .iter()
-            .for_each(|e| anchor.push(*e));
+        let mut anchor = self
+            .ctm
+            .get_slice(start_index, self.ctm.get_index())
+            .to_string();
         if let Some(last) = self.section_anchors.last() {
             anchor = format!("{}-{}", last, anchor);
         }
@@ -72,14 +93,25 @@ impl ParseLine for Parser {
         Ok(Header::new(line, anchor))
     }
 
-    /// parses a single list item defined with -
+    /// parses a single list item defined with -, +, *, o or a number.
+    /// The checkbox and metadata of the item follow the marker
     fn parse_list_item(&mut self) -> ParseResult<ListItem> {
         let start_index = self.ctm.get_index();
-        self.ctm.seek_any(&INLINE_WHITESPACE)?;
-        let level = self.ctm.get_index() - start_index;
+        while self.ctm.get_current() != LB && self.ctm.get_current().is_whitespace() {
+            self.ctm.seek_one()?;
+        }
+        let tab_width = self
+            .options
+            .normalization
+            .tab_width
+            .unwrap_or(DEFAULT_TAB_WIDTH)
+            .max(1);
+        let indentation = self.ctm.get_slice(start_index, self.ctm.get_index());
+        let level = get_indentation_width(indentation, tab_width);
         self.ctm
             .assert_any(&LIST_SPECIAL_CHARS, Some(start_index))?;
-        let ordered = self.ctm.get_current().is_numeric();
+        let marker = self.ctm.get_current();
+        let ordered = marker.is_numeric();
         self.ctm.seek_one()?;
         if ordered {
             while self.ctm.get_current().is_numeric() {
@@ -96,14 +128,123 @@ impl ParseLine for Parser {
         if self.ctm.check_char(&MINUS) {
             return Err(self.ctm.rewind_with_error(start_index).into());
         }
+        // the first item of a list starts after the indentation of its line
+        let line_start = self
+            .ctm
+            .get_slice(0, start_index)
+            .rfind(LB)
+            .map_or(0, |i| i + 1);
+        let content_column = get_indentation_width(
+            self.ctm.get_slice(line_start, self.ctm.get_index()),
+            tab_width,
+        );
+        let checked = if let Ok(checkbox) = self.parse_checkbox() {
+            self.ctm.seek_any(&INLINE_WHITESPACE)?;
+            Some(checkbox.value)
+        } else {
+            None
+        };
+        let metadata_start = self.ctm.get_index();
+        let metadata = match self.parse_inline_metadata() {
+            // text in brackets without any key=value pair stays text
+            Ok(metadata)
+                if self
+                    .ctm
+                    .get_slice(metadata_start, self.ctm.get_index())
+                    .contains(EQ)
+                    && self.ctm.check_any(&INLINE_WHITESPACE) =>
+            {
+                self.ctm.seek_any(&INLINE_WHITESPACE)?;
+                Some(metadata)
+            }
+            Ok(_) => {
+                self.ctm.rewind(metadata_start);
+                None
+            }
+            Err(_) => None,
+        };
 
-        let item = ListItem::new(self.parse_line()?, level as u16, ordered);
+        let mut item = ListItem::new(self.parse_line()?, level as u16, ordered);
+        item.blocks = self.parse_list_item_blocks(content_column, tab_width);
+        if !item.blocks.is_empty() {
+            // the break of the blank line between the text and the blocks
+            if let Line::Text(text) = &mut item.text {
+                if let Some(LineBreak) = text.subtext.last() {
+                    text.subtext.pop();
+                }
+            }
+        }
+        item.checked = checked;
+        item.bullet = if ordered { None } else { Some(marker) };
+        item.metadata = metadata;
+        item.span = self.get_span(start_index);
         log::trace!("Line::ListItem");
 
         Ok(item)
     }
 
-    /// parses a table row/head
+    /// Parses the blocks after the text of a list item whose lines are indented at least
+    /// to the column of the text. Blank lines between them are part of the blocks
+    /// and a line that starts another list item ends them.
+    fn parse_list_item_blocks(&mut self, content_column: usize, tab_width: usize) -> Vec<Block> {
+        let start_index = self.ctm.get_index();
+        let mut text = String::new();
+        let mut source_starts = Vec::new();
+        let mut blank_lines = Vec::new();
+        let mut end = start_index;
+        let mut line_start = start_index;
+
+        for line in self.ctm.get_remaining().split_inclusive(LB) {
+            let index = line_start;
+            line_start += line.len();
+            let content = line.trim_start();
+            if content.is_empty() {
+                blank_lines.push((index, line.len()));
+                continue;
+            }
+            let indentation = &line[..line.len() - content.len()];
+            if get_indentation_width(indentation, tab_width) < content_column
+                || is_list_item(content)
+            {
+                break;
+            }
+            if !text.is_empty() {
+                for (blank_start, _) in blank_lines.iter() {
+                    source_starts.push(*blank_start);
+                    text.push(LB);
+                }
+            }
+            blank_lines.clear();
+            let offset = get_indentation_offset(indentation, content_column, tab_width);
+            source_starts.push(index + offset);
+            text.push_str(&line[offset..]);
+            end = line_start;
+        }
+        if text.is_empty() {
+            return Vec::new();
+        }
+        let max_depth = self.options.nesting_limits.max_list_depth;
+        if self.nested.len() >= max_depth {
+            self.nesting_limit_exceeded("List items", max_depth, start_index);
+            return Vec::new();
+        }
+        // a blank line after the blocks belongs to the item like a blank line after its text
+        if let Some((blank_start, length)) = blank_lines.first() {
+            if *blank_start == end {
+                end += length;
+            }
+        }
+        if !text.ends_with(LB) {
+            text.push(LB);
+        }
+        source_starts.push(end);
+        while self.ctm.get_index() < end && self.ctm.try_seek() {}
+
+        self.parse_nested(text, source_starts)
+    }
+
+    /// parses a table row/head.
+    /// A backslash at the end of a line continues the current cell on the next line
     fn parse_row(&mut self) -> ParseResult<Row> {
         let start_index = self.ctm.get_index();
         self.ctm.seek_any(&INLINE_WHITESPACE)?;
@@ -113,20 +254,27 @@ impl ParseLine for Parser {
             return Err(self.ctm.rewind_with_error(start_index).into());
         }
         self.inline_break_at.push(PIPE);
+        self.join_escaped_lines = true;
 
         self.ctm.seek_any(&INLINE_WHITESPACE)?;
         let mut row = Row::new();
         loop {
             let mut element = TextLine::new();
+            let spans = self.parse_cell_spans();
+            let cell_start = self.ctm.get_index();
             while let Ok(inline) = self.parse_inline() {
                 element.subtext.push(inline);
                 if self.ctm.check_char(&LB) || self.ctm.check_char(&PIPE) || self.ctm.check_eof() {
                     break;
                 }
             }
-            row.add_cell(Cell {
-                text: Line::Text(element),
-            });
+            element.span = self.get_span(cell_start);
+            let mut cell = Cell::new(Line::Text(element));
+            if let Some((colspan, rowspan)) = spans {
+                cell.colspan = colspan;
+                cell.rowspan = rowspan;
+            }
+            row.add_cell(cell);
             if self.ctm.check_char(&PIPE) {
                 self.ctm.seek_one()?;
             }
@@ -136,6 +284,7 @@ impl ParseLine for Parser {
             self.ctm.seek_any(&INLINE_WHITESPACE)?;
         }
         self.inline_break_at.clear();
+        self.join_escaped_lines = false;
 
         if self.ctm.check_char(&PIPE) {
             self.ctm.seek_one()?;
@@ -146,12 +295,75 @@ impl ParseLine for Parser {
 
         if row.cells.len() > 0 {
             log::trace!("Line::TableRow");
+            row.span = self.get_span(start_index);
             Ok(row)
         } else {
             return Err(self.ctm.rewind_with_error(start_index).into());
         }
     }
 
+    /// parses the [colspan=n rowspan=m] metadata at the start of a table cell.
+    /// Other metadata is left to be parsed as the content of the cell
+    fn parse_cell_spans(&mut self) -> Option<(usize, usize)> {
+        let start_index = self.ctm.get_index();
+        let metadata = self.parse_inline_metadata().ok()?;
+        let colspan = metadata.get_integer("colspan");
+        let rowspan = metadata.get_integer("rowspan");
+
+        if colspan.is_none() && rowspan.is_none() {
+            self.ctm.rewind(start_index);
+            return None;
+        }
+        let _ = self.ctm.seek_any(&INLINE_WHITESPACE);
+
+        Some((
+            colspan.unwrap_or(1).max(1) as usize,
+            rowspan.unwrap_or(1).max(1) as usize,
+        ))
+    }
+
+    /// parses the delimiter row of a table and returns the alignment of each column.
+    /// The machine stops at the line break of the row
+    fn parse_table_alignments(&mut self) -> ParseResult<Vec<Alignment>> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_any(&INLINE_WHITESPACE)?;
+        self.ctm.assert_char(&PIPE, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let mut alignments = Vec::new();
+
+        loop {
+            self.ctm.seek_any(&INLINE_WHITESPACE)?;
+            if self.ctm.check_char(&LB) {
+                break;
+            }
+            let left = self.ctm.check_char(&COLON);
+            if left {
+                self.ctm.seek_one()?;
+            }
+            self.ctm.assert_char(&MINUS, Some(start_index))?;
+            self.ctm.seek_any(&[MINUS])?;
+            let right = self.ctm.check_char(&COLON);
+            if right {
+                self.ctm.seek_one()?;
+            }
+            alignments.push(match (left, right) {
+                (true, true) => Alignment::Center,
+                (true, false) => Alignment::Left,
+                (false, true) => Alignment::Right,
+                (false, false) => Alignment::None,
+            });
+            self.ctm.seek_any(&INLINE_WHITESPACE)?;
+
+            if self.ctm.check_char(&PIPE) {
+                self.ctm.seek_one()?;
+            } else if !self.ctm.check_char(&LB) {
+                return Err(self.ctm.rewind_with_error(start_index).into());
+            }
+        }
+
+        Ok(alignments)
+    }
+
     /// parses centered text
     fn parse_centered(&mut self) -> ParseResult<Centered> {
         let start_index = self.ctm.get_index();
@@ -160,7 +372,10 @@ impl ParseLine for Parser {
         self.ctm.seek_one()?;
         let line = self.parse_text_line()?;
 
-        Ok(Centered { line })
+        Ok(Centered {
+            line,
+            span: self.get_span(start_index),
+        })
     }
 
     /// parses a ruler
@@ -171,7 +386,9 @@ impl ParseLine for Parser {
         while !self.ctm.check_char(&LB) {
             self.ctm.seek_one()?;
         }
-        Ok(Ruler {})
+        Ok(Ruler {
+            span: self.get_span(start_index),
+        })
     }
 
     /// Parses a line of text
@@ -180,11 +397,13 @@ impl ParseLine for Parser {
         let start_index = self.ctm.get_index();
 
         while let Ok(subtext) = self.parse_inline() {
+            let hard_break = matches!(subtext, LineBreak);
             text.add_subtext(subtext);
-            if self.ctm.check_eof() || self.ctm.check_any(&self.inline_break_at) {
+            if hard_break || self.ctm.check_eof() || self.ctm.check_any(&self.inline_break_at) {
                 break;
             }
         }
+        text.span = self.get_span(start_index);
 
         // add a linebreak when encountering \n\n
         if self.ctm.check_char(&LB) {
@@ -212,6 +431,7 @@ impl ParseLine for Parser {
 
         let mut line = TextLine::new();
         line.subtext.push(LineBreak);
+        line.span = self.get_span(start_index);
 
         Ok(line)
     }
@@ -236,11 +456,11 @@ impl ParseLine for Parser {
             match BibliographyEntry::from_hash_map(&string_map) {
                 Ok(entry) => *entry,
                 Err(msg) => {
-                    log::error!(
-                        "Failed to parse bib entry with key '{}': {}\n\t--> {}\n",
-                        key,
-                        msg,
-                        self.get_position_string()
+                    self.add_diagnostic(
+                        Severity::Error,
+                        DiagnosticCode::InvalidBibEntry,
+                        format!("Failed to parse bib entry with key '{}': {}", key, msg),
+                        start_index,
                     );
                     return Err(self.ctm.rewind_with_error(start_index).into());
                 }
@@ -257,11 +477,11 @@ impl ParseLine for Parser {
             match BibliographyEntry::from_hash_map(&map) {
                 Ok(entry) => *entry,
                 Err(msg) => {
-                    log::error!(
-                        "Failed to parse bib entry with key '{}': {}\n\t--> {}\n",
-                        key,
-                        msg,
-                        self.get_position_string()
+                    self.add_diagnostic(
+                        Severity::Error,
+                        DiagnosticCode::InvalidBibEntry,
+                        format!("Failed to parse bib entry with key '{}': {}", key, msg),
+                        start_index,
                     );
                     return Err(self.ctm.rewind_with_error(start_index).into());
                 }
@@ -286,6 +506,77 @@ impl ParseLine for Parser {
                 .get(&key)
                 .unwrap(),
             key,
+            span: self.get_span(start_index),
         })
     }
+
+    /// parses a footnote definition [^label]: text
+    fn parse_footnote_definition(&mut self) -> ParseResult<Arc<Mutex<FootnoteDefinition>>> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_any(&INLINE_WHITESPACE)?;
+        self.ctm
+            .assert_sequence(&SQ_FOOTNOTE_START, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let label = self.ctm.get_string_until_any_or_rewind(
+            &[FOOTNOTE_CLOSE],
+            &[LB, SPACE],
+            start_index,
+        )?;
+        self.ctm.seek_one()?;
+        self.ctm
+            .assert_char(&FOOTNOTE_DATA_START, Some(start_index))?;
+        self.ctm.seek_one()?;
+        self.ctm.seek_any(&INLINE_WHITESPACE)?;
+        let mut text = self
+            .parse_text_line()
+            .map_err(|_| self.ctm.rewind_with_error(start_index))?;
+
+        if let Some(LineBreak) = text.subtext.last() {
+            text.subtext.pop();
+        }
+        let mut definition = FootnoteDefinition::new(label, text);
+        definition.span = self.get_span(start_index);
+
+        Ok(self
+            .options
+            .document
+            .footnotes
+            .lock()
+            .add_definition(definition))
+    }
+
+    /// parses an abbreviation definition *[short]: long
+    fn parse_abbreviation_definition(&mut self) -> ParseResult<AbbreviationDefinition> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_any(&INLINE_WHITESPACE)?;
+        self.ctm
+            .assert_sequence(&SQ_ABBREVIATION_START, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let short = self
+            .ctm
+            .get_string_until_any_or_rewind(&[L_BRACKET], &[LB], start_index)?;
+        self.ctm.seek_one()?;
+        self.ctm.assert_char(&COLON, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let long = self
+            .ctm
+            .get_string_until_any(&[LB], &[])
+            .unwrap_or_default();
+        if short.trim().is_empty() || long.trim().is_empty() {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+        self.ctm.try_seek();
+        let definition = AbbreviationDefinition {
+            short: short.trim().to_string(),
+            long: long.trim().to_string(),
+            span: self.get_span(start_index),
+        };
+        self.options
+            .document
+            .abbreviations
+            .lock()
+            .add_definition(&definition);
+
+        Ok(definition)
+    }
 }
@@ -0,0 +1,35 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// The time spent in the phases of parsing a document
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ParseMetrics {
+    /// Parsing the blocks of the document. Imports that are parsed
+    /// in the calling thread, like deterministic imports, are included.
+    pub block_parsing: Duration,
+    /// Waiting for the imports that are parsed in parallel and importing the
+    /// files of the settings
+    pub imports: Duration,
+    /// Assembling the imported documents, numbering sections and assigning references
+    pub post_processing: Duration,
+    /// Processing the placeholders of the document and its imports
+    pub placeholders: Duration,
+    pub total: Duration,
+    /// The number of documents imported by the document and its imports
+    pub imported_documents: usize,
+}
+
+/// Measures the time of a phase and adds it to the given duration
+pub(crate) fn measure<T, F: FnOnce() -> T>(duration: &mut Duration, phase: F) -> T {
+    let start = Instant::now();
+    let result = phase();
+    *duration += start.elapsed();
+
+    result
+}
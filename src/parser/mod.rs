@@ -5,31 +5,81 @@
  */
 
 pub(crate) mod block;
+#[cfg(feature = "tokio")]
+mod deferred;
+pub mod diagnostics;
+pub mod events;
+pub mod extensions;
+pub mod import_cache;
+pub mod import_resolver;
+pub mod incremental;
 pub(crate) mod inline;
 pub(crate) mod line;
+pub mod metrics;
+pub mod tapemachine;
 
 use self::block::ParseBlock;
+use self::diagnostics::{Diagnostic, DiagnosticCode, Diagnostics, LogReporter, Reporter, Severity};
+use self::events::Events;
+use self::extensions::{Extension, Extensions};
+use self::import_cache::ImportCache;
+#[cfg(feature = "remote-imports")]
+use self::import_resolver::RemoteResolver;
+use self::import_resolver::{is_url, FileSystemResolver, ImportResolver};
+use self::incremental::DocumentSource;
+use self::metrics::{measure, ParseMetrics};
+use self::tapemachine::{CharTapeMachine, TapeError};
+use crate::elements::containers::ContainerHandler;
+use crate::elements::lazy::LazyDocument;
 use crate::elements::tokens::LB;
-use crate::elements::{Document, ImportAnchor};
+use crate::elements::transforms::{SmartPunctuation, Transform};
+use crate::elements::{
+    BibReference, Block, Cell, CodeBlock, Document, ImportAnchor, Inline, Line, Paragraph,
+    PlainText, Row, Span, Table, TextLine,
+};
+use crate::references::placeholders::PlaceholderHandler;
+use crate::settings::import_settings::ImportSettings;
 use crate::settings::SettingsError;
-use charred::tapemachine::{CharTapeMachine, TapeError};
+use crate::utils::parsing::parse_csv;
+use crate::utils::threading::ThreadPool;
+use bibliographix::references::bib_reference::BibRef;
 use crossbeam_utils::sync::WaitGroup;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
-use std::fs::{read_to_string, File};
-use std::io::{self, BufReader};
-use std::path::PathBuf;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
+use std::time::Instant;
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
+lazy_static::lazy_static! {
+    /// The pool imported documents are parsed in. It's shared between all parsers
+    /// so that documents with many imports don't start a thread for every import.
+    static ref IMPORT_POOL: ThreadPool = ThreadPool::new(
+        thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(4)
+    );
+}
+
 #[derive(Debug)]
 pub enum ParseError {
     TapeError(TapeError),
     SettingsError(SettingsError),
     IoError(io::Error),
+    /// An import of the document that failed with the error import policy
+    ImportError(String),
+    /// Elements that are nested deeper than the limit of the parser options
+    NestingLimitExceeded {
+        element: &'static str,
+        limit: usize,
+    },
 }
 
 impl fmt::Display for ParseError {
@@ -38,6 +88,10 @@ impl fmt::Display for ParseError {
             ParseError::TapeError(e) => write!(f, "{}", e),
             ParseError::SettingsError(e) => write!(f, "{}", e),
             ParseError::IoError(e) => write!(f, "IO Error: {}", e),
+            ParseError::ImportError(path) => write!(f, "Import of \"{}\" failed", path),
+            ParseError::NestingLimitExceeded { element, limit } => {
+                write!(f, "{} are nested deeper than {} levels", element, limit)
+            }
         }
     }
 }
@@ -64,8 +118,132 @@ impl From<io::Error> for ParseError {
 pub struct ParserOptions {
     pub path: Option<PathBuf>,
     pub paths: Arc<Mutex<Vec<PathBuf>>>,
+    pub errors: Arc<Mutex<Vec<ParseError>>>,
+    pub diagnostics: Arc<Mutex<Diagnostics>>,
+    pub reporter: Arc<dyn Reporter>,
     pub document: Document,
     pub is_child: bool,
+    pub resolver: Arc<dyn ImportResolver>,
+    pub autolinks: bool,
+    pub smart_punctuation: bool,
+    pub allow_imports: bool,
+    pub raw_html: bool,
+    pub line_comments: bool,
+    pub values: Arc<HashMap<String, String>>,
+    pub strict_values: bool,
+    pub import_roots: Vec<PathBuf>,
+    pub deterministic_imports: bool,
+    pub import_limits: ImportLimits,
+    pub nesting_limits: NestingLimits,
+    pub normalization: InputNormalization,
+    pub sandbox_root: Option<PathBuf>,
+    pub import_errors: ImportErrorPolicy,
+    pub lazy_imports: bool,
+    pub import_cache: Option<ImportCache>,
+    pub extensions: Extensions,
+    /// How many documents deep the parsed document is imported
+    pub(crate) import_depth: usize,
+    /// The canonical paths of the documents that import the parsed document
+    pub(crate) ancestors: Vec<PathBuf>,
+    /// The number of documents imported by the root document and its imports
+    pub(crate) imported_documents: Arc<AtomicUsize>,
+}
+
+/// How imports that fail because the file is missing, was already imported
+/// or can't be read are handled
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportErrorPolicy {
+    /// The failure is reported as an error and returned by `Parser::parse_with_errors`
+    Error,
+    /// The failure is reported as a warning and the import stays without content
+    Warning,
+    /// The failure is reported as a warning and the import is replaced
+    /// with a visible notice about the missing import
+    Notice,
+}
+
+/// Limits for the documents that are imported while parsing to protect
+/// services that parse untrusted input. All limits are disabled by default.
+#[derive(Clone, Debug, Default)]
+pub struct ImportLimits {
+    /// The maximum number of documents nested into each other by imports
+    pub max_depth: Option<usize>,
+    /// The maximum number of documents imported by the root document and its imports
+    pub max_files: Option<usize>,
+    /// The maximum size of an imported document in bytes
+    pub max_file_size: Option<usize>,
+}
+
+/// Limits for how deep elements can be nested into each other. Elements that would exceed
+/// a limit aren't nested and are parsed as text instead, so that deeply nested input
+/// can't overflow the stack of the parser or the renderers.
+#[derive(Clone, Debug)]
+pub struct NestingLimits {
+    /// The maximum number of sections nested into each other
+    pub max_section_depth: usize,
+    /// The maximum number of quotes nested into each other
+    pub max_quote_depth: usize,
+    /// The maximum number of list items nested into each other.
+    /// Deeper items are added to the deepest allowed level instead
+    pub max_list_depth: usize,
+    /// The maximum number of formatted inlines like bold or italic text nested into each other
+    pub max_inline_depth: usize,
+    /// The maximum number of aligned blocks and columns nested into each other
+    pub max_container_depth: usize,
+}
+
+impl Default for NestingLimits {
+    fn default() -> Self {
+        Self {
+            max_section_depth: 16,
+            max_quote_depth: 32,
+            max_list_depth: 32,
+            max_inline_depth: 64,
+            max_container_depth: 32,
+        }
+    }
+}
+
+/// How the text of documents is normalized before it's parsed
+#[derive(Clone, Debug)]
+pub struct InputNormalization {
+    /// Replaces CRLF and CR line endings with LF
+    pub line_endings: bool,
+    /// Replaces tabs in the indentation of lines with spaces up to the next multiple of the width
+    /// so that the indentation of list items is the same for tabs and spaces
+    pub tab_width: Option<usize>,
+    /// Removes the byte order mark at the start of the text
+    pub strip_bom: bool,
+}
+
+impl Default for InputNormalization {
+    fn default() -> Self {
+        Self {
+            line_endings: true,
+            tab_width: None,
+            strip_bom: true,
+        }
+    }
+}
+
+impl InputNormalization {
+    /// Applies the normalization to the given text
+    pub fn normalize(&self, mut text: String) -> String {
+        if self.strip_bom && text.starts_with('\u{feff}') {
+            text.drain(..'\u{feff}'.len_utf8());
+        }
+        if self.line_endings && text.contains('\r') {
+            text = text.replace("\r\n", "\n").replace('\r', "\n");
+        }
+        if let Some(width) = self.tab_width {
+            if text.contains('\t') {
+                text = expand_indentation_tabs(&text, width.max(1));
+            }
+        }
+
+        text
+    }
 }
 
 impl Default for ParserOptions {
@@ -73,13 +251,75 @@ impl Default for ParserOptions {
         Self {
             path: None,
             paths: Arc::new(Mutex::new(Vec::new())),
+            errors: Arc::new(Mutex::new(Vec::new())),
+            diagnostics: Arc::new(Mutex::new(Diagnostics::new())),
+            reporter: Arc::new(LogReporter),
             document: Document::new(),
             is_child: false,
+            #[cfg(not(feature = "remote-imports"))]
+            resolver: Arc::new(FileSystemResolver),
+            #[cfg(feature = "remote-imports")]
+            resolver: Arc::new(RemoteResolver::new(FileSystemResolver)),
+            autolinks: true,
+            smart_punctuation: true,
+            allow_imports: true,
+            raw_html: false,
+            line_comments: false,
+            values: Arc::new(HashMap::new()),
+            strict_values: false,
+            import_roots: Vec::new(),
+            deterministic_imports: false,
+            import_limits: ImportLimits::default(),
+            nesting_limits: NestingLimits::default(),
+            normalization: InputNormalization::default(),
+            sandbox_root: None,
+            import_errors: ImportErrorPolicy::Warning,
+            lazy_imports: false,
+            import_cache: None,
+            extensions: Extensions::new(),
+            import_depth: 0,
+            ancestors: Vec::new(),
+            imported_documents: Arc::new(AtomicUsize::new(0)),
         }
     }
 }
 
 impl ParserOptions {
+    /// Creates a copy of the options for parsing another document.
+    /// The document, paths and diagnostics are created anew so that
+    /// documents parsed with the copies don't share their state.
+    pub fn detached(&self) -> Self {
+        let mut document = Document::new();
+        *document.config.lock() = self.document.config.lock().clone();
+        document.placeholder_handlers = self.document.placeholder_handlers.clone();
+        document.container_handlers = self.document.container_handlers.clone();
+
+        Self {
+            path: None,
+            paths: Arc::new(Mutex::new(Vec::new())),
+            errors: Arc::new(Mutex::new(Vec::new())),
+            diagnostics: Arc::new(Mutex::new(Diagnostics::new())),
+            document,
+            is_child: false,
+            import_depth: 0,
+            ancestors: Vec::new(),
+            imported_documents: Arc::new(AtomicUsize::new(0)),
+            ..self.clone()
+        }
+    }
+
+    /// Reports the diagnostics found while processing the document
+    pub(crate) fn report_diagnostics(&self, found: Vec<Diagnostic>) {
+        let mut diagnostics = self.diagnostics.lock().unwrap();
+
+        for diagnostic in found {
+            if !diagnostics.contains(&diagnostic) {
+                self.reporter.report(&diagnostic);
+                diagnostics.add(diagnostic);
+            }
+        }
+    }
+
     /// Adds a path to the parser options
     pub fn add_path(mut self, path: PathBuf) -> Self {
         self.path = Some(path.clone());
@@ -87,27 +327,282 @@ impl ParserOptions {
 
         self
     }
+
+    /// Sets the resolver used to load the document and its imports
+    pub fn with_resolver<R: ImportResolver + 'static>(mut self, resolver: R) -> Self {
+        self.resolver = Arc::new(resolver);
+
+        self
+    }
+
+    /// Sets if bare urls and email addresses are turned into links
+    pub fn with_autolinks(mut self, autolinks: bool) -> Self {
+        self.autolinks = autolinks;
+
+        self
+    }
+
+    /// Sets if character sequences like -> are replaced with arrows,
+    /// straight quotes with typographic ones and -- and ... with dashes and ellipses
+    pub fn with_smart_punctuation(mut self, smart_punctuation: bool) -> Self {
+        self.smart_punctuation = smart_punctuation;
+
+        self
+    }
+
+    /// Sets if imports in the document and the files of the manifest are loaded.
+    /// Disabled imports are reported as warnings and stay in the document without content
+    pub fn with_imports(mut self, allow_imports: bool) -> Self {
+        self.allow_imports = allow_imports;
+
+        self
+    }
+
+    /// Sets the directories that are searched for imports that don't exist
+    /// relative to the importing document
+    pub fn with_import_roots(mut self, import_roots: Vec<PathBuf>) -> Self {
+        self.import_roots = import_roots;
+
+        self
+    }
+
+    /// Sets if imported documents are parsed one after another in the order they appear
+    /// instead of in parallel. The footnotes, images and diagnostics of the imports are then
+    /// registered in the same order on every run so that the output is reproducible
+    pub fn with_deterministic_imports(mut self, deterministic: bool) -> Self {
+        self.deterministic_imports = deterministic;
+
+        self
+    }
+
+    /// Sets if imported documents are only parsed when they're rendered or
+    /// materialized with `Document::materialize_imports` instead of while parsing
+    pub fn with_lazy_imports(mut self, lazy: bool) -> Self {
+        self.lazy_imports = lazy;
+
+        self
+    }
+
+    /// Sets the cache imported documents are read from if they haven't changed
+    /// since they were parsed the last time. Imports with diagnostics aren't cached.
+    pub fn with_import_cache(mut self, cache: ImportCache) -> Self {
+        self.import_cache = Some(cache);
+
+        self
+    }
+
+    /// Sets how imports that fail are handled
+    pub fn with_import_error_policy(mut self, policy: ImportErrorPolicy) -> Self {
+        self.import_errors = policy;
+
+        self
+    }
+
+    /// Sets the limits for the depth, number and size of imported documents
+    pub fn with_import_limits(mut self, limits: ImportLimits) -> Self {
+        self.import_limits = limits;
+
+        self
+    }
+
+    /// Sets how the text of the document and its imports is normalized before it's parsed
+    pub fn with_normalization(mut self, normalization: InputNormalization) -> Self {
+        self.normalization = normalization;
+
+        self
+    }
+
+    /// Sets the limits for how deep sections, quotes, lists and inlines can be nested
+    pub fn with_nesting_limits(mut self, limits: NestingLimits) -> Self {
+        self.nesting_limits = limits;
+
+        self
+    }
+
+    /// Restricts imports to files inside the given directory. Imports with absolute paths,
+    /// urls or paths containing .. are rejected so that user provided documents can be parsed
+    pub fn with_sandbox_root(mut self, root: PathBuf) -> Self {
+        self.sandbox_root = Some(root);
+
+        self
+    }
+
+    /// Sets if raw html blocks and tags are passed through to the output
+    /// instead of being parsed as text
+    pub fn with_raw_html(mut self, raw_html: bool) -> Self {
+        self.raw_html = raw_html;
+
+        self
+    }
+
+    /// Sets if lines starting with // are treated as comments
+    pub fn with_line_comments(mut self, line_comments: bool) -> Self {
+        self.line_comments = line_comments;
+
+        self
+    }
+
+    /// Sets the reporter that receives diagnostics while parsing
+    pub fn with_reporter<R: Reporter + 'static>(mut self, reporter: R) -> Self {
+        self.reporter = Arc::new(reporter);
+
+        self
+    }
+
+    /// Sets the values that [[value:key]] placeholders resolve against
+    pub fn with_values(mut self, values: HashMap<String, String>) -> Self {
+        self.values = Arc::new(values);
+
+        self
+    }
+
+    /// Sets if [[value:key]] and [[env:NAME]] placeholders without a value are reported as errors
+    pub fn with_strict_values(mut self, strict: bool) -> Self {
+        self.strict_values = strict;
+
+        self
+    }
+
+    /// Registers a handler that resolves placeholders with the given name
+    pub fn with_placeholder_handler<H: PlaceholderHandler + 'static>(
+        mut self,
+        name: &str,
+        handler: H,
+    ) -> Self {
+        self.document.register_placeholder_handler(name, handler);
+
+        self
+    }
+
+    /// Registers a handler that transforms custom containers with the given name
+    pub fn with_container_handler<H: ContainerHandler + 'static>(
+        mut self,
+        name: &str,
+        handler: H,
+    ) -> Self {
+        self.document.register_container_handler(name, handler);
+
+        self
+    }
+
+    /// Registers an extension that adds syntax to the parser.
+    /// Extensions are tried in the order they are registered.
+    pub fn with_extension<E: Extension + 'static>(mut self, extension: E) -> Self {
+        self.extensions.register(extension);
+
+        self
+    }
+}
+
+impl ImportErrorPolicy {
+    /// Returns the severity failed imports are reported with
+    pub fn get_severity(&self) -> Severity {
+        match self {
+            ImportErrorPolicy::Error => Severity::Error,
+            ImportErrorPolicy::Warning | ImportErrorPolicy::Notice => Severity::Warning,
+        }
+    }
+}
+
+impl FromStr for ImportErrorPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(ImportErrorPolicy::Error),
+            "warning" => Ok(ImportErrorPolicy::Warning),
+            "notice" => Ok(ImportErrorPolicy::Notice),
+            _ => Err(format!(
+                "Unknown import error policy '{}'. Expected error, warning or notice",
+                s
+            )),
+        }
+    }
+}
+
+impl ImportLimits {
+    /// Returns an error message if the text of an imported document is larger than allowed
+    pub(crate) fn check_file_size(&self, text: &str) -> Result<(), String> {
+        match self.max_file_size {
+            Some(max_size) if text.len() > max_size => Err(format!(
+                "The file is {} bytes large but only {} bytes are allowed.",
+                text.len(),
+                max_size
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A text that is parsed in place of the text of the document,
+/// e.g. the content of a quote without the quote markers
+struct NestedText {
+    /// The tape of the enclosing text
+    outer: CharTapeMachine,
+    /// The byte index each line of the nested text starts at
+    line_starts: Vec<usize>,
+    /// The byte index in the enclosing text each line of the nested text starts at
+    source_starts: Vec<usize>,
 }
 
 pub struct Parser {
     pub(crate) options: ParserOptions,
     pub(crate) ctm: CharTapeMachine,
+    line_starts: Vec<usize>,
+    nested: Vec<NestedText>,
     section_nesting: u8,
     sections: Vec<u8>,
     section_anchors: Vec<String>,
     section_return: Option<u8>,
+    /// How many formatted inlines deep the parser currently is
+    pub(crate) inline_depth: usize,
+    /// The elements whose nesting limit was already reported
+    reported_nesting: Vec<&'static str>,
     wg: WaitGroup,
+    /// The files imported by the document and its imports
+    dependencies: Arc<Mutex<Vec<PathBuf>>>,
     pub(crate) block_break_at: Vec<char>,
     pub(crate) inline_break_at: Vec<char>,
     pub(crate) parse_variables: bool,
+    pub(crate) join_escaped_lines: bool,
+    pub(crate) parse_autolinks: bool,
+    /// The source of the root document that is kept in the document to parse it again after edits
+    source: Option<DocumentSource>,
+    /// Document imports that are parsed after the document when parsing asynchronously
+    #[cfg(feature = "tokio")]
+    deferred_imports: Option<Vec<deferred::DeferredImport>>,
 }
 
 impl Parser {
+    /// Creates a new parser for the given text with the given options or the default ones.
+    /// The path of the options is only used to resolve imports relative to it
+    pub fn new(text: String, options: Option<ParserOptions>) -> Self {
+        let options = options.unwrap_or_default();
+        let mut text = options.normalization.normalize(text);
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+
+        Self::from_text(text, options)
+    }
+
+    /// Creates a new parser for the text read from the reader, for example stdin.
+    /// Imports are resolved relative to the path of the options or the working directory
+    pub fn new_from_reader<R: Read>(
+        mut reader: R,
+        options: Option<ParserOptions>,
+    ) -> io::Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        Ok(Self::new(text, options))
+    }
+
     /// Creates a new parser with the default values given
     pub fn with_defaults(options: ParserOptions) -> Self {
         let text = if let Some(path) = &options.path {
-            let mut text = read_to_string(&path).unwrap();
-            text = text.replace("\r\n", "\n");
+            let text = options.resolver.read_to_string(path).unwrap();
+            let mut text = options.normalization.normalize(text);
             if text.chars().last() != Some('\n') {
                 text.push('\n');
             }
@@ -116,47 +611,100 @@ impl Parser {
         } else {
             "".to_string()
         };
+
+        Self::from_text(text, options)
+    }
+
+    fn from_text(text: String, options: ParserOptions) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices(LB).map(|(i, _)| i + 1));
+
         Self {
+            parse_autolinks: options.autolinks,
             options,
             sections: Vec::new(),
             section_anchors: Vec::new(),
             section_nesting: 0,
             section_return: None,
+            inline_depth: 0,
+            reported_nesting: Vec::new(),
             wg: WaitGroup::new(),
-            ctm: CharTapeMachine::new(text.chars().collect()),
+            dependencies: Arc::new(Mutex::new(Vec::new())),
+            ctm: CharTapeMachine::new(text),
+            line_starts,
+            nested: Vec::new(),
             inline_break_at: Vec::new(),
             block_break_at: Vec::new(),
             parse_variables: false,
+            join_escaped_lines: false,
+            source: None,
+            #[cfg(feature = "tokio")]
+            deferred_imports: None,
         }
     }
 
-    /// Creates a new child parser
-    fn create_child(&self, path: PathBuf) -> Self {
+    /// Creates the options for a child parser
+    fn create_child_options(&self, path: PathBuf) -> ParserOptions {
         let mut options = self.options.clone().add_path(path.clone());
         options.document = self.options.document.create_child();
         options.document.path = Some(path.to_str().unwrap().to_string());
         options.is_child = true;
+        options.import_depth += 1;
+        if let Some(own_path) = &self.options.path {
+            options.ancestors.push(self.canonical_path(own_path));
+        }
+
+        options
+    }
+
+    /// Returns the canonical form of the path or the path itself if it can't be resolved
+    fn canonical_path(&self, path: &Path) -> PathBuf {
+        self.options
+            .resolver
+            .canonicalize(path)
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// Returns the canonical form of the path. The parts of the path that don't exist
+    /// are appended to the canonical form of the longest part that exists
+    fn canonical_existing_path(&self, path: &Path) -> PathBuf {
+        for ancestor in path.ancestors() {
+            let existing = if ancestor.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                ancestor
+            };
+            if let Ok(canonical) = self.options.resolver.canonicalize(existing) {
+                return canonical.join(path.strip_prefix(ancestor).unwrap());
+            }
+        }
+
+        path.to_path_buf()
+    }
 
-        Self::with_defaults(options)
+    /// Returns if the path is the parsed document or one of the documents that import it
+    fn is_ancestor_path(&self, path: &Path) -> bool {
+        let path = self.canonical_path(path);
+
+        self.options.ancestors.contains(&path)
+            || self
+                .options
+                .path
+                .as_ref()
+                .map(|own_path| self.canonical_path(own_path) == path)
+                .unwrap_or(false)
     }
 
     /// Returns a string of the current position in the file
     pub(crate) fn get_position_string(&self) -> String {
-        let char_index = self.ctm.get_index();
-        self.get_position_string_for_index(char_index)
+        let index = self.ctm.get_index();
+        self.get_position_string_for_index(index)
     }
 
     /// Returns a string of the given index position in the file
-    fn get_position_string_for_index(&self, char_index: usize) -> String {
-        let text = self.ctm.get_text();
-        let mut text_unil = text[..char_index].to_vec();
-        let line_number = text_unil.iter().filter(|c| c == &&LB).count();
-        text_unil.reverse();
-        let mut inline_pos = 0;
+    fn get_position_string_for_index(&self, index: usize) -> String {
+        let (line_number, inline_pos) = self.get_line_and_column(self.get_source_index(index));
 
-        while inline_pos < text_unil.len() && text_unil[inline_pos] != LB {
-            inline_pos += 1;
-        }
         if let Some(path) = &self.options.path {
             format!("{}:{}:{}", path.to_str().unwrap(), line_number, inline_pos)
         } else {
@@ -164,53 +712,470 @@ impl Parser {
         }
     }
 
+    /// Returns the byte index in the text of the document
+    /// for a byte index of the text that is currently parsed
+    fn get_source_index(&self, index: usize) -> usize {
+        self.nested.iter().rev().fold(index, |index, nested| {
+            let line = nested
+                .line_starts
+                .partition_point(|start| *start <= index)
+                .saturating_sub(1);
+
+            nested.source_starts[line] + index - nested.line_starts[line]
+        })
+    }
+
+    /// Returns the zero based line and column of the given byte index
+    /// in the text of the document. The column is counted in chars.
+    fn get_line_and_column(&self, index: usize) -> (usize, usize) {
+        let tape = self.nested.first().map(|n| &n.outer).unwrap_or(&self.ctm);
+        let line = self
+            .line_starts
+            .partition_point(|start| *start <= index)
+            .saturating_sub(1);
+        let column = tape
+            .get_slice(self.line_starts[line], index)
+            .chars()
+            .count();
+
+        (line, column)
+    }
+
+    /// Returns the span from the given start index to the current position
+    pub(crate) fn get_span(&self, start_index: usize) -> Span {
+        let start = self.get_source_index(start_index);
+        let (line, column) = self.get_line_and_column(start);
+
+        Span {
+            start,
+            end: self.get_source_index(self.ctm.get_index()),
+            line,
+            column,
+        }
+    }
+
+    /// Parses the blocks of a text that is nested in the current text.
+    /// The source starts are the indices in the current text each line
+    /// of the nested text was taken from including the end of the last line.
+    pub(crate) fn parse_nested(&mut self, text: String, source_starts: Vec<usize>) -> Vec<Block> {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices(LB).map(|(i, _)| i + 1));
+        let outer = std::mem::replace(&mut self.ctm, CharTapeMachine::new(text));
+        self.nested.push(NestedText {
+            outer,
+            line_starts,
+            source_starts,
+        });
+        let section_nesting = std::mem::replace(&mut self.section_nesting, 0);
+        let sections = std::mem::take(&mut self.sections);
+        let section_return = self.section_return.take();
+
+        let mut blocks = Vec::new();
+        while let Some(block) = self.parse_next_block() {
+            blocks.push(block);
+        }
+
+        self.section_nesting = section_nesting;
+        self.sections = sections;
+        self.section_return = section_return;
+        if let Some(nested) = self.nested.pop() {
+            self.ctm = nested.outer;
+        }
+
+        blocks
+    }
+
+    /// Returns the error for elements that are nested deeper than the given limit.
+    /// The error is only reported once for each kind of element, because elements
+    /// at the limit are tried again for every enclosing element
+    pub(crate) fn nesting_limit_exceeded(
+        &mut self,
+        element: &'static str,
+        limit: usize,
+        start_index: usize,
+    ) -> ParseError {
+        if !self.reported_nesting.contains(&element) {
+            self.reported_nesting.push(element);
+            let error = ParseError::NestingLimitExceeded { element, limit };
+            self.add_diagnostic(
+                Severity::Error,
+                DiagnosticCode::NestingLimitExceeded,
+                error.to_string(),
+                start_index,
+            );
+            self.options.errors.lock().unwrap().push(error);
+        }
+
+        ParseError::NestingLimitExceeded { element, limit }
+    }
+
+    /// Records a diagnostic that spans from the given index to the current position
+    /// and passes it to the reporter
+    pub(crate) fn add_diagnostic(
+        &self,
+        severity: Severity,
+        code: DiagnosticCode,
+        message: String,
+        start_index: usize,
+    ) {
+        let diagnostic = Diagnostic {
+            severity,
+            code,
+            message,
+            path: self
+                .options
+                .path
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string()),
+            span: self.get_span(start_index),
+        };
+        let mut diagnostics = self.options.diagnostics.lock().unwrap();
+        // elements that are parsed again after backtracking report the same diagnostic
+        if !diagnostics.contains(&diagnostic) {
+            self.options.reporter.report(&diagnostic);
+            diagnostics.add(diagnostic);
+        }
+    }
+
+    /// Returns the value for [[env:NAME]] placeholders from the environment variables
+    /// and for [[value:key]] placeholders from the values given to the parser.
+    /// Missing values are reported as errors in strict mode
+    pub(crate) fn get_placeholder_value(&self, name: &str, start_index: usize) -> Option<String> {
+        let (kind, key) = name.split_once(':')?;
+        let key = key.trim();
+        let value = match kind.trim().to_lowercase().as_str() {
+            "env" => std::env::var(key).ok(),
+            "value" => self.options.values.get(key).cloned(),
+            _ => return None,
+        };
+        if value.is_none() && self.options.strict_values {
+            self.add_diagnostic(
+                Severity::Error,
+                DiagnosticCode::MissingValue,
+                format!("Missing value for placeholder [[{}]]", name),
+                start_index,
+            );
+        }
+
+        value
+    }
+
+    /// Creates a reference to a bibliography entry and registers it
+    /// so that it gets assigned the entry with the given key
+    pub(crate) fn create_bib_reference(
+        &mut self,
+        key: String,
+        start_index: usize,
+    ) -> Arc<RwLock<BibReference>> {
+        let bib_ref = BibRef::new(key.clone());
+        let mut bib_reference = BibReference::new(
+            key,
+            Some(
+                self.options
+                    .document
+                    .config
+                    .lock()
+                    .style
+                    .bib_ref_display
+                    .clone(),
+            ),
+            bib_ref.anchor(),
+        );
+        bib_reference.span = self.get_span(start_index);
+        self.options
+            .document
+            .bibliography
+            .root_ref_anchor()
+            .lock()
+            .insert(bib_ref);
+
+        Arc::new(RwLock::new(bib_reference))
+    }
+
     /// transform an import path to be relative to the current parsers file
-    fn transform_path(&mut self, path: String) -> PathBuf {
+    pub(crate) fn transform_path(&mut self, path: String) -> PathBuf {
         let mut path = PathBuf::from(path);
 
-        if !path.is_absolute() {
+        if !path.is_absolute() && !is_url(&path) {
+            let relative = path.clone();
             if let Some(selfpath) = &self.options.path {
                 if let Some(dir) = selfpath.parent() {
                     path = PathBuf::new().join(dir).join(path);
                 }
             }
+            if !self.options.resolver.exists(&path) {
+                if let Some(root_path) = self
+                    .options
+                    .import_roots
+                    .iter()
+                    .map(|root| root.join(&relative))
+                    .find(|p| self.options.resolver.exists(p))
+                {
+                    path = root_path;
+                }
+            }
         }
 
         path
     }
 
-    /// starts up a new thread to parse the imported document
-    fn import_document(&mut self, path: PathBuf) -> ParseResult<Arc<RwLock<ImportAnchor>>> {
-        if !path.exists() || !path.is_file() {
-            log::error!(
-                "Import of \"{}\" failed: The file doesn't exist.\n\t--> {}\n",
-                path.to_str().unwrap(),
-                self.get_position_string(),
+    /// Returns the anchor of an import that failed depending on the import error policy.
+    /// The anchor contains a notice about the missing import with the notice policy
+    pub(crate) fn create_failed_import_anchor(&mut self, path: &str) -> Arc<RwLock<ImportAnchor>> {
+        let anchor = Arc::new(RwLock::new(ImportAnchor::new()));
+
+        match self.options.import_errors {
+            ImportErrorPolicy::Error => self
+                .options
+                .errors
+                .lock()
+                .unwrap()
+                .push(ParseError::ImportError(path.to_string())),
+            ImportErrorPolicy::Warning => {}
+            ImportErrorPolicy::Notice => {
+                let mut document = self.options.document.create_child();
+                document.add_element(Block::Paragraph(Paragraph::with_text(vec![
+                    Inline::bold(vec![Inline::plain("Missing import:")]),
+                    Inline::plain(" "),
+                    Inline::monospace(path),
+                ])));
+                anchor.write().unwrap().set_document(document);
+            }
+        }
+
+        anchor
+    }
+
+    /// Checks that the import path stays inside the sandbox root if one is set
+    fn check_sandbox(&self, import_path: &str, path: &Path) -> Result<(), String> {
+        let root = match &self.options.sandbox_root {
+            Some(root) => root,
+            None => return Ok(()),
+        };
+        let import_path = Path::new(import_path);
+        if is_url(import_path) {
+            return Err("Urls can't be imported.".to_string());
+        }
+        // windows paths with a drive but without a root like C:file.md are relative to the drive
+        if import_path.has_root()
+            || import_path
+                .components()
+                .any(|c| matches!(c, std::path::Component::Prefix(_)))
+        {
+            return Err("Absolute paths can't be imported.".to_string());
+        }
+        if import_path
+            .components()
+            .any(|c| c == std::path::Component::ParentDir)
+        {
+            return Err("Paths can't contain '..'.".to_string());
+        }
+        // resolves symbolic links to prevent escaping the root with them
+        let root = self.canonical_existing_path(root);
+        let path = self.canonical_existing_path(path);
+        if !path.starts_with(&root) {
+            return Err(format!(
+                "The file is outside of the import root \"{}\".",
+                root.to_string_lossy()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// parses the imported document in the import pool
+    fn import_document(
+        &mut self,
+        path: PathBuf,
+        args: &HashMap<String, String>,
+    ) -> ParseResult<Arc<RwLock<ImportAnchor>>> {
+        if let Err(message) = self.check_import_limits() {
+            self.add_diagnostic(
+                Severity::Error,
+                DiagnosticCode::ImportLimitExceeded,
+                format!(
+                    "Import of \"{}\" skipped: {}",
+                    path.to_string_lossy(),
+                    message
+                ),
+                self.ctm.get_index(),
+            );
+            // the import stays in the document without content like a disabled import
+            return Ok(Arc::new(RwLock::new(ImportAnchor::new())));
+        }
+        #[cfg(feature = "tokio")]
+        if self.deferred_imports.is_some() {
+            return Ok(self.defer_import(path, args));
+        }
+        if !self.options.resolver.exists(&path) {
+            self.add_diagnostic(
+                self.options.import_errors.get_severity(),
+                DiagnosticCode::ImportNotFound,
+                format!(
+                    "Import of \"{}\" failed: The file doesn't exist.",
+                    path.to_str().unwrap()
+                ),
+                self.ctm.get_index(),
             );
             return Err(self.ctm.assert_error(None).into());
         }
         let anchor = Arc::new(RwLock::new(ImportAnchor::new()));
+        if self.options.lazy_imports {
+            let options = self.create_child_options(path.clone());
+            let section = args.get("section").cloned();
+            let origin = ImportOrigin {
+                path: self
+                    .options
+                    .path
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().to_string()),
+                span: self.get_span(self.ctm.get_index()),
+            };
+            anchor.write().unwrap().set_lazy(LazyDocument::new(move || {
+                parse_lazy_import(&options, &path, section.as_deref(), &origin)
+            }));
+            return Ok(anchor);
+        }
         let anchor_clone = Arc::clone(&anchor);
-        let wg = self.wg.clone();
-        let mut child_parser = self.create_child(path.clone());
+        let text = self.options.resolver.read_to_string(&path)?;
+        if let Err(message) = self.options.import_limits.check_file_size(&text) {
+            self.add_diagnostic(
+                Severity::Error,
+                DiagnosticCode::ImportLimitExceeded,
+                format!(
+                    "Import of \"{}\" skipped: {}",
+                    path.to_string_lossy(),
+                    message
+                ),
+                self.ctm.get_index(),
+            );
+            return Ok(anchor);
+        }
+        let section = args.get("section").cloned();
+        if let Some(cache) = &self.options.import_cache {
+            if let Some((cached, dependencies)) = cache.get(&path, &text, &self.options) {
+                let mut document = self.options.document.create_child();
+                document.register_cached(&cached);
+                document.elements = cached.elements;
+                document.path = cached.path;
+                if let Some(section) = section {
+                    self.retain_import_section(&mut document, &section);
+                }
+                self.options
+                    .paths
+                    .lock()
+                    .unwrap()
+                    .extend(dependencies.clone());
+                self.dependencies.lock().unwrap().extend(dependencies);
+                anchor.write().unwrap().set_document(document);
 
-        let _ = thread::spawn(move || {
-            let document = child_parser.parse();
+                return Ok(anchor);
+            }
+        }
+        let mut child_parser =
+            Self::new(text.clone(), Some(self.create_child_options(path.clone())));
+        let dependencies = Arc::clone(&self.dependencies);
+        let parse_import = move || {
+            let mut document = child_parser.parse();
+            let child_dependencies = child_parser.dependencies.lock().unwrap().clone();
+            if let Some(cache) = &child_parser.options.import_cache {
+                if !child_parser.has_diagnostics(&path, &child_dependencies) {
+                    cache.insert(
+                        &path,
+                        &text,
+                        &child_parser.options,
+                        &child_dependencies,
+                        &document,
+                    );
+                }
+            }
+            dependencies.lock().unwrap().extend(child_dependencies);
+            if let Some(section) = section {
+                child_parser.retain_import_section(&mut document, &section);
+            }
             anchor_clone.write().unwrap().set_document(document);
+        };
 
-            drop(wg);
-        });
+        if self.options.deterministic_imports {
+            parse_import();
+        } else {
+            let wg = self.wg.clone();
+            IMPORT_POOL.execute(move || {
+                parse_import();
+                drop(wg);
+            });
+        }
 
         Ok(anchor)
     }
 
+    /// Returns if any diagnostics were reported for the given files
+    fn has_diagnostics(&self, path: &Path, dependencies: &[PathBuf]) -> bool {
+        self.options.diagnostics.lock().unwrap().iter().any(|d| {
+            d.path.as_deref().is_some_and(|p| {
+                Path::new(p) == path || dependencies.iter().any(|dependency| p == dependency)
+            })
+        })
+    }
+
+    /// Checks the depth of the imported document and counts it towards the number of imported files
+    fn check_import_limits(&self) -> Result<(), String> {
+        let limits = &self.options.import_limits;
+        if let Some(max_depth) = limits.max_depth {
+            if self.options.import_depth >= max_depth {
+                return Err(format!(
+                    "The maximum import depth of {} was reached.",
+                    max_depth
+                ));
+            }
+        }
+        let imported = self
+            .options
+            .imported_documents
+            .fetch_add(1, Ordering::SeqCst)
+            + 1;
+        if let Some(max_files) = limits.max_files {
+            if imported > max_files {
+                return Err(format!(
+                    "The maximum number of {} imported files was reached.",
+                    max_files
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes everything but the given section from the imported document
+    fn retain_import_section(&self, document: &mut Document, section: &str) {
+        if !document.retain_section(section) {
+            self.add_diagnostic(
+                Severity::Warning,
+                DiagnosticCode::ImportFailed,
+                format!("Section '{}' wasn't found in the document", section),
+                0,
+            );
+        }
+    }
+
+    /// Returns if the imported file exists. Urls aren't checked when parsing
+    /// asynchronously as they are fetched without blocking when they're parsed.
+    fn import_exists(&self, path: &Path) -> bool {
+        #[cfg(feature = "tokio")]
+        if self.deferred_imports.is_some() && is_url(path) {
+            return true;
+        }
+
+        self.options.resolver.exists(path)
+    }
+
     /// Imports a bibliography toml file
     fn import_bib(&mut self, path: PathBuf) -> ParseResult<()> {
-        let f = File::open(path).map_err(|_| self.ctm.err())?;
+        let contents = self.import_text_file(path).map_err(|_| self.ctm.err())?;
         self.options
             .document
             .bibliography
-            .read_bib_file(&mut BufReader::new(f))
+            .read_bib_file(&mut BufReader::new(contents.as_bytes()))
             .map_err(|_| self.ctm.err())?;
 
         Ok(())
@@ -218,7 +1183,74 @@ impl Parser {
 
     /// Returns the text of an imported text file
     fn import_text_file(&self, path: PathBuf) -> ParseResult<String> {
-        read_to_string(path).map_err(ParseError::from)
+        self.options
+            .resolver
+            .read_to_string(&path)
+            .map_err(ParseError::from)
+    }
+
+    /// Imports a source code file as a code block with the language inferred
+    /// from the file extension and an optional range of lines like range=10-20
+    fn import_code(&self, path: PathBuf, args: &HashMap<String, String>) -> ParseResult<CodeBlock> {
+        let contents = self.import_text_file(path.clone())?;
+        let language = args
+            .get("language")
+            .cloned()
+            .or_else(|| path.extension().map(|e| e.to_string_lossy().to_string()))
+            .unwrap_or_default();
+        let code = if let Some(range) = args.get("range") {
+            select_lines(&contents, range).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid line range '{}'", range),
+                )
+            })?
+        } else {
+            contents
+        };
+
+        Ok(CodeBlock {
+            language,
+            code,
+            metadata: None,
+            span: Span::default(),
+        })
+    }
+
+    /// Imports a csv file as a table with the first row being the header.
+    /// The delimiter can be set with the delimiter argument and header=false
+    /// makes the first row a regular row.
+    fn import_csv(&self, path: PathBuf, args: &HashMap<String, String>) -> ParseResult<Table> {
+        let contents = self.import_text_file(path)?;
+        let delimiter = match args.get("delimiter").map(|d| d.as_str()) {
+            Some("tab") | Some("\\t") => '\t',
+            Some(d) => d.chars().next().unwrap_or(','),
+            None => ',',
+        };
+        let mut rows = parse_csv(&contents, delimiter).into_iter().map(|fields| {
+            let mut row = Row::new();
+            for field in fields {
+                row.add_cell(Cell::new(Line::Text(TextLine {
+                    subtext: vec![Inline::Plain(PlainText {
+                        value: field.into(),
+                        span: Span::default(),
+                    })],
+                    span: Span::default(),
+                })));
+            }
+            row
+        });
+        let header = if args.get("header").map(|h| h == "false").unwrap_or(false) {
+            Row::new()
+        } else {
+            rows.next().unwrap_or_else(Row::new)
+        };
+        let mut table = Table::new(header);
+        for row in rows {
+            table.add_row(row);
+        }
+
+        Ok(table)
     }
 
     fn import_stylesheet(&mut self, path: PathBuf) -> ParseResult<()> {
@@ -234,14 +1266,22 @@ impl Parser {
     }
 
     fn import_manifest(&mut self, path: PathBuf) -> ParseResult<()> {
+        let contents = self.import_text_file(path)?;
         self.options
             .document
             .config
             .lock()
-            .merge(path)
+            .merge_toml(&contents)
             .map_err(ParseError::from)
     }
 
+    /// Imports the html template the rendered document is inserted into
+    fn import_template(&mut self, path: PathBuf) -> ParseResult<()> {
+        self.options.document.html_template = Some(self.import_text_file(path)?);
+
+        Ok(())
+    }
+
     /// Imports a glossary
     fn import_glossary(&self, path: PathBuf) -> ParseResult<()> {
         let contents = self.import_text_file(path)?;
@@ -253,7 +1293,14 @@ impl Parser {
             .glossary
             .lock()
             .assign_from_toml(value)
-            .unwrap_or_else(|e| log::error!("{}", e));
+            .unwrap_or_else(|e| {
+                self.add_diagnostic(
+                    Severity::Error,
+                    DiagnosticCode::InvalidGlossary,
+                    e.to_string(),
+                    self.ctm.get_index(),
+                )
+            });
 
         Ok(())
     }
@@ -265,14 +1312,36 @@ impl Parser {
             path,
             self.get_position_string()
         );
+        let mut args = args.clone();
+        let path = match path.split_once('#') {
+            Some((path, section)) => {
+                args.insert("section".to_string(), section.to_string());
+                path.to_string()
+            }
+            None => path,
+        };
+        let original_path = path.clone();
         let path = self.transform_path(path);
-        if !path.exists() {
-            log::error!(
-                "Import of \"{}\" failed: The file doesn't exist.\n\t--> {}\n",
-                path.to_str().unwrap(),
-                self.get_position_string(),
+        if let Err(message) = self.check_sandbox(&original_path, &path) {
+            self.add_diagnostic(
+                self.options.import_errors.get_severity(),
+                DiagnosticCode::ImportOutsideSandbox,
+                format!("Import of \"{}\" rejected: {}", original_path, message),
+                self.ctm.get_index(),
             );
-            return ImportType::None;
+            return ImportType::Failed;
+        }
+        if !self.import_exists(&path) {
+            self.add_diagnostic(
+                self.options.import_errors.get_severity(),
+                DiagnosticCode::ImportNotFound,
+                format!(
+                    "Import of \"{}\" failed: The file doesn't exist.",
+                    path.to_str().unwrap()
+                ),
+                self.ctm.get_index(),
+            );
+            return ImportType::Failed;
         }
         if let Some(fname) = path
             .file_name()
@@ -283,127 +1352,295 @@ impl Parser {
                 return ImportType::None;
             }
         }
+        let import_type = get_import_type(&path, &args);
+        let is_code = import_type == "code";
+        // only documents are parsed so other files can't cause a cycle
+        if import_type == "document" && self.is_ancestor_path(&path) {
+            self.add_diagnostic(
+                self.options.import_errors.get_severity(),
+                DiagnosticCode::ImportCycle,
+                format!(
+                    "Import of \"{}\" failed: The file is part of an import cycle.",
+                    path.to_str().unwrap()
+                ),
+                self.ctm.get_index(),
+            );
+            return ImportType::Failed;
+        }
         {
-            let mut paths = self.options.paths.lock().unwrap();
-            if paths.iter().find(|item| **item == path).is_some() {
-                log::warn!(
-                    "Import of \"{}\" failed: Already imported.\n\t--> {}\n",
-                    path.to_str().unwrap(),
-                    self.get_position_string(),
+            let paths = Arc::clone(&self.options.paths);
+            let mut paths = paths.lock().unwrap();
+            // code and section imports can be repeated next to each other
+            // as they only take a part of the file or don't parse it
+            let repeatable = args.contains_key("section") || is_code;
+            if paths.contains(&path) && !repeatable {
+                self.add_diagnostic(
+                    self.options.import_errors.get_severity(),
+                    DiagnosticCode::DuplicateImport,
+                    format!(
+                        "Import of \"{}\" failed: Already imported.",
+                        path.to_str().unwrap()
+                    ),
+                    self.ctm.get_index(),
                 );
-                return ImportType::None;
+                return ImportType::Failed;
             }
-            paths.push(path.clone());
-        }
-        match args.get("type").cloned() {
-            Some(s) if s == "stylesheet".to_string() => {
-                ImportType::Stylesheet(self.import_stylesheet(path))
-            }
-            Some(s) if s == "document".to_string() => {
-                ImportType::Document(self.import_document(path))
-            }
-            Some(s) if s == "bibliography".to_string() => {
-                ImportType::Bibliography(self.import_bib(path))
-            }
-            Some(s) if s == "manifest".to_string() || s == "config" => {
-                ImportType::Manifest(self.import_manifest(path))
-            }
-            Some(s) if s == "glossary".to_string() => {
-                ImportType::Glossary(self.import_glossary(path))
-            }
-            _ => {
-                lazy_static::lazy_static! {
-                    static ref BIB_NAME: Regex = Regex::new(r".*\.bib\.toml$").unwrap();
-                }
-                if let Some(fname) = path.file_name().and_then(|f| Some(f.to_str().unwrap())) {
-                    if BIB_NAME.is_match(fname) {
-                        return ImportType::Bibliography(self.import_bib(path));
-                    }
-                }
-                match path.extension().map(|e| e.to_str().unwrap().to_lowercase()) {
-                    Some(e) if e == "css" => ImportType::Stylesheet(self.import_stylesheet(path)),
-                    Some(e) if e == "toml" => ImportType::Manifest(self.import_manifest(path)),
-                    _ => ImportType::Document(self.import_document(path)),
-                }
+            if !paths.contains(&path) {
+                paths.push(path.clone());
             }
         }
+        self.dependencies.lock().unwrap().push(path.clone());
+        let path_string = path.to_string_lossy().to_string();
+        let import_type = self.import_path(path, &args);
+        if let Some(err) = import_type.get_error() {
+            self.add_diagnostic(
+                self.options.import_errors.get_severity(),
+                DiagnosticCode::ImportFailed,
+                format!("Import of \"{}\" failed: {}", path_string, err),
+                self.ctm.get_index(),
+            );
+        }
+
+        import_type
+    }
+
+    /// Imports the given path depending on the type argument or the file name
+    fn import_path(&mut self, path: PathBuf, args: &HashMap<String, String>) -> ImportType {
+        match get_import_type(&path, args) {
+            "stylesheet" => ImportType::Stylesheet(self.import_stylesheet(path)),
+            "bibliography" => ImportType::Bibliography(self.import_bib(path)),
+            "manifest" => ImportType::Manifest(self.import_manifest(path)),
+            "glossary" => ImportType::Glossary(self.import_glossary(path)),
+            "template" => ImportType::Template(self.import_template(path)),
+            "code" => ImportType::Code(self.import_code(path, args)),
+            "csv" => ImportType::Table(self.import_csv(path, args)),
+            _ => ImportType::Document(self.import_document(path, args)),
+        }
     }
 
     /// parses the given text into a document
     pub fn parse(&mut self) -> Document {
+        self.parse_with_metrics().0
+    }
+
+    /// Parses the given text into a document and returns it together with
+    /// the time spent in the phases of parsing
+    pub fn parse_with_metrics(&mut self) -> (Document, ParseMetrics) {
+        let start = Instant::now();
+        let mut metrics = ParseMetrics::default();
+        let wg = measure(&mut metrics.block_parsing, || self.parse_blocks());
+        measure(&mut metrics.imports, || {
+            wg.wait();
+            self.import_settings();
+        });
+        let document = self.finish_document(&mut metrics);
+        metrics.imported_documents = self.options.imported_documents.load(Ordering::SeqCst);
+        metrics.total = start.elapsed();
+
+        (document, metrics)
+    }
+
+    /// Parses the blocks of the document and imports the manifest.
+    /// Returns the wait group of the imported documents.
+    fn parse_blocks(&mut self) -> WaitGroup {
         self.options.document.path = if let Some(path) = &self.options.path {
-            Some(path.canonicalize().unwrap().to_str().unwrap().to_string())
+            Some(
+                self.options
+                    .resolver
+                    .canonicalize(path)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+            )
         } else {
             None
         };
+        if !self.options.is_child {
+            self.source = Some(DocumentSource::new(
+                self.ctm.get_remaining().to_string(),
+                &self.options,
+            ));
+        }
+        if let Ok(front_matter) = self.parse_front_matter() {
+            self.options.document.front_matter = Some(front_matter);
+        }
 
-        while !self.ctm.check_eof() {
-            match self.parse_block() {
-                Ok(block) => self.options.document.add_element(block),
-                Err(err) => {
-                    if self.ctm.check_eof() {
-                        break;
-                    }
-                    match err {
-                        ParseError::TapeError(t) => {
-                            log::error!(
-                                "Parse Error: {}\n\t--> {}\n",
-                                t,
-                                self.get_position_string_for_index(t.get_index())
-                            )
-                        }
-                        _ => {
-                            log::error!("{}", err)
-                        }
-                    }
-                    break;
-                }
-            }
+        while let Some(block) = self.parse_next_block() {
+            self.options.document.add_element(block);
         }
 
         let wg = self.wg.clone();
         self.wg = WaitGroup::new();
-        if !self.options.is_child {
-            self.import(
+        if !self.options.is_child && self.options.allow_imports {
+            self.import_default(
                 "Manifest.toml".to_string(),
                 &maplit::hashmap! {"type".to_string() => "manifest".to_string()},
             );
         }
-        wg.wait();
+
+        wg
+    }
+
+    /// Applies the front matter and imports the files of the settings of the root document
+    fn import_settings(&mut self) {
         if !self.options.is_child {
-            self.import_from_config();
+            if let Some(front_matter) = &self.options.document.front_matter {
+                self.options
+                    .document
+                    .config
+                    .lock()
+                    .set_from_front_matter(front_matter.clone());
+            }
+            if self.options.allow_imports {
+                self.import_from_config();
+            }
+        }
+    }
+
+    /// Processes the parsed document and returns it
+    fn finish_document(&mut self, metrics: &mut ParseMetrics) -> Document {
+        let start = Instant::now();
+        if let Some(source) = self.source.take() {
+            self.options.document.source = Some(Box::new(source.scan(&self.options.document)));
         }
-        self.options.document.post_process();
-        let document = std::mem::replace(&mut self.options.document, Document::new());
+        self.options.document.post_process_with_metrics(metrics);
+        if self.options.smart_punctuation {
+            SmartPunctuation.apply(&mut self.options.document);
+        }
+        self.options
+            .extensions
+            .post_process_document(&mut self.options.document);
+        let diagnostics = std::mem::take(&mut self.options.document.diagnostics);
+        self.options.report_diagnostics(diagnostics);
+        metrics.post_processing = start.elapsed() - metrics.placeholders;
+
+        std::mem::replace(&mut self.options.document, Document::new())
+    }
+
+    /// Parses the next top level block and returns None when the end of the document is reached.
+    /// Errors are logged and recorded and the rest of the line is skipped.
+    pub(crate) fn parse_next_block(&mut self) -> Option<Block> {
+        while !self.ctm.check_eof() {
+            match self.parse_block() {
+                Ok(block) => return Some(block),
+                Err(err) => {
+                    if self.ctm.check_eof() {
+                        break;
+                    }
+                    let index = match &err {
+                        ParseError::TapeError(t) => t.get_index(),
+                        _ => self.ctm.get_index(),
+                    };
+                    self.add_diagnostic(
+                        Severity::Error,
+                        DiagnosticCode::SyntaxError,
+                        format!("Parse Error: {}", err),
+                        index,
+                    );
+                    self.options.errors.lock().unwrap().push(err);
+                    self.skip_line();
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns an iterator over the events of the document that parses
+    /// one top level block at a time instead of building the whole document
+    pub fn events(&mut self) -> Events<'_> {
+        Events::new(self)
+    }
+
+    /// parses the given text into a document and returns it together with
+    /// all errors that were skipped while parsing the document and its imports
+    pub fn parse_with_errors(&mut self) -> (Document, Vec<ParseError>) {
+        let document = self.parse();
+        let errors = std::mem::take(&mut *self.options.errors.lock().unwrap());
+
+        (document, errors)
+    }
 
-        document
+    /// Skips the rest of the current line to continue parsing after an error
+    fn skip_line(&mut self) {
+        while !self.ctm.check_char(&LB) && self.ctm.next_char().is_some() {}
+        self.ctm.try_seek();
+    }
+
+    /// Returns the diagnostics of the document and its imports.
+    /// Diagnostics of imported documents are only complete after parsing finished.
+    pub fn get_diagnostics(&self) -> Diagnostics {
+        self.options.diagnostics.lock().unwrap().clone()
     }
 
     pub fn get_paths(&self) -> Vec<PathBuf> {
         self.options.paths.lock().unwrap().clone()
     }
 
+    /// Registers an extension that adds syntax to the parser.
+    /// It is used for the documents imported after the call as well.
+    pub fn register_extension<E: Extension + 'static>(&mut self, extension: E) {
+        self.options.extensions.register(extension);
+    }
+
+    /// Imports a settings file that is looked for without the user asking for it.
+    /// Missing files aren't reported as most documents don't have all of them.
+    fn import_default(&mut self, path: String, args: &HashMap<String, String>) {
+        let resolved = self.transform_path(path.clone());
+
+        if self.import_exists(&resolved) {
+            self.import(path, args);
+        } else {
+            log::debug!(
+                "Skipping the default import of {} as it doesn't exist",
+                path
+            );
+        }
+    }
+
     /// Imports files from the configs import values
     fn import_from_config(&mut self) {
         let config = Arc::clone(&self.options.document.config);
+        let defaults = ImportSettings::default();
 
-        let mut stylesheets = config.lock().imports.included_stylesheets.clone();
+        let mut stylesheets = config.lock().style.stylesheets.clone();
+        stylesheets.reverse();
+        stylesheets.append(&mut config.lock().imports.included_stylesheets.clone());
         let args = maplit::hashmap! {"type".to_string() => "stylesheet".to_string()};
         while let Some(s) = stylesheets.pop() {
-            self.import(s, &args);
+            if defaults.included_stylesheets.contains(&s) {
+                self.import_default(s, &args);
+            } else {
+                self.import(s, &args);
+            }
         }
 
         let mut bibliography = config.lock().imports.included_bibliography.clone();
         let args = maplit::hashmap! {"type".to_string() => "bibliography".to_string()};
         while let Some(s) = bibliography.pop() {
-            self.import(s, &args);
+            if defaults.included_bibliography.contains(&s) {
+                self.import_default(s, &args);
+            } else {
+                self.import(s, &args);
+            }
         }
 
         let mut glossaries = config.lock().imports.included_glossaries.clone();
 
         let args = maplit::hashmap! {"type".to_string() =>"glossary".to_string()};
         while let Some(s) = glossaries.pop() {
-            self.import(s, &args);
+            if defaults.included_glossaries.contains(&s) {
+                self.import_default(s, &args);
+            } else {
+                self.import(s, &args);
+            }
+        }
+
+        let template = config.lock().imports.html_template.clone();
+        if let Some(template) = template {
+            let args = maplit::hashmap! {"type".to_string() => "template".to_string()};
+            self.import(template, &args);
         }
     }
 }
@@ -414,5 +1651,167 @@ pub(crate) enum ImportType {
     Bibliography(ParseResult<()>),
     Manifest(ParseResult<()>),
     Glossary(ParseResult<()>),
+    Template(ParseResult<()>),
+    Code(ParseResult<CodeBlock>),
+    Table(ParseResult<Table>),
+    /// The import was skipped because of an error that has already been reported
+    Failed,
     None,
 }
+
+impl ImportType {
+    /// Returns the error of the import if it failed
+    fn get_error(&self) -> Option<&ParseError> {
+        match self {
+            ImportType::Document(Err(e))
+            | ImportType::Stylesheet(Err(e))
+            | ImportType::Bibliography(Err(e))
+            | ImportType::Manifest(Err(e))
+            | ImportType::Glossary(Err(e))
+            | ImportType::Template(Err(e)) => Some(e),
+            ImportType::Code(Err(e)) | ImportType::Table(Err(e)) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the type of the import given by the type argument or the file name
+fn get_import_type(path: &Path, args: &HashMap<String, String>) -> &'static str {
+    lazy_static::lazy_static! {
+        static ref BIB_NAME: Regex = Regex::new(r".*\.bib\.toml$").unwrap();
+    }
+    match args.get("type").map(String::as_str) {
+        Some("stylesheet") => return "stylesheet",
+        Some("document") => return "document",
+        Some("bibliography") => return "bibliography",
+        Some("manifest") | Some("config") => return "manifest",
+        Some("glossary") => return "glossary",
+        Some("template") => return "template",
+        Some("code") => return "code",
+        Some("csv") => return "csv",
+        _ => {}
+    }
+    if let Some(fname) = path.file_name().and_then(|f| Some(f.to_str().unwrap())) {
+        if BIB_NAME.is_match(fname) {
+            return "bibliography";
+        }
+    }
+    match path.extension().map(|e| e.to_str().unwrap().to_lowercase()) {
+        Some(e) if e == "css" => "stylesheet",
+        Some(e) if e == "toml" => "manifest",
+        Some(e) if e == "csv" => "csv",
+        _ => "document",
+    }
+}
+
+/// The location of an import statement that diagnostics of lazy imports are reported at
+struct ImportOrigin {
+    path: Option<String>,
+    span: Span,
+}
+
+/// Parses the document of a lazy import with the options of the importing parser.
+/// Files that can't be read are reported at the import statement and stay empty
+fn parse_lazy_import(
+    options: &ParserOptions,
+    path: &Path,
+    section: Option<&str>,
+    origin: &ImportOrigin,
+) -> Document {
+    let report = |severity, code, message| {
+        options.report_diagnostics(vec![Diagnostic {
+            severity,
+            code,
+            message,
+            path: origin.path.clone(),
+            span: origin.span,
+        }])
+    };
+    let text = match options.resolver.read_to_string(path) {
+        Ok(text) => text,
+        Err(_) if !options.resolver.exists(path) => {
+            report(
+                options.import_errors.get_severity(),
+                DiagnosticCode::ImportNotFound,
+                format!(
+                    "Import of \"{}\" failed: The file doesn't exist.",
+                    path.to_string_lossy()
+                ),
+            );
+            return options.document.clone();
+        }
+        Err(e) => {
+            report(
+                options.import_errors.get_severity(),
+                DiagnosticCode::ImportFailed,
+                format!("Import of \"{}\" failed: {}", path.to_string_lossy(), e),
+            );
+            return options.document.clone();
+        }
+    };
+    if let Err(message) = options.import_limits.check_file_size(&text) {
+        report(
+            Severity::Error,
+            DiagnosticCode::ImportLimitExceeded,
+            format!(
+                "Import of \"{}\" skipped: {}",
+                path.to_string_lossy(),
+                message
+            ),
+        );
+        return options.document.clone();
+    }
+    let mut parser = Parser::new(text, Some(options.clone()));
+    let mut document = parser.parse();
+    if let Some(section) = section {
+        parser.retain_import_section(&mut document, section);
+    }
+
+    document
+}
+
+/// Returns the lines of a text in a range like 10-20, 10- or 10 with the first line being 1
+fn select_lines(text: &str, range: &str) -> Option<String> {
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    let start = start.trim().parse::<usize>().ok()?.max(1);
+    let end = match end.trim() {
+        "" => usize::MAX,
+        end => end.parse::<usize>().ok()?,
+    };
+    if end < start {
+        return None;
+    }
+
+    Some(
+        text.lines()
+            .skip(start - 1)
+            .take(end - start + 1)
+            .map(|line| format!("{}\n", line))
+            .collect(),
+    )
+}
+
+/// Replaces the tabs in the indentation of each line with spaces
+/// up to the next multiple of the tab width
+fn expand_indentation_tabs(text: &str, tab_width: usize) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_start_matches([' ', '\t']);
+        let mut column = 0;
+
+        for ch in line[..line.len() - content.len()].chars() {
+            if ch == '\t' {
+                let spaces = tab_width - column % tab_width;
+                result.push_str(&" ".repeat(spaces));
+                column += spaces;
+            } else {
+                result.push(ch);
+                column += 1;
+            }
+        }
+        result.push_str(content);
+    }
+
+    result
+}
@@ -0,0 +1,506 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+const ESCAPE: char = '\\';
+
+#[derive(Debug)]
+pub struct TapeError {
+    index: usize,
+}
+
+impl TapeError {
+    pub fn new(index: usize) -> Self {
+        Self { index }
+    }
+
+    /// Returns the byte index the error occurred on
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+}
+
+impl Display for TapeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Tape Error at: {}", self.index)
+    }
+}
+
+impl Error for TapeError {}
+
+pub type TapeResult<T> = Result<T, TapeError>;
+
+/// A cursor over the text of a document.
+/// The index is a byte offset into the text that always lies on a char boundary.
+/// When the end of the text is reached the index equals the length of the text
+/// and the current char keeps the value of the last char.
+pub struct CharTapeMachine {
+    text: String,
+    index: usize,
+    current_char: char,
+    previous_char: char,
+}
+
+impl CharTapeMachine {
+    pub fn new(text: String) -> Self {
+        let current_char = text.chars().next().unwrap_or(' ');
+
+        Self {
+            text,
+            index: 0,
+            previous_char: current_char,
+            current_char,
+        }
+    }
+
+    /// Returns the text between the two byte indices
+    #[inline]
+    pub fn get_slice(&self, start: usize, end: usize) -> &str {
+        &self.text[start..end]
+    }
+
+    /// Returns the text from the current char to the end
+    #[inline]
+    pub fn get_remaining(&self) -> &str {
+        &self.text[self.index..]
+    }
+
+    /// Returns the byte index of the current char
+    #[inline]
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// returns the current char
+    #[inline]
+    pub fn get_current(&self) -> char {
+        self.current_char
+    }
+
+    /// Creates an error at the current position
+    #[inline]
+    pub fn err(&self) -> TapeError {
+        TapeError::new(self.index)
+    }
+
+    /// Returns the char starting at the given byte index
+    #[inline]
+    fn char_at(&self, index: usize) -> Option<char> {
+        self.text.get(index..)?.chars().next()
+    }
+
+    /// Returns the char that ends at the given byte index
+    #[inline]
+    fn char_before(&self, index: usize) -> Option<char> {
+        self.text.get(..index)?.chars().next_back()
+    }
+
+    /// Returns the next char
+    /// if there is any
+    pub fn next_char(&mut self) -> Option<char> {
+        if self.index < self.text.len() {
+            self.index += self.current_char.len_utf8();
+            self.previous_char = self.current_char;
+            self.current_char = self.char_at(self.index)?;
+
+            Some(self.current_char)
+        } else {
+            None
+        }
+    }
+
+    /// Peeks the next available char
+    #[inline]
+    pub fn peek_char(&self) -> Option<char> {
+        self.char_at(self.index + self.current_char.len_utf8())
+    }
+
+    /// Rewinds to a given byte index
+    #[inline]
+    pub fn rewind(&mut self, index: usize) {
+        if let Some(current) = self.char_at(index) {
+            self.index = index;
+            self.current_char = current;
+            if let Some(previous) = self.char_before(index) {
+                self.previous_char = previous;
+            }
+        }
+    }
+
+    /// Rewinds by the given number of chars
+    pub fn rewind_chars(&mut self, count: usize) {
+        let index = self.text[..self.index]
+            .char_indices()
+            .rev()
+            .nth(count.saturating_sub(1))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        self.rewind(index);
+    }
+
+    /// Rewinds to a given index and returns an error
+    #[inline]
+    pub fn rewind_with_error(&mut self, index: usize) -> TapeError {
+        self.rewind(index);
+        TapeError::new(index)
+    }
+
+    /// Seeks one character or returns an error
+    /// if there is no next character
+    #[inline]
+    pub fn seek_one(&mut self) -> TapeResult<()> {
+        if self.next_char().is_some() {
+            Ok(())
+        } else {
+            Err(TapeError::new(self.index))
+        }
+    }
+
+    /// Seeks one character and returns
+    /// if it seeked or an error occurred
+    #[inline]
+    pub fn try_seek(&mut self) -> bool {
+        self.seek_one().is_ok()
+    }
+
+    /// Seeks any character of the given group until none is encountered anymore
+    pub fn seek_any(&mut self, chars: &[char]) -> TapeResult<()> {
+        while self.check_any(chars) {
+            self.seek_one()?;
+        }
+
+        Ok(())
+    }
+
+    /// Seeks until it encounters a non whitespace character
+    pub fn seek_whitespace(&mut self) {
+        if self.current_char.is_whitespace() {
+            while let Some(next) = self.next_char() {
+                if !next.is_whitespace() || self.check_escaped() {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Checks if the machine has reached the eof
+    pub fn check_eof(&self) -> bool {
+        self.index >= self.text.len()
+    }
+
+    /// checks if the current char is escaped
+    #[inline]
+    pub fn check_escaped(&self) -> bool {
+        self.text.as_bytes()[..self.index]
+            .iter()
+            .rev()
+            .take_while(|b| **b == ESCAPE as u8)
+            .count()
+            % 2
+            == 1
+    }
+
+    /// Returns true if the given character is equal to the current one
+    /// and the current character is not escaped
+    #[inline]
+    pub fn check_char(&self, value: &char) -> bool {
+        self.current_char == *value && !self.check_escaped()
+    }
+
+    /// Checks if one of the given chars matches the current one
+    #[inline]
+    pub fn check_any(&self, chars: &[char]) -> bool {
+        !self.check_escaped() && chars.contains(&self.current_char)
+    }
+
+    /// checks if the next characters match a given sequence of characters
+    /// and moves to the last character of the sequence if they do.
+    /// The sequence has to be followed by at least one other character.
+    pub fn check_sequence(&mut self, sequence: &[char]) -> bool {
+        if sequence.is_empty() || self.check_escaped() || self.check_eof() {
+            return false;
+        }
+        let mut length = 0;
+
+        for (sq_character, character) in sequence.iter().zip(self.text[self.index..].chars()) {
+            if sq_character != &character {
+                return false;
+            }
+            length += character.len_utf8();
+        }
+        if self.index + length >= self.text.len() {
+            return false;
+        }
+        let last = sequence[sequence.len() - 1];
+        self.rewind(self.index + length - last.len_utf8());
+
+        true
+    }
+
+    /// checks if the next characters match any given sequence
+    #[inline]
+    pub fn check_any_sequence(&mut self, sequences: &[&[char]]) -> bool {
+        for seq in sequences {
+            if self.check_sequence(seq) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// returns an error on the current position and optionally rewinds
+    /// if a rewind index is given
+    #[inline]
+    pub fn assert_error(&mut self, rewind_index: Option<usize>) -> TapeError {
+        if let Some(index) = rewind_index {
+            self.rewind_with_error(index)
+        } else {
+            TapeError::new(self.index)
+        }
+    }
+
+    /// returns an error if the given char doesn't match the current one and rewinds
+    /// if a rewind index is given
+    #[inline]
+    pub fn assert_char(&mut self, value: &char, rewind_index: Option<usize>) -> TapeResult<()> {
+        if self.check_char(value) {
+            Ok(())
+        } else {
+            Err(self.assert_error(rewind_index))
+        }
+    }
+
+    /// returns an error if the current char doesn't match any of the given group
+    #[inline]
+    pub fn assert_any(&mut self, chars: &[char], rewind_index: Option<usize>) -> TapeResult<()> {
+        if self.check_any(chars) {
+            Ok(())
+        } else {
+            Err(self.assert_error(rewind_index))
+        }
+    }
+
+    /// returns an error if the next chars don't match a special sequence
+    #[inline]
+    pub fn assert_sequence(
+        &mut self,
+        sequence: &[char],
+        rewind_index: Option<usize>,
+    ) -> TapeResult<()> {
+        if self.check_sequence(sequence) {
+            Ok(())
+        } else {
+            Err(self.assert_error(rewind_index))
+        }
+    }
+
+    /// returns an error if the next chars don't match any given sequence
+    pub fn assert_any_sequence(
+        &mut self,
+        sequences: &[&[char]],
+        rewind_index: Option<usize>,
+    ) -> TapeResult<()> {
+        if self.check_any_sequence(sequences) {
+            Ok(())
+        } else {
+            Err(self.assert_error(rewind_index))
+        }
+    }
+
+    /// returns the string until any given character is matched is matched.
+    /// rewinds with error if it encounters a character form the error group
+    #[inline]
+    pub fn get_string_until_any(&mut self, until: &[char], err_at: &[char]) -> TapeResult<String> {
+        let start_index = self.index;
+
+        self.get_string_until_any_or_rewind(until, err_at, start_index)
+    }
+
+    /// Returns the string until it encounters a given sequence or rewinds with error
+    /// if it encounters an err sequence
+    pub fn get_string_until_sequence(
+        &mut self,
+        until: &[&[char]],
+        err_at: &[&[char]],
+    ) -> Result<String, TapeError> {
+        let start_index = self.index;
+
+        if self.check_any_sequence(until) {
+            return Ok(String::new());
+        } else if self.check_any_sequence(err_at) {
+            return Err(TapeError::new(self.index));
+        }
+
+        let mut end_index = self.text.len();
+        while self.next_char().is_some() {
+            let index = self.index;
+            if self.check_any_sequence(until) || self.check_any_sequence(err_at) {
+                end_index = index;
+                break;
+            }
+        }
+
+        if self.check_any_sequence(err_at) {
+            Err(self.rewind_with_error(start_index))
+        } else {
+            Ok(self.text[start_index..end_index].to_string())
+        }
+    }
+
+    /// returns the string until a special char is found
+    /// or rewinds if an err_at char is found
+    pub fn get_string_until_any_or_rewind(
+        &mut self,
+        until: &[char],
+        err_at: &[char],
+        rewind_index: usize,
+    ) -> TapeResult<String> {
+        let start_index = self.index;
+
+        if self.check_any(until) {
+            return Ok(String::new());
+        } else if self.check_any(err_at) {
+            return Err(self.rewind_with_error(rewind_index));
+        }
+
+        while self.next_char().is_some() {
+            if self.check_any(until) || self.check_any(err_at) {
+                break;
+            }
+        }
+        let end_index = self.index;
+
+        if self.check_any(err_at) {
+            Err(self.rewind_with_error(rewind_index))
+        } else {
+            Ok(self.text[start_index..end_index].to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a tape machine that is moved to the given byte index
+    fn tape_at(text: &str, index: usize) -> CharTapeMachine {
+        let mut ctm = CharTapeMachine::new(text.to_string());
+        ctm.rewind(index);
+
+        ctm
+    }
+
+    #[test]
+    fn check_escaped_counts_backslashes() {
+        assert!(!tape_at("a", 0).check_escaped());
+        assert!(tape_at(r"\a", 1).check_escaped());
+        assert!(!tape_at(r"\\a", 2).check_escaped());
+        assert!(tape_at(r"\\\a", 3).check_escaped());
+        assert!(!tape_at(r"\\\\a", 4).check_escaped());
+    }
+
+    #[test]
+    fn check_char_ignores_escaped_chars() {
+        assert!(tape_at("*", 0).check_char(&'*'));
+        assert!(!tape_at(r"\*", 1).check_char(&'*'));
+        assert!(tape_at(r"\\*", 2).check_char(&'*'));
+    }
+
+    #[test]
+    fn check_sequence_moves_to_the_last_char() {
+        let mut ctm = tape_at("**bold", 0);
+
+        assert!(ctm.check_sequence(&['*', '*']));
+        assert_eq!(ctm.get_index(), 1);
+        assert_eq!(ctm.get_current(), '*');
+    }
+
+    #[test]
+    fn check_sequence_fails_at_end_of_input() {
+        let mut ctm = tape_at("**", 0);
+        assert!(!ctm.check_sequence(&['*', '*']));
+        assert_eq!(ctm.get_index(), 0);
+
+        let mut ctm = tape_at("a", 0);
+        assert!(ctm.next_char().is_none());
+        assert!(ctm.check_eof());
+        assert!(!ctm.check_sequence(&['a']));
+    }
+
+    #[test]
+    fn check_sequence_fails_when_escaped() {
+        let mut ctm = tape_at(r"\**a", 1);
+
+        assert!(!ctm.check_sequence(&['*', '*']));
+        assert_eq!(ctm.get_index(), 1);
+    }
+
+    #[test]
+    fn rewind_and_seek() {
+        let mut ctm = tape_at("abc def", 0);
+        ctm.seek_one().unwrap();
+        ctm.seek_one().unwrap();
+        assert_eq!(ctm.get_current(), 'c');
+
+        ctm.rewind_chars(2);
+        assert_eq!(ctm.get_index(), 0);
+        assert_eq!(ctm.get_current(), 'a');
+
+        ctm.rewind(3);
+        ctm.seek_whitespace();
+        assert_eq!(ctm.get_current(), 'd');
+
+        let error = ctm.rewind_with_error(1);
+        assert_eq!(error.get_index(), 1);
+        assert_eq!(ctm.get_current(), 'b');
+
+        while ctm.try_seek() {}
+        assert!(ctm.check_eof());
+        assert!(ctm.seek_one().is_err());
+    }
+
+    #[test]
+    fn handles_multibyte_chars() {
+        let mut ctm = tape_at("äö€x", 0);
+
+        assert_eq!(ctm.peek_char(), Some('ö'));
+        assert_eq!(ctm.next_char(), Some('ö'));
+        assert_eq!(ctm.get_index(), 2);
+        assert_eq!(ctm.next_char(), Some('€'));
+        assert_eq!(ctm.get_index(), 4);
+        assert_eq!(ctm.get_remaining(), "€x");
+
+        // indices inside of a char are ignored
+        ctm.rewind(1);
+        assert_eq!(ctm.get_index(), 4);
+
+        ctm.rewind_chars(1);
+        assert_eq!(ctm.get_current(), 'ö');
+        assert_eq!(ctm.get_string_until_any(&['x'], &[]).unwrap(), "ö€");
+        assert_eq!(ctm.get_slice(0, 2), "ä");
+    }
+
+    #[test]
+    fn get_string_until_sequence_rewinds_on_error() {
+        let mut ctm = tape_at("text!end", 0);
+        let result = ctm.get_string_until_sequence(&[&['e', 'n', 'd']], &[&['!']]);
+
+        assert!(result.is_err());
+        assert_eq!(ctm.get_index(), 0);
+    }
+
+    #[test]
+    fn get_string_until_sequence_stops_before_the_sequence() {
+        let mut ctm = tape_at("text end", 0);
+        let result = ctm.get_string_until_sequence(&[&[' ', 'e']], &[]);
+
+        assert_eq!(result.unwrap(), "text");
+        assert_eq!(ctm.get_current(), 'e');
+    }
+}
@@ -0,0 +1,240 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Block, Inline, Line, ListItem, PlainText, Span, TextLine};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A definition of an abbreviation *[short]: long
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AbbreviationDefinition {
+    pub short: String,
+    pub long: String,
+    pub span: Span,
+}
+
+/// An occurrence of a defined abbreviation in the text
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Abbreviation {
+    pub short: String,
+    pub long: String,
+    pub span: Span,
+}
+
+/// An abbreviation manager that collects the definitions of all documents
+/// and wraps the occurrences of the abbreviations in the text
+#[derive(Clone, Debug, Default)]
+pub struct AbbreviationManager {
+    definitions: HashMap<String, String>,
+}
+
+impl AbbreviationManager {
+    /// Creates a new abbreviation manager
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new abbreviation definition to the manager
+    pub fn add_definition(&mut self, definition: &AbbreviationDefinition) {
+        let previous = self
+            .definitions
+            .insert(definition.short.clone(), definition.long.clone());
+        // the same definition is added again when the parser backtracks
+        if previous.is_some_and(|long| long != definition.long) {
+            log::warn!(
+                "Abbreviation '{}' is defined more than once",
+                definition.short
+            );
+        }
+        log::debug!("Added abbreviation {}", definition.short);
+    }
+
+    /// Returns the expansion of the given abbreviation
+    pub fn get(&self, short: &str) -> Option<&String> {
+        self.definitions.get(short)
+    }
+
+    /// Replaces all occurrences of the abbreviations in the plain text of the blocks
+    pub fn expand_abbreviations(&self, blocks: &mut [Block]) {
+        if self.definitions.is_empty() {
+            return;
+        }
+        let mut shorts: Vec<&String> = self.definitions.keys().collect();
+        // longer abbreviations are matched first if one starts with another
+        shorts.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        let pattern = shorts
+            .iter()
+            .map(|s| regex::escape(s))
+            .collect::<Vec<String>>()
+            .join("|");
+        let expander = Expander {
+            definitions: &self.definitions,
+            regex: Regex::new(&pattern).unwrap(),
+        };
+        expander.expand_blocks(blocks);
+    }
+}
+
+struct Expander<'a> {
+    definitions: &'a HashMap<String, String>,
+    regex: Regex,
+}
+
+impl<'a> Expander<'a> {
+    fn expand_blocks(&self, blocks: &mut [Block]) {
+        for block in blocks {
+            match block {
+                Block::Section(section) => {
+                    self.expand_line(&mut section.header.line);
+                    self.expand_blocks(&mut section.elements);
+                }
+                Block::Paragraph(paragraph) => paragraph
+                    .elements
+                    .iter_mut()
+                    .for_each(|l| self.expand_line(l)),
+                Block::List(list) => self.expand_list_items(&mut list.items),
+                Block::Table(table) => {
+                    for row in std::iter::once(&mut table.header).chain(table.rows.iter_mut()) {
+                        row.cells
+                            .iter_mut()
+                            .for_each(|c| self.expand_line(&mut c.text));
+                    }
+                }
+                Block::Quote(quote) => self.expand_blocks(&mut quote.elements),
+                Block::Aligned(aligned) => self.expand_blocks(&mut aligned.content),
+                Block::Columns(columns) => columns
+                    .columns
+                    .iter_mut()
+                    .for_each(|column| self.expand_blocks(column)),
+                Block::CustomContainer(container) => {
+                    self.expand_blocks(&mut container.content);
+                    if let Some(value) = &mut container.value {
+                        self.expand_blocks(std::slice::from_mut(value));
+                    }
+                }
+                Block::DefinitionList(list) => {
+                    for item in &mut list.items {
+                        self.expand_text(&mut item.term);
+                        item.definitions
+                            .iter_mut()
+                            .for_each(|d| self.expand_text(d));
+                    }
+                }
+                Block::Figure(figure) => self.expand_text(&mut figure.caption),
+                _ => {}
+            }
+        }
+    }
+
+    fn expand_list_items(&self, items: &mut [ListItem]) {
+        for item in items {
+            self.expand_line(&mut item.text);
+            self.expand_blocks(&mut item.blocks);
+            self.expand_list_items(&mut item.children);
+        }
+    }
+
+    fn expand_line(&self, line: &mut Line) {
+        match line {
+            Line::Text(text) => self.expand_text(text),
+            Line::Centered(centered) => self.expand_text(&mut centered.line),
+            Line::Anchor(anchor) => self.expand_line(&mut anchor.inner),
+            Line::RefLink(link) => self.expand_text(&mut link.description),
+            Line::FootnoteDefinition(footnote) => self.expand_text(&mut footnote.lock().text),
+            _ => {}
+        }
+    }
+
+    fn expand_text(&self, text: &mut TextLine) {
+        self.expand_inlines(&mut text.subtext)
+    }
+
+    fn expand_inlines(&self, inlines: &mut Vec<Inline>) {
+        let mut expanded = Vec::with_capacity(inlines.len());
+
+        for mut inline in inlines.drain(..) {
+            match &mut inline {
+                Inline::Plain(plain) => {
+                    self.split_plain(plain, &mut expanded);
+                    continue;
+                }
+                Inline::Bold(b) => self.expand_inlines(&mut b.value),
+                Inline::Italic(i) => self.expand_inlines(&mut i.value),
+                Inline::Underlined(u) => self.expand_inlines(&mut u.value),
+                Inline::Striked(s) => self.expand_inlines(&mut s.value),
+                Inline::Spoiler(s) => self.expand_inlines(&mut s.value),
+                Inline::Highlight(h) => self.expand_inlines(&mut h.value),
+                Inline::Superscript(s) => self.expand_inlines(&mut s.value),
+                Inline::Subscript(s) => self.expand_inlines(&mut s.value),
+                Inline::Url(url) => {
+                    if let Some(description) = &mut url.description {
+                        self.expand_inlines(description)
+                    }
+                }
+                Inline::Colored(colored) => {
+                    // the colored text can only hold a single element
+                    let mut value = vec![(*colored.value).clone()];
+                    self.expand_inlines(&mut value);
+                    if value.len() == 1 {
+                        *colored.value = value.remove(0);
+                    }
+                }
+                Inline::Styled(styled) => {
+                    let mut value = vec![(*styled.value).clone()];
+                    self.expand_inlines(&mut value);
+                    if value.len() == 1 {
+                        *styled.value = value.remove(0);
+                    }
+                }
+                Inline::Anchor(anchor) => self.expand_line(&mut anchor.inner),
+                _ => {}
+            }
+            expanded.push(inline);
+        }
+        *inlines = expanded;
+    }
+
+    /// Splits plain text at the occurrences of abbreviations
+    fn split_plain(&self, plain: &PlainText, output: &mut Vec<Inline>) {
+        let value = &plain.value;
+        let mut last_end = 0;
+
+        for found in self.regex.find_iter(value) {
+            let is_word = !value[..found.start()]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric)
+                && !value[found.end()..]
+                    .chars()
+                    .next()
+                    .is_some_and(char::is_alphanumeric);
+            if !is_word {
+                continue;
+            }
+            if found.start() > last_end {
+                output.push(Inline::Plain(PlainText {
+                    value: value[last_end..found.start()].into(),
+                    span: plain.span,
+                }));
+            }
+            output.push(Inline::Abbreviation(Abbreviation {
+                short: found.as_str().to_string(),
+                long: self.definitions[found.as_str()].clone(),
+                span: plain.span,
+            }));
+            last_end = found.end();
+        }
+        if last_end == 0 {
+            output.push(Inline::Plain(plain.clone()));
+        } else if last_end < value.len() {
+            output.push(Inline::Plain(PlainText {
+                value: value[last_end..].into(),
+                span: plain.span,
+            }));
+        }
+    }
+}
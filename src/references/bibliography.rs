@@ -4,7 +4,9 @@
  * See LICENSE for more information.
  */
 
-use crate::elements::{Anchor, BoldText, ItalicText, Line, List, ListItem, PlainText, TextLine};
+use crate::elements::{
+    Anchor, BoldText, ItalicText, Line, List, ListItem, PlainText, Span, TextLine,
+};
 use crate::elements::{Inline, Url};
 use bibliographix::bibliography::bib_types::article::Article;
 use bibliographix::bibliography::bib_types::book::Book;
@@ -395,3 +397,70 @@ fn get_item_for_website(entry: &BibliographyEntry, w: &Website) -> ListItem {
 
     list_item!(text, entry.key())
 }
+
+/// Serializes bibliography entries as the map of their fields
+pub(crate) mod entry_serde {
+    use bibliographix::bibliography::bibliography_entry::{
+        BibliographyEntry, BibliographyEntryReference,
+    };
+    use bibliographix::bibliography::FromHashMap;
+    use parking_lot::Mutex;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    pub fn serialize<S: Serializer>(
+        entry: &BibliographyEntryReference,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        entry.lock().raw_fields.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BibliographyEntryReference, D::Error> {
+        let fields = HashMap::<String, String>::deserialize(deserializer)?;
+        let entry = BibliographyEntry::from_hash_map(&fields).map_err(D::Error::custom)?;
+
+        Ok(Arc::new(Mutex::new(*entry)))
+    }
+}
+
+/// Serializes the anchor of a bibliography reference as the entry it points to
+pub(crate) mod anchor_serde {
+    use bibliographix::bibliography::bibliography_entry::BibliographyEntry;
+    use bibliographix::bibliography::FromHashMap;
+    use bibliographix::references::bib_reference::BibRefAnchor;
+    use parking_lot::Mutex;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    pub fn serialize<S: Serializer>(
+        anchor: &Arc<Mutex<BibRefAnchor>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        anchor
+            .lock()
+            .entry
+            .as_ref()
+            .map(|entry| entry.lock().raw_fields.clone())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Arc<Mutex<BibRefAnchor>>, D::Error> {
+        let entry =
+            if let Some(fields) = Option::<HashMap<String, String>>::deserialize(deserializer)? {
+                let entry = BibliographyEntry::from_hash_map(&fields).map_err(D::Error::custom)?;
+                Some(Arc::new(Mutex::new(*entry)))
+            } else {
+                None
+            };
+
+        Ok(Arc::new(Mutex::new(BibRefAnchor { entry })))
+    }
+}
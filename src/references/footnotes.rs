@@ -0,0 +1,197 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Anchor, BibReference, Line, List, ListItem, Span, TextLine};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::list_item;
+
+/// A footnote manager responsible for linking footnote references to their definitions
+#[derive(Clone, Debug, Default)]
+pub struct FootnoteManager {
+    definitions: HashMap<String, Arc<Mutex<FootnoteDefinition>>>,
+    references: Vec<Arc<Mutex<FootnoteReference>>>,
+    footnotes: Vec<Arc<Mutex<FootnoteDefinition>>>,
+}
+
+/// A single footnote definition [^label]: text
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FootnoteDefinition {
+    pub label: String,
+    pub text: TextLine,
+    pub number: Option<usize>,
+    pub span: Span,
+}
+
+/// A single footnote reference [^label]
+///
+/// The syntax is shared with bibliography references, so the reference
+/// falls back to the citation if no footnote with the label is defined.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FootnoteReference {
+    pub label: String,
+    pub definition: Option<Arc<Mutex<FootnoteDefinition>>>,
+    pub citation: Arc<RwLock<BibReference>>,
+    pub span: Span,
+}
+
+impl FootnoteManager {
+    /// Creates a new footnote manager
+    pub fn new() -> Self {
+        Self {
+            definitions: HashMap::new(),
+            references: Vec::new(),
+            footnotes: Vec::new(),
+        }
+    }
+
+    /// Adds a new footnote definition to the manager
+    pub fn add_definition(
+        &mut self,
+        definition: FootnoteDefinition,
+    ) -> Arc<Mutex<FootnoteDefinition>> {
+        let definition = Arc::new(Mutex::new(definition));
+        self.insert_definition(Arc::clone(&definition));
+
+        definition
+    }
+
+    /// Adds an existing footnote definition to the manager
+    pub(crate) fn insert_definition(&mut self, definition: Arc<Mutex<FootnoteDefinition>>) {
+        let label = definition.lock().label.clone();
+        if self.definitions.insert(label.clone(), definition).is_some() {
+            log::warn!("Footnote '{}' is defined more than once", label);
+        }
+        log::debug!("Added footnote definition {}", label);
+    }
+
+    /// Adds a new footnote reference to the manager
+    pub fn add_reference(&mut self, reference: FootnoteReference) -> Arc<Mutex<FootnoteReference>> {
+        let reference = Arc::new(Mutex::new(reference));
+        self.insert_reference(Arc::clone(&reference));
+
+        reference
+    }
+
+    /// Adds an existing footnote reference to the manager
+    pub(crate) fn insert_reference(&mut self, reference: Arc<Mutex<FootnoteReference>>) {
+        self.references.push(reference);
+    }
+
+    /// Removes all definitions and references and resets the assigned definitions and numbers
+    pub(crate) fn clear(&mut self) {
+        self.footnotes
+            .drain(..)
+            .for_each(|definition| definition.lock().number = None);
+        self.references
+            .drain(..)
+            .for_each(|reference| reference.lock().definition = None);
+        self.definitions.clear();
+    }
+
+    /// Moves the definitions and references of the other manager into this one.
+    /// The footnotes of the other manager keep their references and are numbered after
+    /// the existing ones. Definitions with a label that already exists get a unique label
+    pub(crate) fn append(&mut self, other: &mut FootnoteManager) {
+        for definition in other.definitions.values() {
+            let mut definition = definition.lock();
+            let mut label = definition.label.clone();
+            let mut index = 1;
+            while self.definitions.contains_key(&label) {
+                index += 1;
+                label = format!("{}-{}", definition.label, index);
+            }
+            definition.label = label;
+        }
+        for (_, definition) in other.definitions.drain() {
+            let label = definition.lock().label.clone();
+            self.definitions.insert(label, definition);
+        }
+        for reference in other.references.drain(..) {
+            let label = reference
+                .lock()
+                .definition
+                .as_ref()
+                .map(|d| d.lock().label.clone());
+            if let Some(label) = label {
+                reference.lock().label = label;
+            }
+            self.references.push(reference);
+        }
+        for footnote in other.footnotes.drain(..) {
+            footnote.lock().number = Some(self.footnotes.len() + 1);
+            self.footnotes.push(footnote);
+        }
+    }
+
+    /// Assigns definitions to references and numbers the footnotes
+    /// in the order they are first referenced
+    pub fn assign_definitions_to_references(&mut self) {
+        for reference in &self.references {
+            let mut reference = reference.lock();
+
+            if let Some(definition) = self.definitions.get(&reference.label) {
+                reference.definition = Some(Arc::clone(definition));
+                let mut definition_inner = definition.lock();
+
+                if definition_inner.number.is_none() {
+                    definition_inner.number = Some(self.footnotes.len() + 1);
+                    self.footnotes.push(Arc::clone(definition));
+                }
+            }
+        }
+    }
+
+    /// Returns all referenced footnotes in the order of their numbers
+    pub fn get_footnotes(&self) -> Vec<Arc<Mutex<FootnoteDefinition>>> {
+        self.footnotes.clone()
+    }
+
+    /// Creates an ordered list of all referenced footnotes
+    pub fn create_footnote_list(&self) -> List {
+        let mut list = List::new();
+        list.ordered = true;
+
+        for footnote in &self.footnotes {
+            let footnote = footnote.lock();
+            list.add_item(list_item!(footnote.text.clone(), footnote.get_anchor_key()));
+        }
+
+        list
+    }
+}
+
+impl FootnoteDefinition {
+    /// Creates a new footnote definition
+    pub fn new(label: String, text: TextLine) -> Self {
+        Self {
+            label,
+            text,
+            number: None,
+            span: Span::default(),
+        }
+    }
+
+    /// Returns the key of the anchor the footnote is rendered with
+    pub fn get_anchor_key(&self) -> String {
+        format!("footnote-{}", self.label)
+    }
+}
+
+impl FootnoteReference {
+    /// Creates a new footnote reference
+    pub fn new(label: String, citation: Arc<RwLock<BibReference>>) -> Self {
+        Self {
+            label,
+            definition: None,
+            citation,
+            span: Span::default(),
+        }
+    }
+}
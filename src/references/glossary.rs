@@ -5,9 +5,10 @@
  */
 
 use crate::elements::{
-    Anchor, BoldText, Inline, ItalicText, Line, List, ListItem, PlainText, TextLine,
+    Anchor, BoldText, Inline, ItalicText, Line, List, ListItem, PlainText, Span, TextLine,
 };
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -27,7 +28,7 @@ pub struct GlossaryManager {
 }
 
 /// A single glossary entry
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GlossaryEntry {
     pub short: String,
     pub long: String,
@@ -36,16 +37,17 @@ pub struct GlossaryEntry {
 }
 
 /// A single glossary reference
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GlossaryReference {
     pub short: String,
     pub display: GlossaryDisplay,
     pub entry: Option<Arc<Mutex<GlossaryEntry>>>,
+    pub span: Span,
 }
 
 /// A glossary display value that determines which value
 /// of a glossary entry will be rendered
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum GlossaryDisplay {
     Short,
     Long,
@@ -73,11 +75,30 @@ impl GlossaryManager {
     /// Adds a new glossary reference to the manager
     pub fn add_reference(&mut self, reference: GlossaryReference) -> Arc<Mutex<GlossaryReference>> {
         let reference = Arc::new(Mutex::new(reference));
-        self.references.push(Arc::clone(&reference));
+        self.insert_reference(Arc::clone(&reference));
 
         reference
     }
 
+    /// Adds an existing glossary reference to the manager
+    pub(crate) fn insert_reference(&mut self, reference: Arc<Mutex<GlossaryReference>>) {
+        self.references.push(reference);
+    }
+
+    /// Removes all references while keeping the entries
+    pub(crate) fn clear_references(&mut self) {
+        self.references.clear();
+    }
+
+    /// Moves the entries and references of the other manager into this one.
+    /// Entries that already exist are kept
+    pub(crate) fn append(&mut self, other: &mut GlossaryManager) {
+        for (key, entry) in other.entries.drain() {
+            self.entries.entry(key).or_insert(entry);
+        }
+        self.references.append(&mut other.references);
+    }
+
     /// Assignes bibliography entries from toml
     pub fn assign_from_toml(&mut self, value: toml::Value) -> Result<(), String> {
         let table = value.as_table().ok_or("Failed to parse toml".to_string())?;
@@ -164,6 +185,7 @@ impl GlossaryManager {
                 Line::Anchor(Anchor {
                     inner: Box::new(Line::Text(line)),
                     key: entry.short.clone(),
+                    span: Span::default(),
                 }),
                 0,
                 false,
@@ -181,6 +203,7 @@ impl GlossaryReference {
             short: key,
             display: GlossaryDisplay::Short,
             entry: None,
+            span: Span::default(),
         }
     }
 
@@ -190,6 +213,7 @@ impl GlossaryReference {
             short: key,
             display,
             entry: None,
+            span: Span::default(),
         }
     }
 }
@@ -0,0 +1,219 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Block, Inline, PlainText, Section, Span, Url};
+use std::collections::HashMap;
+use std::path::Path;
+
+const SECTION_SEPARATOR: char = '#';
+const LABEL_SEPARATOR: char = '|';
+
+/// A link to another document or a section of a document
+/// with the syntax [[Document#Section|Label]].
+/// References to a section of any document can be written as [[#Section]].
+#[derive(Clone, Debug)]
+pub struct WikiLink {
+    pub document: String,
+    pub section: Option<String>,
+    pub label: Option<String>,
+    pub numbered: bool,
+}
+
+/// A section that can be the target of a link
+#[derive(Clone, Debug)]
+pub struct LinkTarget {
+    pub anchor: String,
+    pub title: String,
+}
+
+/// The sections of a single document
+#[derive(Clone, Debug)]
+struct LinkDocument {
+    name: String,
+    targets: Vec<LinkTarget>,
+}
+
+/// A link manager that collects the sections of all documents
+/// to resolve wiki links against them
+#[derive(Clone, Debug, Default)]
+pub struct LinkManager {
+    documents: Vec<LinkDocument>,
+    numbers: HashMap<String, String>,
+}
+
+impl WikiLink {
+    /// Parses a wiki link from the name of a placeholder.
+    /// Returns None if the name contains neither a section nor a label
+    pub fn parse(name: &str) -> Option<Self> {
+        if !name.contains(SECTION_SEPARATOR) && !name.contains(LABEL_SEPARATOR) {
+            return None;
+        }
+        let (target, label) = match name.split_once(LABEL_SEPARATOR) {
+            Some((target, label)) => (target, Some(label.trim().to_string())),
+            None => (name, None),
+        };
+        let (document, section) = match target.split_once(SECTION_SEPARATOR) {
+            Some((document, section)) => (document, Some(section.trim().to_string())),
+            None => (target, None),
+        };
+
+        Some(Self {
+            document: document.trim().to_string(),
+            section,
+            label,
+            numbered: false,
+        })
+    }
+
+    /// Returns the link in its original syntax
+    pub fn get_target(&self) -> String {
+        let mut target = self.document.clone();
+        if let Some(section) = &self.section {
+            target.push(SECTION_SEPARATOR);
+            target.push_str(section);
+        }
+
+        target
+    }
+}
+
+impl LinkManager {
+    /// Creates a new link manager
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the sections of a document with the given path
+    pub fn add_document(&mut self, path: Option<&str>, elements: &[Block]) {
+        let name = path
+            .and_then(|p| Path::new(p).file_stem())
+            .map(|s| normalize_name(&s.to_string_lossy()))
+            .unwrap_or_default();
+        let mut targets = Vec::new();
+        collect_targets(elements, &mut targets);
+
+        self.documents.push(LinkDocument { name, targets })
+    }
+
+    /// Assigns numbers like 3.2 to all sections of the assembled document
+    pub fn assign_numbers(&mut self, elements: &[Block]) {
+        self.numbers.clear();
+        assign_section_numbers(elements, "", &mut self.numbers);
+    }
+
+    /// Returns the number of the section with the given anchor
+    pub fn get_number(&self, anchor: &str) -> Option<&String> {
+        self.numbers.get(anchor)
+    }
+
+    /// Resolves the link to an url pointing to the anchor of the target section.
+    /// If no document is given the sections of all documents are searched.
+    pub fn resolve(&self, link: &WikiLink) -> Result<Url, String> {
+        let mut documents = self.find_documents(&link.document).peekable();
+        if documents.peek().is_none() {
+            return Err(format!("Document '{}' wasn't found", link.document));
+        }
+
+        let target = if let Some(section) = &link.section {
+            let section = normalize_name(section);
+            documents
+                .flat_map(|d| d.targets.iter())
+                .find(|t| {
+                    normalize_name(&t.title) == section || normalize_name(&t.anchor) == section
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "Section '{}' wasn't found in '{}'",
+                        link.section.as_ref().unwrap(),
+                        link.document
+                    )
+                })?
+        } else {
+            documents
+                .find_map(|d| d.targets.first())
+                .ok_or_else(|| format!("Document '{}' has no sections", link.document))?
+        };
+        let label = match (&link.label, self.numbers.get(&target.anchor)) {
+            (Some(label), _) => label.clone(),
+            (None, Some(number)) if link.numbered => format!("Section {}", number),
+            _ => target.title.clone(),
+        };
+
+        Ok(Url {
+            description: Some(vec![Inline::Plain(PlainText {
+                value: label.into(),
+                span: Span::default(),
+            })]),
+            url: format!("#{}", target.anchor),
+            span: Span::default(),
+        })
+    }
+
+    /// Resolves the link or returns its label as plain text
+    /// together with the reason the target doesn't exist
+    pub fn resolve_or_plain(&self, link: &WikiLink, span: &Span) -> (Inline, Option<String>) {
+        match self.resolve(link) {
+            Ok(url) => (Inline::Url(url), None),
+            Err(e) => {
+                let label = Inline::Plain(PlainText {
+                    value: link
+                        .label
+                        .clone()
+                        .unwrap_or_else(|| link.get_target())
+                        .into(),
+                    span: *span,
+                });
+
+                (label, Some(e))
+            }
+        }
+    }
+
+    fn find_documents<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a LinkDocument> {
+        let name = normalize_name(name);
+
+        self.documents
+            .iter()
+            .filter(move |d| name.is_empty() || d.name == name)
+    }
+}
+
+/// Collects the anchors of all sections
+fn collect_targets(elements: &[Block], targets: &mut Vec<LinkTarget>) {
+    for element in elements {
+        if let Block::Section(Section {
+            header, elements, ..
+        }) = element
+        {
+            targets.push(LinkTarget {
+                anchor: header.anchor.clone(),
+                title: header.get_plain_text(),
+            });
+            collect_targets(elements, targets);
+        }
+    }
+}
+
+/// Numbers the sections by their position in the section tree
+fn assign_section_numbers(elements: &[Block], prefix: &str, numbers: &mut HashMap<String, String>) {
+    let sections = elements.iter().filter_map(|e| match e {
+        Block::Section(section) => Some(section),
+        _ => None,
+    });
+    for (index, section) in sections.enumerate() {
+        let number = format!("{}{}", prefix, index + 1);
+        assign_section_numbers(&section.elements, &format!("{}.", number), numbers);
+        numbers.insert(section.header.anchor.clone(), number);
+    }
+}
+
+/// Normalizes a name so that case, whitespace, dashes and underscores are ignored
+pub(crate) fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
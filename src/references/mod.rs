@@ -4,7 +4,10 @@
  * See LICENSE for more information.
  */
 
+pub mod abbreviations;
 pub mod bibliography;
+pub mod footnotes;
 pub mod glossary;
+pub mod links;
 pub mod placeholders;
 pub mod templates;
@@ -4,10 +4,18 @@
  * See LICENSE for more information.
  */
 
+use crate::elements::visitor::{walk_block, walk_inline, Visitor};
 use crate::elements::*;
+use crate::parser::diagnostics::{Diagnostic, DiagnosticCode, Severity};
 use crate::references::bibliography::create_bib_list;
+use crate::references::links::WikiLink;
+use chrono::format::{Item, StrftimeItems};
 use chrono::prelude::*;
 use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Debug};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
 
 macro_rules! block {
     ($inner:expr) => {
@@ -15,19 +23,115 @@ macro_rules! block {
     };
 }
 
-#[allow(unused)]
-macro_rules! line {
-    ($inner:expr) => {
-        Element::Line(Box::new($inner))
-    };
-}
-
 macro_rules! inline {
     ($inner:expr) => {
         Element::Inline(Box::new($inner))
     };
 }
 
+/// Resolves placeholders with a custom name.
+/// Implement this to add placeholders in addition to the built-in ones.
+pub trait PlaceholderHandler: Send + Sync {
+    /// Returns the value of the placeholder or None if it can't be resolved.
+    /// The document contains the elements of all imports at this point.
+    fn process(&self, placeholder: &Placeholder, document: &Document) -> Option<Element>;
+}
+
+impl Document {
+    /// Returns the placeholders in the text of the document and its imports in document order.
+    /// Their values can be inspected and replaced after the document was processed
+    pub fn placeholders(&self) -> Vec<Arc<RwLock<Placeholder>>> {
+        let mut collector = PlaceholderCollector::default();
+        collector.visit_document(self);
+
+        collector.placeholders
+    }
+
+    /// Sets the value of all placeholders with the given name and returns how many were changed.
+    /// Values that were assigned while processing the document are replaced
+    pub fn resolve_placeholder(&self, name: &str, value: Element) -> usize {
+        let placeholders = self
+            .placeholders()
+            .into_iter()
+            .filter(|p| p.read().unwrap().name.eq_ignore_ascii_case(name))
+            .collect::<Vec<_>>();
+
+        for placeholder in &placeholders {
+            placeholder.write().unwrap().set_value(value.clone());
+        }
+
+        placeholders.len()
+    }
+}
+
+#[derive(Default)]
+struct PlaceholderCollector {
+    placeholders: Vec<Arc<RwLock<Placeholder>>>,
+}
+
+impl Visitor for PlaceholderCollector {
+    fn visit_block(&mut self, block: &Block) {
+        if let Block::Placeholder(placeholder) = block {
+            self.placeholders.push(Arc::clone(placeholder));
+        }
+        walk_block(self, block)
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        if let Inline::Placeholder(placeholder) = inline {
+            self.placeholders.push(Arc::clone(placeholder));
+        }
+        walk_inline(self, inline)
+    }
+}
+
+impl<F> PlaceholderHandler for F
+where
+    F: Fn(&Placeholder, &Document) -> Option<Element> + Send + Sync,
+{
+    fn process(&self, placeholder: &Placeholder, document: &Document) -> Option<Element> {
+        self(placeholder, document)
+    }
+}
+
+/// The custom placeholder handlers by the name of the placeholder they resolve
+#[derive(Clone, Default)]
+pub struct PlaceholderHandlers {
+    handlers: HashMap<String, Arc<dyn PlaceholderHandler>>,
+}
+
+impl PlaceholderHandlers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the handler for placeholders with the given name.
+    /// Handlers take precedence over built-in placeholders with the same name.
+    pub fn register<H: PlaceholderHandler + 'static>(&mut self, name: &str, handler: H) {
+        self.handlers.insert(name.to_lowercase(), Arc::new(handler));
+    }
+
+    /// Adds the handlers of the other collection that don't exist in this one
+    pub(crate) fn extend(&mut self, other: &PlaceholderHandlers) {
+        for (name, handler) in &other.handlers {
+            self.handlers
+                .entry(name.clone())
+                .or_insert_with(|| Arc::clone(handler));
+        }
+    }
+
+    /// Returns the handler for placeholders with the given name
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn PlaceholderHandler>> {
+        self.handlers.get(&name.to_lowercase())
+    }
+}
+
+impl Debug for PlaceholderHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.handlers.keys()).finish()
+    }
+}
+
 pub(crate) trait ProcessPlaceholders {
     fn process_placeholders(&mut self);
     fn process_definitions(&mut self);
@@ -37,50 +141,153 @@ const S_VALUE: &str = "value";
 
 const P_TOC: &str = "toc";
 const P_BIB: &str = "bib";
+const P_BIBLIOGRAPHY: &str = "bibliography";
 const P_GLS: &str = "gls";
+const P_FOOTNOTES: &str = "footnotes";
 const P_DATE: &str = "date";
 const P_TIME: &str = "time";
 const P_DATETIME: &str = "datetime";
 const P_AUTHOR: &str = "author";
 const P_TITLE: &str = "title";
+const P_SET: &str = "set";
+const P_FILENAME: &str = "filename";
+const P_WORDCOUNT: &str = "wordcount";
+
+/// The placeholders that are replaced with a block and can't be used inside text
+pub(crate) const BLOCK_PLACEHOLDERS: &[&str] = &[P_BIBLIOGRAPHY];
 
 impl ProcessPlaceholders for Document {
     /// parses all placeholders and assigns values to them
     fn process_placeholders(&mut self) {
+        lazy_static::lazy_static! {
+            static ref RE_VAR: Regex = Regex::new(r"^var:(.*)$").unwrap();
+        }
+
+        let mut warnings = BTreeSet::new();
+        let mut broken_links = Vec::new();
+        let word_count = self.get_word_count();
+
         self.placeholders.iter().for_each(|p| {
+            let handler = self
+                .placeholder_handlers
+                .get(&p.read().unwrap().name)
+                .cloned();
+            if let Some(handler) = handler {
+                let value = handler.process(&p.read().unwrap(), self);
+                if let Some(value) = value {
+                    p.write().unwrap().set_value(value);
+                }
+                return;
+            }
             let mut pholder = p.write().unwrap();
+            if let Some(cap) = RE_VAR.captures(&pholder.name.clone()) {
+                let name = cap.get(1).unwrap().as_str().trim();
+                let value = self
+                    .config
+                    .lock()
+                    .custom_attributes
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.clone());
+
+                if let Some(value) = value {
+                    let span = pholder.span;
+                    pholder.set_value(inline!(Inline::Plain(PlainText {
+                        value: value.into(),
+                        span
+                    })))
+                } else {
+                    warnings.insert((
+                        pholder.span.line,
+                        pholder.span.column,
+                        format!("Undefined variable '{}'", name),
+                    ));
+                }
+                return;
+            }
             match pholder.name.to_lowercase().as_str() {
                 P_TOC => {
-                    let ordered = if let Some(meta) = &pholder.metadata {
-                        meta.get_bool("ordered")
+                    let options = if let Some(meta) = &pholder.metadata {
+                        TocOptions {
+                            ordered: meta.get_bool("ordered"),
+                            numbered: meta.get_bool("numbered"),
+                            max_depth: meta.get_integer("depth").map(|d| d.max(1) as usize),
+                        }
                     } else {
-                        false
+                        TocOptions::default()
                     };
-                    pholder.set_value(block!(Block::List(self.create_toc(ordered))))
+                    pholder.set_value(block!(Block::List(self.create_toc(&options))))
                 }
                 P_BIB => pholder.set_value(block!(Block::List(create_bib_list(
                     self.bibliography.get_entry_list_by_occurrence()
                 )))),
+                P_BIBLIOGRAPHY => {
+                    let mut entries = self.bibliography.get_entry_list_by_occurrence();
+                    entries.sort_by_key(|e| e.lock().key().to_lowercase());
+                    pholder.set_value(block!(Block::List(create_bib_list(entries))))
+                }
                 P_GLS => pholder.set_value(block!(Block::List(
                     self.glossary.lock().create_glossary_list()
                 ))),
-                P_DATE => pholder.set_value(inline!(Inline::Plain(PlainText {
-                    value: get_date_string()
-                }))),
-                P_TIME => pholder.set_value(inline!(Inline::Plain(PlainText {
-                    value: get_time_string()
-                }))),
-                P_DATETIME => pholder.set_value(inline!(Inline::Plain(PlainText {
-                    value: format!("{} {}", get_date_string(), get_time_string())
+                P_FOOTNOTES => pholder.set_value(block!(Block::List(
+                    self.footnotes.lock().create_footnote_list()
+                ))),
+                P_DATE => {
+                    let front_matter_date = self
+                        .front_matter
+                        .as_ref()
+                        .and_then(|f| f.get_string(P_DATE));
+                    let value = front_matter_date
+                        .unwrap_or_else(|| format_now(&pholder, get_date_string, &mut warnings));
+                    pholder.set_value(inline!(Inline::Plain(PlainText {
+                        value: value.into(),
+                        span: Span::default(),
+                    })))
+                }
+                P_TIME => {
+                    let value = format_now(&pholder, get_time_string, &mut warnings);
+                    pholder.set_value(inline!(Inline::Plain(PlainText {
+                        value: value.into(),
+                        span: Span::default(),
+                    })))
+                }
+                P_DATETIME => {
+                    let value = format_now(
+                        &pholder,
+                        || format!("{} {}", get_date_string(), get_time_string()),
+                        &mut warnings,
+                    );
+                    pholder.set_value(inline!(Inline::Plain(PlainText {
+                        value: value.into(),
+                        span: Span::default(),
+                    })))
+                }
+                P_FILENAME => {
+                    if let Some(name) = self.path.as_ref().and_then(|p| Path::new(p).file_name()) {
+                        pholder.set_value(inline!(Inline::Plain(PlainText {
+                            value: name.to_string_lossy().into(),
+                            span: Span::default(),
+                        })))
+                    }
+                }
+                P_WORDCOUNT => pholder.set_value(inline!(Inline::Plain(PlainText {
+                    value: word_count.to_string().into(),
+                    span: Span::default(),
                 }))),
                 P_AUTHOR => {
                     if let Some(value) = self.config.lock().metadata.author.clone() {
-                        pholder.set_value(inline!(Inline::Plain(PlainText { value })))
+                        pholder.set_value(inline!(Inline::Plain(PlainText {
+                            value: value.into(),
+                            span: Span::default(),
+                        })))
                     }
                 }
                 P_TITLE => {
                     if let Some(value) = self.config.lock().metadata.title.clone() {
-                        pholder.set_value(inline!(Inline::Plain(PlainText { value })))
+                        pholder.set_value(inline!(Inline::Plain(PlainText {
+                            value: value.into(),
+                            span: Span::default(),
+                        })))
                     }
                 }
                 _ => {
@@ -91,11 +298,40 @@ impl ProcessPlaceholders for Document {
                         .get(pholder.name.to_lowercase().as_str())
                         .cloned()
                     {
-                        pholder.set_value(inline!(Inline::Plain(PlainText { value })))
+                        pholder.set_value(inline!(Inline::Plain(PlainText {
+                            value: value.into(),
+                            span: Span::default(),
+                        })))
+                    } else if let Some(mut link) = WikiLink::parse(&pholder.name) {
+                        link.numbered = pholder
+                            .metadata
+                            .as_ref()
+                            .map(|m| m.get_bool("numbered"))
+                            .unwrap_or(false);
+                        let (value, error) =
+                            self.links.lock().resolve_or_plain(&link, &pholder.span);
+                        if let Some(error) = error {
+                            broken_links.push(Diagnostic {
+                                severity: Severity::Warning,
+                                code: DiagnosticCode::BrokenWikiLink,
+                                message: format!(
+                                    "Broken link to '{}': {}",
+                                    link.get_target(),
+                                    error
+                                ),
+                                path: self.path.clone(),
+                                span: pholder.span,
+                            });
+                        }
+                        pholder.set_value(inline!(value))
                     }
                 }
             }
-        })
+        });
+        self.diagnostics.append(&mut broken_links);
+        for (line, column, message) in warnings {
+            log::warn!("{} at {}:{}", message, line, column);
+        }
     }
 
     fn process_definitions(&mut self) {
@@ -108,11 +344,24 @@ impl ProcessPlaceholders for Document {
             let mut pholder = p.write().unwrap();
             let name = pholder.name.clone();
 
+            if name.to_lowercase() == P_SET {
+                pholder.value = Some(inline!(Inline::Plain(PlainText {
+                    value: "".into(),
+                    span: Span::default(),
+                })));
+                if let Some(meta) = &pholder.metadata {
+                    for (key, value) in &meta.data {
+                        self.config.lock().set_from_meta(key, value.clone())
+                    }
+                }
+                return;
+            }
             if let Some(cap) = RE_SET.captures(&name) {
                 if let Some(key) = cap.get(1) {
                     let key: &str = key.as_str();
                     pholder.value = Some(inline!(Inline::Plain(PlainText {
-                        value: "".to_string()
+                        value: "".into(),
+                        span: Span::default(),
                     })));
                     if let Some(meta) = &pholder.metadata {
                         if let Some(value) = meta.data.get(S_VALUE) {
@@ -126,6 +375,38 @@ impl ProcessPlaceholders for Document {
     }
 }
 
+/// Formats the current time with the strftime format given in the
+/// format metadata of the placeholder or returns the default value
+fn format_now<F: FnOnce() -> String>(
+    placeholder: &Placeholder,
+    default: F,
+    warnings: &mut BTreeSet<(usize, usize, String)>,
+) -> String {
+    let format = if let Some(format) = placeholder
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get_string("format"))
+    {
+        format
+    } else {
+        return default();
+    };
+    let items = StrftimeItems::new(&format).collect::<Vec<Item>>();
+
+    if items.iter().any(|i| matches!(i, Item::Error)) {
+        warnings.insert((
+            placeholder.span.line,
+            placeholder.span.column,
+            format!("Invalid date format '{}'", format),
+        ));
+        default()
+    } else {
+        Local::now()
+            .format_with_items(items.into_iter())
+            .to_string()
+    }
+}
+
 fn get_time_string() -> String {
     let now = Local::now();
     format!("{:02}:{:02}:{:02}", now.hour(), now.minute(), now.second())
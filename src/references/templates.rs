@@ -4,7 +4,8 @@
  * See LICENSE for more information.
  */
 
-use crate::elements::{Block, Element, Inline, Line, ListItem};
+use crate::elements::{Block, Element, Inline, Line, ListItem, Span};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
@@ -16,18 +17,19 @@ pub trait GetTemplateVariables {
     fn get_template_variables(&self) -> Vec<Arc<RwLock<TemplateVariable>>>;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Template {
     pub(crate) text: Vec<Element>,
     pub(crate) variables: HashMap<String, Arc<RwLock<TemplateVariable>>>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TemplateVariable {
     pub(crate) prefix: String,
     pub(crate) name: String,
     pub(crate) suffix: String,
     pub(crate) value: Option<Element>,
+    pub span: Span,
 }
 
 impl Template {
@@ -71,18 +73,34 @@ impl GetTemplateVariables for Inline {
         match self {
             Inline::TemplateVar(temp) => vec![Arc::clone(temp)],
             Inline::Colored(col) => col.value.get_template_variables(),
+            Inline::Styled(styled) => styled.value.get_template_variables(),
             Inline::Superscript(sup) => sup
                 .value
                 .iter()
                 .map(|e| e.get_template_variables())
                 .flatten()
                 .collect(),
+            Inline::Subscript(sub) => sub
+                .value
+                .iter()
+                .flat_map(|e| e.get_template_variables())
+                .collect(),
             Inline::Striked(striked) => striked
                 .value
                 .iter()
                 .map(|e| e.get_template_variables())
                 .flatten()
                 .collect(),
+            Inline::Spoiler(spoiler) => spoiler
+                .value
+                .iter()
+                .flat_map(|e| e.get_template_variables())
+                .collect(),
+            Inline::Highlight(highlight) => highlight
+                .value
+                .iter()
+                .flat_map(|e| e.get_template_variables())
+                .collect(),
             Inline::Underlined(under) => under
                 .value
                 .iter()
@@ -143,15 +161,25 @@ impl GetTemplateVariables for Block {
                 .flatten()
                 .collect(),
             Block::Quote(q) => q
-                .text
+                .elements
+                .iter()
+                .flat_map(|e| e.get_template_variables())
+                .collect(),
+            Block::Aligned(aligned) => aligned
+                .content
+                .iter()
+                .flat_map(|e| e.get_template_variables())
+                .collect(),
+            Block::Columns(columns) => columns
+                .columns
                 .iter()
-                .map(|t| {
-                    t.subtext
-                        .iter()
-                        .map(|i| i.get_template_variables())
-                        .flatten()
-                })
                 .flatten()
+                .flat_map(|e| e.get_template_variables())
+                .collect(),
+            Block::CustomContainer(container) => container
+                .content
+                .iter()
+                .flat_map(|e| e.get_template_variables())
                 .collect(),
             Block::List(list) => list
                 .items
@@ -159,6 +187,18 @@ impl GetTemplateVariables for Block {
                 .map(|item| item.get_template_variables())
                 .flatten()
                 .collect(),
+            Block::DefinitionList(list) => list
+                .items
+                .iter()
+                .flat_map(|item| std::iter::once(&item.term).chain(item.definitions.iter()))
+                .flat_map(|t| t.subtext.iter().flat_map(|i| i.get_template_variables()))
+                .collect(),
+            Block::Figure(figure) => figure
+                .caption
+                .subtext
+                .iter()
+                .flat_map(|i| i.get_template_variables())
+                .collect(),
             _ => Vec::new(),
         }
     }
@@ -186,6 +226,11 @@ impl FreezeVariables for Inline {
                     col.value = Box::new(Inline::TemplateVar(temp))
                 }
             }
+            Inline::Styled(styled) => {
+                if let Some(temp) = styled.value.freeze_variables() {
+                    *styled.value = Inline::TemplateVar(temp)
+                }
+            }
 
             Inline::Superscript(sup) => {
                 sup.value = sup
@@ -200,6 +245,45 @@ impl FreezeVariables for Inline {
                     })
                     .collect();
             }
+            Inline::Subscript(sub) => {
+                sub.value = sub
+                    .value
+                    .iter_mut()
+                    .map(|e| {
+                        if let Some(temp) = e.freeze_variables() {
+                            Inline::TemplateVar(temp)
+                        } else {
+                            e.clone()
+                        }
+                    })
+                    .collect();
+            }
+            Inline::Highlight(highlight) => {
+                highlight.value = highlight
+                    .value
+                    .iter_mut()
+                    .map(|e| {
+                        if let Some(temp) = e.freeze_variables() {
+                            Inline::TemplateVar(temp)
+                        } else {
+                            e.clone()
+                        }
+                    })
+                    .collect();
+            }
+            Inline::Spoiler(spoiler) => {
+                spoiler.value = spoiler
+                    .value
+                    .iter_mut()
+                    .map(|e| {
+                        if let Some(temp) = e.freeze_variables() {
+                            Inline::TemplateVar(temp)
+                        } else {
+                            e.clone()
+                        }
+                    })
+                    .collect();
+            }
             Inline::Striked(striked) => {
                 striked.value = striked
                     .value
@@ -267,6 +351,7 @@ impl GetTemplateVariables for ListItem {
             .flatten()
             .collect();
         inner_vars.append(&mut self.text.get_template_variables());
+        inner_vars.extend(self.blocks.iter().flat_map(|b| b.get_template_variables()));
 
         inner_vars
     }
@@ -317,8 +402,41 @@ impl FreezeVariables for Block {
             Block::Paragraph(p) => p.elements.iter_mut().for_each(|l| {
                 l.freeze_variables();
             }),
-            Block::Quote(q) => q.text.iter_mut().for_each(|t| {
-                t.subtext = t
+            Block::Quote(q) => q.elements.iter_mut().for_each(|b| {
+                b.freeze_variables();
+            }),
+            Block::Aligned(aligned) => aligned.content.iter_mut().for_each(|b| {
+                b.freeze_variables();
+            }),
+            Block::Columns(columns) => columns.columns.iter_mut().flatten().for_each(|b| {
+                b.freeze_variables();
+            }),
+            Block::CustomContainer(container) => container.content.iter_mut().for_each(|b| {
+                b.freeze_variables();
+            }),
+            Block::List(list) => list.items.iter_mut().for_each(|item| {
+                item.freeze_variables();
+            }),
+            Block::DefinitionList(list) => list.items.iter_mut().for_each(|item| {
+                std::iter::once(&mut item.term)
+                    .chain(item.definitions.iter_mut())
+                    .for_each(|t| {
+                        t.subtext = t
+                            .subtext
+                            .iter_mut()
+                            .map(|i| {
+                                if let Some(t) = i.freeze_variables() {
+                                    Inline::TemplateVar(t)
+                                } else {
+                                    (*i).clone()
+                                }
+                            })
+                            .collect()
+                    })
+            }),
+            Block::Figure(figure) => {
+                figure.caption.subtext = figure
+                    .caption
                     .subtext
                     .iter_mut()
                     .map(|i| {
@@ -329,10 +447,7 @@ impl FreezeVariables for Block {
                         }
                     })
                     .collect()
-            }),
-            Block::List(list) => list.items.iter_mut().for_each(|item| {
-                item.freeze_variables();
-            }),
+            }
             _ => {}
         };
 
@@ -358,6 +473,9 @@ impl FreezeVariables for ListItem {
             child.freeze_variables();
         });
         self.text.freeze_variables();
+        self.blocks.iter_mut().for_each(|b| {
+            b.freeze_variables();
+        });
         None
     }
 }
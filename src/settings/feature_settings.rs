@@ -11,6 +11,16 @@ pub struct FeatureSettings {
     pub embed_external: bool,
     pub smart_arrows: bool,
     pub include_mathjax: bool,
+    pub math_renderer: MathRenderer,
+    pub numbered_headings: bool,
+    pub sanitize_html: bool,
+}
+
+/// The script that is included to render TeX math
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum MathRenderer {
+    MathJax,
+    KaTeX,
 }
 
 impl Default for FeatureSettings {
@@ -19,6 +29,9 @@ impl Default for FeatureSettings {
             embed_external: true,
             smart_arrows: true,
             include_mathjax: true,
+            math_renderer: MathRenderer::MathJax,
+            numbered_headings: false,
+            sanitize_html: false,
         }
     }
 }
@@ -11,6 +11,9 @@ pub struct ImageSettings {
     pub format: Option<String>,
     pub max_width: Option<u32>,
     pub max_height: Option<u32>,
+    /// A directory relative to the output file that images are copied to
+    /// instead of embedding them into the document
+    pub assets: Option<String>,
 }
 
 impl Default for ImageSettings {
@@ -19,6 +22,7 @@ impl Default for ImageSettings {
             format: None,
             max_height: None,
             max_width: None,
+            assets: None,
         }
     }
 }
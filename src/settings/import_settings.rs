@@ -12,6 +12,7 @@ pub struct ImportSettings {
     pub included_stylesheets: Vec<String>,
     pub included_bibliography: Vec<String>,
     pub included_glossaries: Vec<String>,
+    pub html_template: Option<String>,
 }
 
 impl Default for ImportSettings {
@@ -21,6 +22,7 @@ impl Default for ImportSettings {
             included_stylesheets: vec!["style.css".to_string()],
             included_bibliography: vec!["Bibliography.toml".to_string()],
             included_glossaries: vec!["Glossary.toml".to_string()],
+            html_template: None,
         }
     }
 }
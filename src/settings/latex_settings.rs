@@ -0,0 +1,29 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LatexSettings {
+    pub document_class: String,
+    pub code_package: CodePackage,
+}
+
+/// The package that is used to typeset code blocks
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CodePackage {
+    Listings,
+    Minted,
+}
+
+impl Default for LatexSettings {
+    fn default() -> Self {
+        Self {
+            document_class: "article".to_string(),
+            code_package: CodePackage::Listings,
+        }
+    }
+}
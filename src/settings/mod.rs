@@ -8,6 +8,7 @@ use crate::elements::{Metadata, MetadataValue};
 use crate::settings::feature_settings::FeatureSettings;
 use crate::settings::image_settings::ImageSettings;
 use crate::settings::import_settings::ImportSettings;
+use crate::settings::latex_settings::LatexSettings;
 use crate::settings::metadata_settings::MetadataSettings;
 use crate::settings::pdf_settings::PDFSettings;
 use crate::settings::style_settings::StyleSettings;
@@ -23,6 +24,7 @@ use std::path::PathBuf;
 pub mod feature_settings;
 pub mod image_settings;
 pub mod import_settings;
+pub mod latex_settings;
 pub mod metadata_settings;
 pub mod pdf_settings;
 pub mod style_settings;
@@ -34,6 +36,7 @@ pub enum SettingsError {
     IoError(io::Error),
     ConfigError(ConfigError),
     TomlError(toml::ser::Error),
+    TomlParseError(toml::de::Error),
 }
 
 impl Display for SettingsError {
@@ -42,6 +45,7 @@ impl Display for SettingsError {
             Self::IoError(e) => write!(f, "IO Error: {}", e),
             Self::ConfigError(e) => write!(f, "Config Error: {}", e),
             Self::TomlError(e) => write!(f, "Toml Error: {}", e),
+            Self::TomlParseError(e) => write!(f, "Toml Error: {}", e),
         }
     }
 }
@@ -66,12 +70,19 @@ impl From<toml::ser::Error> for SettingsError {
     }
 }
 
+impl From<toml::de::Error> for SettingsError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::TomlParseError(e)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Settings {
     pub metadata: MetadataSettings,
     pub features: FeatureSettings,
     pub imports: ImportSettings,
     pub pdf: PDFSettings,
+    pub latex: LatexSettings,
     pub images: ImageSettings,
     pub style: StyleSettings,
     pub custom_attributes: HashMap<String, String>,
@@ -117,6 +128,18 @@ impl Settings {
         Ok(())
     }
 
+    /// Merges the current settings with the given toml content
+    pub fn merge_toml(&mut self, content: &str) -> SettingsResult<()> {
+        let mut settings = config::Config::default();
+        settings
+            .merge(self.clone())?
+            .merge(config::File::from_str(content, config::FileFormat::Toml))?;
+        let mut settings: Self = settings.try_into()?;
+        mem::swap(self, &mut settings);
+
+        Ok(())
+    }
+
     pub fn append_metadata<M: Metadata>(&mut self, metadata: M) {
         let entries = metadata.get_string_map();
         for (key, value) in entries {
@@ -124,6 +147,27 @@ impl Settings {
         }
     }
 
+    /// Sets the document metadata from the front matter of the root document.
+    /// All entries are additionally stored as custom attributes.
+    pub fn set_from_front_matter<M: Metadata>(&mut self, front_matter: M) {
+        if let Some(title) = front_matter.get_string("title") {
+            self.metadata.title = Some(title);
+        }
+        if let Some(author) = front_matter.get_string("author") {
+            self.metadata.author = Some(author);
+        }
+        if let Some(description) = front_matter.get_string("description") {
+            self.metadata.description = Some(description);
+        }
+        if let Some(language) = front_matter.get_string("language") {
+            self.metadata.language = language;
+        }
+        if let Some(keywords) = front_matter.get_string("keywords") {
+            self.metadata.keywords = keywords.split(',').map(|k| k.trim().to_string()).collect();
+        }
+        self.append_metadata(front_matter);
+    }
+
     pub fn set_from_meta(&mut self, key: &str, value: MetadataValue) {
         self.custom_attributes
             .insert(key.to_string(), value.to_string());
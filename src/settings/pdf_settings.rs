@@ -15,6 +15,8 @@ pub struct PDFSettings {
     pub page_width: Option<f32>,
     pub page_scale: f32,
     pub margin: PDFMarginSettings,
+    pub font_family: Option<String>,
+    pub font_size: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -49,6 +51,8 @@ impl Default for PDFSettings {
             page_width: None,
             page_scale: 1.0,
             margin: Default::default(),
+            font_family: None,
+            font_size: None,
         }
     }
 }
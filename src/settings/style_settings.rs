@@ -10,6 +10,10 @@ use serde::{Deserialize, Serialize};
 pub struct StyleSettings {
     pub bib_ref_display: String,
     pub theme: Theme,
+    /// Additional stylesheets that are included after the theme
+    pub stylesheets: Vec<String>,
+    /// Embeds the stylesheets into the html instead of linking them
+    pub inline_css: bool,
 }
 
 impl Default for StyleSettings {
@@ -17,6 +21,8 @@ impl Default for StyleSettings {
         Self {
             bib_ref_display: "{{number}}".to_string(),
             theme: Theme::GitHub,
+            stylesheets: Vec::new(),
+            inline_css: true,
         }
     }
 }
@@ -29,4 +35,7 @@ pub enum Theme {
     OceanDark,
     OceanLight,
     MagicDark,
+    Light,
+    Dark,
+    Print,
 }
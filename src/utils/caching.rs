@@ -9,6 +9,7 @@ use sha2::Digest;
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Clone, Debug)]
 pub struct CacheStorage {
@@ -47,6 +48,16 @@ impl CacheStorage {
         cache_path.exists()
     }
 
+    /// Returns the time since the cache file for the given file was last written
+    pub fn get_age(&self, path: &PathBuf) -> Option<Duration> {
+        let cache_path = self.get_file_path(path);
+
+        fs::metadata(cache_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+    }
+
     /// Writes into the corresponding cache file
     pub fn read(&self, path: &PathBuf) -> io::Result<Vec<u8>> {
         let cache_path = self.get_file_path(path);
@@ -57,10 +68,18 @@ impl CacheStorage {
     /// Reads the corresponding cache file
     pub fn write<R: AsRef<[u8]>>(&self, path: &PathBuf, contents: R) -> io::Result<()> {
         let cache_path = self.get_file_path(path);
+        fs::create_dir_all(&self.location)?;
 
         fs::write(cache_path, contents)
     }
 
+    /// Removes the corresponding cache file
+    pub fn remove(&self, path: &PathBuf) -> io::Result<()> {
+        let cache_path = self.get_file_path(path);
+
+        fs::remove_file(cache_path)
+    }
+
     /// Clears the cache directory by deleting and recreating it
     pub fn clear(&self) -> io::Result<()> {
         fs::remove_dir_all(&self.location)?;
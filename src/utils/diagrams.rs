@@ -0,0 +1,46 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::DiagramKind;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Renders the diagram source to svg with the command line tool of the diagram kind.
+/// Graphviz diagrams are rendered with `dot` and mermaid diagrams with `mmdc`.
+pub fn render_svg(kind: &DiagramKind, source: &str) -> io::Result<String> {
+    let mut command = match kind {
+        DiagramKind::Graphviz => {
+            let mut command = Command::new("dot");
+            command.arg("-Tsvg");
+            command
+        }
+        DiagramKind::Mermaid => {
+            let mut command = Command::new("mmdc");
+            command.args(&["--input", "-", "--output", "-", "--outputFormat", "svg"]);
+            command
+        }
+    };
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "Failed to open stdin"))?
+        .write_all(source.as_bytes())?;
+    let output = child.wait_with_output()?;
+
+    if output.status.success() {
+        String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ))
+    }
+}
@@ -5,6 +5,7 @@
  */
 
 use crate::utils::caching::CacheStorage;
+#[cfg(feature = "cli")]
 use indicatif::{ProgressBar, ProgressStyle};
 use parking_lot::Mutex;
 use rayon::prelude::*;
@@ -36,7 +37,14 @@ impl DownloadManager {
         pending
     }
 
+    /// Returns all download entries
+    #[cfg(feature = "tokio")]
+    pub(crate) fn get_downloads(&self) -> Vec<Arc<Mutex<PendingDownload>>> {
+        self.downloads.clone()
+    }
+
     /// Downloads all download entries
+    #[cfg(feature = "cli")]
     pub fn download_all(&self) {
         let pb = Arc::new(Mutex::new(ProgressBar::new(self.downloads.len() as u64)));
         pb.lock().set_style(
@@ -52,6 +60,12 @@ impl DownloadManager {
         });
         pb.lock().finish_and_clear();
     }
+
+    /// Downloads all download entries
+    #[cfg(not(feature = "cli"))]
+    pub fn download_all(&self) {
+        self.downloads.par_iter().for_each(|d| d.lock().download());
+    }
 }
 
 /// A pending download entry.
@@ -61,6 +75,7 @@ pub struct PendingDownload {
     pub(crate) path: String,
     pub(crate) data: Option<Vec<u8>>,
     pub(crate) use_cache: bool,
+    downloaded: bool,
     cache: CacheStorage,
 }
 
@@ -70,13 +85,32 @@ impl PendingDownload {
             path,
             data: None,
             use_cache: true,
+            downloaded: false,
             cache: CacheStorage::new(),
         }
     }
 
     /// Downloads the file and writes the content to the content field
+    /// if it wasn't downloaded before
     pub fn download(&mut self) {
-        self.data = self.read_content();
+        if !self.downloaded {
+            self.data = self.read_content();
+            self.downloaded = true;
+        }
+    }
+
+    /// Downloads the file without blocking the executor.
+    /// The download isn't locked while it's fetched.
+    #[cfg(feature = "tokio")]
+    pub async fn download_async(download: &Mutex<Self>) {
+        let pending = download.lock().clone();
+        if pending.downloaded {
+            return;
+        }
+        let data = pending.read_content_async().await;
+        let mut download = download.lock();
+        download.data = data;
+        download.downloaded = true;
     }
 
     /// Reads the fiels content or downloads it if it doesn't exist in the filesystem
@@ -98,6 +132,26 @@ impl PendingDownload {
         }
     }
 
+    /// Reads the files content or downloads it without blocking the executor
+    #[cfg(feature = "tokio")]
+    async fn read_content_async(&self) -> Option<Vec<u8>> {
+        let path = PathBuf::from(&self.path);
+
+        if path.exists() {
+            tokio::fs::read(path).await.ok()
+        } else if let Some(contents) = self.read_from_cache() {
+            log::debug!("Read {} from cache.", self.path.clone());
+            Some(contents)
+        } else {
+            let data = download_path_async(self.path.clone()).await;
+            if let Some(data) = &data {
+                self.store_to_cache(data);
+            }
+
+            data
+        }
+    }
+
     /// Stores the data to a cache file to retrieve it later
     fn store_to_cache(&self, data: &Vec<u8>) {
         if self.use_cache {
@@ -124,6 +178,14 @@ impl PendingDownload {
     }
 }
 
+/// Downloads the content from the given url without blocking the executor
+#[cfg(feature = "tokio")]
+pub async fn download_path_async(path: String) -> Option<Vec<u8>> {
+    let response = reqwest::get(&path).await.ok()?;
+
+    response.bytes().await.ok().map(|b| b.to_vec())
+}
+
 pub fn download_path(path: String) -> Option<Vec<u8>> {
     reqwest::blocking::get(&path)
         .ok()
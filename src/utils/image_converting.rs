@@ -10,13 +10,17 @@ use crate::utils::downloads::download_path;
 use image::imageops::FilterType;
 use image::io::Reader as ImageReader;
 use image::{GenericImageView, ImageFormat, ImageResult};
+#[cfg(feature = "cli")]
 use indicatif::{ProgressBar, ProgressStyle};
 use mime::Mime;
 use parking_lot::Mutex;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::fs;
 use std::io;
 use std::io::Cursor;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Clone, Debug)]
@@ -51,7 +55,53 @@ impl ImageConverter {
         image
     }
 
+    /// Removes an image that was added but isn't used
+    pub fn remove_image(&mut self, image: &Arc<Mutex<PendingImage>>) {
+        self.images.retain(|i| !Arc::ptr_eq(i, image))
+    }
+
+    /// Adds an image that was already created
+    pub(crate) fn insert_image(&mut self, image: Arc<Mutex<PendingImage>>) {
+        self.images.push(image);
+    }
+
+    /// Moves the images of the other converter into this one
+    pub(crate) fn append(&mut self, other: &mut ImageConverter) {
+        self.images.append(&mut other.images);
+    }
+
+    /// Removes all images
+    pub(crate) fn clear(&mut self) {
+        self.images.clear();
+    }
+
+    /// Returns the paths of all images
+    pub fn get_paths(&self) -> Vec<PathBuf> {
+        self.images.iter().map(|i| i.lock().path.clone()).collect()
+    }
+
+    /// Copies all images into the given directory so that they are referenced
+    /// by the directory name joined with the file name instead of being embedded
+    pub fn copy_all(&mut self, dir: &Path, name: &str) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        for image in &self.images {
+            let mut image = image.lock();
+            let file_name = image.get_asset_name();
+            let data = if let Some(data) = image.data.take() {
+                data
+            } else {
+                fs::read(image.get_path()?)?
+            };
+            fs::write(dir.join(&file_name), data)?;
+            image.url = Some(format!("{}/{}", name, file_name));
+        }
+
+        Ok(())
+    }
+
     /// Converts all images
+    #[cfg(feature = "cli")]
     pub fn convert_all(&mut self) {
         let pb = Arc::new(Mutex::new(ProgressBar::new(self.images.len() as u64)));
         pb.lock().set_style(
@@ -68,13 +118,30 @@ impl ImageConverter {
         });
         pb.lock().finish_and_clear();
     }
+
+    /// Converts all images
+    #[cfg(not(feature = "cli"))]
+    pub fn convert_all(&mut self) {
+        self.images.par_iter().for_each(|image| {
+            let mut image = image.lock();
+            if let Err(e) = image.convert(self.target_format.clone(), self.target_size.clone()) {
+                log::error!("Failed to embed image {:?}: {}", image.path, e)
+            }
+        });
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PendingImage {
     pub path: PathBuf,
+    /// The embedded data isn't serialized, deserialized images are referenced by their url or path
+    #[serde(skip)]
     pub data: Option<Vec<u8>>,
+    /// The url the image is referenced by if it isn't embedded
+    pub url: Option<String>,
+    #[serde(skip, default = "CacheStorage::new")]
     cache: CacheStorage,
+    #[serde(with = "mime_serde")]
     pub mime: Mime,
     brightness: Option<i32>,
     contrast: Option<f32>,
@@ -90,6 +157,7 @@ impl PendingImage {
         Self {
             path,
             data: None,
+            url: None,
             cache: CacheStorage::new(),
             mime,
             brightness: None,
@@ -203,6 +271,29 @@ impl PendingImage {
         Ok(self.path.clone())
     }
 
+    /// Returns the unique file name of the image in the assets directory
+    fn get_asset_name(&self) -> String {
+        let mut hasher = sha2::Sha256::default();
+        hasher.update(self.path.to_string_lossy().as_bytes());
+        let hash = format!("{:x}", hasher.finalize());
+        let stem = self
+            .path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = mime_guess::get_mime_extensions(&self.mime)
+            .and_then(|e| e.first())
+            .map(|e| e.to_string())
+            .or_else(|| {
+                self.path
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "png".to_string());
+
+        format!("{}-{}.{}", stem, &hash[..8], extension)
+    }
+
     /// Returns the output file name after converting the image
     fn get_output_path(
         &self,
@@ -247,3 +338,20 @@ fn get_mime(path: &PathBuf) -> Mime {
     .unwrap_or(mime::IMAGE_PNG);
     mime
 }
+
+/// Serializes mime types as their string representation
+mod mime_serde {
+    use mime::Mime;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(mime: &Mime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(mime.as_ref())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Mime, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Strings longer than this are unlikely to appear again and aren't interned
+const MAX_INTERNED_LENGTH: usize = 64;
+
+/// Shares the text of inlines that appears multiple times in a document,
+/// like words, whitespace and punctuation, so that it's only stored once
+#[derive(Clone, Debug, Default)]
+pub struct StringInterner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl StringInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared string with the given value
+    pub fn intern(&mut self, value: &str) -> Arc<str> {
+        if value.len() > MAX_INTERNED_LENGTH {
+            return Arc::from(value);
+        }
+        if let Some(string) = self.strings.get(value) {
+            return Arc::clone(string);
+        }
+        let string: Arc<str> = Arc::from(value);
+        self.strings.insert(Arc::clone(&string));
+
+        string
+    }
+
+    /// Returns the number of distinct interned strings
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
@@ -7,7 +7,10 @@
 #[macro_export]
 macro_rules! plain_text {
     ($e:expr) => {
-        Inline::Plain(PlainText { value: $e })
+        Inline::Plain(PlainText {
+            value: $e.into(),
+            span: Span::default(),
+        })
     };
 }
 
@@ -15,7 +18,11 @@ macro_rules! plain_text {
 macro_rules! bold_text {
     ($e:expr) => {
         Inline::Bold(BoldText {
-            value: vec![Inline::Plain(PlainText { value: $e })],
+            value: vec![Inline::Plain(PlainText {
+                value: $e.into(),
+                span: Span::default(),
+            })],
+            span: Span::default(),
         })
     };
 }
@@ -24,7 +31,11 @@ macro_rules! bold_text {
 macro_rules! italic_text {
     ($e:expr) => {
         Inline::Italic(ItalicText {
-            value: vec![Inline::Plain(PlainText { value: $e })],
+            value: vec![Inline::Plain(PlainText {
+                value: $e.into(),
+                span: Span::default(),
+            })],
+            span: Span::default(),
         })
     };
 }
@@ -35,6 +46,7 @@ macro_rules! url_text {
         Inline::Url(Url {
             url: $e,
             description: None,
+            span: Span::default(),
         })
     };
 }
@@ -46,6 +58,7 @@ macro_rules! list_item {
             Line::Anchor(Anchor {
                 inner: Box::new(Line::Text($e)),
                 key: $k,
+                span: Span::default(),
             }),
             0,
             true,
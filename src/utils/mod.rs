@@ -5,7 +5,15 @@
  */
 
 pub mod caching;
+#[cfg(feature = "diagrams")]
+pub mod diagrams;
 pub mod downloads;
 pub mod image_converting;
+pub mod interning;
 pub mod macros;
 pub mod parsing;
+#[cfg(feature = "serve")]
+pub mod serving;
+pub mod threading;
+#[cfg(feature = "watch")]
+pub mod watching;
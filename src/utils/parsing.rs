@@ -4,7 +4,11 @@
  * See LICENSE for more information.
  */
 
+use crate::elements::{InlineMetadata, MetadataValue};
 use regex::Regex;
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthChar;
+use yaml_rust::{ScanError, Yaml, YamlLoader};
 #[macro_export]
 macro_rules! parse {
     ($str:expr) => {
@@ -19,3 +23,115 @@ pub(crate) fn remove_single_backlslash<S: ToString>(content: S) -> String {
 
     R.replace_all(&*content, "$c").to_string()
 }
+
+/// Parses yaml content into metadata. Lists of values are joined into a single string,
+/// nested mappings are ignored.
+pub(crate) fn metadata_from_yaml(content: &str) -> Result<InlineMetadata, ScanError> {
+    let documents = YamlLoader::load_from_str(content)?;
+    let mut data = HashMap::new();
+
+    if let Some(Yaml::Hash(hash)) = documents.first() {
+        for (key, value) in hash {
+            if let (Some(key), Some(value)) = (yaml_to_string(key), yaml_to_metadata_value(value)) {
+                data.insert(key, value);
+            } else {
+                log::debug!("Ignoring yaml entry {:?}", key);
+            }
+        }
+    }
+
+    Ok(InlineMetadata { data })
+}
+
+/// Converts a single yaml value into a metadata value
+fn yaml_to_metadata_value(value: &Yaml) -> Option<MetadataValue> {
+    match value {
+        Yaml::Boolean(b) => Some(MetadataValue::Bool(*b)),
+        Yaml::Integer(i) => Some(MetadataValue::Integer(*i)),
+        Yaml::Real(_) => value.as_f64().map(MetadataValue::Float),
+        Yaml::Array(values) => Some(MetadataValue::String(
+            values
+                .iter()
+                .filter_map(yaml_to_string)
+                .collect::<Vec<String>>()
+                .join(", "),
+        )),
+        _ => yaml_to_string(value).map(MetadataValue::String),
+    }
+}
+
+/// Returns the string representation of a scalar yaml value
+fn yaml_to_string(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Real(r) => Some(r.clone()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Parses csv content into rows of fields. Fields can be quoted with double quotes
+/// to contain the delimiter or line breaks and quotes are escaped by doubling them.
+pub(crate) fn parse_csv(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if quoted => field.push(c),
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows
+}
+
+/// Returns the width of the indentation in columns. Tabs advance to the next multiple of
+/// the tab width, wide characters like the ideographic space count as two columns
+/// and non-breaking spaces as one
+pub(crate) fn get_indentation_width(indentation: &str, tab_width: usize) -> usize {
+    indentation
+        .chars()
+        .fold(0, |width, ch| advance_column(width, ch, tab_width))
+}
+
+/// Returns the byte offset in the indentation where the given column is reached
+pub(crate) fn get_indentation_offset(indentation: &str, column: usize, tab_width: usize) -> usize {
+    let mut width = 0;
+
+    for (offset, ch) in indentation.char_indices() {
+        if width >= column {
+            return offset;
+        }
+        width = advance_column(width, ch, tab_width);
+    }
+
+    indentation.len()
+}
+
+fn advance_column(width: usize, ch: char, tab_width: usize) -> usize {
+    match ch {
+        '\t' => width + tab_width - width % tab_width,
+        ch => width + ch.width().unwrap_or(0),
+    }
+}
@@ -0,0 +1,176 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use parking_lot::{Mutex, RwLock};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use tungstenite::{Message, WebSocket};
+
+const RELOAD_PATH: &str = "/__reload";
+const RELOAD_SCRIPT: &str = "<script>(function(){\
+    var socket = new WebSocket((location.protocol === 'https:' ? 'wss://' : 'ws://') + location.host + '/__reload');\
+    socket.onmessage = function() { location.reload(); };\
+    })();</script>";
+
+/// A development server that serves the rendered html of a document.
+/// Opened pages hold a websocket connection and are reloaded when the html changes.
+/// Other files are served from the root directory so that linked assets can be loaded.
+pub struct PreviewServer {
+    address: SocketAddr,
+    html: Arc<RwLock<String>>,
+    clients: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
+}
+
+impl PreviewServer {
+    /// Starts the server on the given address in a background thread
+    pub fn start<A: ToSocketAddrs>(address: A, root: PathBuf) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let server = Self {
+            address: listener.local_addr()?,
+            html: Arc::new(RwLock::new(String::new())),
+            clients: Arc::new(Mutex::new(Vec::new())),
+        };
+        let html = Arc::clone(&server.html);
+        let clients = Arc::clone(&server.clients);
+        let root = Arc::new(root);
+
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(Result::ok) {
+                let html = Arc::clone(&html);
+                let clients = Arc::clone(&clients);
+                let root = Arc::clone(&root);
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, &html, &clients, &root) {
+                        log::debug!("Failed to handle a preview connection: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(server)
+    }
+
+    /// Returns the address the server is listening on
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Replaces the served html and reloads all opened pages
+    pub fn set_html(&self, html: String) {
+        *self.html.write() = html;
+        self.clients.lock().retain_mut(|client| {
+            client
+                .write_message(Message::Text("reload".to_string()))
+                .is_ok()
+        });
+    }
+}
+
+/// Answers a http request or keeps the connection open if it's a websocket for reloading
+fn handle_connection(
+    stream: TcpStream,
+    html: &RwLock<String>,
+    clients: &Mutex<Vec<WebSocket<TcpStream>>>,
+    root: &Path,
+) -> io::Result<()> {
+    // the request is peeked so that the websocket handshake can read it again
+    let mut buffer = [0u8; 1024];
+    let length = stream.peek(&mut buffer)?;
+    let request = String::from_utf8_lossy(&buffer[..length]);
+    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+    if path == RELOAD_PATH {
+        let socket = tungstenite::accept(stream).map_err(|e| io::Error::other(e.to_string()))?;
+        clients.lock().push(socket);
+        return Ok(());
+    }
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+    let mut stream = stream;
+
+    if path == "/" || path == "/index.html" {
+        let html = html.read();
+        let page = match html.rfind("</body>") {
+            Some(index) => format!("{}{}{}", &html[..index], RELOAD_SCRIPT, &html[index..]),
+            None => format!("{}{}", html, RELOAD_SCRIPT),
+        };
+        write_response(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            page.as_bytes(),
+        )
+    } else if let Some(file) = get_file_path(root, &path).filter(|p| p.is_file()) {
+        let mime = mime_guess::from_path(&file).first_or_octet_stream();
+        write_response(&mut stream, "200 OK", mime.as_ref(), &fs::read(file)?)
+    } else {
+        write_response(&mut stream, "404 Not Found", "text/plain", b"Not Found")
+    }
+}
+
+/// Returns the path of a requested file if it's inside of the root directory
+fn get_file_path(root: &Path, path: &str) -> Option<PathBuf> {
+    let path = path
+        .split('?')
+        .next()
+        .unwrap_or(path)
+        .trim_start_matches('/');
+    let path = PathBuf::from(percent_decode(path));
+
+    if path.components().all(|c| matches!(c, Component::Normal(_))) {
+        Some(root.join(path))
+    } else {
+        None
+    }
+}
+
+/// Decodes %XX escapes in a url path
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(&String::from_utf8_lossy(hex), 16).ok());
+        if let Some(byte) = escaped {
+            decoded.push(byte);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+
+    stream.flush()
+}
@@ -0,0 +1,91 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use parking_lot::Mutex;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A pool with a fixed number of threads.
+/// Jobs are only passed to the pool when one of its threads is idle and run on the
+/// calling thread otherwise. This way jobs that wait for other jobs they started
+/// themselves, like documents waiting for their imports, can't block the whole pool.
+pub struct ThreadPool {
+    sender: Mutex<Sender<Job>>,
+    idle: Arc<AtomicUsize>,
+}
+
+impl ThreadPool {
+    /// Creates a new pool and starts the given number of threads
+    pub fn new(size: usize) -> Self {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let idle = Arc::new(AtomicUsize::new(size));
+
+        for _ in 0..size {
+            let receiver = Arc::clone(&receiver);
+            let idle = Arc::clone(&idle);
+            thread::spawn(move || run_worker(&receiver, &idle));
+        }
+
+        Self {
+            sender: Mutex::new(sender),
+            idle,
+        }
+    }
+
+    /// Runs the job on an idle thread of the pool or on the current thread if all threads are busy
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        if self.reserve_thread() {
+            if let Err(e) = self.sender.lock().send(Box::new(job)) {
+                run_job(e.0);
+            }
+        } else {
+            run_job(Box::new(job));
+        }
+    }
+
+    /// Marks one of the idle threads as busy. Returns false if there's no idle thread.
+    fn reserve_thread(&self) -> bool {
+        let mut idle = self.idle.load(Ordering::SeqCst);
+
+        while idle > 0 {
+            match self.idle.compare_exchange_weak(
+                idle,
+                idle - 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return true,
+                Err(current) => idle = current,
+            }
+        }
+
+        false
+    }
+}
+
+fn run_worker(receiver: &Mutex<Receiver<Job>>, idle: &AtomicUsize) {
+    loop {
+        let message = receiver.lock().recv();
+        match message {
+            Ok(job) => run_job(job),
+            Err(_) => return,
+        }
+        idle.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Runs the job and logs if it panicked so that a failing job doesn't take down the thread
+fn run_job(job: Job) {
+    if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+        log::error!("A job of the thread pool panicked");
+    }
+}
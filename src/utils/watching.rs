@@ -0,0 +1,64 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::Document;
+use crate::Parser;
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+/// Parses the document and calls the handler with the result. The document and all
+/// of its imports are watched afterwards and parsed again whenever one of them changes.
+/// Changes are collected for the debounce duration before the document is parsed again.
+/// The parser is created for every run so that imports that were added or removed
+/// are watched as well. Returns when watching fails or the handler returns false.
+pub fn watch_document<C, H>(
+    mut create_parser: C,
+    debounce: Duration,
+    mut handler: H,
+) -> notify::Result<()>
+where
+    C: FnMut() -> Parser,
+    H: FnMut(Document, &Parser) -> bool,
+{
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, debounce)?;
+    let mut watched_paths: HashSet<PathBuf> = HashSet::new();
+
+    loop {
+        let mut parser = create_parser();
+        let document = parser.parse();
+        if !handler(document, &parser) {
+            return Ok(());
+        }
+        let paths: HashSet<PathBuf> = parser.get_paths().into_iter().collect();
+
+        for path in watched_paths.difference(&paths) {
+            let _ = watcher.unwatch(path);
+        }
+        // paths are watched again as editors often replace files when saving them
+        for path in &paths {
+            if let Err(e) = watcher.watch(path, RecursiveMode::NonRecursive) {
+                log::warn!("Failed to watch {}: {}", path.to_string_lossy(), e);
+            }
+        }
+        watched_paths = paths;
+
+        loop {
+            match rx.recv() {
+                Ok(DebouncedEvent::Write(_))
+                | Ok(DebouncedEvent::Create(_))
+                | Ok(DebouncedEvent::Remove(_))
+                | Ok(DebouncedEvent::Rename(_, _)) => break,
+                Ok(DebouncedEvent::Error(e, _)) => return Err(e),
+                Ok(_) => {}
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+}
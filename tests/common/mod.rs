@@ -0,0 +1,52 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use snekdown::elements::Document;
+use snekdown::parser::ParserOptions;
+use snekdown::Parser;
+
+pub const DOCUMENT: &str = r#"# Chapter
+
+Some *formatted* text with **bold**, ~~striked~~ and `monospace` parts,
+a [link](https://github.com/Trivernis/snekdown) and a footnote[^note].
+
+## Section
+
+- first item
+- second item with :rocket:
+    - nested item
+1. ordered item
+2. another ordered item
+
+| Column | Other Column |
+|--------|--------------|
+| value  | other value  |
+
+```rust
+fn main() {
+    println!("Hello World");
+}
+```
+
+> A quote
+> over multiple lines
+> -- Douglas Adams, The Hitchhiker's Guide
+
+## Other Section
+
+:::warning [title=Careful]
+Text in a container
+:::
+
+Term
+: Definition
+
+[^note]: The definition of the footnote
+"#;
+
+pub fn parse(text: &str) -> Document {
+    Parser::new(text.to_string(), Some(ParserOptions::default())).parse()
+}
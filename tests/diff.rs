@@ -0,0 +1,115 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+mod common;
+
+use common::{parse, DOCUMENT};
+use snekdown::elements::diff::{diff, Change};
+use snekdown::elements::{Block, Span};
+
+/// Returns the changes between both texts
+fn diff_texts(old: &str, new: &str) -> Vec<Change> {
+    diff(&parse(old), &parse(new))
+}
+
+/// Returns the text of the span without trailing newlines
+fn text_at(text: &str, span: Span) -> &str {
+    text[span.start..span.end].trim_end()
+}
+
+#[test]
+fn equal_documents_have_no_changes() {
+    assert!(diff_texts(DOCUMENT, DOCUMENT).is_empty());
+}
+
+#[test]
+fn added_blocks_have_the_span_of_the_new_document() {
+    let old = "# Title\n\n> quote\n\n```rust\ncode\n```\n";
+    let new = "# Title\n\n> quote\n\n- item\n\n```rust\ncode\n```\n";
+    let changes = diff_texts(old, new);
+
+    assert_eq!(changes.len(), 1);
+    match &changes[0] {
+        Change::Added { block, span } => {
+            assert!(matches!(block.as_ref(), Block::List(_)));
+            assert_eq!(span.start, new.find("- item").unwrap());
+            assert_eq!(span.line, 4);
+            assert_eq!(text_at(new, *span), "- item");
+        }
+        change => panic!("expected an added block, got {:?}", change),
+    }
+}
+
+#[test]
+fn removed_blocks_have_the_span_of_the_old_document() {
+    let old = "# Title\n\n> quote\n\n- item\n\n```rust\ncode\n```\n";
+    let new = "# Title\n\n> quote\n\n```rust\ncode\n```\n";
+    let changes = diff_texts(old, new);
+
+    assert_eq!(changes.len(), 1);
+    match &changes[0] {
+        Change::Removed { block, span } => {
+            assert!(matches!(block.as_ref(), Block::List(_)));
+            assert_eq!(span.start, old.find("- item").unwrap());
+            assert_eq!(text_at(old, *span), "- item");
+            assert_eq!(changes[0].get_span(), *span);
+        }
+        change => panic!("expected a removed block, got {:?}", change),
+    }
+}
+
+#[test]
+fn modified_blocks_have_the_spans_of_both_documents() {
+    let old = "# Title\n\n> quote\n\n```rust\nold\n```\n";
+    let new = "# Title\n\n> quote\n\n```rust\nnew\n```\n";
+    let changes = diff_texts(old, new);
+
+    assert_eq!(changes.len(), 1);
+    match &changes[0] {
+        Change::Modified {
+            old: old_block,
+            new: new_block,
+            old_span,
+            new_span,
+        } => {
+            assert!(matches!(old_block.as_ref(), Block::CodeBlock(_)));
+            assert!(matches!(new_block.as_ref(), Block::CodeBlock(_)));
+            assert_eq!(text_at(old, *old_span), "```rust\nold\n```");
+            assert_eq!(text_at(new, *new_span), "```rust\nnew\n```");
+            assert_eq!(changes[0].get_span(), *new_span);
+        }
+        change => panic!("expected a modified block, got {:?}", change),
+    }
+}
+
+#[test]
+fn renamed_sections_only_list_their_header() {
+    let old = "# Title\n\ntext\n\n## Old Name\n\ncontent\n";
+    let new = "# Title\n\ntext\n\n## New Name\n\ncontent\n";
+    let changes = diff_texts(old, new);
+
+    assert_eq!(changes.len(), 1);
+    match &changes[0] {
+        Change::Modified {
+            old_span, new_span, ..
+        } => {
+            assert_eq!(text_at(old, *old_span), "## Old Name");
+            assert_eq!(text_at(new, *new_span), "## New Name");
+        }
+        change => panic!("expected a modified header, got {:?}", change),
+    }
+}
+
+#[test]
+fn changes_inside_sections_are_listed_separately() {
+    let old = "# Title\n\none\n\n## Section\n\ntwo\n";
+    let new = "# Title\n\none\n\n## Section\n\nchanged\n";
+    let changes = diff_texts(old, new);
+
+    assert_eq!(changes.len(), 1);
+    assert!(matches!(&changes[0], Change::Modified { .. }));
+    assert_eq!(text_at(new, changes[0].get_span()), "changed");
+}
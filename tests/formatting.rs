@@ -0,0 +1,39 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+mod common;
+
+use common::{parse, DOCUMENT};
+use snekdown::parser::ParserOptions;
+use snekdown::Parser;
+
+/// Returns the source formatted like `snekdown fmt` does
+fn format(text: &str) -> String {
+    let options = ParserOptions::default()
+        .with_imports(false)
+        .with_smart_punctuation(false);
+
+    Parser::new(text.to_string(), Some(options))
+        .parse()
+        .to_markdown()
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let formatted = format(DOCUMENT);
+
+    assert_eq!(format(&formatted), formatted);
+}
+
+#[test]
+fn formatting_keeps_the_document() {
+    let formatted = format(DOCUMENT);
+
+    assert_eq!(
+        parse(&formatted).to_markdown(),
+        parse(DOCUMENT).to_markdown()
+    );
+}
@@ -0,0 +1,189 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use snekdown::parser::diagnostics::DiagnosticCode;
+use snekdown::parser::{ImportLimits, ParserOptions};
+use snekdown::Parser;
+use std::fs;
+use std::path::PathBuf;
+
+/// Writes the files into a new temporary directory and returns its path
+fn create_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("snekdown-test-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    for (file, content) in files {
+        fs::write(dir.join(file), content).unwrap();
+    }
+
+    dir
+}
+
+/// Parses the file and returns the codes of the reported diagnostics
+fn parse_codes(path: PathBuf, options: ParserOptions) -> Vec<DiagnosticCode> {
+    let mut parser = Parser::with_defaults(options.add_path(path));
+    parser.parse();
+
+    parser.get_diagnostics().iter().map(|d| d.code).collect()
+}
+
+/// Returns how often the code was reported
+fn count(codes: &[DiagnosticCode], code: DiagnosticCode) -> usize {
+    codes.iter().filter(|c| **c == code).count()
+}
+
+#[test]
+fn sandbox_rejects_escaping_imports() {
+    let dir = create_files(
+        "sandbox",
+        &[
+            (
+                "main.md",
+                "<[../outside.md]\n\n<[/etc/hostname]\n\n<[https://example.com/a.md]\n",
+            ),
+            ("inside.md", "inside\n"),
+        ],
+    );
+    let options = ParserOptions::default().with_sandbox_root(dir.clone());
+    let codes = parse_codes(dir.join("main.md"), options);
+
+    assert_eq!(count(&codes, DiagnosticCode::ImportOutsideSandbox), 3);
+}
+
+#[cfg(unix)]
+#[test]
+fn sandbox_rejects_symlinks_to_outside_files() {
+    let outside = create_files("sandbox-outside", &[("secret.md", "secret\n")]);
+    let dir = create_files("sandbox-symlink", &[("main.md", "<[link.md]\n")]);
+    std::os::unix::fs::symlink(outside.join("secret.md"), dir.join("link.md")).unwrap();
+    let options = ParserOptions::default().with_sandbox_root(dir.clone());
+    let codes = parse_codes(dir.join("main.md"), options);
+
+    assert_eq!(count(&codes, DiagnosticCode::ImportOutsideSandbox), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn sandbox_rejects_missing_files_in_symlinks_to_outside_directories() {
+    let outside = create_files("sandbox-outside-dir", &[]);
+    let dir = create_files(
+        "sandbox-dir-symlink",
+        &[("main.md", "<[link/missing.md]\n")],
+    );
+    std::os::unix::fs::symlink(&outside, dir.join("link")).unwrap();
+    let options = ParserOptions::default().with_sandbox_root(dir.clone());
+    let codes = parse_codes(dir.join("main.md"), options);
+
+    assert_eq!(count(&codes, DiagnosticCode::ImportOutsideSandbox), 1);
+    assert_eq!(count(&codes, DiagnosticCode::ImportNotFound), 0);
+}
+
+#[test]
+fn sandbox_allows_imports_inside_the_root() {
+    let dir = create_files(
+        "sandbox-inside",
+        &[("main.md", "<[inside.md]\n"), ("inside.md", "inside\n")],
+    );
+    let options = ParserOptions::default().with_sandbox_root(dir.clone());
+    let codes = parse_codes(dir.join("main.md"), options);
+
+    assert_eq!(count(&codes, DiagnosticCode::ImportOutsideSandbox), 0);
+}
+
+#[test]
+fn section_import_of_itself_is_a_cycle() {
+    let dir = create_files(
+        "self-import",
+        &[("main.md", "# Intro\n\ntext\n\n<[main.md#Intro]\n")],
+    );
+    let codes = parse_codes(dir.join("main.md"), ParserOptions::default());
+
+    assert_eq!(count(&codes, DiagnosticCode::ImportCycle), 1);
+}
+
+#[test]
+fn code_import_of_itself_is_not_a_cycle() {
+    let dir = create_files(
+        "self-code-import",
+        &[("main.md", "# Source\n\n<[main.md][type=code]\n")],
+    );
+    let codes = parse_codes(dir.join("main.md"), ParserOptions::default());
+
+    assert!(codes.is_empty(), "unexpected diagnostics {:?}", codes);
+}
+
+#[test]
+fn mutual_section_imports_are_a_cycle() {
+    let dir = create_files(
+        "mutual-import",
+        &[
+            ("a.md", "# A\n\n<[b.md#B]\n"),
+            ("b.md", "# B\n\n<[a.md#A]\n"),
+        ],
+    );
+    let codes = parse_codes(dir.join("a.md"), ParserOptions::default());
+
+    assert_eq!(count(&codes, DiagnosticCode::ImportCycle), 1);
+}
+
+#[test]
+fn sibling_section_imports_can_repeat() {
+    let dir = create_files(
+        "sibling-import",
+        &[
+            (
+                "main.md",
+                "<[chapter.md#A]\n\n<[chapter.md#A]\n\n<[chapter.md#B]\n",
+            ),
+            ("chapter.md", "# A\n\none\n\n# B\n\ntwo\n"),
+        ],
+    );
+    let codes = parse_codes(dir.join("main.md"), ParserOptions::default());
+
+    assert_eq!(count(&codes, DiagnosticCode::DuplicateImport), 0);
+    assert_eq!(count(&codes, DiagnosticCode::ImportCycle), 0);
+}
+
+#[test]
+fn missing_default_files_are_not_reported() {
+    let dir = create_files("default-files", &[("main.md", "# Title\n\ntext\n")]);
+    let codes = parse_codes(dir.join("main.md"), ParserOptions::default());
+
+    assert!(codes.is_empty(), "unexpected diagnostics {:?}", codes);
+}
+
+#[test]
+fn lazy_import_failures_are_reported() {
+    let dir = create_files(
+        "lazy-import",
+        &[
+            ("main.md", "<[removed.md]\n\n<[large.md]\n"),
+            ("removed.md", "removed\n"),
+            ("large.md", "a text that is too large\n"),
+        ],
+    );
+    let limits = ImportLimits {
+        max_file_size: Some(10),
+        ..Default::default()
+    };
+    let options = ParserOptions::default()
+        .with_lazy_imports(true)
+        .with_import_limits(limits)
+        .add_path(dir.join("main.md"));
+    let mut parser = Parser::with_defaults(options);
+    let mut document = parser.parse();
+    fs::remove_file(dir.join("removed.md")).unwrap();
+
+    assert_eq!(document.materialize_imports(), 2);
+    let diagnostics = parser.get_diagnostics();
+    let codes = diagnostics.iter().map(|d| d.code).collect::<Vec<_>>();
+    assert_eq!(count(&codes, DiagnosticCode::ImportNotFound), 1);
+    assert_eq!(count(&codes, DiagnosticCode::ImportLimitExceeded), 1);
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.path.as_deref() == dir.join("main.md").to_str()));
+}
@@ -0,0 +1,53 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+mod common;
+
+use common::{parse, DOCUMENT};
+use snekdown::parser::incremental::TextEdit;
+
+/// Applies the edit to the parsed document and checks that the result
+/// is the same as parsing the edited text completely
+fn assert_reparse_matches(edit: TextEdit) {
+    let mut text = DOCUMENT.to_string();
+    text.replace_range(edit.range.clone(), &edit.text);
+    let mut document = parse(DOCUMENT);
+
+    assert!(document.reparse_range(edit));
+    assert_eq!(document.to_json().unwrap(), parse(&text).to_json().unwrap());
+}
+
+#[test]
+fn reparse_inside_section_matches_full_parse() {
+    let start = DOCUMENT.find("first item").unwrap();
+
+    assert_reparse_matches(TextEdit::new(start..start + 5, "changed"));
+}
+
+#[test]
+fn reparse_inserting_section_matches_full_parse() {
+    let start = DOCUMENT.find("## Other Section").unwrap();
+
+    assert_reparse_matches(TextEdit::new(
+        start..start,
+        "## New Section\n\nnew text\n\n",
+    ));
+}
+
+#[test]
+fn reparse_removing_text_matches_full_parse() {
+    let start = DOCUMENT.find("> A quote").unwrap();
+    let end = DOCUMENT.find("## Other Section").unwrap();
+
+    assert_reparse_matches(TextEdit::new(start..end, ""));
+}
+
+#[test]
+fn reparse_rejects_invalid_range() {
+    let mut document = parse(DOCUMENT);
+
+    assert!(!document.reparse_range(TextEdit::new(0..DOCUMENT.len() + 1, "")));
+}
@@ -0,0 +1,18 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+mod common;
+
+use common::{parse, DOCUMENT};
+use snekdown::elements::Document;
+
+#[test]
+fn json_round_trip_keeps_the_document() {
+    let json = parse(DOCUMENT).to_json().unwrap();
+    let document = Document::from_json(&json).unwrap();
+
+    assert_eq!(document.to_json().unwrap(), json);
+}
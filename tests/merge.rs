@@ -0,0 +1,82 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+mod common;
+
+use common::{parse, DOCUMENT};
+use snekdown::elements::{Document, HeaderShift};
+
+/// Returns how many elements match the selector
+fn count(document: &Document, selector: &str) -> usize {
+    document.select(selector).unwrap().len()
+}
+
+#[test]
+fn merge_appends_the_blocks() {
+    let mut document = parse("# Intro\n\ntext\n");
+    document.merge(parse(DOCUMENT), None);
+
+    assert_eq!(count(&document, "section[title=Intro]"), 1);
+    assert_eq!(count(&document, "section[level=1][title=Chapter]"), 1);
+    assert_eq!(count(&document, "codeblock[lang=rust]"), 1);
+}
+
+#[test]
+fn merge_shifts_headers_by_levels() {
+    let mut document = parse("# Intro\n\ntext\n");
+    document.merge(
+        parse("# Other\n\n## Nested\n\ntext\n"),
+        Some(HeaderShift::Levels(2)),
+    );
+
+    assert_eq!(count(&document, "section[level=1][title=Intro]"), 1);
+    assert_eq!(count(&document, "section[level=3][title=Other]"), 1);
+    assert_eq!(count(&document, "section[level=4][title=Nested]"), 1);
+}
+
+#[test]
+fn merge_shifts_headers_below_the_last_section() {
+    let mut document = parse("# Intro\n\n## Usage\n\ntext\n");
+    document.merge(parse("# Other\n\ntext\n"), Some(HeaderShift::Nesting));
+
+    assert_eq!(count(&document, "section[level=3][title=Other]"), 1);
+}
+
+#[test]
+fn merge_renames_colliding_anchors_and_their_links() {
+    let mut document = parse("# Intro\n\ntext\n");
+    document.merge(
+        parse("# Intro\n\nSee [the intro](#Intro) for more.\n"),
+        None,
+    );
+
+    assert_eq!(count(&document, "section[anchor=Intro]"), 1);
+    assert_eq!(count(&document, "section[anchor=Intro-2]"), 1);
+    assert_eq!(count(&document, "link[url=#Intro-2]"), 1);
+    assert_eq!(count(&document, "link[url=#Intro]"), 0);
+}
+
+#[test]
+fn merge_renames_colliding_footnotes_and_numbers_them_after_the_existing_ones() {
+    let mut document = parse("text[^note]\n\n[^note]: first\n");
+    document.merge(parse("more[^note]\n\n[^note]: second\n"), None);
+    let footnotes = document
+        .get_footnotes()
+        .iter()
+        .map(|footnote| {
+            let footnote = footnote.lock();
+            (footnote.label.clone(), footnote.number)
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        footnotes,
+        vec![
+            ("note".to_string(), Some(1)),
+            ("note-2".to_string(), Some(2))
+        ]
+    );
+}